@@ -3,6 +3,7 @@ use itertools::Itertools;
 use crate::Result;
 use crate::analysis::{type_coercion_rules, AnalyzerRule, GlobalAggregates, ResolveAliases, ResolveFunctions, ResolveGenerate, ResolveReferences, ResolveRelations};
 use crate::expr::Expr;
+use crate::expr::aggregate::DistinctAggFunction;
 use crate::logical_plan::{Aggregate, LogicalPlan, RelationPlaceholder};
 use crate::tree_node::{Transformed, TreeNode, TreeNodeRecursion};
 use crate::types::DataType;
@@ -60,6 +61,9 @@ impl Analyzer {
                             Expr::UnresolvedAttribute(name_parts) => {
                                 Err(format!("cannot resolve {} column", name_parts.iter().join(".")))
                             },
+                            Expr::UnresolvedNamedLambdaVariable(name_parts) => {
+                                Err(format!("cannot resolve {} lambda variable", name_parts.iter().join(".")))
+                            },
                             e => match e.check_input_data_types() {
                                 Ok(_) => Ok(Transformed::no(e)),
                                 Err(s) => Err(format!("cannot resolve {:?} due to data type mismatch: {}", e, s))
@@ -97,6 +101,12 @@ impl Analyzer {
         for expr in &aggregate.aggregate_exprs {
             Self::check_valid_agg_expr(expr, &aggregate.grouping_exprs)?;
         }
+        if let Some(having) = &aggregate.having {
+            if having.data_type() != &DataType::Boolean {
+                return Err(format!("having condition should be boolean type, but got {}: {}", having.data_type(), having.sql()));
+            }
+            Self::check_valid_agg_expr(having, &aggregate.grouping_exprs)?;
+        }
         Ok(())
     }
 
@@ -121,6 +131,10 @@ impl Analyzer {
                     }
                 }
             },
+            Expr::TypedAggFunction(f) if f.as_any().is::<DistinctAggFunction>() => {
+                // distinct的参数是内部聚合函数, 检查内部聚合函数的参数
+                Self::check_valid_agg_expr(f.args()[0], grouping_exprs)?;
+            },
             Expr::TypedAggFunction(f) => {
                 for x in f.args() {
                     if matches!(x, Expr::DeclarativeAggFunction(_) | Expr::TypedAggFunction(_)) {
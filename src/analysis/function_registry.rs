@@ -71,6 +71,35 @@ fn builtin_function_registry() -> FunctionRegistry {
         "substring" | "substr" => Substring,
         "concat_ws" => ConcatWs,
         "concat" => Concat,
+        "map_keys" => MapKeys,
+        "map_values" => MapValues,
+        "map_from_arrays" => MapFromArrays,
+        "str_to_map" => StringToMap,
+        // array and struct functions
+        "array" => CreateArray,
+        "named_struct" => CreateNamedStruct,
+        "struct" => CreateStruct,
+        "size" | "cardinality" => Size,
+        "array_contains" => ArrayContains,
+        "array_position" => ArrayPosition,
+        "array_distinct" => ArrayDistinct,
+        "array_union" => ArrayUnion,
+        "array_intersect" => ArrayIntersect,
+        "array_except" => ArrayExcept,
+        "array_join" => ArrayJoin,
+        "slice" => Slice,
+        "sort_array" => SortArray,
+        "flatten" => Flatten,
+        "arrays_zip" => ArraysZip,
+        "sequence" => Sequence,
+        "element_at" => ElementAt,
+        // higher order functions
+        "transform" => ArrayTransform,
+        "filter" => ArrayFilter,
+        "exists" => ArrayExists,
+        "forall" => ArrayForAll,
+        "aggregate" | "reduce" => ArrayAggregate,
+        "array_sort" => ArraySort,
         "split" => StringSplit,
         "split_part" => SplitPart,
         "replace" => StringReplace,
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use itertools::Itertools;
 use crate::analysis::lookup_function;
 use crate::expr::*;
+use crate::expr::aggregate::DistinctAggFunction;
 use crate::logical_plan::{Aggregate, Generate, LogicalPlan, Project, RelationPlaceholder};
 use crate::tree_node::{Transformed, TreeNode, TreeNodeRecursion};
 use crate::types::DataType;
@@ -111,6 +112,7 @@ impl ResolveReferences {
 fn extract_value(child: Expr, extraction: Expr) -> Result<Expr> {
     match child.data_type() {
         DataType::Array(_) => Ok(Expr::ScalarFunction(Box::new(GetArrayItem::new(Box::new(child), Box::new(extraction))))),
+        DataType::Map(_, _) => Ok(Expr::ScalarFunction(Box::new(GetMapValue::new(Box::new(child), Box::new(extraction))))),
         DataType::Struct(fields) => match &extraction {
             Expr::Literal(Literal{value, data_type}) if data_type == DataType::string_type() && !value.is_null() => {
                 let name = value.get_string();
@@ -188,7 +190,10 @@ impl AnalyzerRule for ResolveReferences {
                 let transformed = p.map_expressions(|expr| {
                     self.resolve_expr(expr, &dict, &qualified)
                 })?;
-                Ok(transformed)
+                transformed.transform_data(|p| match p {
+                    LogicalPlan::Aggregate(agg) if agg.having.as_ref().is_some_and(|h| !h.resolved()) => Self::resolve_having_aliases(agg),
+                    p => Ok(Transformed::no(p)),
+                })
             }
         })
     }
@@ -198,6 +203,27 @@ impl AnalyzerRule for ResolveReferences {
     }
 }
 
+impl ResolveReferences {
+    /// having中无法从输入列解析的名称, 替换为select中同名别名的表达式
+    fn resolve_having_aliases(agg: Aggregate) -> Result<Transformed<LogicalPlan>> {
+        let Aggregate{grouping_exprs, aggregate_exprs, having, child} = agg;
+        let aliases: HashMap<_, _> = aggregate_exprs.iter().filter_map(|e| match e {
+            Expr::Alias(Alias{child, name, ..}) => Some((name.to_lowercase(), child.as_ref().clone())),
+            _ => None,
+        }).collect();
+        let having = having.unwrap().transform_up(|expr| match expr {
+            Expr::UnresolvedAttribute(name_parts) if name_parts.len() == 1 => {
+                match aliases.get(&name_parts[0].to_lowercase()) {
+                    Some(e) => Ok(Transformed::yes(e.clone())),
+                    None => Ok(Transformed::no(Expr::UnresolvedAttribute(name_parts))),
+                }
+            },
+            e => Ok(Transformed::no(e)),
+        })?;
+        Ok(having.update_data(|having| LogicalPlan::Aggregate(Aggregate{grouping_exprs, aggregate_exprs, having: Some(having), child})))
+    }
+}
+
 fn contains_star(expr: &Expr) -> bool {
     let mut contains = false;
     expr.apply(|expr| {
@@ -282,8 +308,21 @@ impl AnalyzerRule for ResolveFunctions {
                 let transformed = p.map_expressions(|expr| {
                     expr.transform_up(|expr| {
                         match &expr {
-                            Expr::UnresolvedFunction(UnresolvedFunction{name, arguments}) => {
+                            // 参数未解析(如列不存在)时保持不变, 由check_analysis报错
+                            // lambda函数由高阶函数按其它参数的类型绑定, 不要求已解析
+                            Expr::UnresolvedFunction(UnresolvedFunction{arguments, ..}) | Expr::UnresolvedGenerator(UnresolvedGenerator{arguments, ..})
+                                if !arguments.iter().all(|arg| arg.resolved() || matches!(arg, Expr::LambdaFunction(_))) => Ok(Transformed::no(expr)),
+                            Expr::UnresolvedFunction(UnresolvedFunction{name, arguments, is_distinct: true}) => {
+                                match lookup_function(name, arguments.clone())? {
+                                    e @ (Expr::DeclarativeAggFunction(_) | Expr::TypedAggFunction(_)) =>
+                                        Ok(Transformed::yes(Expr::TypedAggFunction(Box::new(DistinctAggFunction::new(e))))),
+                                    _ => Err(format!("DISTINCT specified, but {} is not an aggregate function", name)),
+                                }
+                            },
+                            Expr::UnresolvedFunction(UnresolvedFunction{name, arguments, ..}) => {
                                 match lookup_function(name, arguments.clone()) {
+                                    Ok(e) if e.children().iter().any(|arg| matches!(arg, Expr::LambdaFunction(f) if !f.bound())) =>
+                                        Err(format!("function {} does not support lambda function argument", name)),
                                     Ok(e) => Ok(Transformed::yes(e)),
                                     Err(e) => Err(e)
                                 }
@@ -377,11 +416,12 @@ impl AnalyzerRule for ResolveAliases {
                     child
                 })))
             },
-            LogicalPlan::Aggregate(Aggregate{grouping_exprs, aggregate_exprs, child})
+            LogicalPlan::Aggregate(Aggregate{grouping_exprs, aggregate_exprs, having, child})
                 if child.resolved() && Self::has_unresolved_alias(&aggregate_exprs) => {
                 Ok(Transformed::yes(LogicalPlan::Aggregate(Aggregate{
                     grouping_exprs,
                     aggregate_exprs: Self::assign_aliases(aggregate_exprs),
+                    having,
                     child
                 })))
             }
@@ -421,7 +461,7 @@ impl AnalyzerRule for GlobalAggregates {
         plan.transform_up(|plan| match &plan {
             LogicalPlan::Project(Project{project_list,child})
                 if project_list.into_iter().any(|e| Self::contains_aggregates(e)) => {
-                Ok(Transformed::yes(LogicalPlan::Aggregate(Aggregate::new(vec![], project_list.clone(), child.clone()))))
+                Ok(Transformed::yes(LogicalPlan::Aggregate(Aggregate::new(vec![], project_list.clone(), None, child.clone()))))
             },
             _ => Ok(Transformed::no(plan)),
         })
@@ -1,8 +1,8 @@
 use crate::analysis::AnalyzerRule;
 use crate::data::Value;
-use crate::expr::{BinaryOperator, In, Expr, If, CaseWhen, Coalesce, Least, Greatest, ScalarFunction};
+use crate::expr::{BinaryOperator, In, Expr, If, CaseWhen, Coalesce, Least, Greatest, CreateArray, ScalarFunction};
 use crate::logical_plan::LogicalPlan;
-use crate::{match_downcast, match_downcast_ref, Operator};
+use crate::{decimal_utils, match_downcast, match_downcast_ref, Operator};
 use crate::tree_node::{Transformed, TreeNode};
 use crate::types::{AbstractDataType, DataType};
 
@@ -29,7 +29,14 @@ impl AnalyzerRule for ImplicitTypeCasts {
                     Some(common_type) => match op {
                         Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo
                           | Operator::BitAnd | Operator::BitOr | Operator::BitXor => {
-                            if common_type.is_numeric_type() {
+                            if let DataType::Decimal(_, _) = common_type {
+                                // decimal运算的结果类型由两边的精度决定, 整数转换为能容纳的decimal, 不转换为公共类型
+                                let left_type = decimal_type_for(left.data_type()).unwrap_or(&common_type).clone();
+                                let right_type = decimal_type_for(right.data_type()).unwrap_or(&common_type).clone();
+                                let new_left = cast_if_not_same_type(*left, &left_type);
+                                let new_right = cast_if_not_same_type(*right, &right_type);
+                                Ok(Transformed::yes(Expr::BinaryOperator(BinaryOperator{left: Box::new(new_left), op: op.clone(), right: Box::new(new_right)})))
+                            } else if common_type.is_numeric_type() {
                                 let new_left = if left.data_type() == &common_type {
                                     left
                                 } else {
@@ -85,7 +92,8 @@ impl AnalyzerRule for ImplicitTypeCasts {
                 }
             },
             // /除法只支持bigint和double类型
-            Expr::BinaryOperator(BinaryOperator{left, op, right}) if op == Operator::Divide && left.data_type() == right.data_type() && (left.data_type() != DataType::long_type() && left.data_type() != DataType::double_type() ) => {
+            Expr::BinaryOperator(BinaryOperator{left, op, right}) if op == Operator::Divide && left.data_type() == right.data_type() && (left.data_type() != DataType::long_type() && left.data_type() != DataType::double_type() )
+              && !matches!(left.data_type(), DataType::Decimal(_, _)) => {
                 let common_type = if left.data_type() == DataType::float_type() {
                     DataType::Double
                 } else {
@@ -364,6 +372,17 @@ impl AnalyzerRule for FunctionArgumentConversion {
                             return Ok(Transformed::yes(Expr::ScalarFunction(Box::new(Greatest::new(children)))))
                         }
                     }
+                } else if let Some(CreateArray{children, ..}) = any.downcast_ref::<CreateArray>() {
+                    if ! children.into_iter().all(|e| e.data_type() == children[0].data_type()) {
+                        let mut types = Vec::with_capacity(children.len());
+                        for e in children {
+                            types.push(e.data_type().clone());
+                        }
+                        if let Some(common_type) = find_wider_common_type(types) {
+                            let children = children.into_iter().map(|e|cast_if_not_same_type(e.clone(), &common_type)).collect();
+                            return Ok(Transformed::yes(Expr::ScalarFunction(Box::new(CreateArray::new(children)))))
+                        }
+                    }
                 }
                 Ok(Transformed::no(Expr::ScalarFunction(func)))
             }
@@ -389,6 +408,10 @@ fn find_tightest_common_type(type1:  DataType, type2:  DataType) -> Option<DataT
         (t1, t2) if t1 == t2 => Some(t1),
         (t1, DataType::Null) => Some(t1),
         (DataType::Null, t2) => Some(t2),
+        (DataType::Decimal(p1, s1), DataType::Decimal(p2, s2)) => Some(wider_decimal_type(p1, s1, p2, s2).clone()),
+        (DataType::Decimal(_, _), DataType::Float | DataType::Double) | (DataType::Float | DataType::Double, DataType::Decimal(_, _)) => Some(DataType::Double),
+        (t1 @ DataType::Decimal(_, _), t2) | (t2, t1 @ DataType::Decimal(_, _)) if t2.is_integral_type() =>
+            find_tightest_common_type(t1, decimal_type_for(&t2).unwrap().clone()),
         (t1, t2) if t1.is_numeric_type() && t2.is_numeric_type() =>
             Some(NUMERIC_PRECEDENCE.iter().rfind(|t| *t == &t1 || *t == &t2).unwrap().clone()),
         _ => None
@@ -396,6 +419,65 @@ fn find_tightest_common_type(type1:  DataType, type2:  DataType) -> Option<DataT
 
 }
 
+/// 整数能无损转换的decimal类型
+fn decimal_type_for(data_type: &DataType) -> Option<&'static DataType> {
+    match data_type {
+        DataType::Int => Some(DataType::decimal_type(10, 0)),
+        DataType::Long => Some(DataType::decimal_type(20, 0)),
+        DataType::Decimal(p, s) => Some(DataType::decimal_type(*p, *s)),
+        _ => None
+    }
+}
+
+fn wider_decimal_type(p1: u8, s1: u8, p2: u8, s2: u8) -> &'static DataType {
+    let scale = s1.max(s2);
+    let range = (p1 - s1).max(p2 - s2);
+    bounded_decimal_type(range as u32 + scale as u32, scale as u32)
+}
+
+fn bounded_decimal_type(precision: u32, scale: u32) -> &'static DataType {
+    let max = decimal_utils::MAX_PRECISION as u32;
+    DataType::decimal_type(precision.min(max) as u8, scale.min(max) as u8)
+}
+
+/// 精度超过38时优先保留整数部分, 标度最少保留6位
+fn adjust_decimal_type(precision: u32, scale: u32) -> &'static DataType {
+    let max = decimal_utils::MAX_PRECISION as u32;
+    if precision <= max {
+        DataType::decimal_type(precision as u8, scale as u8)
+    } else {
+        let int_digits = precision - scale;
+        let min_scale = scale.min(6);
+        let adjusted_scale = max.saturating_sub(int_digits).max(min_scale);
+        DataType::decimal_type(max as u8, adjusted_scale as u8)
+    }
+}
+
+/// decimal算术运算的结果类型, 参考spark DecimalPrecision:
+/// +,-: (max(s1, s2) + max(p1-s1, p2-s2) + 1, max(s1, s2)),
+/// *: (p1 + p2 + 1, s1 + s2),
+/// /: (p1 - s1 + s2 + max(6, s1 + p2 + 1), max(6, s1 + p2 + 1)),
+/// %: (min(p1-s1, p2-s2) + max(s1, s2), max(s1, s2))
+pub fn decimal_arithmetic_result_type(op: Operator, p1: u8, s1: u8, p2: u8, s2: u8) -> &'static DataType {
+    let (p1, s1, p2, s2) = (p1 as u32, s1 as u32, p2 as u32, s2 as u32);
+    match op {
+        Operator::Plus | Operator::Minus => {
+            let scale = s1.max(s2);
+            adjust_decimal_type((p1 - s1).max(p2 - s2) + scale + 1, scale)
+        },
+        Operator::Multiply => adjust_decimal_type(p1 + p2 + 1, s1 + s2),
+        Operator::Divide => {
+            let scale = 6.max(s1 + p2 + 1);
+            adjust_decimal_type(p1 - s1 + s2 + scale, scale)
+        },
+        Operator::Modulo => {
+            let scale = s1.max(s2);
+            adjust_decimal_type((p1 - s1).min(p2 - s2) + scale, scale)
+        },
+        _ => panic!("{:?} not support decimal type", op),
+    }
+}
+
 static NUMERIC_PRECEDENCE: [DataType; 4] = [DataType::Int, DataType::Long, DataType::Float, DataType::Double];
 
 fn find_common_type_for_binary_comparison(type1:  &DataType, type2:  &DataType) -> Option<DataType> {
@@ -448,6 +530,7 @@ fn has_string_type(data_type: &DataType) -> bool {
     match data_type {
         DataType::String => true,
         DataType::Array(data_type) => has_string_type(data_type),
+        DataType::Map(key_type, value_type) => has_string_type(key_type) || has_string_type(value_type),
         _ => false
     }
 }
@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{decimal_utils, Result};
 use crate::codecs::Deserializer;
 use crate::data::{GenericRow, Row, Value};
 use crate::types::{DataType, Field, Schema};
@@ -95,6 +95,11 @@ fn create_converter(data_type: &DataType, schema: &AvroSchema)  -> Result<Box<dy
             DataType::Binary => Box::new(BytesToBinaryConverter),
             _ => return Err(not_match_err(data_type, schema)),
         },
+        AvroSchema::Decimal(inner) => match data_type {
+            DataType::Decimal(precision, scale) if inner.scale <= decimal_utils::MAX_PRECISION as usize =>
+                Box::new(DecimalToDecimalConverter { from_scale: inner.scale as u8, precision: *precision, scale: *scale }),
+            _ => return Err(not_match_err(data_type, schema)),
+        },
         AvroSchema::Union(inner) => Box::new(UnionNullConverter::new(data_type, inner)?),
         AvroSchema::Record(inner) => match data_type {
             DataType::Struct(fields) => Box::new(RecordToStructConverter::new(&fields.0, &inner.fields)?),
@@ -104,6 +109,10 @@ fn create_converter(data_type: &DataType, schema: &AvroSchema)  -> Result<Box<dy
             DataType::Array(ele_type) => Box::new(ArrayToArrayConverter::new(ele_type, &inner.items)?),
             _ => return Err(not_match_err(data_type, schema)),
         },
+        AvroSchema::Map(inner) => match data_type {
+            DataType::Map(key_type, value_type) if key_type.as_ref() == &DataType::String => Box::new(MapToMapConverter::new(value_type, &inner.types)?),
+            _ => return Err(not_match_err(data_type, schema)),
+        },
         _ => return Err(format!("not support schema: {:?}", schema)),
     };
     Ok(converter)
@@ -206,6 +215,27 @@ impl ValueConverter for BytesToBinaryConverter {
     }
 }
 
+/// 超出精度时返回null
+struct DecimalToDecimalConverter {
+    from_scale: u8,
+    precision: u8,
+    scale: u8,
+}
+
+impl ValueConverter for DecimalToDecimalConverter {
+    fn convert(&self, value: AvroValue) -> Result<Value> {
+        match value {
+            AvroValue::Decimal(v) => {
+                let bytes = Vec::<u8>::try_from(&v).map_err(|e| e.to_string())?;
+                let v = decimal_utils::from_signed_bytes_be(&bytes)
+                    .and_then(|v| decimal_utils::change_precision(v, self.from_scale, self.precision, self.scale));
+                Ok(v.map(|v| Value::Decimal(v, self.scale)).unwrap_or(Value::Null))
+            },
+            v => Err(format!("invalid value for DecimalToDecimalConverter: {:?}", v)),
+        }
+    }
+}
+
 struct UnionNullConverter {
     converter: Box<dyn ValueConverter>,
 }
@@ -308,3 +338,30 @@ impl ValueConverter for ArrayToArrayConverter {
     }
 }
 
+/// avro map的key只能是string
+struct MapToMapConverter {
+    converter: Box<dyn ValueConverter>,
+}
+
+impl MapToMapConverter {
+    fn new(value_type: &DataType, value_schema: &AvroSchema)  -> Result<Self> {
+        let converter = create_converter(value_type, value_schema)?;
+        Ok(Self{converter})
+    }
+}
+
+impl ValueConverter for MapToMapConverter {
+    fn convert(&self, value: AvroValue) -> Result<Value> {
+        match value {
+            AvroValue::Map(values) => {
+                let mut entries = Vec::with_capacity(values.len());
+                for (k, v) in values {
+                    entries.push((Value::String(Arc::new(k)), self.converter.convert(v)?));
+                }
+                Ok(Value::Map(Arc::new(entries)))
+            }
+            v => Err(format!("invalid value for MapToMapConverter: {:?}", v)),
+        }
+    }
+}
+
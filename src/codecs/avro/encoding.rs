@@ -4,7 +4,7 @@ use apache_avro::Schema as AvroSchema;
 use apache_avro::schema::{RecordField, UnionSchema};
 use apache_avro::types::Value as AvroValue;
 use crate::codecs::Serializer;
-use crate::Result;
+use crate::{decimal_utils, Result};
 use crate::data::{Row, Value};
 use crate::types::{DataType, Field, Schema};
 
@@ -84,6 +84,7 @@ fn create_converter(data_type: &DataType, schema: &AvroSchema)  -> Result<Box<dy
             DataType::Float => Box::new(FloatToStringConverter),
             DataType::Double => Box::new(DoubleToStringConverter),
             DataType::Boolean => Box::new(BooleanToStringConverter),
+            DataType::Decimal(_, _) => Box::new(DecimalToStringConverter),
             DataType::String => Box::new(StringToStringConverter),
              _ => return Err(not_match_err(data_type, schema)),
         },
@@ -91,11 +92,20 @@ fn create_converter(data_type: &DataType, schema: &AvroSchema)  -> Result<Box<dy
             DataType::Binary => Box::new(BinaryToBytesConverter),
              _ => return Err(not_match_err(data_type, schema)),
         },
+        AvroSchema::Decimal(inner) => match data_type {
+            DataType::Decimal(_, _) if inner.precision <= decimal_utils::MAX_PRECISION as usize =>
+                Box::new(DecimalToDecimalConverter { precision: inner.precision as u8, scale: inner.scale as u8 }),
+             _ => return Err(not_match_err(data_type, schema)),
+        },
         AvroSchema::Union(inner) => Box::new(UnionNullConverter::new(data_type, inner)?),
         AvroSchema::Array(inner) => match data_type {
              DataType::Array(ele_type) => Box::new(ArrayToArrayConverter::new(ele_type, &inner.items)?),
              _ => return Err(not_match_err(data_type, schema)),
         },
+        AvroSchema::Map(inner) => match data_type {
+             DataType::Map(key_type, value_type) if key_type.as_ref() == &DataType::String => Box::new(MapToMapConverter::new(value_type, &inner.types)?),
+             _ => return Err(not_match_err(data_type, schema)),
+        },
         AvroSchema::Record(inner) => match data_type {
             DataType::Struct(fields) => Box::new(StructToRecordConverter::new(&fields.0, &inner.fields)?),
              _ => return Err(not_match_err(data_type, schema)),
@@ -190,6 +200,37 @@ impl ValueConverter for StringToStringConverter {
     }
 }
 
+struct DecimalToStringConverter;
+
+impl ValueConverter for DecimalToStringConverter {
+    fn convert(&self, value: &Value) -> ConverterResult {
+        match value {
+            Value::Null => ConverterResult::Null,
+            Value::Decimal(_, _) => ConverterResult::Value(AvroValue::String(value.to_string())),
+            _ => ConverterResult::Err(format!("invalid value for DecimalToStringConverter: {:?}", value)),
+        }
+    }
+}
+
+/// 转换为schema的精度和标度, 超出精度时报错
+struct DecimalToDecimalConverter {
+    precision: u8,
+    scale: u8,
+}
+
+impl ValueConverter for DecimalToDecimalConverter {
+    fn convert(&self, value: &Value) -> ConverterResult {
+        match value {
+            Value::Null => ConverterResult::Null,
+            Value::Decimal(v, scale) => match decimal_utils::change_precision(*v, *scale, self.precision, self.scale) {
+                Some(v) => ConverterResult::Value(AvroValue::Decimal(decimal_utils::to_signed_bytes_be(v).into())),
+                None => ConverterResult::Err(format!("{} out of range for decimal({},{})", value, self.precision, self.scale)),
+            },
+            _ => ConverterResult::Err(format!("invalid value for DecimalToDecimalConverter: {:?}", value)),
+        }
+    }
+}
+
 struct BooleanToBooleanConverter;
 
 impl ValueConverter for BooleanToBooleanConverter {
@@ -279,6 +320,7 @@ impl StructToRecordConverter {
                         AvroSchema::Bytes => AvroValue::Bytes(Vec::new()),
                         AvroSchema::String => AvroValue::String("".to_string()),
                         AvroSchema::Array(_) => AvroValue::Array(Vec::new()),
+                        AvroSchema::Decimal(_) => AvroValue::Decimal(vec![0u8].into()),
                         _ => return Err(format!("not default value for field {:?}", rf)),
                     }
                 },
@@ -363,6 +405,38 @@ impl ValueConverter for ArrayToArrayConverter {
     }
 }
 
+/// avro map的key只能是string, null的value不输出
+struct MapToMapConverter {
+    converter: Box<dyn ValueConverter>,
+}
+
+impl MapToMapConverter {
+    fn new(value_type: &DataType, value_schema: &AvroSchema)  -> Result<Self> {
+        let converter = create_converter(value_type, value_schema)?;
+        Ok(Self{converter})
+    }
+}
+
+impl ValueConverter for MapToMapConverter {
+    fn convert(&self, value: &Value) -> ConverterResult {
+        match value {
+            Value::Null => ConverterResult::Null,
+            Value::Map(entries) => {
+                let mut map = HashMap::with_capacity(entries.len());
+                for (k, v) in entries.iter() {
+                    match self.converter.convert(v) {
+                        ConverterResult::Null => continue,
+                        ConverterResult::Value(v) => map.insert(k.get_string().to_string(), v),
+                        ConverterResult::Err(e) => return ConverterResult::Err(e),
+                    };
+                }
+                ConverterResult::Value(AvroValue::Map(map))
+            },
+            _ => ConverterResult::Err(format!("invalid value for MapToMapConverter: {:?}", value)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -500,5 +574,53 @@ mod tests {
         println!("{}", rst);
     }
 
+    #[test]
+    fn test_decimal() {
+        let avro_schema = AvroSchema::parse_str(r#"
+        {
+            "type": "record",
+            "name": "test",
+            "fields": [
+                {"name": "amount", "type": {"type": "bytes", "logicalType": "decimal", "precision": 18, "scale": 2}},
+                {"name": "price", "type": ["null", {"type": "fixed", "name": "price", "size": 8, "logicalType": "decimal", "precision": 10, "scale": 4}]}
+            ]
+        }
+        "#).unwrap();
+        let schema = crate::parser::parse_schema("amount decimal(18,2), price decimal(10,4)").unwrap();
+        let mut serializer = AvroSerializer::new(schema.clone(), avro_schema.clone()).unwrap();
+        let mut deserializer = AvroDeserializer::new(schema.clone(), avro_schema.clone()).unwrap();
+        let row = GenericRow::new(vec![Value::Decimal(-12345, 2), Value::Decimal(314159, 4)]);
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_string(), "[-123.45, 31.4159]");
+        let row = GenericRow::new(vec![Value::Decimal(1, 0), Value::Null]);
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_string(), "[1.00, null]");
+        assert!(serializer.serialize(&GenericRow::new(vec![Value::Decimal(1, 0), Value::Decimal(10_000_000, 0)])).is_err());
+    }
 
+    #[test]
+    fn test_map_round_trip() {
+        let avro_schema = AvroSchema::parse_str(r#"
+        {
+            "type": "record",
+            "name": "test",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "attrs", "type": ["null", {"type": "map", "values": "long"}]}
+            ]
+        }
+        "#).unwrap();
+        let schema = crate::parser::parse_schema("id bigint, attrs map<string, bigint>").unwrap();
+        let mut serializer = AvroSerializer::new(schema.clone(), avro_schema.clone()).unwrap();
+        let mut deserializer = AvroDeserializer::new(schema.clone(), avro_schema.clone()).unwrap();
+        let row = GenericRow::new(vec![
+            Value::long(1),
+            Value::Map(Arc::new(vec![(Value::string("a"), Value::long(1)), (Value::string("b"), Value::long(2))])),
+        ]);
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_generic_row(), row);
+        let row = GenericRow::new(vec![Value::long(2), Value::Null]);
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_generic_row(), row);
+    }
 }
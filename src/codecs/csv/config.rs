@@ -34,4 +34,8 @@ impl DeserializerConfig for CsvDeserializerConfig {
         Ok(Box::new(CsvDeserializer::new(schema, self.clone())?))
     }
 
+    /// 引号中可以包含换行, 使用csv reader切分
+    fn split_records<'a>(&self, content: &'a [u8]) -> Result<Vec<&'a [u8]>> {
+        CsvDeserializer::new(Schema::new(Vec::new()), self.clone())?.split_records(content)
+    }
 }
\ No newline at end of file
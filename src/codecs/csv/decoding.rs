@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use csv::ReaderBuilder;
+use csv::{ByteRecord, ReaderBuilder};
 use crate::codecs::csv::config::CsvDeserializerConfig;
 use crate::Result;
 use crate::codecs::Deserializer;
@@ -60,14 +60,33 @@ impl CsvDeserializer {
     }
 }
 
-impl Deserializer for CsvDeserializer {
-    fn deserialize(&mut self, bytes: &[u8]) -> Result<&dyn Row> {
-        let mut rdr = ReaderBuilder::new()
-            .has_headers(false)
+impl CsvDeserializer {
+    fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder.has_headers(false)
             .delimiter(self.config.delimiter)
             .quote(self.config.quote).quoting(self.config.quoting)
-            .double_quote(self.config.double_quote).escape(self.config.escape)
-            .from_reader(bytes);
+            .double_quote(self.config.double_quote).escape(self.config.escape);
+        builder
+    }
+
+    /// 按csv记录切分, 引号中的换行不会切分, 跳过空行
+    pub fn split_records<'a>(&self, content: &'a [u8]) -> Result<Vec<&'a [u8]>> {
+        let mut rdr = self.reader_builder().flexible(true).from_reader(content);
+        let mut record = ByteRecord::new();
+        let mut records = Vec::new();
+        while rdr.read_byte_record(&mut record).map_err(|e| e.to_string())? {
+            let start = record.position().map(|p| p.byte() as usize).unwrap_or(0);
+            let end = rdr.position().byte() as usize;
+            records.push(&content[start..end.min(content.len())]);
+        }
+        Ok(records)
+    }
+}
+
+impl Deserializer for CsvDeserializer {
+    fn deserialize(&mut self, bytes: &[u8]) -> Result<&dyn Row> {
+        let mut rdr = self.reader_builder().from_reader(bytes);
         match rdr.records().next() {
             None => Err("not input data".to_string()),
             Some(r) => match r {
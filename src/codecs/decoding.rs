@@ -8,6 +8,11 @@ use crate::types::Schema;
 #[serde(tag = "codec")]
 pub trait DeserializerConfig: DynClone + Debug + Send + Sync {
     fn build(&self, schema: Schema) -> Result<Box<dyn Deserializer>>;
+
+    /// 把文件内容切分为一条条数据, 默认每行一条数据, 跳过空行
+    fn split_records<'a>(&self, content: &'a [u8]) -> Result<Vec<&'a [u8]>> {
+        Ok(content.split(|b| *b == b'\n').map(|line| line.strip_suffix(b"\r").unwrap_or(line)).filter(|line| !line.trim_ascii().is_empty()).collect())
+    }
 }
 dyn_clone::clone_trait_object!(DeserializerConfig);
 
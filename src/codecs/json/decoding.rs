@@ -5,7 +5,7 @@ use std::vec;
 use serde::de::MapAccess;
 use serde::Deserializer as SerdeDeserializer;
 use serde_json::Value as JsonValue;
-use crate::Result;
+use crate::{decimal_utils, Result};
 use crate::data::{GenericRow, Row, Value};
 use crate::codecs::Deserializer;
 use crate::types::{DataType, Field, Fields, Schema};
@@ -136,6 +136,10 @@ fn json_value_to_value(value: JsonValue, data_type: &DataType) -> Result<Value>
                     Ok(Value::Null)
                 }
             },
+            DataType::Decimal(p, s) => match decimal_utils::parse(&v.to_string(), *p, *s) {
+                Some(v) => Ok(Value::Decimal(v, *s)),
+                None => Ok(Value::Null),
+            },
             DataType::String => Ok(Value::String(Arc::new(v.to_string()))),
             _ => Err(format!("Cannot convert json number to {}", data_type)),
         }
@@ -161,6 +165,10 @@ fn json_value_to_value(value: JsonValue, data_type: &DataType) -> Result<Value>
                 Ok(v) => Ok(Value::Boolean(v)),
                 _ => Ok(Value::Null),
             },
+            DataType::Decimal(p, scale) => match decimal_utils::parse(&s, *p, *scale) {
+                Some(v) => Ok(Value::Decimal(v, *scale)),
+                None => Ok(Value::Null),
+            },
             //DataType::Struct(_) => {}
             //DataType::Array(_) => {}
             _ => Err(format!("Cannot convert json number to {}", data_type)),
@@ -190,6 +198,17 @@ fn json_value_to_value(value: JsonValue, data_type: &DataType) -> Result<Value>
                 }
                 Ok(Value::Struct(Arc::new(row)))
             },
+            DataType::Map(kt, vt) => {
+                let mut entries = Vec::with_capacity(map.len());
+                for (name, value) in map.into_iter() {
+                    // key转换失败时丢弃
+                    let key = json_value_to_value(JsonValue::String(name), kt)?;
+                    if !key.is_null() {
+                        entries.push((key, json_value_to_value(value, vt)?));
+                    }
+                }
+                Ok(Value::Map(Arc::new(entries)))
+            },
             DataType::String => match serde_json::to_string(&map) {
                 Ok(s) => Ok(Value::String(Arc::new(s))),
                 Err(_) => Ok(Value::Null),
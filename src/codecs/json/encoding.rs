@@ -87,4 +87,24 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_map_round_trip() {
+        use crate::codecs::Deserializer;
+        use crate::codecs::json::JsonDeserializer;
+        let schema = crate::parser::parse_schema("id bigint, attrs map<string, bigint>").unwrap();
+        let mut serializer = JsonSerializer::new(schema.clone());
+        let mut deserializer = JsonDeserializer::new(schema);
+        let row = GenericRow::new(vec![
+            Value::long(1),
+            Value::Map(Arc::new(vec![(Value::string("a"), Value::long(1)), (Value::string("b"), Value::Null)])),
+        ]);
+        // 默认不输出null value
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        let expected = GenericRow::new(vec![Value::long(1), Value::Map(Arc::new(vec![(Value::string("a"), Value::long(1))]))]);
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_generic_row(), expected);
+        serializer.write_null = true;
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_generic_row(), row);
+    }
 }
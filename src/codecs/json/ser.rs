@@ -1,4 +1,6 @@
 use std::io::Write;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use serde::Serializer;
 use crate::data::{Row, Value};
 use crate::{date_utils, datetime_utils};
@@ -49,6 +51,9 @@ impl serde::ser::Serialize for RowWriter<'_> {
                 DataType::Double => compound.serialize_value(&row.get_double(i))?,
                 DataType::String => compound.serialize_value(row.get_string(i))?,
                 DataType::Boolean => compound.serialize_value(&row.get_boolean(i))?,
+                DataType::Decimal(_, _) => compound.serialize_value(&row.get(i).to_string())?,
+                // 二进制输出为base64字符串
+                DataType::Binary => compound.serialize_value(&STANDARD.encode(row.get_binary_bytes(i)))?,
                 DataType::Date => {
                     let date = date_utils::num_days_to_date(row.get_int(i)).to_string();
                     compound.serialize_value(&date)?
@@ -61,6 +66,9 @@ impl serde::ser::Serialize for RowWriter<'_> {
                 DataType::Array(dt) => {
                     compound.serialize_value(&ArrayWriter::new(row.get_array(i).as_ref(), dt.as_ref(), self.write_null))?;
                 },
+                DataType::Map(_, vt) => {
+                    compound.serialize_value(&MapWriter::new(row.get(i).get_map().as_ref(), vt.as_ref(), self.write_null))?;
+                },
                 _ => return Err(serde::ser::Error::custom(format!("does not support {} type", field.data_type))),
             }
         }
@@ -120,6 +128,11 @@ impl serde::ser::Serialize for ArrayWriter<'_> {
                     compound.serialize_element(&v.get_boolean())?;
                 }
             },
+            DataType::Decimal(_, _) => {
+                for v in array {
+                    compound.serialize_element(&v.to_string())?;
+                }
+            },
             DataType::Struct(fs) => {
                 for v in array {
                     compound.serialize_element(&RowWriter::new(v.get_struct().as_row(), &fs.0, self.write_null))?;
@@ -130,6 +143,11 @@ impl serde::ser::Serialize for ArrayWriter<'_> {
                     compound.serialize_element(&ArrayWriter::new(v.get_array().as_ref(), dt.as_ref(), self.write_null))?;
                 }
             },
+            DataType::Map(_, vt) => {
+                for v in array {
+                    compound.serialize_element(&MapWriter::new(v.get_map().as_ref(), vt.as_ref(), self.write_null))?;
+                }
+            },
             _ => return Err(serde::ser::Error::custom(format!("does not support {} type", self.data_type))),
         }
 
@@ -137,6 +155,57 @@ impl serde::ser::Serialize for ArrayWriter<'_> {
     }
 }
 
+/// map输出为json object, key转为字符串
+struct MapWriter<'a>{
+    map: &'a Vec<(Value, Value)>,
+    data_type: &'a DataType,
+    write_null: bool,
+}
+
+impl <'a> MapWriter<'a>{
+    fn new(map: &'a Vec<(Value, Value)>, data_type: &'a DataType, write_null: bool) -> MapWriter<'a> {
+        MapWriter{map, data_type, write_null}
+    }
+}
+
+impl serde::ser::Serialize for MapWriter<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        use serde::ser::SerializeMap;
+        let mut compound = serializer.serialize_map(Some(self.map.len()))?;
+        for (k, v) in self.map.iter() {
+            if v.is_null() && !self.write_null {
+                continue;
+            }
+            match k {
+                Value::String(s) => compound.serialize_key(s.as_str())?,
+                _ => compound.serialize_key(&k.to_string())?,
+            }
+            if v.is_null() {
+                compound.serialize_value(&Option::<()>::None)?;
+                continue;
+            }
+            match self.data_type {
+                DataType::Int => compound.serialize_value(&v.get_int())?,
+                DataType::Long => compound.serialize_value(&v.get_long())?,
+                DataType::Float => compound.serialize_value(&v.get_float())?,
+                DataType::Double => compound.serialize_value(&v.get_double())?,
+                DataType::String => compound.serialize_value(v.get_string())?,
+                DataType::Boolean => compound.serialize_value(&v.get_boolean())?,
+                DataType::Decimal(_, _) => compound.serialize_value(&v.to_string())?,
+                DataType::Struct(fs) => compound.serialize_value(&RowWriter::new(v.get_struct().as_row(), &fs.0, self.write_null))?,
+                DataType::Array(dt) => compound.serialize_value(&ArrayWriter::new(v.get_array().as_ref(), dt.as_ref(), self.write_null))?,
+                DataType::Map(_, vt) => compound.serialize_value(&MapWriter::new(v.get_map().as_ref(), vt.as_ref(), self.write_null))?,
+                _ => return Err(serde::ser::Error::custom(format!("does not support {} type", self.data_type))),
+            }
+        }
+
+        compound.end()
+    }
+}
+
 fn write_struct<T: Write>(serializer: &mut serde_json::Serializer<T>, row: &dyn Row, fields: &Vec<Field>) -> crate::Result<()> {
     use serde::ser::SerializeMap;
     if row.len() != fields.len() {
@@ -156,6 +225,7 @@ fn write_struct<T: Write>(serializer: &mut serde_json::Serializer<T>, row: &dyn
             DataType::Double => tri!(compound.serialize_value(&row.get_double(i))),
             DataType::String => tri!(compound.serialize_value(row.get_string(i))),
             DataType::Boolean => tri!(compound.serialize_value(&row.get_boolean(i))),
+            DataType::Decimal(_, _) => tri!(compound.serialize_value(&row.get(i).to_string())),
             DataType::Struct(fs) => {
                 tri!(compound.serialize_value(&RowWriter::new(row.get_struct(i).as_row(), &fs.0, false)));
                 //write_struct(serializer, row.get_struct(i).as_ref(), &fs.0)?;
@@ -164,6 +234,9 @@ fn write_struct<T: Write>(serializer: &mut serde_json::Serializer<T>, row: &dyn
                 tri!(compound.serialize_value(&ArrayWriter::new(row.get_array(i).as_ref(), dt.as_ref(), false)));
                 //write_array(serializer, row.get_array(i).as_ref(), dt)?;
             },
+            DataType::Map(_, vt) => {
+                tri!(compound.serialize_value(&MapWriter::new(row.get(i).get_map().as_ref(), vt.as_ref(), false)));
+            },
             _ => return Err(format!("does not support {} type", field.data_type)),
         }
     }
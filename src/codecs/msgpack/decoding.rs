@@ -50,6 +50,7 @@ fn create_reader(data_type: DataType) -> Result<Box<dyn ValueReader>> {
         DataType::Boolean => Ok(Box::new(BooleanReader)),
         DataType::Struct(fields) => Ok(Box::new(StructReader::new(fields.0)?)),
         DataType::Array(element_type) => Ok(Box::new(ArrayReader::new(*element_type)?)),
+        DataType::Map(key_type, value_type) => Ok(Box::new(MapReader::new(*key_type, *value_type)?)),
         t => Err(format!("unsupported data type: {:?}", t))
     }
 }
@@ -174,6 +175,70 @@ impl ValueReader for ArrayReader {
     }
 }
 
+/// 重复的key保留最后一个value, 位置为key第一次出现的位置
+#[derive(Debug)]
+struct MapReader {
+    key_reader: Box<dyn ValueReader>,
+    value_reader: Box<dyn ValueReader>,
+    key_indices: HashMap<Value, usize>,
+}
+
+impl MapReader {
+    fn new(key_type: DataType, value_type: DataType) -> Result<Self> {
+        let key_reader = create_reader(key_type)?;
+        let value_reader = create_reader(value_type)?;
+        Ok(Self{key_reader, value_reader, key_indices: HashMap::new()})
+    }
+
+    fn put_entry(&mut self, entries: &mut Vec<(Value, Value)>, key: Value, value: Value) {
+        match self.key_indices.get(&key) {
+            Some(i) => entries[*i].1 = value,
+            None => {
+                self.key_indices.insert(key.clone(), entries.len());
+                entries.push((key, value));
+            },
+        }
+    }
+}
+
+impl ValueReader for MapReader {
+    fn read(&mut self, rd: &mut Cursor<&[u8]>) -> DecodeResult {
+        let len = match decode::read_marker(rd)? {
+            Marker::FixMap(len) => len as usize,
+            Marker::Map16 => {
+                let len = rd.read_data_u16()?;
+                len as usize
+            },
+            Marker::Map32 => {
+                let len = rd.read_data_u32()?;
+                len as usize
+            },
+            other_marker => return Err(create_decode_error(format!("marker can not convert to map: {:?}", other_marker))),
+        };
+        let mut entries = Vec::with_capacity(min(len, PREALLOC_MAX / 64));
+        self.key_indices.clear();
+        for _ in 0..len {
+            let mark = (*rd.get_ref())[rd.position() as usize];
+            // Null key, 跳过这个entry
+            if mark == 0xc0 {
+                rd.set_position(rd.position() + 1);
+                skip_value(rd, 1)?;
+                continue;
+            }
+            let key = self.key_reader.read(rd)?;
+            let mark = (*rd.get_ref())[rd.position() as usize];
+            if mark == 0xc0 {
+                rd.set_position(rd.position() + 1);
+                self.put_entry(&mut entries, key, Value::Null);
+                continue;
+            }
+            let value = self.value_reader.read(rd)?;
+            self.put_entry(&mut entries, key, value);
+        }
+        Ok(Value::Map(Arc::new(entries)))
+    }
+}
+
 #[derive(Debug)]
 struct IntReader;
 
@@ -56,6 +56,7 @@ fn create_writer(data_type: DataType, write_null: bool, timestamp_type: Timestam
         DataType::Timestamp => Ok(Box::new(TimestampWriter{timestamp_type})),
         DataType::Struct(fields) => Ok(Box::new(StructWriter::new(fields.0, write_null, timestamp_type)?)),
         DataType::Array(array) => Ok(Box::new(ArrayWriter::new(*array, write_null, timestamp_type)?)),
+        DataType::Map(key_type, value_type) => Ok(Box::new(MapWriter::new(*key_type, *value_type, write_null, timestamp_type)?)),
         t => Err(format!("unsupported type: {:?}", t))
     }
 }
@@ -201,6 +202,39 @@ impl ValueWriter for ArrayWriter {
     }
 }
 
+#[derive(Debug)]
+struct MapWriter {
+    key_writer: Box<dyn ValueWriter>,
+    value_writer: Box<dyn ValueWriter>,
+}
+
+impl MapWriter {
+    fn new(key_type: DataType, value_type: DataType, write_null: bool, timestamp_type: TimestampType) -> Result<Self> {
+        let key_writer = create_writer(key_type, write_null, timestamp_type.clone())?;
+        let value_writer = create_writer(value_type, write_null, timestamp_type)?;
+        Ok(MapWriter { key_writer, value_writer })
+    }
+}
+
+impl ValueWriter for MapWriter {
+    fn write(&mut self, value: &Value, buf: &mut Vec<u8>) -> EncodeResult {
+        if let Value::Map(entries) = value {
+            encode::write_map_len(buf, entries.len() as u32)?;
+            for (k, v) in entries.iter() {
+                self.key_writer.write(k, buf)?;
+                if v.is_null() {
+                    encode::write_nil(buf).map_err(ValueWriteError::InvalidMarkerWrite)?;
+                } else {
+                    self.value_writer.write(v, buf)?;
+                }
+            }
+            Ok(())
+        } else {
+            Err(create_encode_error(format!("not map type: {:?}", value)))
+        }
+    }
+}
+
 #[derive(Debug)]
 struct IntWriter;
 
@@ -404,4 +438,36 @@ mod tests {
         let rst = deserializer.deserialize(&bytes).unwrap();
         println!("{}", rst);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_map_round_trip() {
+        let schema = crate::parser::parse_schema("id bigint, attrs map<string, bigint>").unwrap();
+        let mut serializer = MessagePackSerializer::new(schema.clone(), false, TimestampType::Millis).unwrap();
+        let mut deserializer = MessagePackDeserializer::new(schema, TimestampType::Millis).unwrap();
+        let row = GenericRow::new(vec![
+            Value::long(1),
+            Value::Map(Arc::new(vec![(Value::string("a"), Value::long(1)), (Value::string("b"), Value::Null)])),
+        ]);
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_generic_row(), row);
+    }
+
+    #[test]
+    fn test_map_duplicate_keys() {
+        let schema = crate::parser::parse_schema("id bigint, attrs map<string, bigint>").unwrap();
+        let mut deserializer = MessagePackDeserializer::new(schema, TimestampType::Millis).unwrap();
+        let mut bytes = Vec::new();
+        rmp::encode::write_map_len(&mut bytes, 2).unwrap();
+        rmp::encode::write_str(&mut bytes, "id").unwrap();
+        rmp::encode::write_sint(&mut bytes, 1).unwrap();
+        rmp::encode::write_str(&mut bytes, "attrs").unwrap();
+        rmp::encode::write_map_len(&mut bytes, 3).unwrap();
+        for (k, v) in [("a", 1), ("b", 2), ("a", 3)] {
+            rmp::encode::write_str(&mut bytes, k).unwrap();
+            rmp::encode::write_sint(&mut bytes, v).unwrap();
+        }
+        let row = deserializer.deserialize(&bytes).unwrap().to_generic_row();
+        // 重复的key保留最后一个value
+        assert_eq!(row.get(1), &Value::Map(Arc::new(vec![(Value::string("a"), Value::long(3)), (Value::string("b"), Value::long(2))])));
+    }
+}
@@ -54,7 +54,10 @@ fn create_converter(data_type: &DataType, fd: &FieldDescriptor)  -> Result<Box<d
 fn create_ele_converter(data_type: &DataType, fd: &FieldDescriptor)  -> Result<Box<dyn ValueConverter>> {
     let kind = &fd.kind();
     if fd.is_map() {
-        return Err(format!("not support map field: {:?}", fd));
+        return match data_type {
+            DataType::Map(key_type, value_type) => Ok(Box::new(MapToMapConverter::new(key_type, value_type, fd)?)),
+            _ => Err(format!("map can not convert to type: {:?}", data_type)),
+        }
     }
     let converter: Box<dyn ValueConverter> = match kind {
         Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => match data_type {
@@ -335,3 +338,35 @@ impl ValueConverter for ListToArrayConverter {
         }
     }
 }
+
+/// map字段为key, value的entry message
+struct MapToMapConverter {
+    key_converter: Box<dyn ValueConverter>,
+    value_converter: Box<dyn ValueConverter>,
+}
+
+impl MapToMapConverter {
+    fn new(key_type: &DataType, value_type: &DataType, fd: &FieldDescriptor) -> Result<Self> {
+        let Kind::Message(entry) = fd.kind() else {
+            return Err(format!("invalid map field: {:?}", fd));
+        };
+        let key_converter = create_ele_converter(key_type, &entry.map_entry_key_field())?;
+        let value_converter = create_ele_converter(value_type, &entry.map_entry_value_field())?;
+        Ok(Self{key_converter, value_converter})
+    }
+}
+
+impl ValueConverter for MapToMapConverter {
+    fn convert(&self, value: PValue) -> Result<Value> {
+        match value {
+            PValue::Map(map) => {
+                let mut entries = Vec::with_capacity(map.len());
+                for (k, v) in map {
+                    entries.push((self.key_converter.convert(PValue::from(k))?, self.value_converter.convert(v)?));
+                }
+                Ok(Value::Map(Arc::new(entries)))
+            }
+            v => Err(format!("invalid value for MapToMapConverter: {:?}", v)),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use bytes::Bytes;
 use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, Value as PValue};
@@ -55,7 +56,10 @@ fn create_converter(data_type: &DataType, fd: &FieldDescriptor)  -> Result<Box<d
 fn create_ele_converter(data_type: &DataType, fd: &FieldDescriptor)  -> Result<Box<dyn ValueConverter>> {
     let kind = &fd.kind();
     if fd.is_map() {
-        return Err(format!("not support map field: {:?}", fd));
+        return match data_type {
+            DataType::Map(key_type, value_type) => Ok(Box::new(MapToMapConverter::new(key_type, value_type, fd)?)),
+            _ => Err(format!("type: {:?} can not convert to map field: {:?}", data_type, fd)),
+        }
     }
     let converter: Box<dyn ValueConverter> = match kind {
         Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => match data_type {
@@ -350,6 +354,51 @@ impl ArrayToListConverter {
     }
  }
 
+/// map字段为key, value的entry message, null的value不输出
+struct MapToMapConverter {
+    key_converter: Box<dyn ValueConverter>,
+    value_converter: Box<dyn ValueConverter>,
+}
+
+impl MapToMapConverter {
+    fn new(key_type: &DataType, value_type: &DataType, fd: &FieldDescriptor) -> Result<Self> {
+        let Kind::Message(entry) = fd.kind() else {
+            return Err(format!("invalid map field: {:?}", fd));
+        };
+        let key_converter = create_ele_converter(key_type, &entry.map_entry_key_field())?;
+        let value_converter = create_ele_converter(value_type, &entry.map_entry_value_field())?;
+        Ok(Self{key_converter, value_converter})
+    }
+}
+
+impl ValueConverter for MapToMapConverter {
+    fn convert(&self, value: &Value) -> ConverterResult {
+        match value {
+            Value::Null => ConverterResult::Null,
+            Value::Map(entries) => {
+                let mut map = HashMap::with_capacity(entries.len());
+                for (k, v) in entries.iter() {
+                    let key = match self.key_converter.convert(k) {
+                        ConverterResult::Value(key) => match key.into_map_key() {
+                            Some(key) => key,
+                            None => return ConverterResult::Err(format!("invalid map key: {:?}", k)),
+                        },
+                        ConverterResult::Null => return ConverterResult::Err("map key can not is null".into()),
+                        ConverterResult::Err(e) => return ConverterResult::Err(e),
+                    };
+                    match self.value_converter.convert(v) {
+                        ConverterResult::Value(v) => map.insert(key, v),
+                        ConverterResult::Null => continue,
+                        ConverterResult::Err(e) => return ConverterResult::Err(e),
+                    };
+                }
+                ConverterResult::Value(PValue::Map(map))
+            },
+            _ => ConverterResult::Err(format!("invalid value for MapToMapConverter: {:?}", value)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -473,4 +522,55 @@ mod tests {
         println!("{}", rst);
     }
 
+    #[test]
+    fn test_map_round_trip() {
+        use prost_reflect::DescriptorPool;
+        use prost_reflect::prost_types::{DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet, MessageOptions};
+        use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+        let field = |name: &str, number: i32, label: Label, tp: Type, type_name: Option<&str>| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type: Some(tp as i32),
+            type_name: type_name.map(|s| s.to_string()),
+            ..Default::default()
+        };
+        let entry = DescriptorProto {
+            name: Some("AttrsEntry".to_string()),
+            field: vec![
+                field("key", 1, Label::Optional, Type::String, None),
+                field("value", 2, Label::Optional, Type::Int64, None),
+            ],
+            options: Some(MessageOptions { map_entry: Some(true), ..Default::default() }),
+            ..Default::default()
+        };
+        let message = DescriptorProto {
+            name: Some("Msg".to_string()),
+            field: vec![
+                field("id", 1, Label::Optional, Type::Int64, None),
+                field("attrs", 2, Label::Repeated, Type::Message, Some(".pkg.Msg.AttrsEntry")),
+            ],
+            nested_type: vec![entry],
+            ..Default::default()
+        };
+        let file = FileDescriptorProto {
+            name: Some("map.proto".to_string()),
+            package: Some("pkg".to_string()),
+            message_type: vec![message],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+        let pool = DescriptorPool::from_file_descriptor_set(FileDescriptorSet { file: vec![file] }).unwrap();
+        let descriptor = pool.get_message_by_name("pkg.Msg").unwrap();
+
+        let schema = crate::parser::parse_schema("id bigint, attrs map<string, bigint>").unwrap();
+        let mut serializer = ProtobufSerializer::new(schema.clone(), descriptor.clone()).unwrap();
+        let mut deserializer = ProtobufDeserializer::new(schema, descriptor).unwrap();
+        let row = GenericRow::new(vec![
+            Value::long(1),
+            Value::Map(Arc::new(vec![(Value::string("a"), Value::long(1)), (Value::string("b"), Value::long(2))])),
+        ]);
+        let bytes = serializer.serialize(&row).unwrap().to_vec();
+        assert_eq!(deserializer.deserialize(&bytes).unwrap().to_generic_row(), row);
+    }
 }
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
@@ -36,6 +37,21 @@ pub fn current_timestamp_millis() -> u64 {
     SystemTime::now().duration_since(UNIX_EPOCH).expect("system time before Unix epoch").as_millis() as u64
 }
 
+thread_local! {
+    static MOCK_PROCESSING_TIME_MILLIS: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// 算子注册timer等使用的处理时间, 当前线程设置了mock时间(retl test)时返回mock时间
+#[inline]
+pub fn processing_time_millis() -> u64 {
+    MOCK_PROCESSING_TIME_MILLIS.with(|time| time.get()).unwrap_or_else(current_timestamp_millis)
+}
+
+/// 设置当前线程的mock处理时间, None时恢复使用系统时间
+pub fn set_mock_processing_time_millis(time: Option<u64>) {
+    MOCK_PROCESSING_TIME_MILLIS.with(|t| t.set(time));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
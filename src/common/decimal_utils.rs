@@ -0,0 +1,352 @@
+use std::cmp::Ordering;
+
+/// decimal的最大精度, 非标度值使用i128保存
+pub const MAX_PRECISION: u8 = 38;
+/// 未指定精度时decimal的默认精度和标度
+pub const DEFAULT_PRECISION: u8 = 10;
+pub const DEFAULT_SCALE: u8 = 0;
+
+const POWERS_OF_TEN: [i128; 39] = {
+    let mut powers = [1i128; 39];
+    let mut i = 1;
+    while i < 39 {
+        powers[i] = powers[i - 1] * 10;
+        i += 1;
+    }
+    powers
+};
+
+#[inline]
+pub fn pow10(n: u8) -> i128 {
+    POWERS_OF_TEN[n as usize]
+}
+
+/// 非标度值的位数是否不超过precision
+#[inline]
+pub fn fits_precision(v: i128, precision: u8) -> bool {
+    v.unsigned_abs() < pow10(precision) as u128
+}
+
+/// 调整标度, 降低标度时四舍五入, 溢出返回None
+pub fn rescale(v: i128, from_scale: u8, to_scale: u8) -> Option<i128> {
+    if to_scale >= from_scale {
+        v.checked_mul(pow10(to_scale - from_scale))
+    } else {
+        let p = pow10(from_scale - to_scale);
+        let (q, r) = (v / p, v % p);
+        if r.unsigned_abs() * 2 >= p as u128 {
+            Some(q + v.signum())
+        } else {
+            Some(q)
+        }
+    }
+}
+
+/// 调整到decimal(precision, scale), 超出精度返回None
+pub fn change_precision(v: i128, from_scale: u8, precision: u8, scale: u8) -> Option<i128> {
+    rescale(v, from_scale, scale).filter(|v| fits_precision(*v, precision))
+}
+
+/// 解析十进制字符串, 支持科学计数法, 超出的小数位四舍五入, 超出精度返回None
+pub fn parse(s: &str, precision: u8, scale: u8) -> Option<i128> {
+    let s = s.trim();
+    let (negative, s) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int_part, frac_part) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    if !int_part.bytes().chain(frac_part.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // 数值为digits * 10^shift / 10^scale
+    let digits: String = int_part.chars().chain(frac_part.chars()).skip_while(|c| *c == '0').collect();
+    let shift = exponent.checked_sub(frac_part.len() as i64)?.checked_add(scale as i64)?;
+    let unscaled = if digits.is_empty() {
+        0
+    } else if shift >= 0 {
+        if shift > MAX_PRECISION as i64 || digits.len() > MAX_PRECISION as usize {
+            return None;
+        }
+        digits.parse::<i128>().ok()?.checked_mul(pow10(shift as u8))?
+    } else {
+        let drop = shift.unsigned_abs() as usize;
+        if drop > digits.len() {
+            0
+        } else {
+            let keep = &digits[..digits.len() - drop];
+            if keep.len() > MAX_PRECISION as usize {
+                return None;
+            }
+            let v = if keep.is_empty() { 0 } else { keep.parse::<i128>().ok()? };
+            if digits.as_bytes()[digits.len() - drop] >= b'5' { v + 1 } else { v }
+        }
+    };
+    let unscaled = if negative { -unscaled } else { unscaled };
+    if fits_precision(unscaled, precision) {
+        Some(unscaled)
+    } else {
+        None
+    }
+}
+
+pub fn format(v: i128, scale: u8) -> String {
+    let digits = v.unsigned_abs().to_string();
+    let sign = if v < 0 { "-" } else { "" };
+    if scale == 0 {
+        return format!("{sign}{digits}");
+    }
+    let scale = scale as usize;
+    let digits = if digits.len() <= scale { format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits) } else { digits };
+    let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+    format!("{sign}{int_part}.{frac_part}")
+}
+
+pub fn to_f64(v: i128, scale: u8) -> f64 {
+    format(v, scale).parse().unwrap()
+}
+
+/// 按double的最短十进制表示转换, 非有限值和超出精度返回None
+pub fn from_f64(v: f64, precision: u8, scale: u8) -> Option<i128> {
+    if v.is_finite() {
+        parse(&v.to_string(), precision, scale)
+    } else {
+        None
+    }
+}
+
+pub fn from_i64(v: i64, precision: u8, scale: u8) -> Option<i128> {
+    change_precision(v as i128, 0, precision, scale)
+}
+
+/// 截断小数部分, 超出i64范围返回None
+pub fn to_i64(v: i128, scale: u8) -> Option<i64> {
+    i64::try_from(v / pow10(scale)).ok()
+}
+
+/// 去掉小数末尾的0, 数值相等的decimal结果相同
+pub fn normalize(mut v: i128, mut scale: u8) -> (i128, u8) {
+    if v == 0 {
+        return (0, 0);
+    }
+    while scale > 0 && v % 10 == 0 {
+        v /= 10;
+        scale -= 1;
+    }
+    (v, scale)
+}
+
+/// 大端补码, 最少字节数
+pub fn to_signed_bytes_be(v: i128) -> Vec<u8> {
+    let bytes = v.to_be_bytes();
+    let sign = if v < 0 { 0xFF } else { 0 };
+    let mut start = 0;
+    while start < bytes.len() - 1 && bytes[start] == sign && (bytes[start + 1] & 0x80) == (sign & 0x80) {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+/// 大端补码, 超过16字节返回None
+pub fn from_signed_bytes_be(bytes: &[u8]) -> Option<i128> {
+    if bytes.len() > 16 {
+        return None;
+    }
+    let sign = if bytes.first().is_some_and(|b| b & 0x80 != 0) { 0xFF } else { 0 };
+    let mut buf = [sign; 16];
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buf))
+}
+
+pub fn compare(v1: i128, s1: u8, v2: i128, s2: u8) -> Ordering {
+    match s1.cmp(&s2) {
+        Ordering::Equal => v1.cmp(&v2),
+        // 放大溢出时溢出的一方绝对值更大
+        Ordering::Less => match rescale(v1, s1, s2) {
+            Some(v1) => v1.cmp(&v2),
+            None => v1.cmp(&0),
+        },
+        Ordering::Greater => match rescale(v2, s2, s1) {
+            Some(v2) => v1.cmp(&v2),
+            None => 0.cmp(&v2),
+        },
+    }
+}
+
+pub fn add(v1: i128, s1: u8, v2: i128, s2: u8, precision: u8, scale: u8) -> Option<i128> {
+    let scale1 = s1.max(s2);
+    let sum = rescale(v1, s1, scale1)?.checked_add(rescale(v2, s2, scale1)?)?;
+    change_precision(sum, scale1, precision, scale)
+}
+
+pub fn subtract(v1: i128, s1: u8, v2: i128, s2: u8, precision: u8, scale: u8) -> Option<i128> {
+    add(v1, s1, v2.checked_neg()?, s2, precision, scale)
+}
+
+pub fn multiply(v1: i128, s1: u8, v2: i128, s2: u8, precision: u8, scale: u8) -> Option<i128> {
+    let product_scale = s1 + s2;
+    let Some(product) = v1.checked_mul(v2) else {
+        return multiply_wide(v1, v2, product_scale, precision, scale);
+    };
+    if product_scale > MAX_PRECISION {
+        let product = rescale(product, product_scale, MAX_PRECISION)?;
+        change_precision(product, MAX_PRECISION, precision, scale)
+    } else {
+        change_precision(product, product_scale, precision, scale)
+    }
+}
+
+/// 乘积超出i128时使用256位的中间结果, 降低到结果标度后再检查精度
+fn multiply_wide(v1: i128, v2: i128, product_scale: u8, precision: u8, scale: u8) -> Option<i128> {
+    if product_scale <= scale {
+        return None;
+    }
+    let mut product = mul_wide(v1.unsigned_abs(), v2.unsigned_abs());
+    let mut drop = product_scale - scale;
+    // 分两次除时只用最后一次的余数四舍五入, 10^38是偶数所以结果不变
+    if drop > MAX_PRECISION {
+        product = div_rem_wide(product, pow10(drop - MAX_PRECISION) as u128).0;
+        drop = MAX_PRECISION;
+    }
+    let divisor = pow10(drop) as u128;
+    let ((hi, lo), r) = div_rem_wide(product, divisor);
+    if hi != 0 {
+        return None;
+    }
+    let q = i128::try_from(lo).ok()?;
+    let q = if r * 2 >= divisor { q.checked_add(1)? } else { q };
+    let q = if (v1 < 0) != (v2 < 0) { -q } else { q };
+    Some(q).filter(|q| fits_precision(*q, precision))
+}
+
+/// 128位无符号数相乘, 返回(高128位, 低128位)
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & MASK, b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let mid1 = a_hi * b_lo;
+    let mid2 = a_lo * b_hi;
+    let (mid, mid_carry) = mid1.overflowing_add(mid2);
+    let (lo, lo_carry) = lo_lo.overflowing_add(mid << 64);
+    let hi = a_hi * b_hi + (mid >> 64) + ((mid_carry as u128) << 64) + lo_carry as u128;
+    (hi, lo)
+}
+
+/// 256位数除以不超过10^38的除数, 按位长除法, 返回(商, 余数)
+fn div_rem_wide((hi, lo): (u128, u128), divisor: u128) -> ((u128, u128), u128) {
+    let (mut q_hi, mut q_lo, mut r) = (0u128, 0u128, 0u128);
+    for i in (0..256).rev() {
+        let bit = if i >= 128 { (hi >> (i - 128)) & 1 } else { (lo >> i) & 1 };
+        // divisor < 2^127, 左移不会溢出
+        r = (r << 1) | bit;
+        if r >= divisor {
+            r -= divisor;
+            if i >= 128 { q_hi |= 1 << (i - 128) } else { q_lo |= 1 << i }
+        }
+    }
+    ((q_hi, q_lo), r)
+}
+
+/// 结果标度为scale, 四舍五入, 除数为0时返回None
+pub fn divide(v1: i128, s1: u8, v2: i128, s2: u8, precision: u8, scale: u8) -> Option<i128> {
+    if v2 == 0 {
+        return None;
+    }
+    // v1 / 10^s1 / (v2 / 10^s2) * 10^scale
+    let shift = scale as i32 + s2 as i32 - s1 as i32;
+    let (dividend, divisor) = if shift >= 0 {
+        (v1.checked_mul(pow10(shift as u8))?, v2)
+    } else {
+        (v1, v2.checked_mul(pow10((-shift) as u8))?)
+    };
+    let (q, r) = (dividend / divisor, dividend % divisor);
+    let q = if r.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+        q + dividend.signum() * divisor.signum()
+    } else {
+        q
+    };
+    Some(q).filter(|q| fits_precision(*q, precision))
+}
+
+pub fn remainder(v1: i128, s1: u8, v2: i128, s2: u8, precision: u8, scale: u8) -> Option<i128> {
+    let scale1 = s1.max(s2);
+    let divisor = rescale(v2, s2, scale1)?;
+    if divisor == 0 {
+        return None;
+    }
+    change_precision(rescale(v1, s1, scale1)? % divisor, scale1, precision, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        let cases = [
+            ("123.456", 10, 2, Some("123.46")),
+            ("-123.454", 10, 2, Some("-123.45")),
+            ("-0.005", 10, 2, Some("-0.01")),
+            ("1.5e3", 10, 2, Some("1500.00")),
+            ("12345e-5", 10, 3, Some("0.123")),
+            (".5", 10, 0, Some("1")),
+            ("007", 3, 1, Some("7.0")),
+            ("1000", 3, 0, None),
+            ("1.2.3", 10, 2, None),
+            ("abc", 10, 2, None),
+            ("", 10, 2, None),
+            ("1e9223372036854775807", 10, 2, None),
+            ("1e-9223372036854775808", 10, 2, Some("0.00")),
+            ("0.1e-9223372036854775808", 10, 2, None),
+            ("0.5e-9223372036854775807", 10, 0, Some("0")),
+            ("1.5e-9223372036854775800", 10, 2, Some("0.00")),
+        ];
+        for (s, precision, scale, expected) in cases {
+            let v = parse(s, precision, scale);
+            assert_eq!(v.map(|v| format(v, scale)).as_deref(), expected, "{}", s);
+        }
+        assert_eq!(format(i128::MAX, 38), "1.70141183460469231731687303715884105727");
+        assert_eq!(from_f64(0.1 + 0.2, 10, 2), Some(30));
+        assert_eq!(to_f64(-1234, 3), -1.234);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        // 1.10 + 2.005 = 3.105
+        assert_eq!(add(110, 2, 2005, 3, 10, 3), Some(3105));
+        assert_eq!(subtract(110, 2, 2005, 3, 10, 3), Some(-905));
+        // 1.10 * 2.005 = 2.20550
+        assert_eq!(multiply(110, 2, 2005, 3, 10, 5), Some(220550));
+        // 1 / 3 = 0.333333
+        // decimal(38,18)的100 * 100 = 10000, 乘积的非标度值超出i128
+        let hundred = 100 * pow10(18);
+        assert_eq!(multiply(hundred, 18, hundred, 18, 38, 6), Some(10000 * pow10(6)));
+        assert_eq!(multiply(-hundred, 18, hundred, 18, 38, 6), Some(-10000 * pow10(6)));
+        // 1.5 * 10^-19 * 1 = 0.0000000000000000002 (标度19四舍五入)
+        assert_eq!(multiply(15 * pow10(17), 37, pow10(37), 37, 38, 19), Some(2));
+        assert_eq!(multiply(i128::MAX, 0, i128::MAX, 0, 38, 0), None);
+        assert_eq!(multiply(pow10(37), 18, pow10(37), 18, 38, 6), None);
+        assert_eq!(divide(1, 0, 3, 0, 10, 6), Some(333333));
+        assert_eq!(divide(-2, 0, 3, 0, 10, 6), Some(-666667));
+        assert_eq!(divide(1, 0, 0, 0, 10, 6), None);
+        assert_eq!(remainder(1000, 2, 3, 0, 10, 2), Some(100));
+        assert_eq!(add(99, 0, 1, 0, 2, 0), None);
+        assert_eq!(compare(100, 2, 1, 0), Ordering::Equal);
+        assert_eq!(compare(i128::MAX, 0, 1, 38), Ordering::Greater);
+        assert_eq!(normalize(1200, 3), (12, 1));
+        for v in [0, 1, -1, 127, 128, -128, -129, 25500, i128::MAX, i128::MIN] {
+            assert_eq!(from_signed_bytes_be(&to_signed_bytes_be(v)), Some(v));
+        }
+        assert_eq!(to_signed_bytes_be(-129), vec![0xFF, 0x7F]);
+    }
+}
@@ -1,9 +1,11 @@
 pub mod date_utils;
 pub mod datetime_utils;
+pub mod decimal_utils;
 pub mod buffer_pool;
 pub mod encrypt;
 pub mod rate_stat;
 pub mod buffer_block;
+pub mod rate_limiter;
 
 use std::cell::Cell;
 use std::fmt::{Debug, Formatter};
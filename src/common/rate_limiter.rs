@@ -0,0 +1,63 @@
+use std::thread::sleep;
+use std::time::Duration;
+use crate::datetime_utils::current_timestamp_millis;
+
+/// source限速时单次等待的最长时间
+pub const RATE_LIMIT_MAX_WAIT_MS: u64 = 200;
+
+/// 按自然秒限制速率, 每秒最多permits_per_second个许可, source的rows_per_second和throttle使用
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    permits_per_second: u64,
+    permits: u64,
+    second_start_ms: u64,
+}
+
+impl RateLimiter {
+    pub fn new(permits_per_second: u64) -> Self {
+        Self { permits_per_second, permits: 0, second_start_ms: 0 }
+    }
+
+    /// now所在秒的许可没有用完时返回true
+    pub fn try_acquire_at(&mut self, now: u64) -> bool {
+        if now >= self.second_start_ms + 1000 {
+            self.second_start_ms = now / 1000 * 1000;
+            self.permits = 0;
+        }
+        if self.permits < self.permits_per_second {
+            self.permits += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 当前秒的许可用完时最多等待max_wait_ms, 返回false时调用方检查停止信号后再重试
+    pub fn acquire_timeout(&mut self, max_wait_ms: u64) -> bool {
+        let now = current_timestamp_millis();
+        if self.try_acquire_at(now) {
+            return true;
+        }
+        sleep(Duration::from_millis((self.second_start_ms + 1000 - now).min(max_wait_ms)));
+        self.try_acquire_at(current_timestamp_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2);
+        assert!(limiter.try_acquire_at(1100));
+        assert!(limiter.try_acquire_at(1500));
+        assert!(!limiter.try_acquire_at(1999));
+        assert!(limiter.try_acquire_at(2000));
+        assert!(limiter.try_acquire_at(5300));
+        assert!(limiter.try_acquire_at(5400));
+        assert!(!limiter.try_acquire_at(5400));
+        assert!(!RateLimiter::new(0).try_acquire_at(1000));
+        assert!(!RateLimiter::new(0).acquire_timeout(10));
+    }
+}
@@ -0,0 +1,213 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::Arc;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use crate::config::BaseIOMetrics;
+use crate::Result;
+use crate::connector::Sink;
+use crate::data::{GenericRow, Row, Value};
+use crate::datetime_utils::current_timestamp_millis;
+use crate::types::{DataType, Field, Schema};
+
+/// 数据解码/计算/写入出错时的处理策略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnErrorConfig {
+    /// 返回错误, 任务退出
+    #[default]
+    Fail,
+    /// 丢弃出错的数据
+    Skip,
+    /// 发送到sinks中指定名称的sink, 数据schema为ErrorHandler::dead_letter_schema
+    DeadLetter(String),
+}
+
+/// 同一subtask内多个算子共享的dead letter sink
+pub type SharedDeadLetterSink = Rc<RefCell<DeadLetterSink>>;
+
+/// 所有引用的ErrorHandler关闭后才关闭sink, 同一checkpoint没有新数据写入时不再重复传递barrier
+pub struct DeadLetterSink {
+    sink: Box<dyn Sink>,
+    opened: bool,
+    row: GenericRow,
+    refs: usize,
+    checkpoint_id: Option<u64>,
+    dirty: bool,
+}
+
+impl DeadLetterSink {
+    pub fn new_shared(sink: Box<dyn Sink>) -> SharedDeadLetterSink {
+        Rc::new(RefCell::new(Self { sink, opened: false, row: GenericRow::new_with_size(4), refs: 0, checkpoint_id: None, dirty: false }))
+    }
+}
+
+/// 每个算子按on_error配置处理出错的数据, dead letter sink在第一次使用时打开
+pub struct ErrorHandler {
+    node_id: u16,
+    on_error: OnErrorConfig,
+    base_iometrics: Arc<BaseIOMetrics>,
+    dead_letter: Option<SharedDeadLetterSink>,
+    closed: Cell<bool>,
+}
+
+impl ErrorHandler {
+    pub fn new(node_id: u16, on_error: OnErrorConfig, base_iometrics: Arc<BaseIOMetrics>, dead_letter: Option<SharedDeadLetterSink>) -> Self {
+        if let Some(dead_letter) = &dead_letter {
+            dead_letter.borrow_mut().refs += 1;
+        }
+        Self { node_id, on_error, base_iometrics, dead_letter, closed: Cell::new(false) }
+    }
+
+    pub fn fail(node_id: u16, base_iometrics: Arc<BaseIOMetrics>) -> Self {
+        Self::new(node_id, OnErrorConfig::Fail, base_iometrics, None)
+    }
+
+    /// payload为原始字节, 保留avro/protobuf等二进制数据
+    pub fn dead_letter_schema() -> Schema {
+        Schema::new(vec![
+            Field::new("node_id", DataType::Int),
+            Field::new("error", DataType::String),
+            Field::new("payload", DataType::Binary),
+            Field::new("timestamp", DataType::Timestamp),
+        ])
+    }
+
+    /// fail策略返回原错误, 其它策略记录后返回Ok
+    pub fn handle(&self, error: String, payload: &[u8]) -> Result<()> {
+        match &self.on_error {
+            OnErrorConfig::Fail => Err(error),
+            OnErrorConfig::Skip => {
+                debug!("node {} skip error record: {}", self.node_id, error);
+                self.base_iometrics.num_records_skipped_inc_by(1);
+                Ok(())
+            },
+            OnErrorConfig::DeadLetter(name) => {
+                let mut dead_letter = self.dead_letter.as_ref().ok_or_else(|| format!("dead letter sink {} not created", name))?.borrow_mut();
+                if !dead_letter.opened {
+                    dead_letter.sink.open()?;
+                    dead_letter.opened = true;
+                }
+                dead_letter.dirty = true;
+                let DeadLetterSink { sink, row, .. } = &mut *dead_letter;
+                row.update(0, Value::Int(self.node_id as i32));
+                row.update(1, Value::string(error));
+                row.update(2, Value::Binary(Arc::new(payload.to_vec())));
+                row.update(3, Value::Long(current_timestamp_millis() as i64 * 1000));
+                sink.invoke(row)?;
+                self.base_iometrics.num_records_dead_letter_inc_by(1);
+                Ok(())
+            },
+        }
+    }
+
+    pub fn barrier(&self, checkpoint_id: u64) -> Result<()> {
+        match &self.dead_letter {
+            Some(dead_letter) => {
+                let mut dead_letter = dead_letter.borrow_mut();
+                if !dead_letter.opened || (dead_letter.checkpoint_id == Some(checkpoint_id) && !dead_letter.dirty) {
+                    return Ok(());
+                }
+                dead_letter.checkpoint_id = Some(checkpoint_id);
+                dead_letter.dirty = false;
+                dead_letter.sink.barrier(checkpoint_id)
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub fn close(&self) -> Result<()> {
+        if self.closed.replace(true) {
+            return Ok(());
+        }
+        match &self.dead_letter {
+            Some(dead_letter) => {
+                let mut dead_letter = dead_letter.borrow_mut();
+                dead_letter.refs -= 1;
+                if dead_letter.refs == 0 && dead_letter.opened {
+                    dead_letter.opened = false;
+                    dead_letter.sink.close()
+                } else {
+                    Ok(())
+                }
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::Registry;
+    use super::*;
+
+    #[derive(Debug)]
+    struct VecSink {
+        rows: Rc<RefCell<Vec<GenericRow>>>,
+        events: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Sink for VecSink {
+        fn invoke(&mut self, row: &dyn Row) -> Result<()> {
+            self.rows.borrow_mut().push(row.to_generic_row());
+            Ok(())
+        }
+
+        fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+            self.events.borrow_mut().push(format!("barrier{}", checkpoint_id));
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.events.borrow_mut().push("close".to_string());
+            Ok(())
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct OnErrorWrapper {
+        on_error: OnErrorConfig,
+    }
+
+    fn parse_on_error(content: &str) -> OnErrorConfig {
+        let config = config::Config::builder().add_source(config::File::from_str(content, config::FileFormat::Yaml)).build().unwrap();
+        config.try_deserialize::<OnErrorWrapper>().unwrap().on_error
+    }
+
+    #[test]
+    fn test_error_handler() {
+        assert_eq!(parse_on_error("on_error: {dead_letter: dlq}"), OnErrorConfig::DeadLetter("dlq".to_string()));
+        assert_eq!(parse_on_error("on_error: skip"), OnErrorConfig::Skip);
+
+        let registry = Registry::new();
+        let base_iometrics = Arc::new(BaseIOMetrics::new(&registry, "test".to_string()));
+        assert!(ErrorHandler::fail(1, base_iometrics.clone()).handle("err".to_string(), b"a").is_err());
+        ErrorHandler::new(1, OnErrorConfig::Skip, base_iometrics.clone(), None).handle("err".to_string(), b"a").unwrap();
+
+        let rows = Rc::new(RefCell::new(Vec::new()));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = DeadLetterSink::new_shared(Box::new(VecSink { rows: rows.clone(), events: events.clone() }));
+        let handler = ErrorHandler::new(2, OnErrorConfig::DeadLetter("dlq".to_string()), base_iometrics.clone(), Some(sink.clone()));
+        let other = ErrorHandler::new(3, OnErrorConfig::DeadLetter("dlq".to_string()), base_iometrics.clone(), Some(sink));
+        handler.handle("parse error".to_string(), b"{bad json").unwrap();
+        other.handle("avro error".to_string(), &[0x02, 0xff, 0xfe]).unwrap();
+        handler.barrier(1).unwrap();
+        other.barrier(1).unwrap();
+        assert_eq!(*events.borrow(), vec!["barrier1"]);
+        // 下游算子在barrier之间写入的数据由后续的barrier刷出
+        other.handle("late error".to_string(), b"x").unwrap();
+        other.barrier(1).unwrap();
+        handler.close().unwrap();
+        handler.close().unwrap();
+        assert_eq!(*events.borrow(), vec!["barrier1", "barrier1"]);
+        other.close().unwrap();
+        assert_eq!(*events.borrow(), vec!["barrier1", "barrier1", "close"]);
+        let rows = rows.borrow();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].get_int(0), 2);
+        assert_eq!(rows[0].get_string(1), "parse error");
+        assert_eq!(rows[0].get_binary(2).as_slice(), b"{bad json");
+        assert_eq!(rows[1].get_int(0), 3);
+        assert_eq!(rows[1].get_binary(2).as_slice(), &[0x02, 0xff, 0xfe]);
+    }
+}
@@ -1,12 +1,17 @@
 use std::fmt::Debug;
+use std::rc::Rc;
 use std::sync::{Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
 use prometheus::{IntCounter,  Registry};
+use prometheus::core::Collector;
+use crate::config::{ErrorHandler, StateConfig};
 
 #[derive(Clone)]
 pub struct TaskContext {
     pub task_config: TaskConfig,
     pub operator_config: OperatorConfig,
     pub base_iometrics: Arc<BaseIOMetrics>,
+    pub error_handler: Rc<ErrorHandler>,
 }
 
 impl Debug for TaskContext {
@@ -20,22 +25,25 @@ impl Debug for TaskContext {
 
 impl TaskContext {
     pub fn new(task_config: TaskConfig, operator_config: OperatorConfig, base_iometrics: Arc<BaseIOMetrics>) -> Self {
+        let error_handler = Rc::new(ErrorHandler::fail(operator_config.id, base_iometrics.clone()));
         Self {
             task_config,
             operator_config,
-            base_iometrics
+            base_iometrics,
+            error_handler,
         }
     }
+
+    pub fn with_error_handler(mut self, error_handler: Rc<ErrorHandler>) -> Self {
+        self.error_handler = error_handler;
+        self
+    }
 }
 
 impl Default for TaskContext {
     fn default() -> Self {
         let registry = Registry::new();
-        Self {
-            task_config: TaskConfig::new(1, 0, registry.clone()),
-            operator_config: OperatorConfig::new(0),
-            base_iometrics: Arc::new(BaseIOMetrics::new(&registry, "".to_string())),
-        }
+        TaskContext::new(TaskConfig::new(1, 0, registry.clone()), OperatorConfig::new(0, ""), Arc::new(BaseIOMetrics::new(&registry, "".to_string())))
     }
 }
 
@@ -44,6 +52,9 @@ pub struct TaskConfig {
     pub subtask_parallelism: u8,
     pub subtask_index: u8,
     pub metrics_registry: Registry,
+    pub state_config: Option<StateConfig>,
+    /// 任务停止信号, 长时间阻塞的算子需要检查
+    pub terminated: Arc<AtomicBool>,
 }
 
 impl TaskConfig {
@@ -52,19 +63,49 @@ impl TaskConfig {
             subtask_parallelism,
             subtask_index,
             metrics_registry,
+            state_config: None,
+            terminated: Arc::new(AtomicBool::new(false)),
         }
     }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::Acquire)
+    }
+
+    /// 把total分给每个subtask, 前total % parallelism个subtask多分一个
+    pub fn subtask_share(&self, total: i64) -> i64 {
+        let num_subtasks = self.subtask_parallelism as i64;
+        let index_of_this_subtask = self.subtask_index as i64;
+        if total % num_subtasks > index_of_this_subtask {
+            total / num_subtasks + 1
+        } else {
+            total / num_subtasks
+        }
+    }
+
+    pub fn with_state_config(mut self, state_config: Option<StateConfig>) -> Self {
+        self.state_config = state_config;
+        self
+    }
+
+    pub fn with_terminated(mut self, terminated: Arc<AtomicBool>) -> Self {
+        self.terminated = terminated;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct OperatorConfig {
     pub id: u16,
+    /// 节点名称, source和transform为第一个output, sink为name
+    pub name: String,
 }
 
 impl OperatorConfig {
-    pub fn new(id: u16) -> Self {
+    pub fn new(id: u16, name: impl Into<String>) -> Self {
         Self {
             id,
+            name: name.into(),
         }
     }
 }
@@ -77,6 +118,8 @@ pub struct BaseIOMetrics {
     // num_records_out_rate: IntGauge,
     num_bytes_in: IntCounter,
     num_bytes_out: IntCounter,
+    num_records_skipped: IntCounter,
+    num_records_dead_letter: IntCounter,
     // num_bytes_in_rate: IntGauge,
     // num_bytes_out_rate: IntGauge,
     // num_records_in_rate_stat: Mutex<SlidingWindowRateStat>,
@@ -94,14 +137,18 @@ impl BaseIOMetrics {
         //let num_records_out_rate = IntGauge::new(format!("{}_num_records_out_rate", prefix), "number of records out rate").unwrap();
         let num_bytes_in = IntCounter::new(format!("{}_num_bytes_in", prefix), "number of bytes in").unwrap();
         let num_bytes_out = IntCounter::new(format!("{}_num_bytes_out", prefix), "number of bytes out").unwrap();
+        let num_records_skipped = IntCounter::new(format!("{}_num_records_skipped", prefix), "number of error records skipped").unwrap();
+        let num_records_dead_letter = IntCounter::new(format!("{}_num_records_dead_letter", prefix), "number of error records sent to dead letter sink").unwrap();
         //let num_bytes_in_rate = IntGauge::new(format!("{}_num_bytes_in_rate", prefix), "number of bytes in rate").unwrap();
         //let num_bytes_out_rate = IntGauge::new(format!("{}_num_bytes_out_rate", prefix), "number of bytes out rate").unwrap();
-        registry.register(Box::new(num_records_in.clone())).unwrap();
-        registry.register(Box::new(num_records_out.clone())).unwrap();
+        register_counter(registry, &num_records_in).unwrap();
+        register_counter(registry, &num_records_out).unwrap();
         //registry.register(Box::new(num_records_in_rate.clone())).unwrap();
         //registry.register(Box::new(num_records_out_rate.clone())).unwrap();
-        registry.register(Box::new(num_bytes_in.clone())).unwrap();
-        registry.register(Box::new(num_bytes_out.clone())).unwrap();
+        register_counter(registry, &num_bytes_in).unwrap();
+        register_counter(registry, &num_bytes_out).unwrap();
+        register_counter(registry, &num_records_skipped).unwrap();
+        register_counter(registry, &num_records_dead_letter).unwrap();
         //registry.register(Box::new(num_bytes_in_rate.clone())).unwrap();
         //registry.register(Box::new(num_bytes_out_rate.clone())).unwrap();
         Self {
@@ -112,6 +159,8 @@ impl BaseIOMetrics {
             //num_records_out_rate,
             num_bytes_in,
             num_bytes_out,
+            num_records_skipped,
+            num_records_dead_letter,
             // num_bytes_in_rate,
             // num_bytes_out_rate,
             // num_records_in_rate_stat:  Mutex::new(SlidingWindowRateStat::with_window(10)),
@@ -161,4 +210,35 @@ impl BaseIOMetrics {
         self.num_bytes_out_rate.set(rate as i64);*/
     }
 
+    pub fn num_records_skipped_inc_by(&self, num_records: u64) {
+        self.num_records_skipped.inc_by(num_records);
+    }
+
+    pub fn num_records_dead_letter_inc_by(&self, num_records: u64) {
+        self.num_records_dead_letter.inc_by(num_records);
+    }
+
+    /// 算子自定义的计数器, 名称为{prefix}_{name}
+    pub fn new_counter(&self, registry: &Registry, name: &str, help: &str) -> crate::Result<IntCounter> {
+        let counter = IntCounter::new(format!("{}_{}", self.prefix, name), help).map_err(|e| e.to_string())?;
+        register_counter(registry, &counter).map_err(|e| e.to_string())?;
+        Ok(counter)
+    }
+
+}
+
+/// 重启时重新创建的算子使用相同的指标名称, 替换已注册的指标并保留之前的计数
+pub fn register_counter(registry: &Registry, counter: &IntCounter) -> prometheus::Result<()> {
+    match registry.register(Box::new(counter.clone())) {
+        Err(prometheus::Error::AlreadyReg) => {
+            let name = &counter.desc()[0].fq_name;
+            let value = registry.gather().iter().find(|f| f.get_name() == name)
+                .and_then(|f| f.get_metric().first().map(|m| m.get_counter().get_value() as u64)).unwrap_or(0);
+            registry.unregister(Box::new(counter.clone()))?;
+            registry.register(Box::new(counter.clone()))?;
+            counter.inc_by(value);
+            Ok(())
+        },
+        rst => rst,
+    }
 }
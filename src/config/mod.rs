@@ -2,20 +2,22 @@ mod source;
 mod transform;
 mod sink;
 mod execution;
+mod error;
+mod preprocess;
+mod secret;
 
 pub use source::*;
 pub use transform::*;
 pub use sink::*;
 pub use execution::*;
+pub use error::*;
+pub use secret::*;
 
-use std::fs;
 use std::error::Error;
-use base64::Engine;
-use base64::engine::general_purpose::STANDARD;
+use std::path::Path;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use config::{Config, Value as ConfigValue};
-use regex::Regex;
-use crate::encrypt::aes_decrypt;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -39,22 +41,34 @@ pub struct EnvConfig {
 pub struct WebConfig {
     #[serde(default)]
     pub enabled: bool,
+    /// 监听地址, 默认0.0.0.0
+    #[serde(default = "default_host")]
+    pub host: String,
     #[serde(default = "default_port")]
     pub port: u32,
     #[serde(default = "default_works")]
     pub works: u32,
+    /// POST /stop需要的token(请求头Authorization: Bearer {token}), 监听非本机地址时没有配置则不能调用/stop, 其它接口不需要token
+    #[serde(default)]
+    pub stop_token: Option<String>,
 }
 
 impl Default for WebConfig {
     fn default() -> Self {
         WebConfig {
             enabled: false,
+            host: default_host(),
             port: default_port(),
             works: default_works(),
+            stop_token: None,
         }
     }
 }
 
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
 fn default_port() -> u32 {
     8000
 }
@@ -69,12 +83,99 @@ pub struct ApplicationConfig {
     pub name: String,
     #[serde(default)]
     pub parallelism: u8,
+    /// 收到停止信号后等待缓存数据输出和sink关闭的最长时间, 超时后直接退出
+    #[serde(default = "default_drain_timeout_ms")]
+    pub drain_timeout_ms: u64,
+    /// source和exchange task出错后的重启策略, 重启时重新创建出错subtask的整个算子链, exchange task继续使用原来的channel接收数据
+    #[serde(default)]
+    pub restart_strategy: RestartStrategyConfig,
+    /// 聚合transform的本地状态快照, 不配置时不保存状态
+    #[serde(default)]
+    pub state: Option<StateConfig>,
+}
+
+impl ApplicationConfig {
+    /// task使用的状态配置, 每个应用的快照保存在{dir}/{name}目录
+    pub fn task_state_config(&self) -> Option<StateConfig> {
+        self.state.as_ref().map(|state| StateConfig {
+            dir: Path::new(&state.dir).join(&self.name).to_string_lossy().into_owned(),
+            snapshot_interval_ms: state.snapshot_interval_ms,
+        })
+    }
 }
 
 fn default_application_name() -> String {
     "retl".to_string()
 }
 
+fn default_drain_timeout_ms() -> u64 {
+    30000
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RestartStrategyConfig {
+    /// 不重启, 任务出错后整个进程退出
+    #[default]
+    None,
+    FixedDelay { max_attempts: u32, delay_ms: u64 },
+    /// 第n次重启等待initial_delay_ms * multiplier^n, 最多等待max_delay_ms
+    ExponentialBackoff {
+        #[serde(default = "default_max_attempts")]
+        max_attempts: u32,
+        #[serde(default = "default_initial_delay_ms")]
+        initial_delay_ms: u64,
+        #[serde(default = "default_max_delay_ms")]
+        max_delay_ms: u64,
+        #[serde(default = "default_multiplier")]
+        multiplier: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateConfig {
+    pub dir: String,
+    /// 有数据更新时快照的间隔, 关闭算子时也会快照
+    #[serde(default = "default_snapshot_interval_ms")]
+    pub snapshot_interval_ms: u64,
+}
+
+fn default_snapshot_interval_ms() -> u64 {
+    60000
+}
+
+impl RestartStrategyConfig {
+    /// 返回第attempt次(从0开始)重启前的等待时间, 返回None时不再重启
+    pub fn restart_delay(&self, attempt: u32) -> Option<Duration> {
+        match *self {
+            RestartStrategyConfig::None => None,
+            RestartStrategyConfig::FixedDelay { max_attempts, delay_ms } => {
+                (attempt < max_attempts).then(|| Duration::from_millis(delay_ms))
+            },
+            RestartStrategyConfig::ExponentialBackoff { max_attempts, initial_delay_ms, max_delay_ms, multiplier } => {
+                let delay = initial_delay_ms as f64 * multiplier.powi(attempt.min(64) as i32);
+                (attempt < max_attempts).then(|| Duration::from_millis(delay.min(max_delay_ms as f64) as u64))
+            },
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    u32::MAX
+}
+
+fn default_initial_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_delay_ms() -> u64 {
+    60000
+}
+
+fn default_multiplier() -> f64 {
+    2.0
+}
+
 #[derive(Clone, Debug)]
 pub struct WrapConfigValue(ConfigValue);
 
@@ -95,34 +196,54 @@ impl config::Source for WrapConfigValue {
 }
 
 pub fn parse_config(config_path: &str) -> Result<AppConfig, Box<dyn Error>> {
-    let content = fs::read_to_string(config_path).map_err(|e| format!("Failed to read config file: {}", e))?;
-    let key = b"fd6b639dbcff0c2a";
-    let iv = b"77b07a672d57d64c";
-    let decrypted_content = decrypt_config_content(&content, key, iv).map_err(|e| format!("Failed to decrypt config: {}", e))?;
-    let config = Config::builder().add_source(config::File::from_str(&decrypted_content, config::FileFormat::Yaml))
-        .build()?.try_deserialize()?;
+    parse_config_with_overrides(config_path, &[])
+}
+
+/// 依次处理!include, ${ENV:-default}, enc@(...), 然后合并includes列表中的文件, 最后应用key=value格式的overrides
+pub fn parse_config_with_overrides(config_path: &str, overrides: &[String]) -> Result<AppConfig, Box<dyn Error>> {
+    let path = Path::new(config_path);
+    let content = load_config_content(path)?;
+    let mut builder = Config::builder();
+    // includes中的文件先加入, 当前文件的配置覆盖includes中的配置
+    for include in parse_includes(&content)? {
+        let include_path = path.parent().unwrap_or(Path::new(".")).join(&include);
+        builder = builder.add_source(config::File::from_str(&load_config_content(&include_path)?, config::FileFormat::Yaml));
+    }
+    builder = builder.add_source(config::File::from_str(&content, config::FileFormat::Yaml));
+    for o in overrides {
+        let (key, value) = o.split_once('=').ok_or_else(|| format!("invalid override {}, expected KEY=VALUE", o))?;
+        builder = builder.set_override(key.trim(), parse_override_value(value.trim()))?;
+    }
+    let config = builder.build()?.try_deserialize()?;
     Ok(config)
 }
 
-fn decrypt_config_content(content: &str, key: &[u8], iv: &[u8]) -> Result<String, String> {
-    let re = Regex::new(r"enc@\(([^)]+)\)").map_err(|e| format!("Regex error: {}", e))?;
-    let mut result = String::new();
+fn load_config_content(path: &Path) -> Result<String, String> {
+    let content = preprocess::resolve_includes(path, &mut Vec::new())?;
+    let content = preprocess::interpolate_env(&content)?;
+    if !content.contains("enc@(") {
+        return Ok(content);
+    }
+    let keys = ConfigKeys::from_env()?;
+    keys.decrypt_content(&content).map_err(|e| format!("Failed to decrypt config: {}", e))
+}
 
-    for line in content.lines() {
-        let mut new_line = line.to_string();
-        for cap in re.captures_iter(line) {
-            let full_match = cap.get(0).unwrap().as_str();
-            let ciphertext = cap.get(1).unwrap().as_str();
-            let ciphertext_bytes = STANDARD.decode(ciphertext).map_err(|e| format!("Base64 decode error: {}", e))?;
-            let bytes = aes_decrypt(ciphertext_bytes.as_slice(), key, iv)?;
-            let plaintext = String::from_utf8(bytes).map_err(|e| format!("UTF-8 decode error: {}", e))?;
-            new_line = new_line.replace(full_match, &plaintext);
-        }
-        result.push_str(&new_line);
-        result.push('\n');
+fn parse_includes(content: &str) -> Result<Vec<String>, String> {
+    let value: serde_yaml::Value = serde_yaml::from_str(content).map_err(|e| format!("Failed to parse config: {}", e))?;
+    match value.get("includes") {
+        Some(includes) => serde_yaml::from_value(includes.clone()).map_err(|e| format!("includes must be a list of file paths: {}", e)),
+        None => Ok(Vec::new()),
     }
+}
 
-    Ok(result)
+/// 按yaml标量解析, 保留数字和布尔类型
+fn parse_override_value(value: &str) -> ConfigValue {
+    match serde_yaml::from_str::<serde_yaml::Value>(value) {
+        Ok(serde_yaml::Value::Bool(b)) => ConfigValue::from(b),
+        Ok(serde_yaml::Value::Number(n)) if n.is_i64() => ConfigValue::from(n.as_i64().unwrap()),
+        Ok(serde_yaml::Value::Number(n)) if n.is_f64() => ConfigValue::from(n.as_f64().unwrap()),
+        _ => ConfigValue::from(value.to_string()),
+    }
 }
 
 #[cfg(test)]
@@ -138,4 +259,28 @@ mod tests {
         println!("{}", serde_json::to_string_pretty(&config).unwrap());
     }
 
+    #[test]
+    fn test_restart_strategy() {
+        let strategy = RestartStrategyConfig::FixedDelay { max_attempts: 2, delay_ms: 100 };
+        assert_eq!(strategy.restart_delay(1), Some(Duration::from_millis(100)));
+        assert_eq!(strategy.restart_delay(2), None);
+        let strategy = RestartStrategyConfig::ExponentialBackoff { max_attempts: 10, initial_delay_ms: 100, max_delay_ms: 1000, multiplier: 2.0 };
+        assert_eq!(strategy.restart_delay(0), Some(Duration::from_millis(100)));
+        assert_eq!(strategy.restart_delay(3), Some(Duration::from_millis(800)));
+        assert_eq!(strategy.restart_delay(4), Some(Duration::from_millis(1000)));
+        assert_eq!(strategy.restart_delay(10), None);
+        assert_eq!(RestartStrategyConfig::None.restart_delay(0), None);
+    }
+
+    #[test]
+    fn test_config_include_overrides()  {
+        let config_path = "config/application_include.yaml";
+        let overrides = vec!["env.application.parallelism=3".to_string(), "env.application.name=override".to_string()];
+        let config: AppConfig = parse_config_with_overrides(config_path, &overrides).unwrap();
+        assert_eq!(config.env.application.parallelism, 3);
+        assert_eq!(config.env.application.name, "override");
+        assert_eq!(config.sinks[0].name, "print_sink");
+        assert!(parse_config_with_overrides(config_path, &["parallelism".to_string()]).is_err());
+    }
+
 }
\ No newline at end of file
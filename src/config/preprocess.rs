@@ -0,0 +1,164 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use regex::{Captures, Regex};
+use serde_yaml::Value;
+
+/// 替换yaml标量值中的${VAR}和${VAR:-default}, $${转义为${, 变量不存在且没有默认值时报错.
+/// 在解析后的标量上替换, 变量值中的yaml特殊字符不会改变配置结构, 注释不替换
+pub fn interpolate_env(content: &str) -> Result<String, String> {
+    if !content.contains("${") {
+        return Ok(content.to_string());
+    }
+    let re = Regex::new(r"\$\$\{|\$\{([A-Za-z_][A-Za-z0-9_]*)(:-([^}]*))?\}").map_err(|e| format!("Regex error: {}", e))?;
+    let mut value: Value = serde_yaml::from_str(content).map_err(|e| format!("Failed to parse config: {}", e))?;
+    interpolate_value(&mut value, &re)?;
+    serde_yaml::to_string(&value).map_err(|e| format!("Failed to serialize config: {}", e))
+}
+
+fn interpolate_value(value: &mut Value, re: &Regex) -> Result<(), String> {
+    match value {
+        Value::String(s) if s.contains("${") => {
+            // 整个标量是一个变量时按yaml标量解析, 保留数字和布尔类型
+            let whole = re.find(s).is_some_and(|m| m.start() == 0 && m.end() == s.len() && m.as_str() != "$${");
+            let replaced = interpolate_str(s, re)?;
+            *value = match serde_yaml::from_str::<Value>(&replaced) {
+                Ok(scalar @ (Value::Null | Value::Bool(_) | Value::Number(_))) if whole => scalar,
+                _ => Value::String(replaced),
+            };
+        },
+        Value::Sequence(values) => {
+            for value in values {
+                interpolate_value(value, re)?;
+            }
+        },
+        Value::Mapping(map) => {
+            for (_, value) in map.iter_mut() {
+                interpolate_value(value, re)?;
+            }
+        },
+        Value::Tagged(tagged) => interpolate_value(&mut tagged.value, re)?,
+        _ => {},
+    }
+    Ok(())
+}
+
+fn interpolate_str(s: &str, re: &Regex) -> Result<String, String> {
+    let mut err = None;
+    let result = re.replace_all(s, |cap: &Captures| {
+        let Some(name) = cap.get(1) else {
+            return "${".to_string();
+        };
+        match (env::var(name.as_str()), cap.get(3)) {
+            (Ok(value), _) => value,
+            (Err(_), Some(default)) => default.as_str().to_string(),
+            (Err(_), None) => {
+                err = Some(format!("environment variable {} not found", name.as_str()));
+                String::new()
+            },
+        }
+    });
+    match err {
+        Some(e) => Err(e),
+        None => Ok(result.into_owned()),
+    }
+}
+
+/// 把`key: !include path`, `- !include path`和单独一行的`!include path`替换为文件内容, path相对于当前文件所在目录
+pub fn resolve_includes(path: &Path, stack: &mut Vec<PathBuf>) -> Result<String, String> {
+    let canonical = path.canonicalize().map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    if stack.contains(&canonical) {
+        return Err(format!("include loop: {} -> {}", stack.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(" -> "), canonical.display()));
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    stack.push(canonical);
+    let re = Regex::new(r"^(\s*)(-\s+|[^\s#][^#]*?:\s+)?!include\s+(\S+)\s*$").map_err(|e| format!("Regex error: {}", e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut result = String::with_capacity(content.len());
+    for line in content.lines() {
+        let cap = match re.captures(line) {
+            Some(cap) => cap,
+            None => {
+                result.push_str(line);
+                result.push('\n');
+                continue;
+            },
+        };
+        let indent = &cap[1];
+        let included = resolve_includes(&base_dir.join(&cap[3]), stack)?;
+        let lines: Vec<&str> = included.lines().filter(|l| l.trim() != "---").collect();
+        let lines = &lines[..lines.iter().rposition(|l| !l.trim().is_empty()).map(|i| i + 1).unwrap_or(0)];
+        match cap.get(2).map(|m| m.as_str()) {
+            Some(item) if item.starts_with('-') => {
+                let child_indent = format!("{}{}", indent, " ".repeat(item.len()));
+                for (i, l) in lines.iter().enumerate() {
+                    if i == 0 {
+                        result.push_str(&format!("{}{}{}\n", indent, item, l));
+                    } else {
+                        result.push_str(&format!("{}{}\n", child_indent, l));
+                    }
+                }
+            },
+            Some(key) => {
+                result.push_str(&format!("{}{}\n", indent, key.trim_end()));
+                for l in lines {
+                    result.push_str(&format!("{}  {}\n", indent, l));
+                }
+            },
+            None => {
+                for l in lines {
+                    result.push_str(&format!("{}{}\n", indent, l));
+                }
+            },
+        }
+    }
+    stack.pop();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_env() {
+        unsafe { env::set_var("RETL_TEST_PARALLELISM", "4") };
+        let content = "parallelism: ${RETL_TEST_PARALLELISM}\nname: ${RETL_TEST_NOT_EXISTS:-test}\n# ${RETL_TEST_NOT_EXISTS}";
+        assert_eq!(interpolate_env(content).unwrap(), "parallelism: 4\nname: test\n");
+        assert!(interpolate_env("name: ${RETL_TEST_NOT_EXISTS}").is_err());
+        assert_eq!(interpolate_env("a: 1 # x\n").unwrap(), "a: 1 # x\n");
+    }
+
+    #[test]
+    fn test_interpolate_env_value() {
+        // 变量值中的换行和yaml语法不会改变配置结构
+        let injected = "x\nsinks: []\n- a: [b";
+        unsafe { env::set_var("RETL_TEST_INJECT", injected) };
+        let content = "name: ${RETL_TEST_INJECT}\nurl: http://${RETL_TEST_INJECT}/a\nsql: |\n  select ${RETL_TEST_NOT_EXISTS:-1} from tbl\n";
+        let value: Value = serde_yaml::from_str(&interpolate_env(content).unwrap()).unwrap();
+        assert_eq!(value.as_mapping().unwrap().len(), 3);
+        assert_eq!(value["name"].as_str(), Some(injected));
+        assert_eq!(value["url"].as_str().unwrap(), format!("http://{}/a", injected));
+        assert_eq!(value["sql"].as_str(), Some("select 1 from tbl\n"));
+
+        // $${转义为${
+        let content = "a: $${RETL_TEST_PARALLELISM}\nb: x$${RETL_TEST_NOT_EXISTS}-${RETL_TEST_PARALLELISM}\n";
+        let value: Value = serde_yaml::from_str(&interpolate_env(content).unwrap()).unwrap();
+        assert_eq!(value["a"].as_str(), Some("${RETL_TEST_PARALLELISM}"));
+        assert_eq!(value["b"].as_str(), Some("x${RETL_TEST_NOT_EXISTS}-4"));
+    }
+
+    #[test]
+    fn test_resolve_includes() {
+        let dir = env::temp_dir().join(format!("retl_include_{}", std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::write(dir.join("common/encoding.yaml"), "codec: json\npretty: false\n\n").unwrap();
+        fs::write(dir.join("common/sink.yaml"), "type: print\nencoding: !include encoding.yaml\n").unwrap();
+        fs::write(dir.join("app.yaml"), "sinks:\n  - !include common/sink.yaml\n    name: print_sink\n").unwrap();
+        let content = resolve_includes(&dir.join("app.yaml"), &mut Vec::new()).unwrap();
+        assert_eq!(content, "sinks:\n  - type: print\n    encoding:\n      codec: json\n      pretty: false\n    name: print_sink\n");
+        fs::write(dir.join("loop.yaml"), "a: !include loop.yaml\n").unwrap();
+        assert!(resolve_includes(&dir.join("loop.yaml"), &mut Vec::new()).unwrap_err().contains("include loop"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use log::warn;
+use regex::{Captures, Regex};
+use crate::Result;
+use crate::encrypt::{aes_decrypt, aes_encrypt};
+
+/// 默认密钥, 格式: key或者key:iv, iv只用于解密旧格式的enc@(ciphertext)
+pub const CONFIG_KEY_ENV: &str = "RETL_CONFIG_KEY";
+/// 密钥文件, 每行格式: kid=key, 没有kid的行为默认密钥, #开头为注释
+pub const CONFIG_KEY_FILE_ENV: &str = "RETL_CONFIG_KEY_FILE";
+pub const DEFAULT_KID: &str = "default";
+/// 为true时允许使用旧版本内置的密钥解密旧格式的enc@(ciphertext), 默认不允许
+pub const LEGACY_KEY_ENV: &str = "RETL_ALLOW_LEGACY_CONFIG_KEY";
+/// 旧版本内置的密钥, 没有配置默认密钥的iv且设置了RETL_ALLOW_LEGACY_CONFIG_KEY时用于解密旧格式的enc@(ciphertext)
+const LEGACY_KEY: &[u8] = b"fd6b639dbcff0c2a";
+const LEGACY_IV: &[u8] = b"77b07a672d57d64c";
+const IV_LEN: usize = 16;
+
+#[derive(Debug)]
+struct ConfigKey {
+    key: Vec<u8>,
+    iv: Option<Vec<u8>>,
+}
+
+/// 配置中enc@(...)使用的aes-128-cbc密钥, key是16字节.
+/// 加密输出enc@(kid:base64(iv + ciphertext)), 每次加密使用随机iv, 相同明文的密文也不同.
+/// 旧格式enc@(ciphertext)没有kid, 使用默认密钥的固定iv解密, 没有配置iv时需要显式允许才使用旧版本内置的密钥.
+/// 旧格式的值可以用retl decrypt解密后再用retl encrypt重新加密.
+#[derive(Debug, Default)]
+pub struct ConfigKeys {
+    keys: HashMap<String, ConfigKey>,
+    legacy_key: bool,
+}
+
+impl ConfigKeys {
+    /// 从环境变量加载, 环境变量中的默认密钥优先于密钥文件中的默认密钥
+    pub fn from_env() -> Result<Self> {
+        let mut keys = match env::var(CONFIG_KEY_FILE_ENV) {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) => Self::default(),
+        };
+        if let Ok(spec) = env::var(CONFIG_KEY_ENV) {
+            keys.add(DEFAULT_KID, &spec).map_err(|e| format!("invalid {}: {}", CONFIG_KEY_ENV, e))?;
+        }
+        Ok(keys.with_legacy_key(legacy_key_from_env()))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read key file {}: {}", path, e))?;
+        let keys = Self::parse(&content).map_err(|e| format!("invalid key file {}: {}", path, e))?;
+        Ok(keys.with_legacy_key(legacy_key_from_env()))
+    }
+
+    /// 是否允许使用旧版本内置的密钥
+    pub fn with_legacy_key(mut self, legacy_key: bool) -> Self {
+        self.legacy_key = legacy_key;
+        self
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut keys = Self::default();
+        for line in content.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            match line.split_once('=') {
+                Some((kid, spec)) => keys.add(kid.trim(), spec.trim())?,
+                None => keys.add(DEFAULT_KID, line)?,
+            }
+        }
+        Ok(keys)
+    }
+
+    fn add(&mut self, kid: &str, spec: &str) -> Result<()> {
+        let (key, iv) = match spec.split_once(':') {
+            Some((key, iv)) => (key, Some(iv)),
+            None => (spec, None),
+        };
+        if key.len() != 16 || iv.is_some_and(|iv| iv.len() != IV_LEN) {
+            return Err(format!("key {}: key and iv must be 16 bytes", kid));
+        }
+        self.keys.insert(kid.to_string(), ConfigKey { key: key.as_bytes().to_vec(), iv: iv.map(|iv| iv.as_bytes().to_vec()) });
+        Ok(())
+    }
+
+    fn get(&self, kid: &str) -> Result<&ConfigKey> {
+        self.keys.get(kid).ok_or_else(|| format!("config key {} not found, set {} or {}", kid, CONFIG_KEY_ENV, CONFIG_KEY_FILE_ENV))
+    }
+
+    /// 返回enc@(kid:...)格式的密文
+    pub fn encrypt(&self, plaintext: &str, kid: &str) -> Result<String> {
+        let key = self.get(kid)?;
+        let iv: [u8; IV_LEN] = rand::random();
+        let mut bytes = iv.to_vec();
+        bytes.extend(aes_encrypt(plaintext.as_bytes(), &key.key, &iv)?);
+        Ok(format!("enc@({}:{})", kid, STANDARD.encode(bytes)))
+    }
+
+    /// value可以是enc@(...)或者enc@()括号中的内容
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let value = value.trim();
+        let value = value.strip_prefix("enc@(").and_then(|v| v.strip_suffix(')')).unwrap_or(value);
+        // base64中没有':', 有':'时前面为kid, 密文前16字节为iv
+        let bytes = match value.split_once(':') {
+            Some((kid, ciphertext)) => {
+                let key = self.get(kid)?;
+                let bytes = STANDARD.decode(ciphertext).map_err(|e| format!("Base64 decode error: {}", e))?;
+                if bytes.len() <= IV_LEN {
+                    return Err(format!("invalid ciphertext for key {}", kid));
+                }
+                aes_decrypt(&bytes[IV_LEN..], &key.key, &bytes[..IV_LEN])?
+            },
+            None => {
+                let (key, iv) = match self.keys.get(DEFAULT_KID) {
+                    Some(ConfigKey { key, iv: Some(iv) }) => (key.as_slice(), iv.as_slice()),
+                    _ if self.legacy_key => {
+                        warn!("decrypting legacy enc@(...) value with the built-in key, re-encrypt it with retl encrypt");
+                        (LEGACY_KEY, LEGACY_IV)
+                    },
+                    _ => return Err(format!("legacy enc@(...) value requires a default key with iv, or set {}=true to use the built-in key", LEGACY_KEY_ENV)),
+                };
+                let bytes = STANDARD.decode(value).map_err(|e| format!("Base64 decode error: {}", e))?;
+                aes_decrypt(&bytes, key, iv)?
+            },
+        };
+        String::from_utf8(bytes).map_err(|e| format!("UTF-8 decode error: {}", e))
+    }
+
+    /// 替换配置内容中所有的enc@(...)
+    pub fn decrypt_content(&self, content: &str) -> Result<String> {
+        let re = Regex::new(r"enc@\(([^)]+)\)").map_err(|e| format!("Regex error: {}", e))?;
+        let mut err = None;
+        let result = re.replace_all(content, |cap: &Captures| {
+            self.decrypt(&cap[1]).unwrap_or_else(|e| {
+                err.get_or_insert(e);
+                String::new()
+            })
+        });
+        match err {
+            Some(e) => Err(e),
+            None => Ok(result.into_owned()),
+        }
+    }
+}
+
+fn legacy_key_from_env() -> bool {
+    env::var(LEGACY_KEY_ENV).is_ok_and(|v| v.eq_ignore_ascii_case("true") || v == "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_keys() {
+        let keys = ConfigKeys::parse("# keys\n0123456789abcdef\nk2 = fedcba9876543210\n").unwrap();
+        let encrypted = keys.encrypt("password", "k2").unwrap();
+        assert!(encrypted.starts_with("enc@(k2:"));
+        let content = format!("user: root\npassword: {}\nurl: {}", encrypted, keys.encrypt("abc", DEFAULT_KID).unwrap());
+        assert_eq!(keys.decrypt_content(&content).unwrap(), "user: root\npassword: password\nurl: abc");
+        assert!(keys.decrypt("enc@(k3:y+49jBd/xd6Kz4pfoQIBbA==)").unwrap_err().contains("k3 not found"));
+        assert!(ConfigKeys::parse("k1=short:key").is_err());
+        assert!(ConfigKeys::parse("k1=0123456789abcdef:short").is_err());
+    }
+
+    #[test]
+    fn test_random_iv() {
+        let keys = ConfigKeys::parse("0123456789abcdef").unwrap();
+        let encrypted1 = keys.encrypt("password", DEFAULT_KID).unwrap();
+        let encrypted2 = keys.encrypt("password", DEFAULT_KID).unwrap();
+        assert_ne!(encrypted1, encrypted2);
+        assert_eq!(keys.decrypt(&encrypted1).unwrap(), "password");
+        assert_eq!(keys.decrypt(&encrypted2).unwrap(), "password");
+    }
+
+    #[test]
+    fn test_legacy_value() {
+        // 旧版本内置密钥加密的值, 需要显式允许
+        assert!(ConfigKeys::default().decrypt("enc@(y+49jBd/xd6Kz4pfoQIBbA==)").unwrap_err().contains(LEGACY_KEY_ENV));
+        assert_eq!(ConfigKeys::default().with_legacy_key(true).decrypt("enc@(y+49jBd/xd6Kz4pfoQIBbA==)").unwrap(), "abc");
+        let keys = ConfigKeys::parse("0123456789abcdef").unwrap();
+        assert!(keys.decrypt("enc@(y+49jBd/xd6Kz4pfoQIBbA==)").is_err());
+        assert_eq!(keys.with_legacy_key(true).decrypt("enc@(y+49jBd/xd6Kz4pfoQIBbA==)").unwrap(), "abc");
+        // 配置了iv的默认密钥解密旧格式的值
+        let keys = ConfigKeys::parse("0123456789abcdef:fedcba9876543210").unwrap();
+        let legacy = STANDARD.encode(aes_encrypt(b"abc", b"0123456789abcdef", b"fedcba9876543210").unwrap());
+        assert_eq!(keys.decrypt(&format!("enc@({})", legacy)).unwrap(), "abc");
+        assert_eq!(keys.decrypt(&keys.encrypt("abc", DEFAULT_KID).unwrap()).unwrap(), "abc");
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 use dyn_clone::DynClone;
 use serde::{Deserialize, Serialize};
-use crate::config::TaskContext;
+use crate::config::{OnErrorConfig, TaskContext};
 use crate::Result;
 use crate::connector::Sink;
 use crate::types::Schema;
@@ -9,7 +9,17 @@ use crate::types::Schema;
 #[derive(Clone, Debug, Serialize,Deserialize)]
 pub struct SinkOuter {
     pub name: String,
+    /// 只作为dead letter sink时可以为空
+    #[serde(default)]
     pub inputs: Vec<String>,
+    /// 在独立的线程中运行, 上游通过容量为queue_size的channel发送数据, channel满时上游阻塞
+    #[serde(default, rename = "async", skip_serializing_if = "std::ops::Not::not")]
+    pub is_async: bool,
+    /// exchange channel容量, 默认EXCHANGE_QUEUE_SIZE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_size: Option<usize>,
+    #[serde(default)]
+    pub on_error: OnErrorConfig,
     #[serde(flatten)]
     pub inner: BoxedSinkConfig,
 }
@@ -18,6 +28,7 @@ pub type BoxedSinkConfig = Box<dyn SinkConfig>;
 
 #[typetag::serde(tag = "type")]
 pub trait SinkConfig: DynClone + Debug + Send + Sync {
+    /// 只检查配置, validate也会调用, 不能创建连接, 需要连接的工作放到create_sink或open中
     fn build(&self, schema: Schema) -> Result<Box<dyn SinkProvider>>;
 }
 dyn_clone::clone_trait_object!(SinkConfig);
@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 use dyn_clone::DynClone;
 use serde::{Deserialize, Serialize};
-use crate::config::TaskContext;
+use crate::config::{OnErrorConfig, TaskContext};
 use crate::Result;
 use crate::connector::Source;
 use crate::types::Schema;
@@ -10,6 +10,11 @@ use crate::types::Schema;
 pub struct SourceOuter {
     pub outputs: Vec<String>,
     pub schema: String,
+    #[serde(default)]
+    pub on_error: OnErrorConfig,
+    /// 覆盖env.application.parallelism, source所在算子链的subtask数
+    #[serde(default)]
+    pub parallelism: Option<u8>,
     #[serde(flatten)]
     pub inner: BoxedSourceConfig,
 }
@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 use dyn_clone::DynClone;
 use serde::{Deserialize, Serialize};
-use crate::config::TaskContext;
+use crate::config::{OnErrorConfig, TaskContext};
 use crate::Result;
 use crate::transform::Transform;
 use crate::types::Schema;
@@ -10,6 +10,16 @@ use crate::types::Schema;
 pub struct TransformOuter {
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partition_by: Vec<String>,
+    /// 在独立的线程中运行, 上游通过容量为queue_size的channel发送数据, channel满时上游阻塞
+    #[serde(default, rename = "async", skip_serializing_if = "std::ops::Not::not")]
+    pub is_async: bool,
+    /// exchange channel容量, 默认EXCHANGE_QUEUE_SIZE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_size: Option<usize>,
+    #[serde(default)]
+    pub on_error: OnErrorConfig,
     #[serde(flatten)]
     pub inner: BoxedTransformConfig,
 }
@@ -19,9 +29,29 @@ pub type BoxedTransformConfig = Box<dyn TransformConfig>;
 #[typetag::serde(tag = "type")]
 pub trait TransformConfig: DynClone + Debug + Send + Sync {
     fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>>;
+
+    /// 除outputs外transform额外输出的命名流, 下游通过inputs引用, side output的数据为transform的输入数据
+    fn side_outputs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// transform执行的sql, explain时输出sql的执行计划
+    fn sql(&self) -> Option<&str> {
+        None
+    }
+
+    /// 多个inputs时合并后的输入schema, 默认只支持单个input
+    fn input_schema(&self, input_schemas: Vec<Schema>) -> Result<Schema> {
+        if input_schemas.len() == 1 {
+            Ok(input_schemas.into_iter().next().unwrap())
+        } else {
+            Err(format!("transform {:?} not support multiple inputs", self))
+        }
+    }
 }
 dyn_clone::clone_trait_object!(TransformConfig);
 
 pub trait TransformProvider: DynClone + Send + Sync {
+    fn schema(&self) -> &Schema;
     fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>>;
 }
\ No newline at end of file
@@ -1,5 +1,8 @@
 use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Condvar, Mutex};
 use serde::{Deserialize, Serialize};
+use crate::Result;
 
 pub trait BatchSettings {
     const MAX_ROWS: usize;
@@ -38,4 +41,66 @@ impl BatchSettings for DefaultBatchSettings {
     const MAX_ROWS: usize = 10000;
     const MAX_BYTES: usize = 1024 * 1024 * 10;
     const INTERVAL_MS: u64 = 30000;
-}
\ No newline at end of file
+}
+
+/// 后台flush线程的写入进度, barrier时等待之前提交的block全部写入
+#[derive(Debug, Default)]
+pub struct FlushProgress {
+    state: Mutex<(usize, usize)>,
+    cvar: Condvar,
+}
+
+impl FlushProgress {
+    /// 提交一个待flush的block, 需要在持有shared_blocks锁时调用
+    pub fn begin(&self) {
+        self.state.lock().unwrap().0 += 1;
+    }
+
+    pub fn end(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.0 -= 1;
+        if !success {
+            state.1 += 1;
+        }
+        self.cvar.notify_all();
+    }
+
+    /// 等待所有已提交的block flush完成, 期间有失败的block返回错误
+    pub fn wait(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        while state.0 > 0 {
+            state = self.cvar.wait(state).unwrap();
+        }
+        let failed = mem::take(&mut state.1);
+        if failed > 0 {
+            Err(format!("{} blocks flush failed", failed))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn test_flush_progress() {
+        let progress = Arc::new(FlushProgress::default());
+        assert!(progress.wait().is_ok());
+        progress.begin();
+        progress.begin();
+        let handle = {
+            let progress = progress.clone();
+            thread::spawn(move || {
+                progress.end(true);
+                progress.end(false);
+            })
+        };
+        assert!(progress.wait().is_err());
+        handle.join().unwrap();
+        assert!(progress.wait().is_ok());
+    }
+}
@@ -309,10 +309,16 @@ fn new_column_data(buffer_pool: BufferPool,ck_type: ClickHouseType) -> Result<Bo
         ClickHouseType::Float64 => Box::new(Float64ColumnData::new(buffer_pool)),
         ClickHouseType::String => Box::new(StringColumnData::new(buffer_pool)),
         ClickHouseType::DateTime => Box::new(DateTimeColumnData::new(buffer_pool)),
+        ClickHouseType::Decimal(precision, scale) => Box::new(DecimalColumnData::new(buffer_pool, precision, scale)),
         ClickHouseType::Nullable(tp) => {
             let inner = new_column_data(buffer_pool, *tp)?;
             Box::new(NullableColumnData::new(inner))
         },
+        ClickHouseType::Map(key_tp, value_tp) => {
+            let keys = new_column_data(buffer_pool.clone(), *key_tp)?;
+            let values = new_column_data(buffer_pool.clone(), *value_tp)?;
+            Box::new(MapColumnData::new(buffer_pool, keys, values))
+        },
         _ => return Err(format!("not support type:{}", ck_type)),
     };
     Ok(data)
@@ -404,6 +410,85 @@ impl ColumnData for NullableColumnData {
     }
 }
 
+/// Map(K, V)按Array(Tuple(K, V))存储: 先是每行结束位置的offsets, 然后是所有key, 最后是所有value
+pub struct MapColumnData {
+    offsets: BufferBlock,
+    offset: u64,
+    keys: Box<dyn ColumnData>,
+    values: Box<dyn ColumnData>,
+    read_pos: usize,
+}
+
+impl MapColumnData {
+    fn new(buffer_pool: BufferPool, keys: Box<dyn ColumnData>, values: Box<dyn ColumnData>) -> Self {
+        MapColumnData {
+            offsets: BufferBlock::new(buffer_pool, BLOCK_BUFFER_SIZE),
+            offset: 0,
+            keys,
+            values,
+            read_pos: 0,
+        }
+    }
+}
+
+impl ColumnData for MapColumnData {
+    fn sql_type(&self) -> ClickHouseType {
+        ClickHouseType::Map(Box::new(self.keys.sql_type()), Box::new(self.values.sql_type()))
+    }
+
+    fn write(&mut self, value: &Value) -> usize {
+        match value {
+            Value::Map(entries) => {
+                let mut written = 8;
+                for (k, v) in entries.iter() {
+                    written += self.keys.write(k);
+                    written += if v.is_null() { self.values.write_default_value() } else { self.values.write(v) };
+                }
+                self.offset += entries.len() as u64;
+                self.offsets.put_u64_le(self.offset);
+                written
+            },
+            Value::Null => self.write_default_value(),
+            _ => panic!("invalid value for MapColumn: {:?}", value),
+        }
+    }
+
+    fn write_default_value(&mut self) -> usize {
+        self.offsets.put_u64_le(self.offset);
+        8
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut total_read = 0;
+        while total_read < buf.len() && self.read_pos < 3 {
+            let bytes_read = match self.read_pos {
+                0 => self.offsets.read(&mut buf[total_read..])?,
+                1 => self.keys.read(&mut buf[total_read..])?,
+                _ => self.values.read(&mut buf[total_read..])?,
+            };
+            total_read += bytes_read;
+            if bytes_read == 0 {
+                self.read_pos += 1;
+            }
+        }
+        Ok(total_read)
+    }
+
+    fn read_reset(&mut self) {
+        self.read_pos = 0;
+        self.offsets.read_reset();
+        self.keys.read_reset();
+        self.values.read_reset();
+    }
+
+    fn release_buffer(&mut self) {
+        self.offset = 0;
+        self.offsets.release_buffer();
+        self.keys.release_buffer();
+        self.values.release_buffer();
+    }
+}
+
 pub struct Int32ColumnData {
     data: BufferBlock,
 }
@@ -662,6 +747,64 @@ impl ColumnData for Float64ColumnData {
     }
 }
 
+/// 按精度使用Int32/Int64/Int128保存非标度值
+pub struct DecimalColumnData {
+    data: BufferBlock,
+    precision: u32,
+    scale: u32,
+    width: usize,
+}
+
+impl DecimalColumnData {
+    fn new(buffer_pool: BufferPool, precision: u32, scale: u32) -> Self {
+        Self {
+            data: BufferBlock::new(buffer_pool, BLOCK_BUFFER_SIZE),
+            precision,
+            scale,
+            width: decimal_width(precision),
+        }
+    }
+}
+
+pub fn decimal_width(precision: u32) -> usize {
+    match precision {
+        0..=9 => 4,
+        10..=18 => 8,
+        _ => 16,
+    }
+}
+
+impl ColumnData for DecimalColumnData {
+    fn sql_type(&self) -> ClickHouseType {
+        ClickHouseType::Decimal(self.precision, self.scale)
+    }
+
+    fn write(&mut self, value: &Value) -> usize {
+        match value {
+            Value::Decimal(v) => self.data.extend_from_slice(&v.to_le_bytes()[..self.width]),
+            _ => panic!("invalid value for DecimalColumn: {:?}", value),
+        }
+        self.width
+    }
+
+    fn write_default_value(&mut self) -> usize {
+        self.data.extend_from_slice(&0i128.to_le_bytes()[..self.width]);
+        self.width
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.data.read(buf)
+    }
+
+    fn read_reset(&mut self) {
+        self.data.read_reset();
+    }
+
+    fn release_buffer(&mut self) {
+        self.data.release_buffer();
+    }
+}
+
 pub struct StringColumnData {
     data: BufferBlock,
 }
@@ -864,6 +1007,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_map_column() -> Result<()> {
+        let pool = BufferPool::new(1024 * 1024, 10 * 1024 * 1024, 300_000);
+        let data_type = DataType::Map(Box::new(DataType::String), Box::new(DataType::Long));
+        let ck_type = ClickHouseType::Map(Box::new(ClickHouseType::String), Box::new(ClickHouseType::Int64));
+        let converter = make_value_converter(data_type, ck_type.clone())?;
+        let mut data = new_column_data(pool, ck_type)?;
+        let rows = vec![
+            EtlValue::Map(Arc::new(vec![(EtlValue::string("a"), EtlValue::Long(1)), (EtlValue::string("b"), EtlValue::Null)])),
+            EtlValue::Map(Arc::new(vec![])),
+            EtlValue::Null,
+            EtlValue::Map(Arc::new(vec![(EtlValue::string("c"), EtlValue::Long(3))])),
+        ];
+        for row in &rows {
+            let v = converter.convert(row)?;
+            data.write(&v);
+        }
+
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 16];
+        loop {
+            match data.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
+        let mut expected = Vec::new();
+        // offsets
+        for offset in [2u64, 2, 2, 3] {
+            expected.extend_from_slice(&offset.to_le_bytes());
+        }
+        // keys
+        expected.extend_from_slice(&[1, b'a', 1, b'b', 1, b'c']);
+        // values, null值写默认值
+        for v in [1i64, 0, 3] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(buffer, expected);
+        Ok(())
+    }
+
     #[test]
     fn test_block() -> Result<()> {
         let pool = BufferPool::new(1024 * 1024, 10 * 1024 * 1024, 300_000);
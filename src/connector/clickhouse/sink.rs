@@ -16,7 +16,7 @@ use serde_json::Value;
 use crate::Result;
 use crate::buffer_pool::BufferPool;
 use crate::config::{BaseIOMetrics, TaskContext};
-use crate::connector::batch::BatchConfig;
+use crate::connector::batch::{BatchConfig, FlushProgress};
 use crate::connector::clickhouse::{lz4, parse_date_type, ArcBlockReader, ArcCompressBlockReader, Block, ClickHouseDefaultBatchSettings, ColumnDesc, ConnectionConfig};
 use crate::connector::Sink;
 use crate::data::Row;
@@ -32,6 +32,7 @@ pub struct ClickHouseSink {
     insert_sql: String,
     stoped: Arc<AtomicBool>,
     shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>,
+    flush_progress: Arc<FlushProgress>,
     flush_handle: Option<JoinHandle<()>>,
 }
 
@@ -54,6 +55,7 @@ impl ClickHouseSink {
             insert_sql,
             stoped,
             shared_blocks,
+            flush_progress: Arc::new(FlushProgress::default()),
             flush_handle: None,
         })
     }
@@ -121,11 +123,12 @@ impl Sink for ClickHouseSink {
         let connection_config = self.connection_config.clone();
         let stoped = self.stoped.clone();
         let shared_blocks = self.shared_blocks.clone();//block_deque
+        let flush_progress = self.flush_progress.clone();
         let interval_ms = self.batch_config.interval_ms;
         let subtask_index =  self.task_context.task_config.subtask_index;
         let thread_name = format!("flush-{}-{}/{}", self.connection_config.table, subtask_index + 1, self.task_context.task_config.subtask_parallelism);
         let flush_handle = thread::Builder::new().name(thread_name).stack_size(512 * 1024).spawn(move || {
-            ClickHouseSink::process_flush_block(subtask_index, base_iometrics, buffer_pool, insert_sql, column_descs, connection_config, stoped, shared_blocks, interval_ms)
+            ClickHouseSink::process_flush_block(subtask_index, base_iometrics, buffer_pool, insert_sql, column_descs, connection_config, stoped, shared_blocks, flush_progress, interval_ms)
         }).map_err(|e| e.to_string())?;
         self.flush_handle = Some(flush_handle);
         Ok(())
@@ -144,6 +147,7 @@ impl Sink for ClickHouseSink {
             while shared_blocks.0.len() >= 1 {
                 shared_blocks = cvar.wait(shared_blocks).unwrap(); // 等待工作线程处理完成
             }
+            self.flush_progress.begin();
             shared_blocks.0.push_back(data_block);
             cvar.notify_one(); // 通知工作线程
         }
@@ -155,6 +159,19 @@ impl Sink for ClickHouseSink {
         Ok(())
     }
 
+    fn barrier(&mut self, _checkpoint_id: u64) -> Result<()> {
+        let (lock, cvar) = self.shared_blocks.as_ref();
+        let mut shared_blocks = lock.lock().unwrap();
+        if shared_blocks.1.rows() > 0 {
+            let data_block = mem::replace(&mut shared_blocks.1, Block::new(self.buffer_pool.clone(), self.column_descs.clone())?);
+            self.flush_progress.begin();
+            shared_blocks.0.push_back(data_block);
+            cvar.notify_one(); // 通知工作线程
+        }
+        drop(shared_blocks);
+        self.flush_progress.wait()
+    }
+
     fn close(&mut self) -> Result<()> {
         self.stoped.store(true, Ordering::SeqCst);
         if let Some(flush_handle) = self.flush_handle.take() {
@@ -168,7 +185,8 @@ impl Sink for ClickHouseSink {
 
 impl ClickHouseSink {
     fn process_flush_block(subtask_index: u8, base_iometrics: Arc<BaseIOMetrics>, buffer_pool: BufferPool, insert_sql: String, column_descs: Vec<ColumnDesc>,
-                           connection_config: ConnectionConfig, stoped: Arc<AtomicBool>, shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>, interval_ms: u64)  {
+                           connection_config: ConnectionConfig, stoped: Arc<AtomicBool>, shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>,
+                           flush_progress: Arc<FlushProgress>, interval_ms: u64)  {
         let urls = connection_config.build_urls();
         let mut url_index = subtask_index as usize % urls.len();
         let mut last_flush_ts = current_timestamp_millis();
@@ -190,7 +208,8 @@ impl ClickHouseSink {
             if let Some(block) = shared_blocks.0.pop_front() {
                 cvar.notify_one(); // 通知生产线程
                 drop(shared_blocks); // 释放共享数据的锁
-                Self::flush_block(&base_iometrics, &insert_sql, &connection_config, &urls, &mut url_index, &mut last_flush_ts, block);
+                let success = Self::flush_block(&base_iometrics, &insert_sql, &connection_config, &urls, &mut url_index, &mut last_flush_ts, block);
+                flush_progress.end(success);
             } else {
                 if current_timestamp_millis() >= last_flush_ts + interval_ms || has_stoped {
                     if shared_blocks.1.rows() == 0 {
@@ -204,8 +223,10 @@ impl ClickHouseSink {
 
                     let empty_block = Block::new(buffer_pool.clone(), column_descs.clone()).unwrap();
                     let block = mem::replace(&mut shared_blocks.1, empty_block);
+                    flush_progress.begin();
                     drop(shared_blocks); // 释放共享数据的锁
-                    Self::flush_block(&base_iometrics, &insert_sql, &connection_config, &urls, &mut url_index,  &mut last_flush_ts, block);
+                    let success = Self::flush_block(&base_iometrics, &insert_sql, &connection_config, &urls, &mut url_index,  &mut last_flush_ts, block);
+                    flush_progress.end(success);
                 }
             }
 
@@ -217,7 +238,7 @@ impl ClickHouseSink {
     }
 
     fn flush_block(base_iometrics: &Arc<BaseIOMetrics>, insert_sql: &str,
-                   connection_config: &ConnectionConfig, urls: &Vec<String>, url_index: &mut usize, last_flush_ts: &mut u64, block: Block )  {
+                   connection_config: &ConnectionConfig, urls: &Vec<String>, url_index: &mut usize, last_flush_ts: &mut u64, block: Block ) -> bool {
         let rows = block.rows() as u64;
         let byte_size = block.byte_size() as u64;
         let compress = true;
@@ -225,6 +246,7 @@ impl ClickHouseSink {
         info!("flush block start:{} rows,{} bytes, after:{}", rows, byte_size, current_timestamp_millis() - *last_flush_ts);
         *last_flush_ts = current_timestamp_millis();
         let mut retry = 0;
+        let mut success = false;
         loop {
             retry += 1;
             match Self::flush_block_inner(insert_sql, connection_config, urls, url_index, arc_block.clone(), compress) {
@@ -232,6 +254,7 @@ impl ClickHouseSink {
                     info!("flush block success:{} rows,{} bytes, {} ms.", rows, byte_size, current_timestamp_millis() - *last_flush_ts);
                     base_iometrics.num_records_out_inc_by(rows);
                     base_iometrics.num_bytes_out_inc_by(byte_size);
+                    success = true;
                     break;
                 }
                 Err(e) => {
@@ -249,6 +272,7 @@ impl ClickHouseSink {
             }
         }
         arc_block.lock().unwrap().release_buffer();
+        success
     }
 
     fn flush_block_inner(insert_sql: &str, connection_config: &ConnectionConfig, urls: &Vec<String>, url_index: &mut usize, arc_block: Arc<Mutex<Block>>,
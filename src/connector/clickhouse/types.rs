@@ -21,8 +21,11 @@ pub enum ClickHouseType {
     Date,
     DateTime,
     DateTime64(u32),
+    /// 精度, 标度, 最大精度38
+    Decimal(u32, u32),
     Nullable(Box<ClickHouseType>),
     Array(Box<ClickHouseType>),
+    Map(Box<ClickHouseType>, Box<ClickHouseType>),
 }
 
 impl ClickHouseType {
@@ -47,8 +50,10 @@ impl ClickHouseType {
             ClickHouseType::Date => "Date".into(),
             ClickHouseType::DateTime => "DateTime".into(),
             ClickHouseType::DateTime64(precision) => format!("DateTime64({precision})").into(),
+            ClickHouseType::Decimal(precision, scale) => format!("Decimal({precision}, {scale})").into(),
             ClickHouseType::Nullable(inner) => format!("Nullable({})", inner).into(),
             ClickHouseType::Array(inner) => format!("Array({})", inner).into(),
+            ClickHouseType::Map(key, value) => format!("Map({}, {})", key, value).into(),
         }
     }
 }
@@ -87,6 +92,12 @@ fn parse_pair_type(pair: Pair<Rule>) -> Option<ClickHouseType> {
             parse_pair_type(pair.into_inner().next().unwrap())
                 .map(|inner| ClickHouseType::Array(Box::new(inner)))
         },
+        Rule::mapDataType => {
+            let mut pairs = pair.into_inner();
+            let key = parse_pair_type(pairs.next().unwrap())?;
+            let value = parse_pair_type(pairs.next().unwrap())?;
+            Some(ClickHouseType::Map(Box::new(key), Box::new(value)))
+        },
         Rule::nullableDataType => {
             parse_pair_type(pair.into_inner().next().unwrap())
                 .map(|inner| ClickHouseType::Nullable(Box::new(inner)))
@@ -115,6 +126,27 @@ fn parse_pair_type(pair: Pair<Rule>) -> Option<ClickHouseType> {
                         None
                     }
                 },
+                s if matches_any_ignore_case!(s, "Decimal" | "Numeric") => {
+                    let precision = pairs.get(1).and_then(|p| p.as_str().parse::<u32>().ok())?;
+                    let scale = match pairs.get(2) {
+                        Some(p) => p.as_str().parse::<u32>().ok()?,
+                        None => 0,
+                    };
+                    if precision > 0 && precision <= 38 && scale <= precision {
+                        Some(ClickHouseType::Decimal(precision, scale))
+                    } else {
+                        None
+                    }
+                },
+                s if matches_any_ignore_case!(s, "Decimal32" | "Decimal64" | "Decimal128") => {
+                    let precision = match s.to_ascii_lowercase().as_str() {
+                        "decimal32" => 9,
+                        "decimal64" => 18,
+                        _ => 38,
+                    };
+                    let scale = pairs.get(1).and_then(|p| p.as_str().parse::<u32>().ok())?;
+                    if scale <= precision { Some(ClickHouseType::Decimal(precision, scale)) } else { None }
+                },
                 s => None
             }
         },
@@ -142,7 +174,10 @@ mod tests {
             "Date",
             "DateTime",
             "DateTime64(3)",
+            "Decimal(18, 2)",
+            "Decimal64(4)",
             "Array(Int32)",
+            "Map(String, Int64)",
             "Nullable(String)",
             "Nullable(Int32)",
             "LowCardinality(String)",
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use crate::connector::clickhouse::ClickHouseType;
-use crate::Result;
+use crate::{decimal_utils, Result};
 use crate::data::Value;
 use crate::types::DataType;
 
@@ -24,7 +24,10 @@ pub enum ClickHouseValue {
     Date(u16),
     DateTime(u32),
     DateTime64(i64, u32),
+    /// 非标度值, 标度为列类型的标度
+    Decimal(i128),
     Array(Vec<ClickHouseValue>),
+    Map(Vec<(ClickHouseValue, ClickHouseValue)>),
 }
 
 impl ClickHouseValue {
@@ -114,6 +117,11 @@ pub fn make_value_converter(data_type: DataType, ck_type: ClickHouseType) -> Res
             DataType::Float => Ok(Box::new(FloatToStringConverter)),
             DataType::Double => Ok(Box::new(DoubleToStringConverter)),
             DataType::String => Ok(Box::new(StringToStringConverter)),
+            DataType::Decimal(_, _) => Ok(Box::new(DecimalToStringConverter)),
+            _ => Err(format!("cant not converter {} to {}", data_type, ck_type)),
+        },
+        ClickHouseType::Decimal(precision, scale) => match data_type {
+            DataType::Decimal(_, _) => Ok(Box::new(DecimalToDecimalConverter { precision: precision as u8, scale: scale as u8 })),
             _ => Err(format!("cant not converter {} to {}", data_type, ck_type)),
         },
         ClickHouseType::DateTime => match data_type {
@@ -125,6 +133,14 @@ pub fn make_value_converter(data_type: DataType, ck_type: ClickHouseType) -> Res
             let value_converter = make_value_converter(data_type, *ck_tp)?;
             Ok(Box::new(NullableValueConverter { value_converter }))
         },
+        ClickHouseType::Map(ref ck_key_tp, ref ck_value_tp) => match data_type {
+            DataType::Map(key_tp, value_tp) => {
+                let key_converter = make_value_converter(*key_tp, ck_key_tp.as_ref().clone())?;
+                let value_converter = make_value_converter(*value_tp, ck_value_tp.as_ref().clone())?;
+                Ok(Box::new(MapToMapConverter { key_converter, value_converter }))
+            },
+            _ => Err(format!("cant not converter {} to {}", data_type, ck_type)),
+        },
         _ => Err(format!("cant not converter {} to {}", data_type, ck_type)),
     }
 }
@@ -237,6 +253,34 @@ impl ToCkValueConverter for StringToStringConverter {
     }
 }
 
+struct DecimalToStringConverter;
+
+impl ToCkValueConverter for DecimalToStringConverter {
+    fn convert(&self, value: &Value) -> Result<ClickHouseValue> {
+        match value {
+            Value::Decimal(_, _) => Ok(ClickHouseValue::String(Arc::new(value.to_string()))),
+            _ => Err(format!("invalid value for DecimalToStringConverter: {:?}", value)),
+        }
+    }
+}
+
+/// 转换为列的精度和标度, 超出精度时报错
+struct DecimalToDecimalConverter {
+    precision: u8,
+    scale: u8,
+}
+
+impl ToCkValueConverter for DecimalToDecimalConverter {
+    fn convert(&self, value: &Value) -> Result<ClickHouseValue> {
+        match value {
+            Value::Decimal(v, scale) => decimal_utils::change_precision(*v, *scale, self.precision, self.scale)
+                .map(ClickHouseValue::Decimal)
+                .ok_or_else(|| format!("{} out of range for Decimal({}, {})", value, self.precision, self.scale)),
+            _ => Err(format!("invalid value for DecimalToDecimalConverter: {:?}", value)),
+        }
+    }
+}
+
 struct TimestampToDateTimeConverter;
 
 impl ToCkValueConverter for TimestampToDateTimeConverter {
@@ -306,5 +350,28 @@ impl ToCkValueConverter for ArrayToArrayConverter {
     }
 }
 
+struct MapToMapConverter {
+    key_converter: Box<dyn ToCkValueConverter>,
+    value_converter: Box<dyn ToCkValueConverter>,
+}
+
+impl ToCkValueConverter for MapToMapConverter {
+    fn convert(&self, value: &Value) -> Result<ClickHouseValue> {
+        match value {
+            Value::Map(v) => {
+                let mut entries = Vec::with_capacity(v.len());
+                for (k, v) in v.iter() {
+                    // null value由MapColumnData写默认值
+                    let v = if v.is_null() { ClickHouseValue::Null } else { self.value_converter.convert(v)? };
+                    entries.push((self.key_converter.convert(k)?, v));
+                }
+                Ok(ClickHouseValue::Map(entries))
+            },
+            Value::Null => Ok(ClickHouseValue::Null),
+            _ => Err(format!("invalid value for MapToMapConverter: {:?}", value)),
+        }
+    }
+}
+
 
 
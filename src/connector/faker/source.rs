@@ -10,6 +10,7 @@ use crate::connector::faker::{Faker, FieldFaker};
 use crate::connector::Source;
 use crate::data::{GenericRow, Row};
 use crate::datetime_utils::current_timestamp_millis;
+use crate::rate_limiter::{RateLimiter, RATE_LIMIT_MAX_WAIT_MS};
 use crate::execution::{Collector, PollStatus};
 use crate::physical_expr::{get_cast_func};
 use crate::types::{DataType, Schema};
@@ -22,11 +23,9 @@ pub struct FakerSource {
     number_of_rows: i64,
     millis_per_row: i64,
     rows_for_subtask: i64,
-    rows_per_second_subtask: i32,
+    rate_limiter: RateLimiter,
     row: GenericRow,
     rows: i64,
-    batch_rows: i32,
-    next_read_ts: u64,
 }
 
 impl FakerSource {
@@ -44,41 +43,26 @@ impl FakerSource {
             FieldFaker::new(i, faker, get_cast_func(from, to))
         }).collect();
         let rows_for_subtask = Self::get_rows_for_subtask(number_of_rows, &task_context);
-        let rows_per_second_subtask = Self::get_rows_per_second_subtask(rows_per_second, &task_context);
+        let rate_limiter = RateLimiter::new(Self::get_rows_per_second_subtask(rows_per_second, &task_context) as u64);
         let row = GenericRow::new_with_size(fields.len());
         let rows = 0;
-        let batch_rows = 0;
-        let next_read_ts = current_timestamp_millis() / 1000 * 1000;
-        Self{ task_context, schema, field_fakers, rows_per_second, number_of_rows, millis_per_row, rows_for_subtask, rows_per_second_subtask, row, rows, batch_rows, next_read_ts }
+        Self{ task_context, schema, field_fakers, rows_per_second, number_of_rows, millis_per_row, rows_for_subtask, rate_limiter, row, rows }
     }
 
     fn get_rows_for_subtask(number_of_rows: i64, task_context: &TaskContext) -> i64 {
         if number_of_rows < 0 {
             i64::MAX
         } else {
-            let num_subtasks = task_context.task_config.subtask_parallelism as i64;
-            let index_of_this_subtask = task_context.task_config.subtask_index as i64;
-            let base_rows_per_subtask = number_of_rows / num_subtasks;
-            if number_of_rows % num_subtasks > index_of_this_subtask  {
-                base_rows_per_subtask + 1
-            } else {
-                base_rows_per_subtask
-            }
+            task_context.task_config.subtask_share(number_of_rows)
         }
     }
 
+    /// 并行度大于rows_per_second时每个subtask至少每秒1行
     fn get_rows_per_second_subtask(rows_per_second: i32, task_context: &TaskContext) -> i32 {
         if rows_per_second < 0 {
             1
         } else {
-            let num_subtasks = task_context.task_config.subtask_parallelism as i32;
-            let index_of_this_subtask = task_context.task_config.subtask_index as i32;
-            let base_rows_per_second_per_subtask = rows_per_second / num_subtasks;
-            if rows_per_second % num_subtasks > index_of_this_subtask {
-                base_rows_per_second_per_subtask + 1
-            } else {
-                base_rows_per_second_per_subtask
-            }
+            task_context.task_config.subtask_share(rows_per_second as i64).max(1) as i32
         }
     }
 
@@ -153,7 +137,6 @@ impl Source for FakerSource  {
         for field_faker in self.field_fakers.iter_mut() {
             field_faker.faker.init()?
         }
-        self.next_read_ts = current_timestamp_millis() / 1000 * 1000;
         Ok(())
     }
 
@@ -161,6 +144,10 @@ impl Source for FakerSource  {
         if self.rows >= self.rows_for_subtask {
             return Ok(PollStatus::End);
         }
+        // 限速等待不超过RATE_LIMIT_MAX_WAIT_MS, 返回后由SourceOperator检查停止信号
+        if self.millis_per_row <= 0 && !self.rate_limiter.acquire_timeout(RATE_LIMIT_MAX_WAIT_MS) {
+            return Ok(PollStatus::More);
+        }
 
         self.task_context.base_iometrics.num_records_in_inc_by(1);
         self.row.fill_null();
@@ -185,17 +172,6 @@ impl Source for FakerSource  {
 
         if self.millis_per_row > 0 {
             sleep(Duration::from_millis(self.millis_per_row as u64));
-        } else {
-            self.batch_rows += 1;
-            if self.batch_rows >= self.rows_per_second_subtask {
-                self.batch_rows = 0;
-                self.next_read_ts += 1000;
-                let current_ts = current_timestamp_millis();
-                if self.next_read_ts > current_ts {
-                    let wait_ms = self.next_read_ts - current_ts;
-                    sleep(Duration::from_millis(wait_ms))
-                }
-            }
         }
 
         Ok(PollStatus::More)
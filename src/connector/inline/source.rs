@@ -5,7 +5,7 @@ use crate::Result;
 use crate::codecs::Deserializer;
 use crate::config::TaskContext;
 use crate::connector::Source;
-use crate::datetime_utils::current_timestamp_millis;
+use crate::rate_limiter::{RateLimiter, RATE_LIMIT_MAX_WAIT_MS};
 use crate::execution::{Collector, PollStatus};
 use crate::types::Schema;
 
@@ -18,51 +18,34 @@ pub struct InlineSource {
     number_of_rows: i64,
     millis_per_row: i64,
     rows_for_subtask: i64,
-    rows_per_second_subtask: i32,
+    rate_limiter: RateLimiter,
     index: usize,
     rows: i64,
-    batch_rows: i32,
-    next_read_ts: u64,
 }
 
 impl InlineSource {
     pub fn new(task_context: TaskContext, schema: Schema, deserializer: Box<dyn Deserializer>, datas: Vec<Vec<u8>>, rows_per_second: i32, number_of_rows: i64, millis_per_row: i64) -> Self {
         let rows_for_subtask = Self::get_rows_for_subtask(number_of_rows, &task_context);
-        let rows_per_second_subtask = Self::get_rows_per_second_subtask(rows_per_second, &task_context);
+        let rate_limiter = RateLimiter::new(Self::get_rows_per_second_subtask(rows_per_second, &task_context) as u64);
         let index = 0;
         let rows = 0;
-        let batch_rows = 0;
-        let next_read_ts = current_timestamp_millis() / 1000 * 1000;
-        Self{ task_context, schema, deserializer, datas, rows_per_second, number_of_rows, millis_per_row, rows_for_subtask, rows_per_second_subtask, index, rows, batch_rows, next_read_ts }
+        Self{ task_context, schema, deserializer, datas, rows_per_second, number_of_rows, millis_per_row, rows_for_subtask, rate_limiter, index, rows }
     }
 
     fn get_rows_for_subtask(number_of_rows: i64, task_context: &TaskContext) -> i64 {
         if number_of_rows < 0 {
             i64::MAX
         } else {
-            let num_subtasks = task_context.task_config.subtask_parallelism as i64;
-            let index_of_this_subtask = task_context.task_config.subtask_index as i64;
-            let base_rows_per_subtask = number_of_rows / num_subtasks;
-            if number_of_rows % num_subtasks > index_of_this_subtask  {
-                base_rows_per_subtask + 1
-            } else {
-                base_rows_per_subtask
-            }
+            task_context.task_config.subtask_share(number_of_rows)
         }
     }
 
+    /// 并行度大于rows_per_second时每个subtask至少每秒1行
     fn get_rows_per_second_subtask(rows_per_second: i32, task_context: &TaskContext) -> i32 {
         if rows_per_second < 0 {
             1
         } else {
-            let num_subtasks = task_context.task_config.subtask_parallelism as i32;
-            let index_of_this_subtask = task_context.task_config.subtask_index as i32;
-            let base_rows_per_second_per_subtask = rows_per_second / num_subtasks;
-            if rows_per_second % num_subtasks > index_of_this_subtask {
-                base_rows_per_second_per_subtask + 1
-            } else {
-                base_rows_per_second_per_subtask
-            }
+            task_context.task_config.subtask_share(rows_per_second as i64).max(1) as i32
         }
     }
 
@@ -83,7 +66,6 @@ impl Source for InlineSource {
     }
 
     fn open(&mut self) -> Result<()> {
-        self.next_read_ts = current_timestamp_millis() / 1000 * 1000;
         Ok(())
     }
 
@@ -91,10 +73,17 @@ impl Source for InlineSource {
         if self.rows >= self.rows_for_subtask {
             return Ok(PollStatus::End);
         }
+        // 限速等待不超过RATE_LIMIT_MAX_WAIT_MS, 返回后由SourceOperator检查停止信号
+        if self.millis_per_row <= 0 && !self.rate_limiter.acquire_timeout(RATE_LIMIT_MAX_WAIT_MS) {
+            return Ok(PollStatus::More);
+        }
 
         self.task_context.base_iometrics.num_records_in_inc_by(1);
-        let row = self.deserializer.deserialize(self.datas[self.index].as_slice())?;
-        out.collect(row)?;
+        let data = self.datas[self.index].as_slice();
+        match self.deserializer.deserialize(data) {
+            Ok(row) => out.collect(row)?,
+            Err(e) => self.task_context.error_handler.handle(e, data)?,
+        }
         self.rows += 1;
         self.index += 1;
         if self.index >= self.datas.len() {
@@ -104,19 +93,38 @@ impl Source for InlineSource {
 
         if self.millis_per_row > 0 {
             sleep(Duration::from_millis(self.millis_per_row as u64));
-        } else {
-            self.batch_rows += 1;
-            if self.batch_rows >= self.rows_per_second_subtask {
-                self.batch_rows = 0;
-                self.next_read_ts += 1000;
-                let current_ts = current_timestamp_millis();
-                if self.next_read_ts > current_ts {
-                    let wait_ms = self.next_read_ts - current_ts;
-                    sleep(Duration::from_millis(wait_ms))
-                }
-            }
         }
 
         Ok(PollStatus::More)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use prometheus::Registry;
+    use crate::codecs::json::JsonDeserializer;
+    use crate::config::{BaseIOMetrics, OperatorConfig, TaskConfig};
+    use crate::execution::VecCollector;
+    use crate::types::{DataType, Field};
+    use super::*;
+
+    #[test]
+    fn test_parallelism_greater_than_rows_per_second() {
+        let schema = Schema::new(vec![Field::new("id", DataType::Int)]);
+        let registry = Registry::new();
+        // rows_per_second为1, 第3个subtask也能输出
+        let task_context = TaskContext::new(TaskConfig::new(3, 2, registry.clone()), OperatorConfig::new(0, ""), Arc::new(BaseIOMetrics::new(&registry, "".to_string())));
+        let deserializer = Box::new(JsonDeserializer::new(schema.clone()));
+        let mut source = InlineSource::new(task_context, schema, deserializer, vec![br#"{"id": 1}"#.to_vec()], 1, 3, 0);
+        let mut out = VecCollector::default();
+        let mut ended = false;
+        for _ in 0..10 {
+            if let PollStatus::End = source.poll_next(&mut out).unwrap() {
+                ended = true;
+                break;
+            }
+        }
+        assert_eq!(out.rows.len(), 1);
+        assert!(ended);
+    }
+}
@@ -12,6 +12,23 @@ pub struct KafkaSourceConfig {
     topics: Vec<String>,
     properties: HashMap<String, String>,
     decoding: Box<dyn DeserializerConfig>,
+    /// 大于0时关闭自动提交, 每隔checkpoint_interval_ms注入barrier, 下游sink都写入成功后提交offset
+    #[serde(default)]
+    checkpoint_interval_ms: u64,
+    #[serde(default)]
+    assignment: PartitionAssignment,
+}
+
+/// 分区分配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionAssignment {
+    /// 使用消费者组订阅, 由broker分配分区, 多个进程使用相同group.id消费时使用
+    #[default]
+    Subscribe,
+    /// 按subtask_index/subtask_parallelism固定分配分区, 所有topic的分区按(topic, partition)排序后轮流分配.
+    /// 只在open时获取分区, 之后新增的分区不会被消费; 每个进程都会消费全部分区, 不能多个进程使用相同group.id分摊分区
+    Assign,
 }
 
 #[typetag::serde(name = "kafka")]
@@ -43,7 +60,9 @@ impl SourceProvider for KafkaSourceProvider {
             self.schema.clone(),
             self.source_config.topics.clone(),
             self.source_config.properties.clone(),
-            self.source_config.decoding.build(self.schema.clone())?
+            self.source_config.decoding.build(self.schema.clone())?,
+            self.source_config.checkpoint_interval_ms,
+            self.source_config.assignment,
         )?;
         Ok(Box::new(kafka_source))
     }
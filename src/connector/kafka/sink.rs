@@ -67,6 +67,10 @@ impl Sink for KafkaSink {
         }
     }
 
+    fn barrier(&mut self, _checkpoint_id: u64) -> Result<()> {
+        self.producer.flush(Duration::from_secs(30)).map_err(|e| e.to_string())
+    }
+
     fn close(&mut self) -> Result<()> {
         self.producer.flush(Duration::from_secs(30)).map_err(|e| e.to_string())
     }
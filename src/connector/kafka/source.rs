@@ -3,16 +3,20 @@ use std::fmt::Debug;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use rdkafka::{ClientConfig, Message};
-use rdkafka::consumer::{BaseConsumer, Consumer};
+use log::{info, warn};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
 use crate::Result;
 use crate::codecs::Deserializer;
 use crate::config::TaskContext;
+use crate::connector::kafka::PartitionAssignment;
 use crate::connector::Source;
+use crate::datetime_utils::current_timestamp_millis;
 use crate::execution::{Collector, PollStatus};
 use crate::types::Schema;
 
 static POLL_TIMEOUT: Duration = Duration::from_millis(200);
+static METADATA_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct KafkaSource {
     task_context: TaskContext,
@@ -20,16 +24,86 @@ pub struct KafkaSource {
     topics: Vec<String>,
     consumer: BaseConsumer,
     deserializer: Box<dyn Deserializer>,
+    checkpoint_interval_ms: u64,
+    assignment: PartitionAssignment,
+    checkpoint_id: u64,
+    last_checkpoint_ts: u64,
+    // (topic, partition, offset) 上次checkpoint之后消费的最大offset
+    offsets: Vec<(String, i32, i64)>,
 }
 
 impl KafkaSource {
-    pub fn new(task_context: TaskContext, schema: Schema, topics: Vec<String>, properties: HashMap<String, String>, deserializer: Box<dyn Deserializer>) -> Result<Self> {
+    pub fn new(task_context: TaskContext, schema: Schema, topics: Vec<String>, properties: HashMap<String, String>, deserializer: Box<dyn Deserializer>, checkpoint_interval_ms: u64, assignment: PartitionAssignment) -> Result<Self> {
         let mut config = ClientConfig::new();
         for (k, v) in properties.into_iter() {
             config.set(k, v);
         }
+        if checkpoint_interval_ms > 0 {
+            config.set("enable.auto.commit", "false");
+        }
         let consumer = config.create().map_err(|e| e.to_string())?;
-        Ok(Self { task_context, schema, topics, consumer, deserializer, })
+        Ok(Self { task_context, schema, topics, consumer, deserializer, checkpoint_interval_ms, assignment, checkpoint_id: 0, last_checkpoint_ts: current_timestamp_millis(), offsets: Vec::new() })
+    }
+
+    /// 返回当前subtask负责的分区
+    fn assign_partitions(mut partitions: Vec<(String, i32)>, subtask_index: u8, subtask_parallelism: u8) -> Vec<(String, i32)> {
+        partitions.sort();
+        partitions.into_iter().enumerate()
+            .filter(|(i, _)| i % subtask_parallelism as usize == subtask_index as usize)
+            .map(|(_, tp)| tp).collect()
+    }
+
+    fn assign(&mut self) -> Result<()> {
+        let mut partitions = Vec::new();
+        for topic in self.topics.iter() {
+            let metadata = self.consumer.fetch_metadata(Some(topic), METADATA_TIMEOUT).map_err(|e| e.to_string())?;
+            let topic_metadata = metadata.topics().iter().find(|t| t.name() == topic).ok_or_else(|| format!("topic {} not found", topic))?;
+            if let Some(e) = topic_metadata.error() {
+                return Err(format!("fetch topic {} metadata error: {:?}", topic, e));
+            }
+            partitions.extend(topic_metadata.partitions().iter().map(|p| (topic.clone(), p.id())));
+        }
+        let task_config = &self.task_context.task_config;
+        let partitions = Self::assign_partitions(partitions, task_config.subtask_index, task_config.subtask_parallelism);
+        if partitions.is_empty() {
+            warn!("subtask {} has no partitions assigned, topics:{:?}", task_config.subtask_index, self.topics);
+        } else {
+            info!("subtask {} assign partitions: {:?}", task_config.subtask_index, partitions);
+        }
+        // 没有指定offset时从消费者组提交的offset开始消费
+        let mut tpl = TopicPartitionList::new();
+        for (topic, partition) in partitions.iter() {
+            tpl.add_partition(topic, *partition);
+        }
+        self.consumer.assign(&tpl).map_err(|e| e.to_string())
+    }
+
+    fn record_offset(offsets: &mut Vec<(String, i32, i64)>, topic: &str, partition: i32, offset: i64) {
+        match offsets.iter_mut().find(|(t, p, _)| *p == partition && t == topic) {
+            Some((_, _, o)) => *o = offset.max(*o),
+            None => offsets.push((topic.to_string(), partition, offset)),
+        }
+    }
+
+    /// 注入barrier, 下游sink都写入成功后同步提交记录的offset
+    fn checkpoint(&mut self, out: &mut dyn Collector) -> Result<()> {
+        self.last_checkpoint_ts = current_timestamp_millis();
+        if self.offsets.is_empty() {
+            return Ok(());
+        }
+        self.checkpoint_id += 1;
+        out.barrier(self.checkpoint_id)?;
+        self.task_context.error_handler.barrier(self.checkpoint_id)?;
+        let mut tpl = TopicPartitionList::new();
+        for (topic, partition, offset) in self.offsets.drain(..) {
+            tpl.add_partition_offset(&topic, partition, Offset::Offset(offset + 1)).map_err(|e| e.to_string())?;
+        }
+        // 提交失败只会导致重复消费
+        match self.consumer.commit(&tpl, CommitMode::Sync) {
+            Ok(_) => info!("checkpoint {} commit offsets: {:?}", self.checkpoint_id, tpl),
+            Err(e) => warn!("checkpoint {} commit offsets error: {}", self.checkpoint_id, e),
+        }
+        Ok(())
     }
 
     fn run(&mut self, out: &mut dyn Collector, terminated: Arc<AtomicBool>) -> Result<()> {
@@ -44,9 +118,13 @@ impl KafkaSource {
                     if let Some(payload) = message.payload() {
                         self.task_context.base_iometrics.num_records_in_inc_by(1);
                         self.task_context.base_iometrics.num_bytes_in_inc_by(payload.len() as u64);
-                        let row = self.deserializer.deserialize(payload)?;
-                        self.task_context.base_iometrics.num_records_out_inc_by(1);
-                        out.collect(row)?;
+                        match self.deserializer.deserialize(payload) {
+                            Ok(row) => {
+                                self.task_context.base_iometrics.num_records_out_inc_by(1);
+                                out.collect(row)?;
+                            },
+                            Err(e) => self.task_context.error_handler.handle(e, payload)?,
+                        }
 
                     }
                 }
@@ -74,7 +152,10 @@ impl Source for KafkaSource {
     }
 
     fn open(&mut self) -> Result<()> {
-        self.consumer.subscribe(self.topics.iter().map(|t| t.as_str()).collect::<Vec<_>>().as_slice()).map_err(|e| e.to_string())
+        match self.assignment {
+            PartitionAssignment::Assign => self.assign(),
+            PartitionAssignment::Subscribe => self.consumer.subscribe(self.topics.iter().map(|t| t.as_str()).collect::<Vec<_>>().as_slice()).map_err(|e| e.to_string()),
+        }
     }
 
     fn poll_next(&mut self, out: &mut dyn Collector) -> Result<PollStatus> {
@@ -85,16 +166,39 @@ impl Source for KafkaSource {
                 if let Some(payload) = message.payload() {
                     self.task_context.base_iometrics.num_records_in_inc_by(1);
                     self.task_context.base_iometrics.num_bytes_in_inc_by(payload.len() as u64);
-                    let row = self.deserializer.deserialize(payload)?;
-                    self.task_context.base_iometrics.num_records_out_inc_by(1);
-                    out.collect(row)?;
+                    match self.deserializer.deserialize(payload) {
+                        Ok(row) => {
+                            self.task_context.base_iometrics.num_records_out_inc_by(1);
+                            out.collect(row)?;
+                        },
+                        Err(e) => self.task_context.error_handler.handle(e, payload)?,
+                    }
+                }
+                if self.checkpoint_interval_ms > 0 {
+                    Self::record_offset(&mut self.offsets, message.topic(), message.partition(), message.offset());
                 }
             }
             Some(Err(e)) => return Err(e.to_string()),
             None => (),
         }
+        if self.checkpoint_interval_ms > 0 && current_timestamp_millis() >= self.last_checkpoint_ts + self.checkpoint_interval_ms {
+            self.checkpoint(out)?;
+        }
         Ok(PollStatus::More)
     }
 
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_partitions() {
+        let partitions: Vec<(String, i32)> = (0..3).map(|p| ("b".to_string(), p)).chain((0..2).map(|p| ("a".to_string(), p))).collect();
+        let assigned: Vec<_> = (0..2).map(|i| KafkaSource::assign_partitions(partitions.clone(), i, 2)).collect();
+        assert_eq!(assigned[0], vec![("a".to_string(), 0), ("b".to_string(), 0), ("b".to_string(), 2)]);
+        assert_eq!(assigned[1], vec![("a".to_string(), 1), ("b".to_string(), 1)]);
+        assert!(KafkaSource::assign_partitions(partitions, 5, 6).is_empty());
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::config::{SinkConfig, SinkProvider, TaskContext};
+use crate::connector::Sink;
+use crate::connector::memory::MemorySink;
+use crate::types::Schema;
+
+/// 输出的json字符串, 运行结束后由调用方读取
+pub type MemoryRows = Arc<Mutex<Vec<String>>>;
+
+/// 把数据序列化为json保存在内存中, 只用于retl test替换配置中的sink
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemorySinkConfig {
+    #[serde(skip)]
+    rows: MemoryRows,
+}
+
+impl MemorySinkConfig {
+    pub fn new(rows: MemoryRows) -> Self {
+        Self { rows }
+    }
+}
+
+#[typetag::serde(name = "memory")]
+impl SinkConfig for MemorySinkConfig {
+    fn build(&self, schema: Schema) -> crate::Result<Box<dyn SinkProvider>> {
+        Ok(Box::new(MemorySinkProvider { schema, rows: self.rows.clone() }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MemorySinkProvider {
+    schema: Schema,
+    rows: MemoryRows,
+}
+
+impl SinkProvider for MemorySinkProvider {
+    fn create_sink(&self, task_context: TaskContext) -> crate::Result<Box<dyn Sink>> {
+        Ok(Box::new(MemorySink::new(self.schema.clone(), self.rows.clone(), task_context)))
+    }
+}
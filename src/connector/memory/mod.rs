@@ -0,0 +1,5 @@
+mod sink;
+mod config;
+
+pub use sink::*;
+pub use config::*;
@@ -0,0 +1,32 @@
+use crate::Result;
+use crate::codecs::Serializer;
+use crate::codecs::json::JsonSerializer;
+use crate::config::TaskContext;
+use crate::connector::Sink;
+use crate::connector::memory::MemoryRows;
+use crate::data::Row;
+use crate::types::Schema;
+
+#[derive(Debug)]
+pub struct MemorySink {
+    serializer: JsonSerializer,
+    rows: MemoryRows,
+    task_context: TaskContext,
+}
+
+impl MemorySink {
+    pub fn new(schema: Schema, rows: MemoryRows, task_context: TaskContext) -> Self {
+        Self { serializer: JsonSerializer::new(schema), rows, task_context }
+    }
+}
+
+impl Sink for MemorySink {
+    fn invoke(&mut self, row: &dyn Row) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        let bytes = self.serializer.serialize(row)?;
+        self.task_context.base_iometrics.num_records_out_inc_by(1);
+        self.task_context.base_iometrics.num_bytes_out_inc_by(bytes.len() as u64);
+        self.rows.lock().unwrap().push(String::from_utf8_lossy(bytes).into_owned());
+        Ok(())
+    }
+}
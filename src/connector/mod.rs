@@ -2,6 +2,7 @@ pub mod faker;
 pub mod source;
 pub mod sink;
 pub mod print;
+pub mod memory;
 #[cfg(feature = "kafka")]
 pub mod kafka;
 #[cfg(feature = "starrocks")]
@@ -74,6 +74,7 @@ impl MysqlSink {
             DataType::Long => sql_value.push_str(&v.get_long().to_string()),
             DataType::Float => sql_value.push_str(&v.get_float().to_string()),
             DataType::Double => sql_value.push_str(&v.get_double().to_string()),
+            DataType::Decimal(_, _) => sql_value.push_str(&v.to_string()),
             DataType::String => {
                 // TODO: escape string.
                 sql_value.push_str("'");
@@ -143,5 +144,18 @@ impl Sink for MysqlSink {
 
         Ok(())
     }
+
+    fn barrier(&mut self, _checkpoint_id: u64) -> crate::Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        self.update_sql.push_str(&self.insert_sql_suffix);
+        info!("flush rows: {}", self.rows);
+        let rst = self.flush().map_err(|e| format!("flush error: {}", e));
+        self.rows = 0;
+        self.update_sql.clear();
+        self.update_sql.push_str(&self.insert_sql_prefix);
+        rst
+    }
 }
 
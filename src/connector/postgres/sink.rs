@@ -10,7 +10,7 @@ use log::{error, info};
 use postgres::{Client, NoTls};
 use crate::Result;
 use crate::config::{BaseIOMetrics, TaskContext};
-use crate::connector::batch::BatchConfig;
+use crate::connector::batch::{BatchConfig, FlushProgress};
 use crate::connector::postgres::config::{PostgresDefaultBatchSettings, PostgresSinkConfig};
 use crate::connector::Sink;
 use crate::data::{Row, Value};
@@ -27,6 +27,7 @@ pub struct PostgresSink {
     sql_value: String,
     stoped: Arc<AtomicBool>,
     shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>,
+    flush_progress: Arc<FlushProgress>,
     flush_handle: Option<JoinHandle<()>>,
 }
 
@@ -58,6 +59,7 @@ impl PostgresSink {
             sql_value,
             stoped,
             shared_blocks,
+            flush_progress: Arc::new(FlushProgress::default()),
             flush_handle: None,
         })
     }
@@ -85,6 +87,7 @@ impl PostgresSink {
             DataType::Long => sql_value.push_str(&v.get_long().to_string()),
             DataType::Float => sql_value.push_str(&v.get_float().to_string()),
             DataType::Double => sql_value.push_str(&v.get_double().to_string()),
+            DataType::Decimal(_, _) => sql_value.push_str(&v.to_string()),
             DataType::String => {
                 let v = v.get_string();
                 Self::put_str_escape(sql_value, v);
@@ -129,11 +132,12 @@ impl Sink for PostgresSink {
         let connect_params = self.connect_params.clone();
         let stoped = self.stoped.clone();
         let shared_blocks = self.shared_blocks.clone();//block_deque
+        let flush_progress = self.flush_progress.clone();
         let interval_ms = self.batch_config.interval_ms;
         let subtask_index =  self.task_context.task_config.subtask_index;
         let thread_name = format!("flush-{}-{}/{}", self.table.as_str(), subtask_index + 1, self.task_context.task_config.subtask_parallelism);
         let flush_handle = thread::Builder::new().name(thread_name).stack_size(512 * 1024).spawn(move || {
-            PostgresSink::process_flush_block(base_iometrics, connect_params, stoped, shared_blocks, interval_ms)
+            PostgresSink::process_flush_block(base_iometrics, connect_params, stoped, shared_blocks, flush_progress, interval_ms)
         }).map_err(|e| e.to_string())?;
         self.flush_handle = Some(flush_handle);
         Ok(())
@@ -153,6 +157,7 @@ impl Sink for PostgresSink {
             while shared_blocks.0.len() >= 1 {
                 shared_blocks = cvar.wait(shared_blocks).unwrap(); // 等待工作线程处理完成
             }
+            self.flush_progress.begin();
             shared_blocks.0.push_back(data_block);
             cvar.notify_one(); // 通知工作线程
         }
@@ -164,6 +169,20 @@ impl Sink for PostgresSink {
         Ok(())
     }
 
+    fn barrier(&mut self, _checkpoint_id: u64) -> Result<()> {
+        let (lock, cvar) = self.shared_blocks.as_ref();
+        let mut shared_blocks = lock.lock().unwrap();
+        if shared_blocks.1.rows > 0 {
+            let empty_block = shared_blocks.1.copy_empty();
+            let data_block = mem::replace(&mut shared_blocks.1, empty_block);
+            self.flush_progress.begin();
+            shared_blocks.0.push_back(data_block);
+            cvar.notify_one(); // 通知工作线程
+        }
+        drop(shared_blocks);
+        self.flush_progress.wait()
+    }
+
     fn close(&mut self) -> Result<()> {
         self.stoped.store(true, Ordering::SeqCst);
         if let Some(flush_handle) = self.flush_handle.take() {
@@ -177,7 +196,7 @@ impl Sink for PostgresSink {
 
 impl PostgresSink {
     fn process_flush_block(base_iometrics: Arc<BaseIOMetrics>, connect_params: Arc<String>, stoped: Arc<AtomicBool>,
-                           shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>, interval_ms: u64)  {
+                           shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>, flush_progress: Arc<FlushProgress>, interval_ms: u64)  {
         let mut last_flush_ts = current_timestamp_millis();
         let (lock, cvar) = shared_blocks.as_ref();
         let mut has_stoped = false;
@@ -196,7 +215,8 @@ impl PostgresSink {
             if let Some(block) = shared_blocks.0.pop_front() {
                 cvar.notify_one(); // 通知生产线程
                 drop(shared_blocks); // 释放共享数据的锁
-                Self::flush_block(&base_iometrics, connect_params.as_str(), &mut last_flush_ts, block);
+                let success = Self::flush_block(&base_iometrics, connect_params.as_str(), &mut last_flush_ts, block);
+                flush_progress.end(success);
             } else {
                 if current_timestamp_millis() >= last_flush_ts + interval_ms || has_stoped {
                     if shared_blocks.1.rows == 0 {
@@ -209,8 +229,10 @@ impl PostgresSink {
 
                     let empty_block = shared_blocks.1.copy_empty();
                     let block = mem::replace(&mut shared_blocks.1, empty_block);
+                    flush_progress.begin();
                     drop(shared_blocks); // 释放共享数据的锁
-                    Self::flush_block(&base_iometrics, connect_params.as_str(), &mut last_flush_ts, block);
+                    let success = Self::flush_block(&base_iometrics, connect_params.as_str(), &mut last_flush_ts, block);
+                    flush_progress.end(success);
                 }
             }
 
@@ -220,7 +242,7 @@ impl PostgresSink {
         }
     }
 
-    fn flush_block(base_iometrics: &BaseIOMetrics, connect_params: &str, last_flush_ts: &mut u64, block: Block) {
+    fn flush_block(base_iometrics: &BaseIOMetrics, connect_params: &str, last_flush_ts: &mut u64, block: Block) -> bool {
         let rows = block.rows as u64;
         let byte_size = block.byte_size as u64;
         info!("flush block start:{} rows,{} bytes, after:{}", rows, byte_size, current_timestamp_millis() - *last_flush_ts);
@@ -230,9 +252,11 @@ impl PostgresSink {
                 info!("flush block success:{} rows,{} bytes, {} ms.", rows, byte_size, current_timestamp_millis() - *last_flush_ts);
                 base_iometrics.num_records_out_inc_by(rows);
                 base_iometrics.num_bytes_out_inc_by(byte_size);
+                true
             }
             Err(e) => {
                 info!("flush block error:{:?}", e);
+                false
             }
         }
     }
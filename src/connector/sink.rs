@@ -8,6 +8,11 @@ pub trait Sink: Debug {
     }
     fn invoke(&mut self, row: &dyn Row) -> Result<()> ;
 
+    /// 收到barrier时调用, 返回时之前invoke的数据需要已经写入成功
+    fn barrier(&mut self, _checkpoint_id: u64) -> Result<()> {
+        Ok(())
+    }
+
     fn close(&mut self) -> Result<()> {
         Ok(())
     }
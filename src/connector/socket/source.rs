@@ -40,8 +40,10 @@ impl Source for SocketSource {
                     return Ok(PollStatus::More);
                 }
                 let bytes = line.as_bytes();
-                let row = self.deserializer.deserialize(bytes)?;
-                out.collect(row)?;
+                match self.deserializer.deserialize(bytes) {
+                    Ok(row) => out.collect(row)?,
+                    Err(e) => self.task_context.error_handler.handle(e, bytes)?,
+                }
                 Ok(PollStatus::More)
             },
             Err(e) => Err(e.to_string())
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use base64::Engine;
 use base64::engine::general_purpose;
@@ -50,32 +51,43 @@ pub struct StarRocksSinkConfig {
 #[typetag::serde(name = "starrocks")]
 impl SinkConfig for StarRocksSinkConfig {
     fn build(&self, schema: Schema) -> Result<Box<dyn SinkProvider>> {
-        let mut config = self.clone();
-        config.connection_config.supplement_properties()?;
-        Ok(Box::new(StarRocksSinkProvider::new(schema, config)))
+        Ok(Box::new(StarRocksSinkProvider::new(schema, self.clone())))
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct StarRocksSinkProvider {
     schema: Schema,
-    sink_config: StarRocksSinkConfig
+    sink_config: StarRocksSinkConfig,
+    /// 补充properties后的连接配置, 需要查询表结构, 在第一次create_sink时生成, 所有subtask共享
+    connection_config: Arc<Mutex<Option<ConnectionConfig>>>,
 }
 
 impl StarRocksSinkProvider {
     pub fn new(schema: Schema, sink_config: StarRocksSinkConfig) -> Self {
         Self {
             schema,
-            sink_config
+            sink_config,
+            connection_config: Arc::new(Mutex::new(None)),
         }
     }
+
+    fn supplemented_connection_config(&self) -> Result<ConnectionConfig> {
+        let mut connection_config = self.connection_config.lock().unwrap();
+        if connection_config.is_none() {
+            let mut config = self.sink_config.connection_config.clone();
+            config.supplement_properties()?;
+            *connection_config = Some(config);
+        }
+        Ok(connection_config.clone().unwrap())
+    }
 }
 
 impl SinkProvider for StarRocksSinkProvider {
     fn create_sink(&self, task_context: TaskContext) -> Result<Box<dyn Sink>> {
         Ok(Box::new(StarRocksSink::new(
             task_context,
-            self.sink_config.connection_config.clone(),
+            self.supplemented_connection_config()?,
             self.sink_config.batch_config.clone(),
             JsonSerializer::new(self.schema.clone()),
         )))
@@ -261,4 +273,20 @@ pub fn basic_auth_header(username: &str, password: &str) -> String {
     let to_encode = format!("{}:{}", username, password);
     let encoded = general_purpose::STANDARD.encode(&to_encode);
     format!("Basic {}", encoded)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_without_connection() {
+        // build只检查配置, 不会查询表结构, 不可达的地址也能通过
+        let config: StarRocksSinkConfig = serde_json::from_value(json!({
+            "host": "127.0.0.1:1", "username": "root", "password": "", "database": "db", "table": "tbl",
+        })).unwrap();
+        let schema = crate::parser::parse_schema("id int, name string").unwrap();
+        assert!(config.build(schema.clone()).is_ok());
+        let provider = StarRocksSinkProvider::new(schema, config);
+        assert!(provider.supplemented_connection_config().is_err());
+    }
+}
@@ -21,7 +21,7 @@ use crate::buffer_pool::BufferPool;
 use crate::codecs::Serializer;
 use crate::codecs::json::JsonSerializer;
 use crate::config::{BaseIOMetrics, TaskContext};
-use crate::connector::batch::BatchConfig;
+use crate::connector::batch::{BatchConfig, FlushProgress};
 use crate::connector::Sink;
 use crate::connector::starrocks::{basic_auth_header, lz4, ConnectionConfig, StarRocksDefaultBatchSettings};
 use crate::data::Row;
@@ -39,6 +39,7 @@ pub struct StarRocksSink {
     buffer_pool: BufferPool,
     stoped: Arc<AtomicBool>,
     shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>,
+    flush_progress: Arc<FlushProgress>,
     flush_handle: Option<JoinHandle<()>>,
     flush_err: Option<String>,
     total_flush: Arc<AtomicU64>,
@@ -61,6 +62,7 @@ impl StarRocksSink {
             buffer_pool,
             stoped,
             shared_blocks,
+            flush_progress: Arc::new(FlushProgress::default()),
             flush_handle: None,
             flush_err: None,
             total_flush: Arc::new(AtomicU64::new(current_timestamp_millis())),
@@ -87,11 +89,12 @@ impl Sink for StarRocksSink {
         let stoped = self.stoped.clone();
         let shared_blocks = self.shared_blocks.clone();//block_deque
         let total_flush = self.total_flush.clone();
+        let flush_progress = self.flush_progress.clone();
         let interval_ms = self.batch_config.interval_ms;
         let subtask_index =  self.task_context.task_config.subtask_index;
         let thread_name = format!("flush-{}-{}/{}", self.connection_config.table, subtask_index + 1, self.task_context.task_config.subtask_parallelism);
         let flush_handle = thread::Builder::new().name(thread_name).stack_size(512 * 1024).spawn(move || {
-            StarRocksSink::process_flush_block(subtask_index, base_iometrics, connection_config, stoped, shared_blocks, total_flush, interval_ms)
+            StarRocksSink::process_flush_block(subtask_index, base_iometrics, connection_config, stoped, shared_blocks, flush_progress, total_flush, interval_ms)
         }).map_err(|e| e.to_string())?;
         self.flush_handle = Some(flush_handle);
         Ok(())
@@ -113,6 +116,7 @@ impl Sink for StarRocksSink {
             while shared_blocks.0.len() >= 1 {
                 shared_blocks = cvar.wait(shared_blocks).unwrap(); // 等待工作线程处理完成
             }
+            self.flush_progress.begin();
             shared_blocks.0.push_back(data_block);
             cvar.notify_one(); // 通知工作线程
         }
@@ -124,6 +128,20 @@ impl Sink for StarRocksSink {
         Ok(())
     }
 
+    fn barrier(&mut self, _checkpoint_id: u64) -> Result<()> {
+        let (lock, cvar) = self.shared_blocks.as_ref();
+        let mut shared_blocks = lock.lock().unwrap();
+        if shared_blocks.1.batch_rows > 0 {
+            shared_blocks.1.write_end();
+            let data_block = mem::replace(&mut shared_blocks.1, Block::new(self.buffer_pool.clone()));
+            self.flush_progress.begin();
+            shared_blocks.0.push_back(data_block);
+            cvar.notify_one(); // 通知工作线程
+        }
+        drop(shared_blocks);
+        self.flush_progress.wait()
+    }
+
     fn close(&mut self) -> Result<()> {
         self.stoped.store(true, Ordering::SeqCst);
         if let Some(flush_handle) = self.flush_handle.take() {
@@ -137,7 +155,7 @@ impl Sink for StarRocksSink {
 
 impl StarRocksSink {
     fn process_flush_block(subtask_index: u8, base_iometrics: Arc<BaseIOMetrics>, connection_config: ConnectionConfig, stoped: Arc<AtomicBool>, shared_blocks: Arc<(Mutex<(VecDeque<Block>, Block)>, Condvar)>,
-                           total_flush: Arc<AtomicU64>, interval_ms: u64,) {
+                           flush_progress: Arc<FlushProgress>, total_flush: Arc<AtomicU64>, interval_ms: u64,) {
         let urls = connection_config.build_urls();
         let mut url_index = subtask_index as usize % urls.len();
         let mut last_flush_ts = current_timestamp_millis();
@@ -159,7 +177,8 @@ impl StarRocksSink {
             if let Some(block) = shared_blocks.0.pop_front() {
                 cvar.notify_one(); // 通知生产线程
                 drop(shared_blocks); // 释放共享数据的锁
-                Self::flush_block(&base_iometrics, &connection_config, &urls, &mut url_index, total_flush.clone(), &mut last_flush_ts, block);
+                let success = Self::flush_block(&base_iometrics, &connection_config, &urls, &mut url_index, total_flush.clone(), &mut last_flush_ts, block);
+                flush_progress.end(success);
             } else {
                 if current_timestamp_millis() >= last_flush_ts + interval_ms || has_stoped {
                     if shared_blocks.1.batch_rows == 0 {
@@ -174,8 +193,10 @@ impl StarRocksSink {
                     shared_blocks.1.write_end();
                     let empty_block = Block::new(shared_blocks.1.buffer_pool.clone());
                     let block = mem::replace(&mut shared_blocks.1, empty_block);
+                    flush_progress.begin();
                     drop(shared_blocks); // 释放共享数据的锁
-                    Self::flush_block(&base_iometrics, &connection_config, &urls, &mut url_index, total_flush.clone(), &mut last_flush_ts, block);
+                    let success = Self::flush_block(&base_iometrics, &connection_config, &urls, &mut url_index, total_flush.clone(), &mut last_flush_ts, block);
+                    flush_progress.end(success);
                 }
             }
 
@@ -187,13 +208,14 @@ impl StarRocksSink {
 
     }
 
-    fn flush_block(base_iometrics: &Arc<BaseIOMetrics>, connection_config: &ConnectionConfig, urls: &Vec<String>, url_index: &mut usize, total_flush: Arc<AtomicU64>,  last_flush_ts: &mut u64, block: Block )  {
+    fn flush_block(base_iometrics: &Arc<BaseIOMetrics>, connection_config: &ConnectionConfig, urls: &Vec<String>, url_index: &mut usize, total_flush: Arc<AtomicU64>,  last_flush_ts: &mut u64, block: Block ) -> bool {
         let batch_rows = block.batch_rows as u64;
         let batch_bytes = block.batch_bytes;
         let buffers: Arc<Vec<BytesMut>> = Arc::new(block.buffers);
         info!("flush block start:{} rows,{} bytes, after:{}", batch_rows, batch_bytes, current_timestamp_millis() - *last_flush_ts);
         *last_flush_ts = current_timestamp_millis();
         let mut retry = 0;
+        let mut success = false;
         loop {
             retry += 1;
             match Self::flush_block_inner(connection_config, urls, url_index, buffers.clone()) {
@@ -202,6 +224,7 @@ impl StarRocksSink {
                     total_flush.fetch_add(batch_rows, Ordering::SeqCst);
                     base_iometrics.num_records_out_inc_by(batch_rows);
                     base_iometrics.num_bytes_out_inc_by(batch_bytes as u64);
+                    success = true;
                     break;
                 },
                 Err(e) => {
@@ -225,6 +248,7 @@ impl StarRocksSink {
         } else {
             warn!("Arc<Vec<BytesMut>> still has multiple references, cannot recycle yet");
         }
+        success
     }
 
     fn flush_block_inner(connection_config: &ConnectionConfig, urls: &Vec<String>, url_index: &mut usize,  buffers: Arc<Vec<BytesMut>>) ->anyhow::Result<serde_json::Value>  {
@@ -59,8 +59,10 @@ impl Source for UdpSource {
                         return Ok(PollStatus::More);
                     }
                     let data = &self.buffer[..len];
-                    let row = self.deserializer.deserialize(data)?;
-                    out.collect(row)?;
+                    match self.deserializer.deserialize(data) {
+                        Ok(row) => out.collect(row)?,
+                        Err(e) => self.task_context.error_handler.handle(e, data)?,
+                    }
                     return Ok(PollStatus::More);
                 }
                 Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
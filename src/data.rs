@@ -1,10 +1,10 @@
 use std::any::Any;
 use std::cmp::Ordering;
-use std::hash::{Hash, Hasher};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::{Arc, LazyLock};
 use std::fmt::{Debug, Display, Formatter};
 use std::string::ToString;
-use crate::{date_utils, datetime_utils};
+use crate::{date_utils, datetime_utils, decimal_utils};
 use crate::types::DataType;
 
 static EMPTY_STRING_VALUE: LazyLock<Value> = LazyLock::new(|| Value::String(Arc::new("".to_string())));
@@ -30,7 +30,10 @@ macro_rules! hash_float_value {
 hash_float_value!((f64, u64), (f32, u32));
 
 pub trait Object: Send + Sync + Debug + ExtendObject {
-
+    /// 状态快照时转换为(类型名称, Value), 通过expr::aggregate::restore_object恢复, 不支持快照时返回None
+    fn snapshot(&self) -> Option<(&'static str, Value)> {
+        None
+    }
 }
 
 pub trait ExtendObject {
@@ -81,6 +84,10 @@ pub enum Value {
     Binary(Arc<Vec<u8>>),
     Struct(Arc<dyn BaseRow>),
     Array(Arc<Vec<Value>>),
+    /// 按插入顺序保存的(key, value), key不为null且不重复
+    Map(Arc<Vec<(Value, Value)>>),
+    /// 非标度值, 标度
+    Decimal(i128, u8),
     Object(Box<dyn Object>),
 }
 
@@ -119,6 +126,17 @@ impl Display for Value {
                 }
                 write!(f, "]")
             },
+            Value::Map(v) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{k}: {v}")?;
+                }
+                write!(f, "}}")
+            },
+            Value::Decimal(v, scale) => write!(f, "{}", decimal_utils::format(*v, *scale)),
             Value::Object(v) => write!(f, "{v}"),
         }
     }
@@ -148,6 +166,10 @@ impl Value {
         Value::Boolean(b)
     }
 
+    pub fn decimal(v: i128, scale: u8) -> Self {
+        Value::Decimal(v, scale)
+    }
+
     pub fn null() -> Self {
         Value::Null
     }
@@ -253,11 +275,37 @@ impl Value {
         }
     }
 
+    pub fn get_map(&self) -> Arc<Vec<(Value, Value)>> {
+        if let Value::Map(v) = self {
+            v.clone()
+        } else {
+            panic!("{:?} is not a map", self)
+        }
+    }
+
+    /// 返回(非标度值, 标度)
+    pub fn get_decimal(&self) -> (i128, u8) {
+        if let Value::Decimal(v, scale) = self {
+            (*v, *scale)
+        } else {
+            panic!("{:?} is not a decimal", self)
+        }
+    }
+
+    /// 按key查找map中的value, 不存在时返回None
+    pub fn map_get(&self, key: &Value) -> Option<&Value> {
+        if let Value::Map(v) = self {
+            v.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+        } else {
+            None
+        }
+    }
+
     pub fn to_sql_string(&self, data_type: &DataType) -> String {
         match self {
             Value::Null => "null".to_string(),
             v => match data_type {
-                DataType::Int | DataType::Long | DataType::Float | DataType::Double
+                DataType::Int | DataType::Long | DataType::Float | DataType::Double | DataType::Decimal(_, _)
                  | DataType::Boolean | DataType::Binary | DataType::Null =>
                     v.to_string(),
                 DataType::String => v.get_string().to_string(),
@@ -267,6 +315,10 @@ impl Value {
                     let array = self.get_array();
                     format!("[{}]", array.iter().map(|v| v.to_sql_string(tp)).collect::<Vec<_>>().join(","))
                 },
+                DataType::Map(key_type, value_type) => {
+                    let map = self.get_map();
+                    format!("{{{}}}", map.iter().map(|(k, v)| format!("{}:{}", k.to_sql_string(key_type), v.to_sql_string(value_type))).collect::<Vec<_>>().join(","))
+                },
                 DataType::Struct(fields) => {
                     let row = self.get_struct();
                     let mut s = String::new();
@@ -302,6 +354,17 @@ impl Hash for Value {
             Binary(v) => v.hash(state),
             Struct(v) => v.hash(state),
             Array(v) => v.hash(state),
+            Map(v) => {
+                // 和entry的顺序无关, 每个entry单独hash后相加
+                let sum = v.iter().fold(0u64, |sum, entry| {
+                    let mut hasher = DefaultHasher::new();
+                    entry.hash(&mut hasher);
+                    sum.wrapping_add(hasher.finish())
+                });
+                v.len().hash(state);
+                sum.hash(state);
+            },
+            Decimal(v, scale) => decimal_utils::normalize(*v, *scale).hash(state),
             Object(_) => 1.hash(state),
         }
     }
@@ -329,6 +392,11 @@ impl PartialEq for Value {
             (Struct(_), _) => false,
             (Array(v1), Array(v2)) => v1.eq(v2),
             (Array(_), _) => false,
+            // map的key不重复, 和entry的顺序无关
+            (Map(v1), Map(v2)) => v1.len() == v2.len() && v1.iter().all(|entry| v2.contains(entry)),
+            (Map(_), _) => false,
+            (Decimal(v1, s1), Decimal(v2, s2)) => decimal_utils::compare(*v1, *s1, *v2, *s2) == Ordering::Equal,
+            (Decimal(_, _), _) => false,
             (Null, Null) => true,
             (Null, _) => false,
             (Object(_), _) => false,
@@ -359,6 +427,9 @@ impl PartialOrd for Value {
             (Struct(_), _) => None,
             (Array(v1), Array(v2)) => v1.partial_cmp(v2),
             (Array(_), _) => None,
+            (Map(_), _) => None,
+            (Decimal(v1, s1), Decimal(v2, s2)) => Some(decimal_utils::compare(*v1, *s1, *v2, *s2)),
+            (Decimal(_, _), _) => None,
             (Null, Null) => Some(Ordering::Equal),
             (Null, _) => None,
             (Object(_), _) => None,
@@ -1086,4 +1157,16 @@ mod tests {
         println!("{}", row.is_null(2));
         println!("{}", row.is_null(3));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_map_eq_hash() {
+        use std::collections::HashSet;
+        let m1 = Value::Map(Arc::new(vec![(Value::string("a"), Value::int(1)), (Value::string("b"), Value::int(2))]));
+        let m2 = Value::Map(Arc::new(vec![(Value::string("b"), Value::int(2)), (Value::string("a"), Value::int(1))]));
+        let m3 = Value::Map(Arc::new(vec![(Value::string("a"), Value::int(2)), (Value::string("b"), Value::int(1))]));
+        assert_eq!(m1, m2);
+        assert_ne!(m1, m3);
+        let set: HashSet<Value> = [m1, m2, m3].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -4,60 +4,43 @@ use std::{env, thread};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
-use std::time::Duration;
-use prometheus::{Encoder, Registry, TextEncoder};
+use std::time::{Duration, Instant};
+use prometheus::Registry;
 use signal_hook::consts::{SIGINT, SIGTERM};
 use signal_hook::flag;
-use tiny_http::{Request, Response, Server};
-use crate::config::{self, AppConfig, WebConfig};
-use crate::execution::{self, NodeParser};
-fn handle_request(registry: &Registry, mut request: Request) {
-    match request.url() {
-        "/metrics" => {
-            let encoder = TextEncoder::new();
-            let metric_families = registry.gather();
-            let mut buffer = vec![];
-            encoder.encode(&metric_families, &mut buffer).unwrap();
+use crate::config::{self, AppConfig};
+use crate::execution::{self, test_runner, Graph, NodeParser};
+use crate::execution::web::start_web;
 
-            let response = Response::from_data(buffer)
-                .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..]).unwrap());
-            request.respond(response).unwrap();
-        },
-        _ => {
-            let response = Response::from_string("Hello, World!")
-                .with_status_code(200);
-            request.respond(response).unwrap();
+/// 收到停止信号后开始计时, drain_timeout_ms内没有结束时直接退出进程
+fn start_drain_watchdog(drain_timeout_ms: u64, terminated: Arc<AtomicBool>, finished: Arc<AtomicBool>) -> crate::Result<JoinHandle<()>> {
+    let builder = thread::Builder::new().stack_size(1024 * 128).name("drain-watchdog".to_string());
+    builder.spawn(move || {
+        while !terminated.load(Ordering::Acquire) {
+            if finished.load(Ordering::Acquire) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
         }
-    }
-}
-fn start_web(web_config: WebConfig, registry: Registry,  terminated: Arc<AtomicBool>) -> crate::Result<Vec<JoinHandle<()>>> {
-    let server = Arc::new(Server::http(format!("0.0.0.0:{}", web_config.port)).map_err(|e| e.to_string())?);
-    info!("start metrics at: http://127.0.0.1:{}/metrics", web_config.port);
-    let mut handles: Vec<JoinHandle<()>> = Vec::new();
-    for i in 0..web_config.works {
-        let registry = registry.clone();
-        let server = server.clone();
-        let terminated = Arc::clone(&terminated);
-        let builder = thread::Builder::new().stack_size(1024 * 128).name(format!("web-{}", i));
-        let handle = builder.spawn(move || {
-            while !terminated.load(Ordering::Acquire) {
-                match server.recv_timeout(Duration::from_secs(1)) {
-                    Ok(Some(request)) => handle_request(&registry, request),
-                    Ok(None) => continue,
-                    Err(_) => continue,
-                }
+        info!("start drain, timeout: {}ms", drain_timeout_ms);
+        let deadline = Instant::now() + Duration::from_millis(drain_timeout_ms);
+        while !finished.load(Ordering::Acquire) {
+            if Instant::now() >= deadline {
+                error!("drain timeout after {}ms, exit", drain_timeout_ms);
+                std::process::exit(1);
             }
-            info!("thread {} exiting", i);
-        }).unwrap();
-        handles.push(handle);
-    }
-    Ok(handles)
+            thread::sleep(Duration::from_millis(100));
+        }
+    }).map_err(|e| e.to_string())
 }
 
-pub fn run_application(config_path: &str) -> crate::Result<()> {
-    let config: AppConfig = config::parse_config(config_path).unwrap();
+/// overrides为--set指定的key=value, 覆盖配置文件中的值
+pub fn run_application(config_path: &str, overrides: &[String]) -> crate::Result<()> {
+    let config: AppConfig = config::parse_config_with_overrides(config_path, overrides).map_err(|e| e.to_string())?;
     let mut parser = NodeParser::new();
     let graph = parser.parse_node_graph(&config)?;
+    // 启动task前检查所有节点的配置, 如parallelism为0
+    graph.validate()?;
     graph.debug_node_chains();
 
     let registry = Registry::new();
@@ -65,16 +48,62 @@ pub fn run_application(config_path: &str) -> crate::Result<()> {
     // 捕获 SIGINT (Ctrl+C) 和 SIGTERM (kill) 信号
     flag::register(SIGINT, terminated.clone()).expect("Failed to register SIGINT handler");
     flag::register(SIGTERM, terminated.clone()).expect("Failed to register SIGTERM handler");
+    let finished: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
     let handles = if config.env.web.enabled {
-        start_web(config.env.web.clone(), registry.clone(), terminated.clone())?
+        start_web(config.env.web.clone(), &config, &graph, registry.clone(), terminated.clone(), finished.clone())?
     } else {
         Vec::new()
     };
+    let watchdog = start_drain_watchdog(config.env.application.drain_timeout_ms, terminated.clone(), finished.clone())?;
     let result = execution::execution_graph(&graph, &config.env.application, registry, terminated.clone());
     info!("execution finish");
+    finished.store(true, Ordering::Release);
     terminated.store(true, Ordering::Release);
+    watchdog.join().unwrap();
     for handle in handles {
         handle.join().unwrap();
     }
     result
-}
\ No newline at end of file
+}
+
+fn parse_graph(config_path: &str, overrides: &[String]) -> crate::Result<(AppConfig, Graph)> {
+    let config: AppConfig = config::parse_config_with_overrides(config_path, overrides).map_err(|e| e.to_string())?;
+    let mut parser = NodeParser::new();
+    let graph = parser.parse_node_graph(&config)?;
+    Ok((config, graph))
+}
+
+/// 解析配置并构建所有节点, 不会创建连接
+pub fn validate_application(config_path: &str, overrides: &[String]) -> crate::Result<()> {
+    let (_, graph) = parse_graph(config_path, overrides)?;
+    graph.validate()
+}
+
+pub fn explain_application(config_path: &str, overrides: &[String]) -> crate::Result<String> {
+    let (_, graph) = parse_graph(config_path, overrides)?;
+    graph.validate()?;
+    graph.explain()
+}
+
+/// 单线程运行cases_path中的所有用例, 返回是否全部通过
+pub fn test_application(config_path: &str, cases_path: &str, overrides: &[String]) -> crate::Result<bool> {
+    let cases = test_runner::parse_test_cases(cases_path)?;
+    let mut num_failed = 0;
+    for case in cases.cases.iter() {
+        let result = test_runner::run_test_case(config_path, overrides, case).unwrap_or_else(|e| test_runner::TestCaseResult {
+            name: case.name.clone(),
+            failures: vec![format!("run error: {}", e)],
+        });
+        if result.passed() {
+            println!("PASS {}", result.name);
+        } else {
+            num_failed += 1;
+            println!("FAIL {}", result.name);
+            for failure in result.failures.iter() {
+                println!("{}", failure);
+            }
+        }
+    }
+    println!("{} passed, {} failed", cases.cases.len() - num_failed, num_failed);
+    Ok(num_failed == 0)
+}
@@ -1,8 +1,13 @@
+use std::rc::Rc;
 use crate::Result;
+use crate::config::ErrorHandler;
 use crate::connector::Sink;
 use crate::data::Row;
 use crate::execution::TimeService;
+use crate::expr::{BoundReference, Cast, Expr};
+use crate::physical_expr::{can_cast, MutableProjection};
 use crate::transform::Transform;
+use crate::types::Schema;
 
 pub trait Collector {
     fn open(&mut self) -> Result<()> {
@@ -10,20 +15,61 @@ pub trait Collector {
     }
     fn collect(&mut self, row: &dyn Row) -> Result<()>;
 
+    /// 输出到side output, 没有下游引用的side output直接丢弃
+    fn collect_side(&mut self, _output: &str, _row: &dyn Row) -> Result<()> {
+        Ok(())
+    }
+
     fn check_timer(&mut self, time: u64) -> Result<()>;
 
+    /// source注入的barrier, 返回时之前collect的数据在下游sink都已经写入
+    fn barrier(&mut self, _checkpoint_id: u64) -> Result<()> {
+        Ok(())
+    }
+
+    /// 输入结束(source结束或者停止任务)时在close之前调用, 触发所有未到期的timer输出缓存的数据
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     fn close(&mut self) -> Result<()> {
         Ok(())
     }
 }
 
+/// 测试用collector, 按顺序保存输出的数据, side output的数据和名称保存在side_rows
+#[cfg(test)]
+#[derive(Default)]
+pub struct VecCollector {
+    pub rows: Vec<crate::data::GenericRow>,
+    pub side_rows: Vec<(String, crate::data::GenericRow)>,
+}
+
+#[cfg(test)]
+impl Collector for VecCollector {
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        self.rows.push(row.to_generic_row());
+        Ok(())
+    }
+
+    fn collect_side(&mut self, output: &str, row: &dyn Row) -> Result<()> {
+        self.side_rows.push((output.to_string(), row.to_generic_row()));
+        Ok(())
+    }
+
+    fn check_timer(&mut self, _time: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
 pub struct SinkCollector {
     sink: Box<dyn Sink>,
+    error_handler: Rc<ErrorHandler>,
 }
 
 impl SinkCollector {
-    pub fn new(sink: Box<dyn Sink> ) -> Self {
-        Self { sink }
+    pub fn new(sink: Box<dyn Sink>, error_handler: Rc<ErrorHandler>) -> Self {
+        Self { sink, error_handler }
     }
 }
 
@@ -32,15 +78,23 @@ impl Collector for SinkCollector {
         self.sink.open()
     }
     fn collect(&mut self, row: &dyn Row) -> Result<()> {
-        self.sink.invoke(row)
+        match self.sink.invoke(row) {
+            Ok(_) => Ok(()),
+            Err(e) => self.error_handler.handle(e, row.to_string().as_bytes()),
+        }
     }
 
     fn check_timer(&mut self, time: u64) -> Result<()> {
         Ok(())
     }
 
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        self.sink.barrier(checkpoint_id)?;
+        self.error_handler.barrier(checkpoint_id)
+    }
+
     fn close(&mut self) -> crate::Result<()> {
-        self.sink.close()
+        self.sink.close().and(self.error_handler.close())
     }
 }
 
@@ -48,22 +102,65 @@ pub struct TransformCollector {
     transform: Box<dyn Transform>,
     out:  Box<dyn Collector>,
     time_service: TimeService,
+    error_handler: Rc<ErrorHandler>,
 }
 
 impl TransformCollector {
-    pub fn new(transform: Box<dyn Transform>, out:  Box<dyn Collector>) -> Self {
+    pub fn new(transform: Box<dyn Transform>, out:  Box<dyn Collector>, error_handler: Rc<ErrorHandler>) -> Self {
         let time_service = TimeService::new();
-        Self { transform, out, time_service }
+        Self { transform, out, time_service, error_handler }
+    }
+}
+
+/// 记录下游是否返回错误, 下游的错误由下游的on_error处理, 不能被当前transform的on_error处理
+struct DownstreamErrorCollector<'a> {
+    out: &'a mut dyn Collector,
+    failed: bool,
+}
+
+impl DownstreamErrorCollector<'_> {
+    fn track(&mut self, rst: Result<()>) -> Result<()> {
+        if rst.is_err() {
+            self.failed = true;
+        }
+        rst
+    }
+}
+
+impl Collector for DownstreamErrorCollector<'_> {
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        let rst = self.out.collect(row);
+        self.track(rst)
+    }
+
+    fn collect_side(&mut self, output: &str, row: &dyn Row) -> Result<()> {
+        let rst = self.out.collect_side(output, row);
+        self.track(rst)
+    }
+
+    fn check_timer(&mut self, time: u64) -> Result<()> {
+        let rst = self.out.check_timer(time);
+        self.track(rst)
+    }
+
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        let rst = self.out.barrier(checkpoint_id);
+        self.track(rst)
     }
 }
 
 impl Collector for TransformCollector {
     fn open(&mut self) -> Result<()> {
         self.out.open()?;
-        self.transform.open()
+        self.transform.open()?;
+        self.transform.restore_state(&mut self.time_service)
     }
     fn collect(&mut self, row: &dyn Row) -> Result<()> {
-        self.transform.process(row, self.out.as_mut(), &mut self.time_service)
+        let mut out = DownstreamErrorCollector { out: self.out.as_mut(), failed: false };
+        match self.transform.process(row, &mut out, &mut self.time_service) {
+            Err(e) if !out.failed => self.error_handler.handle(e, row.to_string().as_bytes()),
+            rst => rst,
+        }
     }
 
     fn check_timer(&mut self, time: u64) -> Result<()> {
@@ -75,8 +172,23 @@ impl Collector for TransformCollector {
         Ok(())
     }
 
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        self.transform.on_barrier(checkpoint_id, self.out.as_mut())?;
+        self.error_handler.barrier(checkpoint_id)?;
+        self.out.barrier(checkpoint_id)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        while self.time_service.next_trigger_time() != u64::MAX {
+            self.transform.on_time(self.time_service.next_trigger_time(), self.out.as_mut())?;
+            self.time_service.poll_trigger_time();
+        }
+        self.transform.finish(self.out.as_mut())?;
+        self.out.finish()
+    }
+
     fn close(&mut self) -> Result<()> {
-        self.transform.close().and(self.out.close())
+        self.transform.close().and(self.error_handler.close()).and(self.out.close())
     }
 }
 
@@ -111,6 +223,20 @@ impl Collector for MultiCollector {
         Ok(())
     }
 
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        for out in self.outs.iter_mut() {
+            out.barrier(checkpoint_id)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for out in self.outs.iter_mut() {
+            out.finish()?;
+        }
+        Ok(())
+    }
+
     fn close(&mut self) -> Result<()> {
         for out in self.outs.iter_mut() {
             out.close()?;
@@ -119,6 +245,125 @@ impl Collector for MultiCollector {
     }
 }
 
+pub struct SideOutputCollector {
+    out: Box<dyn Collector>,
+    side_outs: Vec<(String, Box<dyn Collector>)>,
+}
+
+impl SideOutputCollector {
+    pub fn new(out: Box<dyn Collector>, side_outs: Vec<(String, Box<dyn Collector>)>) -> Self {
+        Self { out, side_outs }
+    }
+}
+
+impl Collector for SideOutputCollector {
+    fn open(&mut self) -> Result<()> {
+        self.out.open()?;
+        for (_, out) in self.side_outs.iter_mut() {
+            out.open()?;
+        }
+        Ok(())
+    }
+
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        self.out.collect(row)
+    }
+
+    fn collect_side(&mut self, output: &str, row: &dyn Row) -> Result<()> {
+        for (name, out) in self.side_outs.iter_mut() {
+            if name == output {
+                return out.collect(row);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_timer(&mut self, time: u64) -> Result<()> {
+        self.out.check_timer(time)?;
+        for (_, out) in self.side_outs.iter_mut() {
+            out.check_timer(time)?;
+        }
+        Ok(())
+    }
+
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        self.out.barrier(checkpoint_id)?;
+        for (_, out) in self.side_outs.iter_mut() {
+            out.barrier(checkpoint_id)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.finish()?;
+        for (_, out) in self.side_outs.iter_mut() {
+            out.finish()?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.out.close()?;
+        for (_, out) in self.side_outs.iter_mut() {
+            out.close()?;
+        }
+        Ok(())
+    }
+}
+
+/// 按位置把数据转换为目标schema的类型
+pub struct CastCollector {
+    projection: MutableProjection,
+    out: Box<dyn Collector>,
+}
+
+impl CastCollector {
+    pub fn new(from: &Schema, to: &Schema, out: Box<dyn Collector>) -> Result<Self> {
+        if from.fields.len() != to.fields.len() {
+            return Err(format!("can not cast {} to {}", from, to));
+        }
+        let mut exprs = Vec::with_capacity(to.fields.len());
+        for (i, (from_field, to_field)) in from.fields.iter().zip(to.fields.iter()).enumerate() {
+            let expr = Expr::BoundReference(BoundReference::new(i, from_field.data_type.clone()));
+            if from_field.data_type == to_field.data_type {
+                exprs.push(expr);
+            } else if can_cast(&from_field.data_type, &to_field.data_type) {
+                exprs.push(Expr::Cast(Cast::new(expr, to_field.data_type.clone())));
+            } else {
+                return Err(format!("can not cast {} to {}", from_field, to_field));
+            }
+        }
+        Ok(Self { projection: MutableProjection::new(exprs)?, out })
+    }
+}
+
+impl Collector for CastCollector {
+    fn open(&mut self) -> Result<()> {
+        self.out.open()
+    }
+
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        let row = self.projection.apply(row);
+        self.out.collect(row)
+    }
+
+    fn check_timer(&mut self, time: u64) -> Result<()> {
+        self.out.check_timer(time)
+    }
+
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        self.out.barrier(checkpoint_id)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.finish()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.out.close()
+    }
+}
+
 pub struct PrintCollector;
 
 impl Collector for PrintCollector {
@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::time::Duration;
+use ahash::AHasher;
+use prometheus::IntGauge;
+use crate::Result;
+use crate::data::{GenericRow, Row};
+use crate::execution::Collector;
+use crate::types::Schema;
+
+pub const EXCHANGE_QUEUE_SIZE: usize = 1024;
+
+pub enum ExchangeMessage {
+    Row(GenericRow),
+    /// 下游处理完barrier之前的数据后通过ack发送端回复结果
+    Barrier(u64, SyncSender<Result<()>>),
+}
+
+/// 发送到一个下游subtask的channel, queue_depth为channel中还未被接收的消息数
+#[derive(Clone)]
+pub struct ExchangeSender {
+    sender: SyncSender<ExchangeMessage>,
+    queue_depth: IntGauge,
+}
+
+impl ExchangeSender {
+    /// channel满时阻塞, 反压到上游
+    pub fn send(&self, message: ExchangeMessage) -> std::result::Result<(), ()> {
+        self.queue_depth.inc();
+        self.sender.send(message).map_err(|_| self.queue_depth.dec())
+    }
+}
+
+pub struct ExchangeReceiver {
+    receiver: Receiver<ExchangeMessage>,
+    queue_depth: IntGauge,
+}
+
+impl ExchangeReceiver {
+    pub fn recv_timeout(&self, timeout: Duration) -> std::result::Result<ExchangeMessage, RecvTimeoutError> {
+        let message = self.receiver.recv_timeout(timeout)?;
+        self.queue_depth.dec();
+        Ok(message)
+    }
+}
+
+pub fn exchange_channel(queue_size: usize, queue_depth: IntGauge) -> (ExchangeSender, ExchangeReceiver) {
+    let (sender, receiver) = sync_channel(queue_size);
+    (ExchangeSender { sender, queue_depth: queue_depth.clone() }, ExchangeReceiver { receiver, queue_depth })
+}
+
+/// 每个exchange节点对应的下游subtask发送端, key为节点id
+pub type ExchangeSenders = HashMap<u16, Vec<ExchangeSender>>;
+
+/// 单线程运行时exchange节点的算子链, 上游直接调用
+pub type LocalExchange = Rc<RefCell<Box<dyn Collector>>>;
+
+/// exchange节点的下游: 多线程运行时为各个subtask的channel发送端, 单线程运行(retl test)时为本地算子链
+pub enum Exchanges {
+    Channel(ExchangeSenders),
+    Local(HashMap<u16, LocalExchange>),
+}
+
+/// 数据直接转发到本地exchange算子链, 算子链的open/check_timer/finish/close由运行方按拓扑顺序调用
+pub struct LocalExchangeCollector {
+    exchange: LocalExchange,
+}
+
+impl LocalExchangeCollector {
+    pub fn new(exchange: LocalExchange) -> Self {
+        Self { exchange }
+    }
+}
+
+impl Collector for LocalExchangeCollector {
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        self.exchange.borrow_mut().collect(row)
+    }
+
+    fn check_timer(&mut self, _time: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        self.exchange.borrow_mut().barrier(checkpoint_id)
+    }
+}
+
+/// 按partition_by列hash路由数据到下游subtask, 没有partition_by时发送到相同序号的下游subtask
+pub struct ExchangeCollector {
+    key_indices: Vec<usize>,
+    subtask_index: usize,
+    senders: Vec<ExchangeSender>,
+}
+
+impl ExchangeCollector {
+    pub fn new(key_indices: Vec<usize>, subtask_index: usize, senders: Vec<ExchangeSender>) -> Self {
+        Self { key_indices, subtask_index, senders }
+    }
+
+    pub fn key_indices(partition_by: &[String], schema: &Schema) -> Result<Vec<usize>> {
+        partition_by.iter().map(|name| {
+            schema.field_index(name).ok_or_else(|| format!("partition_by column {} not found in {}", name, schema))
+        }).collect()
+    }
+
+    fn partition(&self, row: &dyn Row) -> usize {
+        if self.key_indices.is_empty() {
+            return self.subtask_index % self.senders.len();
+        }
+        let mut hasher = AHasher::default();
+        for i in &self.key_indices {
+            row.get(*i).hash(&mut hasher);
+        }
+        (hasher.finish() % self.senders.len() as u64) as usize
+    }
+}
+
+impl Collector for ExchangeCollector {
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        let partition = self.partition(row);
+        self.senders[partition].send(ExchangeMessage::Row(row.to_generic_row())).map_err(|_| format!("exchange channel {} closed", partition))
+    }
+
+    fn check_timer(&mut self, _time: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn barrier(&mut self, checkpoint_id: u64) -> Result<()> {
+        // 发送到所有下游并等待全部ack, channel有序保证之前发送的数据都已处理
+        let (ack_sender, ack_receiver) = sync_channel(self.senders.len());
+        for (i, sender) in self.senders.iter().enumerate() {
+            sender.send(ExchangeMessage::Barrier(checkpoint_id, ack_sender.clone())).map_err(|_| format!("exchange channel {} closed", i))?;
+        }
+        drop(ack_sender);
+        for _ in 0..self.senders.len() {
+            ack_receiver.recv().map_err(|_| format!("exchange barrier {} not acked", checkpoint_id))??;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        // drop发送端, 下游所有上游都关闭后结束
+        self.senders.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Value;
+    use crate::types::{DataType, Field};
+    use super::*;
+
+    #[test]
+    fn test_exchange_partition() {
+        let schema = Schema::new(vec![Field::new("id", DataType::Long), Field::new("cate", DataType::String)]);
+        let key_indices = ExchangeCollector::key_indices(&["cate".to_string()], &schema).unwrap();
+        assert!(ExchangeCollector::key_indices(&["none".to_string()], &schema).is_err());
+        let queue_depth = IntGauge::new("queue_depth", "queue depth").unwrap();
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..3).map(|_| exchange_channel(100, queue_depth.clone())).unzip();
+        let mut collector = ExchangeCollector::new(key_indices, 0, senders);
+        for i in 0..30 {
+            let row = GenericRow::new(vec![Value::long(i), Value::string(format!("cate{}", i % 5))]);
+            collector.collect(&row).unwrap();
+        }
+        let handles: Vec<_> = receivers.into_iter().map(|receiver| std::thread::spawn(move || {
+            let mut rows = Vec::new();
+            loop {
+                match receiver.recv_timeout(Duration::from_secs(10)) {
+                    Ok(ExchangeMessage::Row(row)) => rows.push(row),
+                    Ok(ExchangeMessage::Barrier(_, ack)) => ack.send(Ok(())).unwrap(),
+                    Err(_) => break,
+                }
+            }
+            rows
+        })).collect();
+        collector.barrier(1).unwrap();
+        assert_eq!(queue_depth.get(), 0);
+        collector.close().unwrap();
+        let mut cate_partitions = HashMap::new();
+        let mut count = 0;
+        for (i, handle) in handles.into_iter().enumerate() {
+            for row in handle.join().unwrap() {
+                count += 1;
+                let cate = row.get_string(1).to_string();
+                assert_eq!(*cate_partitions.entry(cate).or_insert(i), i);
+            }
+        }
+        assert_eq!(count, 30);
+        assert_eq!(cate_partitions.len(), 5);
+    }
+}
@@ -1,24 +1,35 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
 use std::thread;
-use log::{error, info};
-use prometheus::Registry;
-use crate::config::{ApplicationConfig, BaseIOMetrics, OperatorConfig, TaskConfig, TaskContext};
+use std::time::{Duration, Instant};
+use log::{error, info, warn};
+use prometheus::{IntCounter, IntGauge, Registry};
+use crate::config::{ApplicationConfig, BaseIOMetrics, DeadLetterSink, ErrorHandler, OnErrorConfig, OperatorConfig, RestartStrategyConfig, SharedDeadLetterSink, TaskConfig, TaskContext};
 use crate::Result;
 use crate::connector::Source;
 use crate::datetime_utils::current_timestamp_millis;
-use crate::execution::{Collector, Graph, MultiCollector, Node, PollStatus, SinkCollector, TransformCollector};
+use crate::execution::{exchange_channel, CastCollector, Collector, ExchangeCollector, ExchangeMessage, ExchangeReceiver, ExchangeSenders, Exchanges, Graph, LocalExchangeCollector, MultiCollector, Node, PollStatus, SideOutputCollector, SinkCollector, TransformCollector};
 use crate::parser::parse_schema;
 use crate::types::Schema;
 
+static EXCHANGE_POLL_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// 同一subtask的算子按名称共享dead letter sink
+pub type DeadLetterSinks = RefCell<HashMap<String, SharedDeadLetterSink>>;
+
 struct SourceOperator {
     source: Box<dyn Source>,
     out: Box<dyn Collector>,
+    error_handler: Rc<ErrorHandler>,
 }
 
 impl SourceOperator {
-    fn new(source: Box<dyn Source>, out: Box<dyn Collector>) -> SourceOperator {
-       SourceOperator{source, out}
+    fn new(source: Box<dyn Source>, out: Box<dyn Collector>, error_handler: Rc<ErrorHandler>) -> SourceOperator {
+       SourceOperator{source, out, error_handler}
     }
 }
 
@@ -45,106 +56,277 @@ impl SourceOperator {
         }
     }
 
+    /// 停止poll后输出下游缓存的数据
+    fn finish(&mut self) -> Result<()> {
+        self.out.finish()
+    }
+
     fn close(&mut self) -> Result<()> {
-        self.source.close().and(self.out.close())
+        self.source.close().and(self.error_handler.close()).and(self.out.close())
     }
 }
 
-pub fn new_source_operator(id: u16, graph: &Graph, task_config: TaskConfig) -> Result<SourceOperator> {
+/// 从exchange channel接收上游数据, 所有上游发送端关闭后结束, 重启时使用同一个receiver
+struct ExchangeOperator<'a> {
+    receiver: &'a ExchangeReceiver,
+    out: Box<dyn Collector>,
+}
+
+impl<'a> ExchangeOperator<'a> {
+    fn new(receiver: &'a ExchangeReceiver, out: Box<dyn Collector>) -> ExchangeOperator<'a> {
+        ExchangeOperator{receiver, out}
+    }
+
+    fn open(&mut self) -> Result<()> {
+        self.out.open()
+    }
+
+    fn run(&mut self) -> Result<()> {
+        loop {
+            match self.receiver.recv_timeout(EXCHANGE_POLL_TIMEOUT) {
+                Ok(ExchangeMessage::Row(row)) => {
+                    self.out.collect(&row)?;
+                    self.out.check_timer(current_timestamp_millis())?;
+                },
+                Ok(ExchangeMessage::Barrier(checkpoint_id, ack)) => {
+                    let rst = self.out.barrier(checkpoint_id);
+                    let _ = ack.send(rst.clone());
+                    rst?;
+                },
+                Err(RecvTimeoutError::Timeout) => {
+                    self.out.check_timer(current_timestamp_millis())?;
+                },
+                Err(RecvTimeoutError::Disconnected) => {
+                    // 所有上游都已经finish并关闭
+                    self.out.check_timer(current_timestamp_millis())?;
+                    return self.out.finish();
+                },
+            }
+        }
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.out.close()
+    }
+}
+
+pub fn new_source_operator(id: u16, graph: &Graph, task_config: TaskConfig, exchanges: &Exchanges, dead_letters: &DeadLetterSinks) -> Result<SourceOperator> {
     let node = graph.node_dict.get(&id).unwrap().as_ref();
     if let Node::Source(source_node) = node {
         let config = &source_node.source_config.inner;
         let schema = parse_schema(&source_node.source_config.schema)?;
         let base_iometrics = Arc::new(BaseIOMetrics::new(&task_config.metrics_registry, format!("source{}_{}", source_node.id, task_config.subtask_index)));
-        let task_context = TaskContext::new(task_config.clone(), OperatorConfig::new(source_node.id), base_iometrics);
+        let error_handler = new_error_handler(source_node.id, &source_node.source_config.on_error, graph, &task_config, base_iometrics.clone(), dead_letters)?;
+        let task_context = TaskContext::new(task_config.clone(), OperatorConfig::new(source_node.id, node.name()), base_iometrics).with_error_handler(error_handler.clone());
         let source = config.build(schema)?.create_source(task_context)?;
-        let mut outs = Vec::new();
-        for ouput_id in source_node.ouput_ids.iter() {
-            let next_node = graph.node_dict.get(ouput_id).unwrap().as_ref();
-            let out_schema = source.schema().clone();
-            let out = if !next_node.is_sink() {
-                new_transform_collector(next_node, graph, task_config.clone(), out_schema)?
-            } else {
-                new_sink_operator(next_node, task_config.clone(), out_schema)?
-            };
-            outs.push(out);
+        let out = new_outputs_collector(&source_node.ouput_ids, graph, task_config, source.schema().clone(), exchanges, dead_letters)?;
+        Ok(SourceOperator::new(source, out, error_handler))
+    } else {
+        Err(format!("not a source node: {:?}", node))
+    }
+}
+
+fn new_exchange_operator<'a>(id: u16, graph: &Graph, task_config: TaskConfig, receiver: &'a ExchangeReceiver, exchanges: &Exchanges, dead_letters: &DeadLetterSinks) -> Result<ExchangeOperator<'a>> {
+    let out = new_exchange_collector(id, graph, task_config, exchanges, dead_letters)?;
+    Ok(ExchangeOperator::new(receiver, out))
+}
+
+/// exchange节点自身的算子链
+pub fn new_exchange_collector(id: u16, graph: &Graph, task_config: TaskConfig, exchanges: &Exchanges, dead_letters: &DeadLetterSinks) -> Result<Box<dyn Collector>> {
+    let node = graph.node_dict.get(&id).unwrap().as_ref();
+    let schema = graph.input_schema(id).clone();
+    if node.is_sink() {
+        new_sink_operator(node, graph, task_config, schema, dead_letters)
+    } else {
+        new_transform_collector(node, graph, task_config, schema, exchanges, dead_letters)
+    }
+}
+
+/// 单线程运行时创建所有exchange节点的本地算子, 按拓扑逆序创建保证下游exchange先创建
+pub fn new_local_exchanges(graph: &Graph, task_config: TaskConfig, dead_letters: &DeadLetterSinks) -> Result<Exchanges> {
+    let mut exchanges = Exchanges::Local(HashMap::new());
+    for id in graph.topological_ids().into_iter().rev().filter(|id| graph.node_dict[id].is_exchange()) {
+        let out = new_exchange_collector(id, graph, task_config.clone(), &exchanges, dead_letters)?;
+        if let Exchanges::Local(outs) = &mut exchanges {
+            outs.insert(id, Rc::new(RefCell::new(out)));
+        }
+    }
+    Ok(exchanges)
+}
+
+pub fn new_outputs_collector(output_ids: &[u16], graph: &Graph, task_config: TaskConfig, schema: Schema, exchanges: &Exchanges, dead_letters: &DeadLetterSinks) -> Result<Box<dyn Collector>> {
+    new_outputs_collector_with_side(output_ids, &HashMap::new(), graph, task_config, schema.clone(), schema, exchanges, dead_letters)
+}
+
+pub fn new_outputs_collector_with_side(output_ids: &[u16], side_outputs: &HashMap<u16, String>, graph: &Graph, task_config: TaskConfig, schema: Schema, side_schema: Schema, exchanges: &Exchanges, dead_letters: &DeadLetterSinks) -> Result<Box<dyn Collector>> {
+    let mut outs = Vec::new();
+    let mut side_outs: Vec<(String, Vec<Box<dyn Collector>>)> = Vec::new();
+    for ouput_id in output_ids.iter() {
+        let next_node = graph.node_dict.get(ouput_id).unwrap().as_ref();
+        let out_schema = if side_outputs.contains_key(ouput_id) { side_schema.clone() } else { schema.clone() };
+        let input_schema = if next_node.is_sink() { out_schema.clone() } else { graph.input_schema(*ouput_id).clone() };
+        let out: Box<dyn Collector> = if next_node.is_exchange() {
+            match exchanges {
+                Exchanges::Channel(senders) => {
+                    let senders = senders.get(ouput_id).ok_or_else(|| format!("exchange channel not found for node: {}", ouput_id))?;
+                    let key_indices = ExchangeCollector::key_indices(next_node.partition_by(), &input_schema)?;
+                    Box::new(ExchangeCollector::new(key_indices, task_config.subtask_index as usize, senders.clone()))
+                },
+                Exchanges::Local(outs) => {
+                    let exchange = outs.get(ouput_id).ok_or_else(|| format!("local exchange not found for node: {}", ouput_id))?;
+                    Box::new(LocalExchangeCollector::new(exchange.clone()))
+                },
+            }
+        } else if next_node.is_sink() {
+            new_sink_operator(next_node, graph, task_config.clone(), input_schema.clone(), dead_letters)?
+        } else {
+            new_transform_collector(next_node, graph, task_config.clone(), input_schema.clone(), exchanges, dead_letters)?
         };
-        let out = if outs.len() == 1 {
-            outs.into_iter().next().unwrap()
+        let out = if out_schema.fields.iter().map(|f| &f.data_type).eq(input_schema.fields.iter().map(|f| &f.data_type)) {
+            out
         } else {
-            Box::new(MultiCollector::new(outs))
+            // 输入schema和上游不一致(union)时转换类型
+            Box::new(CastCollector::new(&out_schema, &input_schema, out)?)
         };
-        Ok(SourceOperator::new(source, out))
+        match side_outputs.get(ouput_id) {
+            Some(name) => match side_outs.iter_mut().find(|(n, _)| n == name) {
+                Some((_, outs)) => outs.push(out),
+                None => side_outs.push((name.clone(), vec![out])),
+            },
+            None => outs.push(out),
+        }
+    };
+    let out = combine_collectors(outs);
+    if side_outs.is_empty() {
+        Ok(out)
     } else {
-        Err(format!("not a source node: {:?}", node))
+        let side_outs = side_outs.into_iter().map(|(name, outs)| (name, combine_collectors(outs))).collect();
+        Ok(Box::new(SideOutputCollector::new(out, side_outs)))
     }
 }
 
-pub fn new_transform_collector(node: &Node, graph: &Graph, task_config: TaskConfig, schema: Schema) -> Result<Box<dyn Collector>> {
+fn combine_collectors(outs: Vec<Box<dyn Collector>>) -> Box<dyn Collector> {
+    if outs.len() == 1 {
+        outs.into_iter().next().unwrap()
+    } else {
+        Box::new(MultiCollector::new(outs))
+    }
+}
+
+pub fn new_transform_collector(node: &Node, graph: &Graph, task_config: TaskConfig, schema: Schema, exchanges: &Exchanges, dead_letters: &DeadLetterSinks) -> Result<Box<dyn Collector>> {
     if let Node::Transform(transform_node) = node {
         let config = &transform_node.transform_config.inner;
         let base_iometrics = Arc::new(BaseIOMetrics::new(&task_config.metrics_registry, format!("transform{}_{}", transform_node.id, task_config.subtask_index)));
-        let task_context = TaskContext::new(task_config.clone(), OperatorConfig::new(transform_node.id), base_iometrics);
-        let transform = config.build(schema)?.create_transform(task_context)?;
-        let mut outs = Vec::new();
-        for ouput_id in transform_node.ouput_ids.iter() {
-            let next_node = graph.node_dict.get(ouput_id).unwrap().as_ref();
-            let out_schema = transform.schema().clone();
-            let out = if !next_node.is_sink() {
-                new_transform_collector(next_node, graph, task_config.clone(), out_schema)?
-            } else {
-                new_sink_operator(next_node, task_config.clone(), out_schema)?
-            };
-            outs.push(out);
-        };
-        let out = if outs.len() == 1 {
-            outs.into_iter().next().unwrap()
-        } else {
-            Box::new(MultiCollector::new(outs))
-        };
-        Ok(Box::new(TransformCollector::new(transform, out)))
+        let error_handler = new_error_handler(transform_node.id, &transform_node.transform_config.on_error, graph, &task_config, base_iometrics.clone(), dead_letters)?;
+        let task_context = TaskContext::new(task_config.clone(), OperatorConfig::new(transform_node.id, node.name()), base_iometrics).with_error_handler(error_handler.clone());
+        let transform = config.build(schema.clone())?.create_transform(task_context)?;
+        let out = new_outputs_collector_with_side(&transform_node.ouput_ids, &transform_node.side_outputs, graph, task_config, transform.schema().clone(), schema, exchanges, dead_letters)?;
+        Ok(Box::new(TransformCollector::new(transform, out, error_handler)))
     } else {
         Err(format!("not a transform node: {:?}", node))
     }
 }
 
-pub fn new_sink_operator(node: &Node, task_config: TaskConfig, schema: Schema) -> Result<Box<dyn Collector>> {
+pub fn new_sink_operator(node: &Node, graph: &Graph, task_config: TaskConfig, schema: Schema, dead_letters: &DeadLetterSinks) -> Result<Box<dyn Collector>> {
     if let Node::Sink(sink_node) = node {
         let config = &sink_node.sink_config.inner;
         let base_iometrics = Arc::new(BaseIOMetrics::new(&task_config.metrics_registry, format!("sink{}_{}", sink_node.id, task_config.subtask_index)));
-        let task_context = TaskContext::new(task_config, OperatorConfig::new(sink_node.id), base_iometrics);
+        let error_handler = new_error_handler(sink_node.id, &sink_node.sink_config.on_error, graph, &task_config, base_iometrics.clone(), dead_letters)?;
+        let task_context = TaskContext::new(task_config, OperatorConfig::new(sink_node.id, node.name()), base_iometrics).with_error_handler(error_handler.clone());
         let sink = config.build(schema)?.create_sink(task_context)?;
-        Ok(Box::new(SinkCollector::new(sink)))
+        Ok(Box::new(SinkCollector::new(sink, error_handler)))
     } else {
         Err(format!("not a sink node: {:?}", node))
     }
 }
 
+/// 同一subtask内同名的dead letter sink只创建一次, 指标前缀为dead_letter_{sink名称}_{subtask}
+fn new_error_handler(id: u16, on_error: &OnErrorConfig, graph: &Graph, task_config: &TaskConfig, base_iometrics: Arc<BaseIOMetrics>, dead_letters: &DeadLetterSinks) -> Result<Rc<ErrorHandler>> {
+    let dead_letter_sink = match on_error {
+        OnErrorConfig::DeadLetter(name) => match dead_letters.borrow().get(name) {
+            Some(sink) => Some(sink.clone()),
+            None => {
+                let sink_config = graph.dead_letter_sinks.get(name).ok_or_else(|| format!("dead letter sink {} not found", name))?;
+                let metrics_name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+                let sink_iometrics = Arc::new(BaseIOMetrics::new(&task_config.metrics_registry, format!("dead_letter_{}_{}", metrics_name, task_config.subtask_index)));
+                let task_context = TaskContext::new(task_config.clone(), OperatorConfig::new(id, name.as_str()), sink_iometrics);
+                let sink = DeadLetterSink::new_shared(sink_config.inner.build(ErrorHandler::dead_letter_schema())?.create_sink(task_context)?);
+                dead_letters.borrow_mut().insert(name.clone(), sink.clone());
+                Some(sink)
+            },
+        },
+        _ => None,
+    };
+    Ok(Rc::new(ErrorHandler::new(id, on_error.clone(), base_iometrics, dead_letter_sink)))
+}
+
 pub fn execution_graph(graph: &Graph, application_config: &ApplicationConfig, registry: Registry, terminated: Arc<AtomicBool>) -> Result<()> {
     let parallelism = application_config.parallelism;
-    let mut handles = Vec::with_capacity(graph.source_ids.len());
+    if parallelism == 0 {
+        return Err("env.application.parallelism must be greater than 0".to_string());
+    }
+    let mut exchanges = ExchangeSenders::new();
+    let mut exchange_receivers = Vec::new();
+    for exchange_id in graph.exchange_ids() {
+        let queue_size = graph.node_dict[&exchange_id].queue_size();
+        let exchange_parallelism = graph.parallelism(exchange_id, parallelism);
+        let mut senders = Vec::with_capacity(exchange_parallelism as usize);
+        let mut receivers = Vec::with_capacity(exchange_parallelism as usize);
+        for i in 0..exchange_parallelism {
+            let queue_depth = IntGauge::new(format!("exchange{}_{}_queue_depth", exchange_id, i), "number of messages in exchange queue").map_err(|e| e.to_string())?;
+            registry.register(Box::new(queue_depth.clone())).map_err(|e| e.to_string())?;
+            let (sender, receiver) = exchange_channel(queue_size, queue_depth);
+            senders.push(sender);
+            receivers.push(receiver);
+        }
+        exchanges.insert(exchange_id, senders);
+        exchange_receivers.push((exchange_id, receivers));
+    }
+    let mut handles = Vec::with_capacity(graph.source_ids.len() + exchange_receivers.len());
     for source_id in graph.source_ids.iter() {
-        for i in 0..parallelism {
+        let source_parallelism = graph.parallelism(*source_id, parallelism);
+        for i in 0..source_parallelism {
             let source_id = *source_id;
             let graph = graph.clone();
-            let task_config = TaskConfig::new(parallelism, i, registry.clone());
+            let exchanges = exchanges.clone();
+            let task_config = TaskConfig::new(source_parallelism, i, registry.clone()).with_state_config(application_config.task_state_config())
+                .with_terminated(terminated.clone());
+            let restart_strategy = application_config.restart_strategy.clone();
+            let num_restarts = IntCounter::new(format!("source{}_{}_num_restarts", source_id, i), "number of task restarts").map_err(|e| e.to_string())?;
+            registry.register(Box::new(num_restarts.clone())).map_err(|e| e.to_string())?;
             let terminated = terminated.clone();
             let builder = thread::Builder::new().stack_size(1024 * 512)
-                .name(format!("{}-{}/{}", graph.get_node_dispaly_by_id(source_id), i + 1, parallelism));
+                .name(format!("{}-{}/{}", graph.get_node_dispaly_by_id(source_id), i + 1, source_parallelism));
             handles.push(builder.spawn(move || {
                 info!("start source: {}", source_id);
-                let result = run_task(source_id, &graph, task_config, terminated.clone());
-                match result {
-                    Ok(_) => Ok(()),
-                    Err(e) => {
-                        error!("source: {} run error:{:?}", source_id, e);
-                        terminated.store(true, Ordering::Release);
-                        Err(e)
-                    },
-                }
+                run_with_restart(&format!("source: {} subtask: {}", source_id, i), &restart_strategy, &num_restarts, &terminated,
+                    || run_task(source_id, &graph, task_config.clone(), exchanges.clone(), terminated.clone()))
             }).map_err(|_| "failed to spawn thread")?);
         }
     }
+    for (exchange_id, receivers) in exchange_receivers {
+        let exchange_parallelism = receivers.len() as u8;
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            let i = i as u8;
+            let graph = graph.clone();
+            let exchanges = exchanges.clone();
+            let task_config = TaskConfig::new(exchange_parallelism, i, registry.clone()).with_state_config(application_config.task_state_config())
+                .with_terminated(terminated.clone());
+            let restart_strategy = application_config.restart_strategy.clone();
+            let num_restarts = IntCounter::new(format!("exchange{}_{}_num_restarts", exchange_id, i), "number of task restarts").map_err(|e| e.to_string())?;
+            registry.register(Box::new(num_restarts.clone())).map_err(|e| e.to_string())?;
+            let terminated = terminated.clone();
+            let builder = thread::Builder::new().stack_size(1024 * 512)
+                .name(format!("{}-{}/{}", graph.get_node_dispaly_by_id(exchange_id), i + 1, exchange_parallelism));
+            handles.push(builder.spawn(move || {
+                info!("start exchange: {}", exchange_id);
+                run_with_restart(&format!("exchange: {} subtask: {}", exchange_id, i), &restart_strategy, &num_restarts, &terminated,
+                    || run_exchange_task(exchange_id, &graph, task_config.clone(), &receiver, exchanges.clone()))
+            }).map_err(|_| "failed to spawn thread")?);
+        }
+    }
+    // 只保留各个task中的发送端, 上游task结束后下游exchange task才能结束
+    drop(exchanges);
     let mut errs = Vec::new();
     for handle in handles {
         let result = handle.join().unwrap();
@@ -159,9 +341,97 @@ pub fn execution_graph(graph: &Graph, application_config: &ApplicationConfig, re
     }
 }
 
-fn run_task(source_id: u16, graph: &Graph, task_config: TaskConfig, terminated: Arc<AtomicBool>) -> Result<()> {
-    let mut source = new_source_operator(source_id, &graph, task_config)?;
-    source.open()?;
-    source.run(terminated)?;
-    source.close()
-}
\ No newline at end of file
+/// 按restart_strategy重启出错的task, 不再重启时设置停止信号让其它task退出
+fn run_with_restart(task: &str, restart_strategy: &RestartStrategyConfig, num_restarts: &IntCounter, terminated: &AtomicBool, mut run: impl FnMut() -> Result<()>) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        let e = match run() {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        error!("{} run error:{:?}", task, e);
+        let delay = restart_strategy.restart_delay(attempt);
+        if delay.is_none() || terminated.load(Ordering::Acquire) {
+            terminated.store(true, Ordering::Release);
+            return Err(e);
+        }
+        attempt += 1;
+        let delay = delay.unwrap();
+        warn!("restart {} after {:?}, attempt: {}", task, delay, attempt);
+        if !sleep_until_terminated(delay, terminated) {
+            return Err(e);
+        }
+        num_restarts.inc();
+    }
+}
+
+/// 等待delay, 期间收到停止信号时返回false
+fn sleep_until_terminated(delay: Duration, terminated: &AtomicBool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < delay {
+        if terminated.load(Ordering::Acquire) {
+            return false;
+        }
+        thread::sleep(EXCHANGE_POLL_TIMEOUT.min(delay.saturating_sub(start.elapsed())));
+    }
+    !terminated.load(Ordering::Acquire)
+}
+
+fn run_task(source_id: u16, graph: &Graph, task_config: TaskConfig, exchanges: ExchangeSenders, terminated: Arc<AtomicBool>) -> Result<()> {
+    let exchanges = Exchanges::Channel(exchanges);
+    let mut source = new_source_operator(source_id, &graph, task_config, &exchanges, &DeadLetterSinks::default())?;
+    drop(exchanges);
+    let result = source.open().and_then(|_| source.run(terminated)).and_then(|_| source.finish());
+    match result {
+        Ok(_) => source.close(),
+        Err(e) => {
+            // 出错时也关闭算子链, 重启前释放连接等资源
+            if let Err(close_err) = source.close() {
+                warn!("source: {} close error after failure:{:?}", source_id, close_err);
+            }
+            Err(e)
+        },
+    }
+}
+
+fn run_exchange_task(exchange_id: u16, graph: &Graph, task_config: TaskConfig, receiver: &ExchangeReceiver, exchanges: ExchangeSenders) -> Result<()> {
+    let exchanges = Exchanges::Channel(exchanges);
+    let mut operator = new_exchange_operator(exchange_id, &graph, task_config, receiver, &exchanges, &DeadLetterSinks::default())?;
+    drop(exchanges);
+    let result = operator.open().and_then(|_| operator.run());
+    match result {
+        Ok(_) => operator.close(),
+        Err(e) => {
+            // 出错时也关闭算子链, 重启前释放连接等资源
+            if let Err(close_err) = operator.close() {
+                warn!("exchange: {} close error after failure:{:?}", exchange_id, close_err);
+            }
+            Err(e)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_with_restart() {
+        let restart_strategy = RestartStrategyConfig::FixedDelay { max_attempts: 2, delay_ms: 0 };
+        let num_restarts = IntCounter::new("test_num_restarts", "number of task restarts").unwrap();
+        let terminated = AtomicBool::new(false);
+        let mut runs = 0;
+        let result = run_with_restart("exchange: 1 subtask: 0", &restart_strategy, &num_restarts, &terminated, || {
+            runs += 1;
+            if runs < 3 { Err(format!("error {}", runs)) } else { Ok(()) }
+        });
+        assert!(result.is_ok());
+        assert_eq!(num_restarts.get(), 2);
+        assert!(!terminated.load(Ordering::Acquire));
+
+        let result = run_with_restart("exchange: 1 subtask: 0", &restart_strategy, &num_restarts, &terminated, || Err("error".to_string()));
+        assert_eq!(result, Err("error".to_string()));
+        assert_eq!(num_restarts.get(), 4);
+        assert!(terminated.load(Ordering::Acquire));
+    }
+}
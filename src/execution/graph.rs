@@ -5,11 +5,12 @@ use std::sync::Arc;
 use itertools::Itertools;
 use log::{debug, info};
 use serde::{Serialize, Serializer};
-use crate::config::{SinkConfig, SinkOuter, SourceConfig, SourceOuter, TransformConfig, TransformOuter};
+use crate::config::{ErrorHandler, OnErrorConfig, SinkConfig, SinkOuter, SourceConfig, SourceOuter, TransformConfig, TransformOuter};
 use crate::Result;
+use crate::execution::{ExchangeCollector, EXCHANGE_QUEUE_SIZE};
 use crate::types::Schema;
 use crate::config::{AppConfig};
-use crate::parser;
+use crate::{parser, sql_utils};
 
 #[derive(Debug, Clone)]
 pub enum Node {
@@ -34,14 +35,47 @@ impl Node {
         }
     }
 
+    pub fn is_exchange(&self) -> bool {
+        match self {
+            Node::Transform(node) => node.transform_config.is_async || !node.transform_config.partition_by.is_empty() || node.input_ids.len() > 1,
+            Node::Sink(node) => node.sink_config.is_async,
+            _ => false,
+        }
+    }
+
+    pub fn partition_by(&self) -> &[String] {
+        match self {
+            Node::Transform(node) => &node.transform_config.partition_by,
+            _ => &[],
+        }
+    }
+
+    pub fn queue_size(&self) -> usize {
+        let queue_size = match self {
+            Node::Transform(node) => node.transform_config.queue_size,
+            Node::Sink(node) => node.sink_config.queue_size,
+            _ => None,
+        };
+        queue_size.unwrap_or(EXCHANGE_QUEUE_SIZE)
+    }
+
     pub fn input_id(&self) -> u16 {
         match self {
-            Node::Transform(node) => node.input_id,
+            Node::Transform(node) => node.input_ids[0],
             Node::Sink(node) => node.input_id,
             Node::Source(node) => panic!("source node has not input id"),
         }
     }
 
+    /// source/transform为第一个output, sink为name
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Source(node) => &node.source_config.outputs[0],
+            Node::Transform(node) => &node.transform_config.outputs[0],
+            Node::Sink(node) => &node.sink_config.name,
+        }
+    }
+
     pub fn id(&self) -> u16 {
         match self {
             Node::Source(node) => node.id,
@@ -66,6 +100,16 @@ impl Node {
         }
     }
 
+    /// output不是节点的主输出(outputs[0])时作为side output
+    pub fn add_output(&mut self, output: &String, output_id: u16) {
+        if let Node::Transform(node) = self {
+            if node.transform_config.outputs.first() != Some(output) {
+                node.side_outputs.insert(output_id, output.clone());
+            }
+        }
+        self.add_output_id(output_id);
+    }
+
 }
 
 impl serde::ser::Serialize for Node {
@@ -98,14 +142,16 @@ impl SourceNode {
 #[derive(Debug, Clone, Serialize)]
 pub struct TransformNode {
     pub id: u16,
-    pub input_id: u16,
+    pub input_ids: Vec<u16>,
     pub ouput_ids: Vec<u16>,
+    /// 输出节点id -> side output名称
+    pub side_outputs: HashMap<u16, String>,
     pub transform_config: TransformOuter,
 }
 
 impl TransformNode {
     pub fn new_unparsed(transform_config: TransformOuter) -> Self {
-        Self { id:0, input_id:0, ouput_ids: vec![], transform_config }
+        Self { id:0, input_ids: vec![], ouput_ids: vec![], side_outputs: HashMap::new(), transform_config }
     }
 }
 
@@ -139,25 +185,64 @@ impl NodeIdGenerator {
 pub struct Graph {
     pub source_ids: Vec<u16>,
     pub node_dict: HashMap<u16, Arc<Node>>,
+    /// on_error引用的dead letter sink, key为sink名称
+    pub dead_letter_sinks: HashMap<String, SinkOuter>,
+    /// 构建图时计算的每个节点的输入/输出schema
+    input_schemas: HashMap<u16, Schema>,
+    output_schemas: HashMap<u16, Schema>,
 }
 
 impl Graph {
 
+    pub fn new(source_ids: Vec<u16>, node_dict: HashMap<u16, Arc<Node>>, dead_letter_sinks: HashMap<String, SinkOuter>) -> Result<Self> {
+        let mut graph = Graph { source_ids, node_dict, dead_letter_sinks, input_schemas: HashMap::new(), output_schemas: HashMap::new() };
+        graph.compute_schemas()?;
+        Ok(graph)
+    }
+
+    /// 按拓扑顺序计算每个节点的schema, 每个transform只构建一次
+    fn compute_schemas(&mut self) -> Result<()> {
+        for id in self.topological_ids() {
+            let node = self.node_dict[&id].clone();
+            let rst = self.compute_node_schemas(&node);
+            let (input_schema, output_schema) = rst.map_err(|e| format!("{} {} invalid: {}", self.get_node_kind_dispaly(&node), node.name(), e))?;
+            self.input_schemas.insert(id, input_schema);
+            self.output_schemas.insert(id, output_schema);
+        }
+        Ok(())
+    }
+
+    fn compute_node_schemas(&self, node: &Node) -> Result<(Schema, Schema)> {
+        match node {
+            Node::Source(node) => Ok((node.schema.clone(), node.schema.clone())),
+            Node::Transform(transform_node) => {
+                let schemas = transform_node.input_ids.iter().map(|input_id| self.edge_schema(*input_id, transform_node.id).clone()).collect();
+                let input_schema = transform_node.transform_config.inner.input_schema(schemas)?;
+                let output_schema = transform_node.transform_config.inner.build(input_schema.clone())?.schema().clone();
+                Ok((input_schema, output_schema))
+            },
+            Node::Sink(sink_node) => {
+                let schema = self.edge_schema(sink_node.input_id, sink_node.id).clone();
+                Ok((schema.clone(), schema))
+            },
+        }
+    }
+
     pub fn get_node_dispaly_by_id(&self, id: u16) -> String {
         match self.node_dict[&id].as_ref() {
             Node::Source(node) => {
                 if node.ouput_ids.len() == 1 {
-                    format!("source({}) -> {}", node.id, self.get_node_dispaly_by_id(node.ouput_ids[0]))
+                    format!("source({}) -> {}", node.id, self.get_output_dispaly_by_id(node.ouput_ids[0]))
                 } else {
-                    format!("source({}) -> ({})", node.id, node.ouput_ids.iter().map(|id| self.get_node_dispaly_by_id(*id)).join(","))
+                    format!("source({}) -> ({})", node.id, node.ouput_ids.iter().map(|id| self.get_output_dispaly_by_id(*id)).join(","))
                 }
 
             },
             Node::Transform(node) => {
                 if node.ouput_ids.len() == 1 {
-                    format!("transform({}) -> {}", node.id, self.get_node_dispaly_by_id(node.ouput_ids[0]))
+                    format!("transform({}) -> {}", node.id, self.get_output_dispaly_by_id(node.ouput_ids[0]))
                 } else {
-                    format!("transform({}) -> ({})", node.id, node.ouput_ids.iter().map(|id| self.get_node_dispaly_by_id(*id)).join(","))
+                    format!("transform({}) -> ({})", node.id, node.ouput_ids.iter().map(|id| self.get_output_dispaly_by_id(*id)).join(","))
                 }
             },
             Node::Sink(node) => {
@@ -166,6 +251,140 @@ impl Graph {
         }
     }
 
+    /// exchange节点在独立的线程中运行, 上游只显示到exchange
+    fn get_output_dispaly_by_id(&self, id: u16) -> String {
+        if self.node_dict[&id].is_exchange() {
+            format!("exchange({})", id)
+        } else {
+            self.get_node_dispaly_by_id(id)
+        }
+    }
+
+    /// 节点所在task的subtask数: source使用自身配置的parallelism, 有partition_by的exchange使用application的parallelism,
+    /// 没有partition_by的exchange按上游subtask序号转发, 使用上游task中最大的parallelism, 其它节点和上游在同一个task中
+    pub fn parallelism(&self, id: u16, default: u8) -> u8 {
+        let node = self.node_dict[&id].as_ref();
+        match node {
+            Node::Source(node) => node.source_config.parallelism.unwrap_or(default),
+            _ if node.is_exchange() && !node.partition_by().is_empty() => default,
+            Node::Transform(node) => node.input_ids.iter().map(|id| self.parallelism(*id, default)).max().unwrap_or(default),
+            Node::Sink(node) => self.parallelism(node.input_id, default),
+        }
+    }
+
+    pub fn exchange_ids(&self) -> Vec<u16> {
+        self.node_dict.values().filter(|node| node.is_exchange()).map(|node| node.id()).sorted().collect()
+    }
+
+    pub fn output_schema(&self, id: u16) -> &Schema {
+        &self.output_schemas[&id]
+    }
+
+    pub fn input_schema(&self, id: u16) -> &Schema {
+        &self.input_schemas[&id]
+    }
+
+    /// input_id节点输出到id节点的数据的schema
+    fn edge_schema(&self, input_id: u16, id: u16) -> &Schema {
+        match self.node_dict[&input_id].as_ref() {
+            // side output输出的是transform的输入数据
+            Node::Transform(node) if node.side_outputs.contains_key(&id) => self.input_schema(input_id),
+            _ => self.output_schema(input_id),
+        }
+    }
+
+    /// 按上游到下游的顺序返回所有节点id
+    pub fn topological_ids(&self) -> Vec<u16> {
+        let mut in_degrees: HashMap<u16, usize> = self.node_dict.keys().map(|id| (*id, 0)).collect();
+        for node in self.node_dict.values().filter(|node| !node.is_sink()) {
+            for output_id in node.output_ids() {
+                *in_degrees.get_mut(output_id).unwrap() += 1;
+            }
+        }
+        let mut ids = Vec::with_capacity(self.node_dict.len());
+        let mut queue: Vec<u16> = self.source_ids.iter().rev().copied().collect();
+        while let Some(id) = queue.pop() {
+            ids.push(id);
+            let node = self.node_dict[&id].as_ref();
+            if node.is_sink() {
+                continue;
+            }
+            for output_id in node.output_ids().iter().rev() {
+                let in_degree = in_degrees.get_mut(output_id).unwrap();
+                *in_degree -= 1;
+                if *in_degree == 0 {
+                    queue.push(*output_id);
+                }
+            }
+        }
+        ids
+    }
+
+    /// 构建所有节点的配置并检查schema和sql, 不会创建连接
+    pub fn validate(&self) -> Result<()> {
+        for id in self.topological_ids() {
+            let node = self.node_dict[&id].as_ref();
+            let rst = match node {
+                Node::Source(source_node) if source_node.source_config.parallelism == Some(0) => Err("parallelism must be greater than 0".to_string()),
+                Node::Source(source_node) => source_node.source_config.inner.build(source_node.schema.clone()).map(|_| ()),
+                // transform在构建图计算schema时已经构建过
+                Node::Transform(transform_node) => ExchangeCollector::key_indices(&transform_node.transform_config.partition_by, self.input_schema(id)).map(|_| ()),
+                Node::Sink(sink_node) => sink_node.sink_config.inner.build(self.input_schema(id).clone()).map(|_| ()),
+            };
+            rst.map_err(|e| format!("{} {} invalid: {}", self.get_node_kind_dispaly(node), node.name(), e))?;
+        }
+        for (name, sink) in self.dead_letter_sinks.iter() {
+            sink.inner.build(ErrorHandler::dead_letter_schema()).map_err(|e| format!("dead letter sink {} invalid: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    fn get_node_kind_dispaly(&self, node: &Node) -> String {
+        match node {
+            Node::Source(node) => format!("source({})", node.id),
+            Node::Transform(node) => format!("transform({})", node.id),
+            Node::Sink(node) => format!("sink({})", node.id),
+        }
+    }
+
+    /// 输出各个线程执行的节点链, 每个节点的输出schema和sql transform的执行计划
+    pub fn explain(&self) -> Result<String> {
+        let mut lines = Vec::new();
+        lines.push("== Tasks ==".to_string());
+        for id in self.source_ids.iter().chain(self.exchange_ids().iter()) {
+            lines.push(self.get_node_dispaly_by_id(*id));
+        }
+        lines.push(String::new());
+        lines.push("== Nodes ==".to_string());
+        for id in self.topological_ids() {
+            let node = self.node_dict[&id].as_ref();
+            lines.push(format!("{} {}", self.get_node_kind_dispaly(node), node.name()));
+            match node {
+                Node::Source(source_node) => {
+                    if let Some(parallelism) = source_node.source_config.parallelism {
+                        lines.push(format!("  parallelism: {}", parallelism));
+                    }
+                },
+                Node::Transform(transform_node) => {
+                    let inputs = transform_node.input_ids.iter().map(|input_id| self.get_node_kind_dispaly(&self.node_dict[input_id])).join(", ");
+                    lines.push(format!("  inputs: {}", inputs));
+                },
+                Node::Sink(sink_node) => lines.push(format!("  inputs: {}", self.get_node_kind_dispaly(&self.node_dict[&sink_node.input_id]))),
+            }
+            lines.push(format!("  schema: {}", self.output_schema(id)));
+            if let Node::Transform(transform_node) = node && let Some(sql) = transform_node.transform_config.inner.sql() {
+                let schema = self.input_schema(id);
+                let analyzed_plan = sql_utils::analyzed_sql_plan(sql, schema)?;
+                let optimized_plan = sql_utils::sql_plan(sql, schema)?;
+                lines.push("  analyzed plan:".to_string());
+                lines.extend(analyzed_plan.tree_string().lines().map(|line| format!("    {}", line)));
+                lines.push("  optimized plan:".to_string());
+                lines.extend(optimized_plan.tree_string().lines().map(|line| format!("    {}", line)));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
     pub fn debug_node_chains(&self) {
         for id in self.source_ids.iter() {
             debug!("source id: {}", id);
@@ -211,14 +430,20 @@ impl NodeParser {
         for transform in config.transforms.iter() {
             let output = transform.outputs[0].clone();
             let node = Rc::new(RefCell::new(Node::Transform(TransformNode::new_unparsed(transform.clone()))));
+            for side_output in transform.inner.side_outputs() {
+                if transform.outputs.contains(&side_output) {
+                    return Err(format!("side output {} is also in outputs of transform {}", side_output, output));
+                }
+                self.unparsed_output_node_dict.insert(side_output, node.clone());
+            }
             self.unparsed_output_node_dict.insert(output, node);
         }
 
         for sink in config.sinks.iter() {
-            let input = &sink.inputs[0];
-            if !config.active_sinks.contains(&sink.name) {
+            if sink.inputs.is_empty() || !config.active_sinks.contains(&sink.name) {
                 continue;
             }
+            let input = &sink.inputs[0];
             let mut in_node = if let Some(node) = self.output_node_dict.get(input) {
                 node.clone()
             } else {
@@ -230,23 +455,50 @@ impl NodeParser {
             let mut node = SinkNode::new_unparsed(sink.clone());
             node.input_id = in_node.borrow().id();
             node.id = NodeIdGenerator::get_next_node_id();
-            in_node.borrow_mut().add_output_id(node.id);
+            in_node.borrow_mut().add_output(input, node.id);
             self.sink_ids.push(node.id);
             self.node_dict.insert(node.id,  Rc::new(RefCell::new(Node::Sink(node))));
         }
         let source_ids = self.source_ids.clone();
         let mut node_dict: HashMap<u16, Arc<Node>> = self.node_dict.iter().map(|(i, node)| (*i, Arc::new(node.borrow().clone()))).collect();
-        Ok(Graph{ source_ids, node_dict})
+        let dead_letter_sinks = Self::parse_dead_letter_sinks(config)?;
+        Graph::new(source_ids, node_dict, dead_letter_sinks)
+    }
+
+    fn parse_dead_letter_sinks(config: &AppConfig) -> Result<HashMap<String, SinkOuter>> {
+        let on_errors = config.sources.iter().map(|s| &s.on_error)
+            .chain(config.transforms.iter().map(|t| &t.on_error))
+            .chain(config.sinks.iter().map(|s| &s.on_error));
+        let mut dead_letter_sinks = HashMap::new();
+        for on_error in on_errors {
+            if let OnErrorConfig::DeadLetter(name) = on_error {
+                let sink = config.sinks.iter().find(|s| &s.name == name).ok_or_else(|| format!("dead letter sink {} not found in sinks", name))?;
+                dead_letter_sinks.insert(name.clone(), sink.clone());
+            }
+        }
+        Ok(dead_letter_sinks)
     }
 
+    /// inputs为当前解析路径上的output, 所有递归共用, 返回前弹出当前input
     fn parse_input_node(&mut self, input: &String, inputs: &mut Vec<String>) -> Result<Rc<RefCell<Node>>> {
         //println!("parse input node: {}", input);
         if inputs.contains(&input) {
             return Err(format!("input node loop for [{}] -> {}", inputs.join(","), input));
         }
         inputs.push(input.clone());
+        let rst = self.parse_unvisited_input_node(input, inputs);
+        inputs.pop();
+        rst
+    }
+
+    fn parse_unvisited_input_node(&mut self, input: &String, inputs: &mut Vec<String>) -> Result<Rc<RefCell<Node>>> {
         if let Some(node) = self.unparsed_output_node_dict.get(input) {
             let node = node.clone();
+            if node.borrow().id() != 0 {
+                // 通过其它output已经解析过
+                self.output_node_dict.insert(input.clone(), node.clone());
+                return Ok(node);
+            }
             match &mut *node.borrow_mut() {
                 Node::Source(sourde_node) => {
                     sourde_node.id = NodeIdGenerator::get_next_node_id();
@@ -256,18 +508,22 @@ impl NodeParser {
                     Ok(node.clone())
                 },
                 Node::Transform(transform_node) => {
-                    let transform_input = &transform_node.transform_config.inputs[0];
-                    let input_node = if let Some(node) = self.output_node_dict.get(transform_input) {
-                        node.clone()
-                    } else {
-                        let input_node = self.parse_input_node(transform_input, inputs)?;
-                        self.output_node_dict.insert(transform_input.clone(), input_node.clone());
-                        //println!("end parse input node: {}", transform_input);
-                        input_node
-                    };
-                    transform_node.input_id = input_node.borrow().id();
                     transform_node.id = NodeIdGenerator::get_next_node_id();
-                    input_node.borrow_mut().add_output_id(transform_node.id);
+                    for transform_input in transform_node.transform_config.inputs.iter() {
+                        let input_node = if let Some(node) = self.output_node_dict.get(transform_input) {
+                            node.clone()
+                        } else {
+                            let input_node = self.parse_input_node(transform_input, inputs)?;
+                            self.output_node_dict.insert(transform_input.clone(), input_node.clone());
+                            //println!("end parse input node: {}", transform_input);
+                            input_node
+                        };
+                        transform_node.input_ids.push(input_node.borrow().id());
+                        input_node.borrow_mut().add_output(transform_input, transform_node.id);
+                    }
+                    if transform_node.input_ids.is_empty() {
+                        return Err(format!("transform {} has no inputs", input));
+                    }
                     self.output_node_dict.insert(input.clone(), node.clone());
                     self.node_dict.insert(transform_node.id, node.clone());
                     Ok(node.clone())
@@ -303,6 +559,45 @@ mod tests {
         // println!("\n{}", serde_json::to_string_pretty(&sink_nodes).unwrap());
     }
 
+    #[test]
+    fn test_validate_explain() {
+        let config: AppConfig = parse_config("config/application_union.yaml").unwrap();
+        let graph = NodeParser::new().parse_node_graph(&config).unwrap();
+        graph.validate().unwrap();
+        let ids = graph.topological_ids();
+        assert_eq!(ids.len(), graph.node_dict.len());
+        assert!(graph.node_dict[&ids[0]].is_source());
+        assert!(graph.node_dict[ids.last().unwrap()].is_sink());
+        let explain = graph.explain().unwrap();
+        println!("{}", explain);
+        assert!(explain.contains("optimized plan:"));
+        assert!(explain.contains("+- RelationPlaceholder tbl"));
+    }
+
+    #[test]
+    fn test_parallelism() {
+        let mut config: AppConfig = parse_config("config/application_union.yaml").unwrap();
+        config.sources[0].parallelism = Some(3);
+        let graph = NodeParser::new().parse_node_graph(&config).unwrap();
+        let union_id = graph.exchange_ids()[0];
+        // union没有partition_by, 按上游最大的parallelism
+        assert_eq!(graph.parallelism(union_id, 2), 3);
+        assert_eq!(graph.parallelism(graph.source_ids[0], 2), 3);
+        assert_eq!(graph.parallelism(graph.source_ids[1], 2), 2);
+
+        config.transforms[0].partition_by = vec!["cate_id".to_string()];
+        let graph = NodeParser::new().parse_node_graph(&config).unwrap();
+        assert_eq!(graph.parallelism(graph.exchange_ids()[0], 2), 2);
+    }
+
+    #[test]
+    fn test_input_node_loop() {
+        let mut config: AppConfig = parse_config("config/application_union.yaml").unwrap();
+        config.transforms[0].inputs = vec!["faker_source".to_string(), "task_aggregate".to_string()];
+        let err = NodeParser::new().parse_node_graph(&config).unwrap_err();
+        assert_eq!(err, "input node loop for [task_aggregate,union] -> task_aggregate");
+    }
+
 }
 
 
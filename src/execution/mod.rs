@@ -4,11 +4,17 @@ mod execution;
 pub mod application;
 mod task;
 mod timer;
+mod exchange;
+mod web;
+mod state;
+pub mod test_runner;
 
 pub use collector::*;
 pub use graph::*;
 pub use execution::*;
 pub use timer::*;
+pub use exchange::*;
+pub use state::*;
 
 pub enum PollStatus {
     More,
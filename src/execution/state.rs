@@ -0,0 +1,354 @@
+use std::fs;
+use std::io::{Cursor, ErrorKind, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use byteorder::{BigEndian, ReadBytesExt};
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::{GenericRow, Row, Value};
+use crate::expr::aggregate::restore_object;
+
+const STATE_MAGIC: &[u8; 4] = b"RETL";
+const STATE_VERSION: u32 = 2;
+
+const TAG_NULL: u8 = 0;
+const TAG_INT: u8 = 1;
+const TAG_LONG: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_DOUBLE: u8 = 4;
+const TAG_STRING: u8 = 5;
+const TAG_BOOLEAN: u8 = 6;
+const TAG_BINARY: u8 = 7;
+const TAG_STRUCT: u8 = 8;
+const TAG_ARRAY: u8 = 9;
+const TAG_OBJECT: u8 = 10;
+const TAG_MAP: u8 = 11;
+const TAG_DECIMAL: u8 = 12;
+
+/// 算子状态快照文件, 路径: {state dir}/{kind}_{node name}_{subtask index}.state, 节点名称不随配置中节点的顺序变化.
+/// 节点名称中字母, 数字, '-'和'_'以外的字节按%XX编码, 不同的名称对应不同的文件
+/// 文件格式: magic(4) + version(u32) + parallelism(u8) + fingerprint长度(u32) + fingerprint + 算子写入的数据, 先写临时文件再rename
+#[derive(Debug)]
+pub struct StateStore {
+    path: PathBuf,
+    subtask_parallelism: u8,
+    /// 状态的key和buffer类型, 修改sql后类型不一致的快照不能恢复
+    fingerprint: String,
+    pub snapshot_interval_ms: u64,
+}
+
+impl StateStore {
+    /// 没有配置state时返回None
+    pub fn new(task_context: &TaskContext, kind: &str, fingerprint: String) -> Option<Self> {
+        let task_config = &task_context.task_config;
+        let name = encode_name(&task_context.operator_config.name);
+        task_config.state_config.as_ref().map(|state_config| Self {
+            path: PathBuf::from(&state_config.dir).join(format!("{}_{}_{}.state", kind, name, task_config.subtask_index)),
+            subtask_parallelism: task_config.subtask_parallelism,
+            fingerprint,
+            snapshot_interval_ms: state_config.snapshot_interval_ms,
+        })
+    }
+
+    pub fn save(&self, data: &[u8]) -> Result<()> {
+        let mut buf = Vec::with_capacity(13 + self.fingerprint.len() + data.len());
+        buf.extend_from_slice(STATE_MAGIC);
+        buf.extend_from_slice(&STATE_VERSION.to_be_bytes());
+        buf.push(self.subtask_parallelism);
+        buf.extend_from_slice(&(self.fingerprint.len() as u32).to_be_bytes());
+        buf.extend_from_slice(self.fingerprint.as_bytes());
+        buf.extend_from_slice(data);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create state dir {}: {}", dir.display(), e))?;
+        }
+        let tmp_path = self.path.with_extension("state.tmp");
+        fs::write(&tmp_path, &buf).map_err(|e| format!("Failed to write state file {}: {}", tmp_path.display(), e))?;
+        fs::rename(&tmp_path, &self.path).map_err(|e| format!("Failed to rename state file {}: {}", tmp_path.display(), e))
+    }
+
+    /// 快照不存在时返回None, 并行度或者状态类型变化时报错
+    pub fn load(&self) -> Result<Option<Vec<u8>>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read state file {}: {}", self.path.display(), e)),
+        };
+        if bytes.len() < 9 || &bytes[..4] != STATE_MAGIC {
+            return Err(format!("invalid state file: {}", self.path.display()));
+        }
+        let version = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        if version != STATE_VERSION {
+            return Err(format!("not support state version {}: {}", version, self.path.display()));
+        }
+        if bytes[8] != self.subtask_parallelism {
+            return Err(format!("state file {} was saved with parallelism {}, current parallelism is {}, delete it to start without state",
+                self.path.display(), bytes[8], self.subtask_parallelism));
+        }
+        let mut reader = StateReader::new(&bytes[9..]);
+        let fingerprint = reader.read_string()?;
+        if fingerprint != self.fingerprint {
+            return Err(format!("state file {} was saved with state types {}, current state types are {}, delete it to start without state",
+                self.path.display(), fingerprint, self.fingerprint));
+        }
+        Ok(Some(bytes[13 + fingerprint.len()..].to_vec()))
+    }
+
+    /// 没有需要保存的状态时删除快照
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(format!("Failed to remove state file {}: {}", self.path.display(), e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn encode_name(name: &str) -> String {
+    name.bytes().map(|b| if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' { (b as char).to_string() } else { format!("%{:02X}", b) }).collect()
+}
+
+/// 状态数据编码, 整数使用大端序, Value以类型tag开头, Object通过Object::snapshot转换为Value
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn write_u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn write_i64(&mut self, v: i64) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn write_row(&mut self, row: &dyn Row) -> Result<()> {
+        self.write_u32(row.len() as u32);
+        for i in 0..row.len() {
+            self.write_value(row.get(i))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_value(&mut self, value: &Value) -> Result<()> {
+        match value {
+            Value::Null => self.buf.push(TAG_NULL),
+            Value::Int(v) => {
+                self.buf.push(TAG_INT);
+                self.buf.extend_from_slice(&v.to_be_bytes());
+            },
+            Value::Long(v) => {
+                self.buf.push(TAG_LONG);
+                self.buf.extend_from_slice(&v.to_be_bytes());
+            },
+            Value::Float(v) => {
+                self.buf.push(TAG_FLOAT);
+                self.buf.extend_from_slice(&v.to_be_bytes());
+            },
+            Value::Double(v) => {
+                self.buf.push(TAG_DOUBLE);
+                self.buf.extend_from_slice(&v.to_be_bytes());
+            },
+            Value::String(v) => {
+                self.buf.push(TAG_STRING);
+                self.write_bytes(v.as_bytes());
+            },
+            Value::Boolean(v) => {
+                self.buf.push(TAG_BOOLEAN);
+                self.buf.push(*v as u8);
+            },
+            Value::Binary(v) => {
+                self.buf.push(TAG_BINARY);
+                self.write_bytes(v);
+            },
+            Value::Struct(v) => {
+                self.buf.push(TAG_STRUCT);
+                self.write_row(v.as_row())?;
+            },
+            Value::Array(v) => {
+                self.buf.push(TAG_ARRAY);
+                self.write_u32(v.len() as u32);
+                for item in v.iter() {
+                    self.write_value(item)?;
+                }
+            },
+            Value::Map(v) => {
+                self.buf.push(TAG_MAP);
+                self.write_u32(v.len() as u32);
+                for (key, value) in v.iter() {
+                    self.write_value(key)?;
+                    self.write_value(value)?;
+                }
+            },
+            Value::Decimal(v, scale) => {
+                self.buf.push(TAG_DECIMAL);
+                self.buf.extend_from_slice(&v.to_be_bytes());
+                self.buf.push(*scale);
+            },
+            Value::Object(v) => {
+                let (name, value) = v.snapshot().ok_or_else(|| format!("not support snapshot object: {:?}", v))?;
+                self.buf.push(TAG_OBJECT);
+                self.write_bytes(name.as_bytes());
+                self.write_value(&value)?;
+            },
+        }
+        Ok(())
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct StateReader<'a> {
+    cursor: Cursor<&'a [u8]>,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { cursor: Cursor::new(bytes) }
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        self.cursor.read_u32::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        self.cursor.read_u64::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64> {
+        self.cursor.read_i64::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_u32()? as usize;
+        let mut bytes = vec![0; len];
+        self.cursor.read_exact(&mut bytes).map_err(|e| format!("invalid state data: {}", e))?;
+        Ok(bytes)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        String::from_utf8(self.read_bytes()?).map_err(|e| format!("invalid state data: {}", e))
+    }
+
+    pub fn read_row(&mut self) -> Result<GenericRow> {
+        let len = self.read_u32()? as usize;
+        let mut values = Vec::with_capacity(len);
+        for _ in 0..len {
+            values.push(self.read_value()?);
+        }
+        Ok(GenericRow::new(values))
+    }
+
+    pub fn read_value(&mut self) -> Result<Value> {
+        let tag = self.cursor.read_u8().map_err(|e| format!("invalid state data: {}", e))?;
+        let value = match tag {
+            TAG_NULL => Value::Null,
+            TAG_INT => Value::Int(self.cursor.read_i32::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))?),
+            TAG_LONG => Value::Long(self.read_i64()?),
+            TAG_FLOAT => Value::Float(self.cursor.read_f32::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))?),
+            TAG_DOUBLE => Value::Double(self.cursor.read_f64::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))?),
+            TAG_STRING => Value::String(Arc::new(self.read_string()?)),
+            TAG_BOOLEAN => Value::Boolean(self.cursor.read_u8().map_err(|e| format!("invalid state data: {}", e))? != 0),
+            TAG_BINARY => Value::Binary(Arc::new(self.read_bytes()?)),
+            TAG_STRUCT => Value::Struct(Arc::new(self.read_row()?)),
+            TAG_ARRAY => {
+                let len = self.read_u32()? as usize;
+                let mut values = Vec::with_capacity(len);
+                for _ in 0..len {
+                    values.push(self.read_value()?);
+                }
+                Value::Array(Arc::new(values))
+            },
+            TAG_MAP => {
+                let len = self.read_u32()? as usize;
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    entries.push((self.read_value()?, self.read_value()?));
+                }
+                Value::Map(Arc::new(entries))
+            },
+            TAG_DECIMAL => {
+                let v = self.cursor.read_i128::<BigEndian>().map_err(|e| format!("invalid state data: {}", e))?;
+                let scale = self.cursor.read_u8().map_err(|e| format!("invalid state data: {}", e))?;
+                Value::Decimal(v, scale)
+            },
+            TAG_OBJECT => {
+                let name = self.read_string()?;
+                let value = self.read_value()?;
+                Value::Object(restore_object(&name, value)?)
+            },
+            _ => return Err(format!("invalid state value tag: {}", tag)),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{OperatorConfig, StateConfig};
+    use super::*;
+
+    #[test]
+    fn test_state_store() {
+        let dir = std::env::temp_dir().join(format!("retl_state_store_{}", std::process::id()));
+        let mut task_context = TaskContext::default();
+        task_context.operator_config = OperatorConfig::new(3, "cate stat");
+        task_context.task_config = task_context.task_config.with_state_config(Some(StateConfig { dir: dir.to_string_lossy().into_owned(), snapshot_interval_ms: 1000 }));
+        let store = StateStore::new(&task_context, "task_aggregate", "key(int),buffer(sum:bigint)".to_string()).unwrap();
+        assert_eq!(store.path, dir.join("task_aggregate_cate%20stat_0.state"));
+        assert_eq!(encode_name("a.b"), "a%2Eb");
+        assert_eq!(encode_name("a_b"), "a_b");
+        assert_eq!(encode_name("a%2Eb"), "a%252Eb");
+        assert_eq!(encode_name("统计"), "%E7%BB%9F%E8%AE%A1");
+        store.save(&[1, 2, 3]).unwrap();
+        assert_eq!(store.load().unwrap(), Some(vec![1, 2, 3]));
+        // 修改sql后状态类型不一致
+        let store = StateStore::new(&task_context, "task_aggregate", "key(int),buffer(max:int)".to_string()).unwrap();
+        assert!(store.load().unwrap_err().contains("state types"));
+        store.clear().unwrap();
+        assert_eq!(store.load().unwrap(), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_state_codec() {
+        let row = GenericRow::new(vec![Value::Null, Value::int(1), Value::long(-2), Value::Float(1.5), Value::Double(2.5), Value::string("a"),
+            Value::Boolean(true), Value::Binary(Arc::new(vec![1, 2])), Value::Struct(Arc::new(GenericRow::new(vec![Value::int(3)]))),
+            Value::Array(Arc::new(vec![Value::string("x"), Value::Null])), Value::Map(Arc::new(vec![(Value::string("k"), Value::long(1))])),
+            Value::Decimal(-12345, 2)]);
+        let mut writer = StateWriter::new();
+        writer.write_u64(100);
+        writer.write_row(&row).unwrap();
+        let bytes = writer.into_bytes();
+        let mut reader = StateReader::new(&bytes);
+        assert_eq!(reader.read_u64().unwrap(), 100);
+        assert_eq!(reader.read_row().unwrap(), row);
+        assert!(reader.read_value().is_err());
+
+        let set = restore_object("collect_set", Value::Array(Arc::new(vec![Value::string("a")]))).unwrap();
+        let list = restore_object("collect_list", Value::Array(Arc::new(vec![Value::int(1), Value::int(1)]))).unwrap();
+        let mut writer = StateWriter::new();
+        writer.write_row(&GenericRow::new(vec![Value::Object(set), Value::Object(list)])).unwrap();
+        let bytes = writer.into_bytes();
+        let row = StateReader::new(&bytes).read_row().unwrap();
+        let snapshots: Vec<_> = (0..2).map(|i| match row.get(i) {
+            Value::Object(obj) => obj.snapshot().unwrap(),
+            _ => panic!("not object"),
+        }).collect();
+        assert_eq!(snapshots, vec![("collect_set", Value::Array(Arc::new(vec![Value::string("a")]))),
+            ("collect_list", Value::Array(Arc::new(vec![Value::int(1), Value::int(1)])))]);
+    }
+}
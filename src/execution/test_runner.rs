@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use prometheus::Registry;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use crate::Result;
+use crate::codecs::Deserializer;
+use crate::codecs::json::JsonDeserializer;
+use crate::config::{self, TaskConfig};
+use crate::connector::memory::{MemoryRows, MemorySinkConfig};
+use crate::datetime_utils::set_mock_processing_time_millis;
+use crate::execution::{new_local_exchanges, new_outputs_collector, Collector, DeadLetterSinks, Exchanges, Graph, LocalExchange, Node, NodeParser};
+
+/// retl test的用例文件
+#[derive(Debug, Deserialize)]
+pub struct TestCases {
+    pub cases: Vec<TestCase>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    /// mock处理时间的初始值
+    #[serde(default)]
+    pub start_time_ms: u64,
+    pub steps: Vec<TestStep>,
+    /// sink名称 -> 期望输出的数据, 没有列出的sink不比较
+    #[serde(default)]
+    pub expected: BTreeMap<String, Vec<JsonValue>>,
+    /// 为false时不比较输出顺序
+    #[serde(default = "default_ordered")]
+    pub ordered: bool,
+}
+
+fn default_ordered() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum TestStep {
+    /// 代替source输出数据, source为source的第一个output名称
+    Rows { source: String, rows: Vec<JsonValue> },
+    /// mock处理时间前进advance_ms并触发到期的timer
+    Advance { advance_ms: u64 },
+}
+
+#[derive(Debug)]
+pub struct TestCaseResult {
+    pub name: String,
+    /// 失败原因, 为空时用例通过
+    pub failures: Vec<String>,
+}
+
+impl TestCaseResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// 单线程运行用例: source替换为用例中的数据, 所有sink替换为memory sink, exchange节点在当前线程中直接调用
+struct TestRunner {
+    /// source节点名称 -> (source的下游算子链, json解析)
+    chains: Vec<(String, Box<dyn Collector>, JsonDeserializer)>,
+    /// 按拓扑顺序
+    exchanges: Vec<LocalExchange>,
+    now: u64,
+}
+
+impl TestRunner {
+    fn new(graph: &Graph, start_time_ms: u64) -> Result<Self> {
+        let task_config = TaskConfig::new(1, 0, Registry::new());
+        let dead_letters = DeadLetterSinks::default();
+        let exchanges = new_local_exchanges(graph, task_config.clone(), &dead_letters)?;
+        let mut chains = Vec::with_capacity(graph.source_ids.len());
+        for source_id in graph.source_ids.iter() {
+            if let Node::Source(source_node) = graph.node_dict[source_id].as_ref() {
+                let out = new_outputs_collector(&source_node.ouput_ids, graph, task_config.clone(), source_node.schema.clone(), &exchanges, &dead_letters)?;
+                chains.push((graph.node_dict[source_id].name().to_string(), out, JsonDeserializer::new(source_node.schema.clone())));
+            }
+        }
+        let exchanges = match exchanges {
+            Exchanges::Local(outs) => graph.topological_ids().into_iter().filter_map(|id| outs.get(&id).cloned()).collect(),
+            Exchanges::Channel(_) => Vec::new(),
+        };
+        Ok(Self { chains, exchanges, now: start_time_ms })
+    }
+
+    fn open(&mut self) -> Result<()> {
+        for exchange in self.exchanges.iter().rev() {
+            exchange.borrow_mut().open()?;
+        }
+        for (_, out, _) in self.chains.iter_mut() {
+            out.open()?;
+        }
+        Ok(())
+    }
+
+    fn run_step(&mut self, step: &TestStep) -> Result<()> {
+        match step {
+            TestStep::Rows { source, rows } => {
+                let (_, out, deserializer) = self.chains.iter_mut().find(|(name, _, _)| name == source)
+                    .ok_or_else(|| format!("source {} not found", source))?;
+                for row in rows {
+                    let bytes = serde_json::to_vec(row).map_err(|e| e.to_string())?;
+                    out.collect(deserializer.deserialize(&bytes)?)?;
+                }
+            },
+            TestStep::Advance { advance_ms } => {
+                self.now += advance_ms;
+                set_mock_processing_time_millis(Some(self.now));
+            },
+        }
+        self.check_timer()
+    }
+
+    fn check_timer(&mut self) -> Result<()> {
+        for (_, out, _) in self.chains.iter_mut() {
+            out.check_timer(self.now)?;
+        }
+        for exchange in self.exchanges.iter() {
+            exchange.borrow_mut().check_timer(self.now)?;
+        }
+        Ok(())
+    }
+
+    /// 上游先finish, 输出的数据下游finish前都能处理
+    fn finish(&mut self) -> Result<()> {
+        for (_, out, _) in self.chains.iter_mut() {
+            out.finish()?;
+        }
+        for exchange in self.exchanges.iter() {
+            exchange.borrow_mut().finish()?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for (_, out, _) in self.chains.iter_mut() {
+            result = result.and(out.close());
+        }
+        for exchange in self.exchanges.iter() {
+            result = result.and(exchange.borrow_mut().close());
+        }
+        result
+    }
+
+    fn run(&mut self, case: &TestCase) -> Result<()> {
+        self.open()?;
+        for step in case.steps.iter() {
+            self.run_step(step)?;
+        }
+        self.finish()
+    }
+}
+
+pub fn run_test_case(config_path: &str, overrides: &[String], case: &TestCase) -> Result<TestCaseResult> {
+    let mut config = config::parse_config_with_overrides(config_path, overrides).map_err(|e| e.to_string())?;
+    config.env.application.parallelism = 1;
+    config.env.application.state = None;
+    let mut outputs: HashMap<String, MemoryRows> = HashMap::new();
+    for sink in config.sinks.iter_mut() {
+        let rows = MemoryRows::default();
+        sink.inner = Box::new(MemorySinkConfig::new(rows.clone()));
+        outputs.insert(sink.name.clone(), rows);
+    }
+    if let Some(name) = case.expected.keys().find(|name| !outputs.contains_key(*name)) {
+        return Err(format!("sink {} not found", name));
+    }
+    let graph = NodeParser::new().parse_node_graph(&config)?;
+
+    set_mock_processing_time_millis(Some(case.start_time_ms));
+    let result = TestRunner::new(&graph, case.start_time_ms).and_then(|mut runner| {
+        let result = runner.run(case);
+        result.and(runner.close())
+    });
+    set_mock_processing_time_millis(None);
+    result?;
+
+    let mut failures = Vec::new();
+    for (name, expected) in case.expected.iter() {
+        let rows = outputs[name].lock().unwrap();
+        let mut actual = rows.iter().map(|row| serde_json::from_str(row).map_err(|e| e.to_string())).collect::<Result<Vec<JsonValue>>>()?;
+        let mut expected = expected.clone();
+        if !case.ordered {
+            actual.sort_by_cached_key(|row| row.to_string());
+            expected.sort_by_cached_key(|row| row.to_string());
+        }
+        if actual != expected {
+            failures.push(diff_rows(name, &expected, &actual));
+        }
+    }
+    Ok(TestCaseResult { name: case.name.clone(), failures })
+}
+
+/// 逐行比较, -为期望的数据, +为实际输出的数据
+fn diff_rows(sink: &str, expected: &[JsonValue], actual: &[JsonValue]) -> String {
+    let mut lines = vec![format!("sink {}: expected {} rows, actual {} rows", sink, expected.len(), actual.len())];
+    for i in 0..expected.len().max(actual.len()) {
+        let (expected_row, actual_row) = (expected.get(i), actual.get(i));
+        if expected_row == actual_row {
+            continue;
+        }
+        if let Some(row) = expected_row {
+            lines.push(format!("  - [{}] {}", i, row));
+        }
+        if let Some(row) = actual_row {
+            lines.push(format!("  + [{}] {}", i, row));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn parse_test_cases(cases_path: &str) -> Result<TestCases> {
+    let content = fs::read_to_string(cases_path).map_err(|e| format!("Failed to read cases file {}: {}", cases_path, e))?;
+    serde_yaml::from_str(&content).map_err(|e| format!("invalid cases file {}: {}", cases_path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_test_case() {
+        let config_path = "config/application_unit_test.yaml";
+        let mut cases = parse_test_cases("config/application_unit_test_cases.yaml").unwrap();
+        for case in cases.cases.iter() {
+            let result = run_test_case(config_path, &[], case).unwrap();
+            assert!(result.passed(), "{}: {:?}", result.name, result.failures);
+        }
+        let case = &mut cases.cases[1];
+        case.expected.get_mut("agg_sink").unwrap().push(serde_json::json!({"cate_id": 2, "bytes": 1, "count": 1}));
+        let result = run_test_case(config_path, &[], case).unwrap();
+        assert_eq!(result.failures, vec!["sink agg_sink: expected 2 rows, actual 1 rows\n  - [1] {\"bytes\":1,\"cate_id\":2,\"count\":1}"]);
+        case.expected.insert("none_sink".to_string(), Vec::new());
+        assert!(run_test_case(config_path, &[], case).is_err());
+    }
+}
@@ -6,7 +6,8 @@ use std::mem;
 use std::sync::Mutex;
 use itertools::Itertools;
 use crate::Result;
-use crate::data::{GenericRow, Row, Value};
+use crate::data::{GenericRow, Object, Row, Value};
+use crate::expr::aggregate::{restore_list, restore_set};
 use crate::expr::{AttributeReference, Expr};
 use crate::types::{AbstractDataType, DataType};
 
@@ -344,4 +345,17 @@ pub trait PhysicalTypedAggFunction {
     fn eval(&self, buffer: &mut GenericRow) -> Value {
         self.eval_value(mem::replace(buffer.get_mut(self.mutable_agg_buffer_offset()), Value::Null))
     }
+    /// buffer中缓存的行数, 如distinct的去重集合大小, 计入max_rows
+    fn buffer_rows(&self, buffer: &GenericRow) -> usize {
+        0
+    }
+}
+
+/// 从状态快照恢复agg buffer中的Object, name为Object::snapshot返回的类型名称
+pub fn restore_object(name: &str, value: Value) -> Result<Box<dyn Object>> {
+    match name {
+        "collect_set" => restore_set(value),
+        "collect_list" => restore_list(value),
+        _ => Err(format!("not support restore object: {}", name)),
+    }
 }
@@ -1,11 +1,16 @@
 use std::fmt::{Debug, Formatter};
 use std::sync::Mutex;
+use crate::data::Value;
+use crate::decimal_utils::MAX_PRECISION;
 use crate::expr::{coalesce2, AttributeReference, Expr, If};
 use crate::expr::aggregate::{CreateDeclarativeAggFunction, DeclarativeAggFunction};
 use crate::types::DataType;
 
 pub struct Average {
     child: Box<Expr>,
+    /// decimal输入时sum为decimal(p + 10, s), 结果为decimal(p + 4, s + 4), 其它输入为double
+    sum_type: DataType,
+    result_type: DataType,
     sum: Mutex<Option<AttributeReference>>,
     count: Mutex<Option<AttributeReference>>,
     input_agg_attrs: Mutex<Vec<AttributeReference>>,
@@ -14,17 +19,24 @@ pub struct Average {
 
 impl Average {
     pub fn new(child: Box<Expr>) -> Self {
+        let (sum_type, result_type) = match child.data_type() {
+            DataType::Decimal(p, s) => (
+                DataType::decimal_type((p + 10).min(MAX_PRECISION), *s).clone(),
+                DataType::decimal_type((p + 4).min(MAX_PRECISION), (s + 4).min(MAX_PRECISION)).clone(),
+            ),
+            _ => (DataType::Double, DataType::Double),
+        };
         let sum = Mutex::new(None);
         let count = Mutex::new(None);
         let input_agg_attrs = Mutex::new(vec![]);
         let result_attr = Mutex::new(None);
-        Self { child, sum, count, input_agg_attrs, result_attr }
+        Self { child, sum_type, result_type, sum, count, input_agg_attrs, result_attr }
     }
 
     fn sum_attr(&self) -> AttributeReference {
         let mut sum_guard = self.sum.lock().unwrap();
         if sum_guard.is_none() {
-            *sum_guard = Some(AttributeReference::new("sum", DataType::Double));
+            *sum_guard = Some(AttributeReference::new("sum", self.sum_type.clone()));
         }
         sum_guard.as_ref().unwrap().clone()
     }
@@ -48,7 +60,7 @@ impl Average {
     fn result_attr(&self) -> AttributeReference {
         let mut result_attr_attr_guard = self.result_attr.lock().unwrap();
         if result_attr_attr_guard.is_none() {
-            *result_attr_attr_guard = Some(AttributeReference::new("average", DataType::Double));
+            *result_attr_attr_guard = Some(AttributeReference::new("average", self.result_type.clone()));
         }
         result_attr_attr_guard.as_ref().unwrap().clone()
     }
@@ -78,7 +90,14 @@ impl Average {
     }
 
     fn child_cast(&self) -> Expr {
-        self.child.clone().cast(DataType::Double)
+        self.child.clone().cast(self.sum_type.clone())
+    }
+
+    fn zero(&self) -> Expr {
+        match self.sum_type {
+            DataType::Decimal(_, s) => Expr::lit(Value::Decimal(0, s), self.sum_type.clone()),
+            _ => Expr::double_lit(0.0),
+        }
     }
 }
 
@@ -96,6 +115,7 @@ impl Debug for Average {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Average")
             .field("child", &self.child)
+            .field("result_type", &self.result_type)
             .field("sum", &self.sum.lock().unwrap())
             .field("count", &self.count.lock().unwrap())
             .field("input_agg_attrs", &self.input_agg_attrs.lock().unwrap())
@@ -108,6 +128,8 @@ impl Clone for Average {
     fn clone(&self) -> Self {
         Self {
             child: self.child.clone(),
+            sum_type: self.sum_type.clone(),
+            result_type: self.result_type.clone(),
             sum: Mutex::new(self.sum.lock().unwrap().clone()),
             count: Mutex::new(self.count.lock().unwrap().clone()),
             input_agg_attrs: Mutex::new(self.input_agg_attrs.lock().unwrap().clone()),
@@ -122,7 +144,7 @@ impl DeclarativeAggFunction for Average {
     }
 
     fn data_type(&self) -> &DataType {
-        DataType::double_type()
+        &self.result_type
     }
 
     fn agg_buffer_attributes(&self) -> Vec<AttributeReference> {
@@ -138,11 +160,11 @@ impl DeclarativeAggFunction for Average {
     }
 
     fn initial_values(&self) -> Vec<Expr> {
-        vec![Expr::double_lit(0.0), Expr::long_lit(0)]
+        vec![self.zero(), Expr::long_lit(0)]
     }
 
     fn update_expressions(&self) -> Vec<Expr> {
-        let sum = self.sum() + coalesce2(self.child_cast(), Expr::double_lit(0.0));
+        let sum = (self.sum() + coalesce2(self.child_cast(), self.zero())).cast(self.sum_type.clone());
         let count = Expr::ScalarFunction(Box::new(If::new(
             Box::new(self.child.clone().is_null()),
             Box::new(self.count()),
@@ -152,13 +174,16 @@ impl DeclarativeAggFunction for Average {
     }
 
     fn merge_expressions(&self) -> Vec<Expr> {
-        let sum = self.sum_left() + self.sum_right();
+        let sum = (self.sum_left() + self.sum_right()).cast(self.sum_type.clone());
         let count = self.count_left() + self.count_right();
         vec![sum, count]
     }
 
     fn evaluate_expression(&self) -> Expr {
-        self.sum() / self.count().cast(DataType::Double)
+        match self.sum_type {
+            DataType::Decimal(_, _) => (self.sum() / self.count().cast(DataType::Decimal(20, 0))).cast(self.result_type.clone()),
+            _ => self.sum() / self.count().cast(DataType::Double),
+        }
     }
 
     fn args(&self) -> Vec<&Expr> {
@@ -169,3 +194,43 @@ impl DeclarativeAggFunction for Average {
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{empty_row, GenericRow, Row, Value};
+    use crate::expr::{AttributeReference, Expr};
+    use crate::expr::aggregate::{Average, DeclarativeAggFunction};
+    use crate::physical_expr::{MutableProjection, MutableProjectionForAgg};
+    use crate::types::DataType;
+
+    #[test]
+    fn test_average_decimal() {
+        let input_attr = AttributeReference::new("input", DataType::Decimal(10, 2));
+        let func = Average::new(Box::new(Expr::AttributeReference(input_attr.clone())));
+        assert_eq!(func.data_type(), &DataType::Decimal(14, 6));
+
+        let mut initializer = MutableProjection::new(func.initial_values()).unwrap();
+        let mut updater = MutableProjectionForAgg::new_with_input_attrs(func.update_expressions(), func.agg_buffer_attributes().into_iter().chain(vec![input_attr]).collect()).unwrap();
+        let mut evaluator = MutableProjection::new_with_input_attrs(vec![func.evaluate_expression()], func.agg_buffer_attributes()).unwrap();
+        updater.targert(initializer.apply(empty_row()).clone());
+        // (0.10 + 0.20 + 0.01) / 3
+        for v in [Value::Decimal(10, 2), Value::Decimal(20, 2), Value::Null, Value::Decimal(1, 2)] {
+            updater.apply(&GenericRow::new(vec![v]));
+        }
+        evaluator.apply(updater.result());
+        assert_eq!(evaluator.result().get(0).to_string(), "0.103333");
+
+        // sum溢出后保持null, 整个分组的结果为null
+        let input_attr = AttributeReference::new("input", DataType::Decimal(38, 0));
+        let func = Average::new(Box::new(Expr::AttributeReference(input_attr.clone())));
+        let mut initializer = MutableProjection::new(func.initial_values()).unwrap();
+        let mut updater = MutableProjectionForAgg::new_with_input_attrs(func.update_expressions(), func.agg_buffer_attributes().into_iter().chain(vec![input_attr]).collect()).unwrap();
+        let mut evaluator = MutableProjection::new_with_input_attrs(vec![func.evaluate_expression()], func.agg_buffer_attributes()).unwrap();
+        updater.targert(initializer.apply(empty_row()).clone());
+        for v in [Value::Decimal(crate::decimal_utils::pow10(38) - 1, 0), Value::Decimal(1, 0), Value::Decimal(-1, 0), Value::Null] {
+            updater.apply(&GenericRow::new(vec![v]));
+        }
+        evaluator.apply(updater.result());
+        assert!(evaluator.result().get(0).is_null());
+    }
+}
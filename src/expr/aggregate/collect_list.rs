@@ -147,4 +147,15 @@ impl List {
     }
 }
 
-impl Object for List {}
\ No newline at end of file
+impl Object for List {
+    fn snapshot(&self) -> Option<(&'static str, Value)> {
+        Some(("collect_list", Value::Array(Arc::new(self.data.clone()))))
+    }
+}
+
+pub(super) fn restore_list(value: Value) -> Result<Box<dyn Object>> {
+    match value {
+        Value::Array(array) => Ok(Box::new(List { data: array.as_ref().clone() })),
+        _ => Err(format!("invalid collect_list state: {}", value)),
+    }
+}
\ No newline at end of file
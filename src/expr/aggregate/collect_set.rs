@@ -140,14 +140,29 @@ impl PhysicalTypedAggFunction for PhysicalCollectSet {
 }
 
 #[derive(Debug, Clone)]
-struct Set {
-    set: HashSet<Value, BuildHasherDefault<AHasher>>
+pub(super) struct Set {
+    pub(super) set: HashSet<Value, BuildHasherDefault<AHasher>>
 }
 
 impl Set {
-    fn new() -> Self {
+    pub(super) fn new() -> Self {
         Set { set: HashSet::with_hasher(BuildHasherDefault::<AHasher>::default()) }
     }
 }
 
-impl Object for Set {}
\ No newline at end of file
+impl Object for Set {
+    fn snapshot(&self) -> Option<(&'static str, Value)> {
+        Some(("collect_set", Value::Array(Arc::new(self.set.iter().cloned().collect()))))
+    }
+}
+
+pub(super) fn restore_set(value: Value) -> Result<Box<dyn Object>> {
+    match value {
+        Value::Array(array) => {
+            let mut set = Set::new();
+            set.set.extend(array.iter().cloned());
+            Ok(Box::new(set))
+        },
+        _ => Err(format!("invalid collect_set state: {}", value)),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+use itertools::Itertools;
+use crate::data::{GenericRow, JoinedRow, Row, Value};
+use crate::{expr, Result};
+use crate::expr::aggregate::{CreateTypedAggFunction, PhysicalTypedAggFunction, Set, TypedAggAttr, TypedAggFunction};
+use crate::expr::{AttributeReference, BoundReference, Expr};
+use crate::physical_expr::{PhysicalExpr, Projection};
+use crate::types::{DataType, Field, Fields};
+
+/// 参数去重的聚合函数, 如count(distinct user_id). 每个分组的buffer保存参数的去重集合, eval时对集合计算内部聚合函数
+#[derive(Debug, Clone)]
+pub struct DistinctAggFunction {
+    child: Box<Expr>,
+    mutable_agg_buffer_offset: usize,
+    data_type: DataType,
+    agg_attr: TypedAggAttr,
+    buf_attr: TypedAggAttr,
+}
+
+impl DistinctAggFunction {
+    pub fn new(child: Expr) -> Self {
+        let data_type = child.data_type().clone();
+        let args = Self::inner_args(&child);
+        let key_type = if args.len() == 1 {
+            args[0].data_type().clone()
+        } else {
+            DataType::Struct(Fields(args.iter().enumerate().map(|(i, arg)| Field::new(format!("arg{}", i), arg.data_type().clone())).collect()))
+        };
+        let agg_attr = TypedAggAttr::new(data_type.clone());
+        let buf_attr = TypedAggAttr::new(DataType::Array(Box::new(key_type)));
+        DistinctAggFunction { child: Box::new(child), mutable_agg_buffer_offset: 0, data_type, agg_attr, buf_attr }
+    }
+
+    fn inner_args(child: &Expr) -> Vec<&Expr> {
+        match child {
+            Expr::DeclarativeAggFunction(f) => f.args(),
+            Expr::TypedAggFunction(f) => f.args(),
+            _ => vec![],
+        }
+    }
+}
+
+impl CreateTypedAggFunction for DistinctAggFunction {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn TypedAggFunction>> {
+        if args.len() != 1 {
+            return Err("requires one argument".into());
+        }
+        match &args[0] {
+            Expr::DeclarativeAggFunction(_) | Expr::TypedAggFunction(_) =>
+                Ok(Box::new(DistinctAggFunction::new(args.into_iter().next().unwrap()))),
+            e => Err(format!("distinct requires aggregate function argument, found:{:?}", e)),
+        }
+    }
+}
+
+impl TypedAggFunction for DistinctAggFunction {
+    fn name(&self) -> &str {
+        match self.child.as_ref() {
+            Expr::DeclarativeAggFunction(f) => f.name(),
+            Expr::TypedAggFunction(f) => f.name(),
+            _ => "distinct",
+        }
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn with_new_mutable_agg_buffer_offset(&self, offset: usize) -> Box<dyn TypedAggFunction> {
+        let mut f = self.clone();
+        f.mutable_agg_buffer_offset = offset;
+        Box::new(f)
+    }
+
+    fn agg_attr(&self) -> &TypedAggAttr {
+        &self.agg_attr
+    }
+
+    // buffer是去重集合, 类型和结果不同
+    fn agg_buffer_attributes(&self) -> Vec<AttributeReference> {
+        vec![self.buf_attr.buf_attr()]
+    }
+
+    fn input_agg_buffer_attributes(&self) -> Vec<AttributeReference> {
+        self.buf_attr.input_agg_attrs()
+    }
+
+    fn physical_function(&self) -> Result<Box<dyn PhysicalTypedAggFunction>> {
+        let args: Vec<_> = Self::inner_args(&self.child).into_iter().map(|arg| expr::create_physical_expr(arg)).try_collect()?;
+        let evaluator = DistinctEvaluator::new(&self.child)?;
+        Ok(Box::new(PhysicalDistinctAggFunction {
+            args,
+            evaluator,
+            mutable_agg_buffer_offset: self.mutable_agg_buffer_offset,
+            data_type: self.data_type.clone(),
+        }))
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child]
+    }
+
+    fn sql(&self) -> String {
+        format!("{}(DISTINCT {})", self.name(), Self::inner_args(&self.child).into_iter().map(|arg| arg.sql()).join(", "))
+    }
+}
+
+/// 对去重后的参数计算内部聚合函数
+enum DistinctEvaluator {
+    Declarative {
+        init_projection: Projection,
+        update_exprs: Vec<(usize, Box<dyn PhysicalExpr>)>,
+        eval_expr: Box<dyn PhysicalExpr>,
+    },
+    Typed(Box<dyn PhysicalTypedAggFunction>),
+}
+
+impl DistinctEvaluator {
+    fn new(child: &Expr) -> Result<Self> {
+        match child {
+            Expr::DeclarativeAggFunction(f) => {
+                // 参数替换为新的属性, 输入为buffer + 参数
+                let arg_attrs: Vec<_> = f.args().into_iter().map(|arg| AttributeReference::new("arg", arg.data_type().clone())).collect();
+                let f = f.rewrite_args(arg_attrs.iter().map(|attr| Expr::AttributeReference(attr.clone())).collect());
+                let buffer_attrs = f.agg_buffer_attributes();
+                let init_projection = Projection::new(f.initial_values())?;
+                let input = buffer_attrs.iter().cloned().chain(arg_attrs.into_iter()).collect();
+                let update_exprs: Vec<_> = BoundReference::bind_references(f.update_expressions(), input)?.iter().enumerate()
+                    .map(|(i, e)| expr::create_physical_expr(e).map(|e| (i, e))).try_collect()?;
+                let eval_expr = expr::create_physical_expr(&BoundReference::bind_reference(f.evaluate_expression(), buffer_attrs)?)?;
+                Ok(DistinctEvaluator::Declarative { init_projection, update_exprs, eval_expr })
+            },
+            Expr::TypedAggFunction(f) => {
+                let args = f.args().into_iter().enumerate().map(|(i, arg)| Expr::BoundReference(BoundReference::new(i, arg.data_type().clone()))).collect();
+                let f = f.rewrite_args(args).with_new_mutable_agg_buffer_offset(0);
+                Ok(DistinctEvaluator::Typed(f.physical_function()?))
+            },
+            e => Err(format!("distinct requires aggregate function argument, found:{:?}", e)),
+        }
+    }
+
+    fn eval<'a>(&self, rows: impl Iterator<Item = &'a GenericRow>) -> Value {
+        match self {
+            DistinctEvaluator::Declarative { init_projection, update_exprs, eval_expr } => {
+                let mut buffer = init_projection.apply(&GenericRow::new(Vec::new()));
+                for row in rows {
+                    for (i, expr) in update_exprs {
+                        let joiner = JoinedRow::new(&buffer, row);
+                        let value = expr.eval(&joiner);
+                        buffer.update(*i, value);
+                    }
+                }
+                eval_expr.eval(&buffer)
+            },
+            DistinctEvaluator::Typed(f) => {
+                let mut buffer = GenericRow::new_with_size(1);
+                f.initialize(&mut buffer);
+                for row in rows {
+                    f.update(&mut buffer, row);
+                }
+                f.eval(&mut buffer)
+            },
+        }
+    }
+}
+
+pub struct PhysicalDistinctAggFunction {
+    args: Vec<Box<dyn PhysicalExpr>>,
+    evaluator: DistinctEvaluator,
+    mutable_agg_buffer_offset: usize,
+    data_type: DataType,
+}
+
+impl PhysicalTypedAggFunction for PhysicalDistinctAggFunction {
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn mutable_agg_buffer_offset(&self) -> usize {
+        self.mutable_agg_buffer_offset
+    }
+
+    fn input_agg_buffer_offset(&self) -> usize {
+        0
+    }
+
+    fn create_agg_buffer(&self) -> Value {
+        Value::Object(Box::new(Set::new()))
+    }
+
+    fn update_value(&self, buffer: &mut Value, input: &dyn Row) {
+        // 和count(distinct)语义一致, 任一参数为null时忽略
+        let mut values = Vec::with_capacity(self.args.len());
+        for arg in &self.args {
+            let value = arg.eval(input);
+            if value.is_null() {
+                return;
+            }
+            values.push(value);
+        }
+        let value = if values.len() == 1 { values.pop().unwrap() } else { Value::Array(Arc::new(values)) };
+        match buffer {
+            Value::Object(obj) => {
+                let set = obj.as_mut_any().downcast_mut::<Set>().unwrap();
+                set.set.insert(value);
+            }
+            _ => panic!("invalid agg buffer")
+        }
+    }
+
+    fn merge_value(&self, buffer: &mut Value, input: Value) {
+        match (buffer, input) {
+            (Value::Object(obj), Value::Object(input)) => {
+                let set = obj.as_mut_any().downcast_mut::<Set>().unwrap();
+                let data = input.into_any().downcast::<Set>().unwrap();
+                set.set.extend(data.set.into_iter());
+            },
+            _ => panic!("invalid agg buffer")
+        }
+    }
+
+    fn eval_value(&self, buffer: Value) -> Value {
+        match buffer {
+            Value::Object(obj) => {
+                let set = obj.into_any().downcast::<Set>().unwrap();
+                let single = self.args.len() == 1;
+                let rows: Vec<GenericRow> = set.set.into_iter().map(|value| match value {
+                    Value::Array(values) if !single => GenericRow::new(values.as_ref().clone()),
+                    value => GenericRow::new(vec![value]),
+                }).collect();
+                self.evaluator.eval(rows.iter())
+            }
+            _ => panic!("invalid agg buffer")
+        }
+    }
+
+    fn buffer_rows(&self, buffer: &GenericRow) -> usize {
+        match buffer.get(self.mutable_agg_buffer_offset) {
+            Value::Object(obj) => obj.as_any().downcast_ref::<Set>().map(|set| set.set.len()).unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
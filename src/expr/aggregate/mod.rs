@@ -8,6 +8,7 @@ mod last;
 mod average;
 mod collect_set;
 mod collect_list;
+mod distinct;
 
 pub use aggregate::*;
 pub use sum::*;
@@ -19,3 +20,4 @@ pub use last::*;
 pub use average::*;
 pub use collect_set::*;
 pub use collect_list::*;
+pub use distinct::*;
@@ -2,14 +2,17 @@ use std::any::Any;
 use std::fmt::{Debug, Formatter};
 use std::sync::Mutex;
 use crate::data::Value;
+use crate::decimal_utils::MAX_PRECISION;
 use crate::expr::aggregate::{CreateDeclarativeAggFunction, DeclarativeAggFunction, ExtendDeclarativeAggFunction};
-use crate::expr::{coalesce2, AttributeReference, Expr};
+use crate::expr::{coalesce2, AttributeReference, Expr, If};
 use crate::types::{AbstractDataType, DataType};
 
 pub struct Sum {
     child: Box<Expr>,
     result_type: DataType,
     sum: Mutex<Option<AttributeReference>>,
+    /// decimal溢出时sum为null并保持到最后, 用is_empty区分没有输入和溢出
+    is_empty: Mutex<Option<AttributeReference>>,
     zero: Expr,
     input_agg_attrs: Mutex<Vec<AttributeReference>>,
     result_attr: Mutex<Option<AttributeReference>>,
@@ -19,12 +22,18 @@ impl Sum {
     pub fn new(child: Box<Expr>) -> Self {
         let (result_type, zero)  = match child.data_type() {
             DataType::Int | DataType::Long => (DataType::Long, Expr::long_lit(0)),
+            // 和spark一样精度增加10
+            DataType::Decimal(p, s) => {
+                let result_type = DataType::decimal_type((p + 10).min(MAX_PRECISION), *s).clone();
+                (result_type.clone(), Expr::lit(Value::Decimal(0, *s), result_type))
+            },
             _ => (DataType::Double, Expr::double_lit(0.0)),
         };
         let sum = Mutex::new(None);
+        let is_empty = Mutex::new(None);
         let input_agg_attrs = Mutex::new(vec![]);
         let result_attr = Mutex::new(None);
-        Self { child, result_type, sum, zero, input_agg_attrs, result_attr }
+        Self { child, result_type, sum, is_empty, zero, input_agg_attrs, result_attr }
     }
 
     fn sum_attr(&self) -> AttributeReference {
@@ -35,10 +44,22 @@ impl Sum {
         sum_guard.as_ref().unwrap().clone()
     }
 
+    fn is_empty_attr(&self) -> AttributeReference {
+        let mut is_empty_guard = self.is_empty.lock().unwrap();
+        if is_empty_guard.is_none() {
+            *is_empty_guard = Some(AttributeReference::new("is_empty", DataType::Boolean));
+        }
+        is_empty_guard.as_ref().unwrap().clone()
+    }
+
+    fn is_decimal(&self) -> bool {
+        matches!(self.result_type, DataType::Decimal(_, _))
+    }
+
     fn input_agg_attrs(&self) -> Vec<AttributeReference> {
         let mut input_agg_attrs_guard = self.input_agg_attrs.lock().unwrap();
         if input_agg_attrs_guard.is_empty() {
-            *input_agg_attrs_guard = vec![self.sum_attr().new_instance()];
+            *input_agg_attrs_guard = self.agg_buffer_attributes().into_iter().map(|attr| attr.new_instance()).collect();
         }
         input_agg_attrs_guard.clone()
     }
@@ -63,6 +84,14 @@ impl Sum {
         Expr::AttributeReference(self.sum_attr())
     }
 
+    fn is_empty_left(&self) -> Expr {
+        Expr::AttributeReference(self.is_empty_attr())
+    }
+
+    fn is_empty_right(&self) -> Expr {
+        Expr::AttributeReference(self.input_agg_attrs()[1].clone())
+    }
+
     fn zero(&self) -> Expr {
         self.zero.clone()
     }
@@ -101,6 +130,7 @@ impl Clone for Sum {
             child: self.child.clone(),
             result_type: self.result_type.clone(),
             sum: Mutex::new(self.sum.lock().unwrap().clone()),
+            is_empty: Mutex::new(self.is_empty.lock().unwrap().clone()),
             zero: self.zero.clone(),
             input_agg_attrs: Mutex::new(self.input_agg_attrs.lock().unwrap().clone()),
             result_attr: Mutex::new(self.result_attr.lock().unwrap().clone()),
@@ -118,7 +148,11 @@ impl DeclarativeAggFunction for Sum {
     }
 
     fn agg_buffer_attributes(&self) -> Vec<AttributeReference> {
-        vec![self.sum_attr()]
+        if self.is_decimal() {
+            vec![self.sum_attr(), self.is_empty_attr()]
+        } else {
+            vec![self.sum_attr()]
+        }
     }
 
     fn input_agg_buffer_attributes(&self) -> Vec<AttributeReference> {
@@ -130,18 +164,41 @@ impl DeclarativeAggFunction for Sum {
     }
 
     fn initial_values(&self) -> Vec<Expr> {
+        if self.is_decimal() {
+            return vec![self.zero(), Expr::boolean_lit(true)];
+        }
         vec![Expr::lit(Value::Null, self.result_type.clone())]
     }
 
     fn update_expressions(&self) -> Vec<Expr> {
-        vec![coalesce2(coalesce2(self.sum(), self.zero()) + self.child_cast(), self.sum())]
+        if self.is_decimal() {
+            // 溢出后sum为null, null加任何值都是null
+            let sum = Expr::ScalarFunction(Box::new(If::new(
+                Box::new(self.child.clone().is_null()),
+                Box::new(self.sum()),
+                Box::new((self.sum() + self.child_cast()).cast(self.result_type.clone())),
+            )));
+            return vec![sum, self.is_empty_left().and(self.child.clone().is_null())];
+        }
+        vec![coalesce2((coalesce2(self.sum(), self.zero()) + self.child_cast()).cast(self.result_type.clone()), self.sum())]
     }
 
     fn merge_expressions(&self) -> Vec<Expr> {
-        vec![coalesce2(coalesce2(self.sum_left(), self.zero()) + self.sum_right(), self.sum_left())]
+        if self.is_decimal() {
+            let sum = (self.sum_left() + self.sum_right()).cast(self.result_type.clone());
+            return vec![sum, self.is_empty_left().and(self.is_empty_right())];
+        }
+        vec![coalesce2((coalesce2(self.sum_left(), self.zero()) + self.sum_right()).cast(self.result_type.clone()), self.sum_left())]
     }
 
     fn evaluate_expression(&self) -> Expr {
+        if self.is_decimal() {
+            return Expr::ScalarFunction(Box::new(If::new(
+                Box::new(self.is_empty_left()),
+                Box::new(Expr::lit(Value::Null, self.result_type.clone())),
+                Box::new(self.sum()),
+            )));
+        }
         self.sum()
     }
 
@@ -238,4 +295,59 @@ mod tests {
         println!("evaluator:{:?}", evaluator.result());
         assert_eq!(evaluator.result().get(0).clone(), Value::long(8));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sum_decimal() {
+        let input_attr = AttributeReference::new("input", DataType::Decimal(10, 2));
+        let input = vec![input_attr.clone()];
+        let func = Sum::new(Box::new(Expr::AttributeReference(input_attr.clone())));
+        assert_eq!(func.data_type(), &DataType::Decimal(20, 2));
+
+        let mut initializer = MutableProjection::new(func.initial_values()).unwrap();
+        let mut updater = MutableProjectionForAgg::new_with_input_attrs(func.update_expressions(), func.agg_buffer_attributes().into_iter().chain(input.clone().into_iter()).collect()).unwrap();
+        let mut evaluator = MutableProjection::new_with_input_attrs(vec![func.evaluate_expression()], func.agg_buffer_attributes()).unwrap();
+        updater.targert(initializer.apply(empty_row()).clone()) ;
+        // 0.1 + 0.2 + null - 0.05
+        for v in [Value::Decimal(10, 2), Value::Decimal(20, 2), Value::Null, Value::Decimal(-5, 2)] {
+            updater.apply(&GenericRow::new(vec![v]));
+        }
+        evaluator.apply(updater.result());
+        assert_eq!(evaluator.result().get(0).clone(), Value::Decimal(25, 2));
+
+        // 没有非null输入时结果为null
+        updater.targert(initializer.apply(empty_row()).clone());
+        updater.apply(&GenericRow::new(vec![Value::Null]));
+        evaluator.apply(updater.result());
+        assert!(evaluator.result().get(0).is_null());
+    }
+
+    #[test]
+    fn test_sum_decimal_overflow() {
+        let input_attr = AttributeReference::new("input", DataType::Decimal(38, 0));
+        let input = vec![input_attr.clone()];
+        let func = Sum::new(Box::new(Expr::AttributeReference(input_attr.clone())));
+        let buffer_attrs = func.agg_buffer_attributes();
+        let mut initializer = MutableProjection::new(func.initial_values()).unwrap();
+        let mut updater = MutableProjectionForAgg::new_with_input_attrs(func.update_expressions(), buffer_attrs.clone().into_iter().chain(input.into_iter()).collect()).unwrap();
+        let mut merger = MutableProjectionForAgg::new_with_input_attrs(func.merge_expressions(), buffer_attrs.clone().into_iter().chain(func.input_agg_attrs().into_iter()).collect()).unwrap();
+        let mut evaluator = MutableProjection::new_with_input_attrs(vec![func.evaluate_expression()], buffer_attrs).unwrap();
+        let max = Value::Decimal(crate::decimal_utils::pow10(38) - 1, 0);
+
+        // 溢出后整个分组的结果为null, 不会保留溢出前的sum
+        updater.targert(initializer.apply(empty_row()).clone());
+        for v in [max.clone(), Value::Decimal(1, 0), Value::Decimal(-1, 0), Value::Null] {
+            updater.apply(&GenericRow::new(vec![v]));
+        }
+        evaluator.apply(updater.result());
+        assert!(evaluator.result().get(0).is_null());
+
+        // 合并时溢出
+        updater.targert(initializer.apply(empty_row()).clone());
+        updater.apply(&GenericRow::new(vec![max]));
+        let partial = updater.result().clone();
+        merger.targert(partial.clone());
+        merger.apply(&partial);
+        evaluator.apply(merger.result());
+        assert!(evaluator.result().get(0).is_null());
+    }
+}
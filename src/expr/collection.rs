@@ -1,6 +1,7 @@
+use itertools::Itertools;
 use crate::Result;
-use crate::expr::{create_physical_expr, CreateScalarFunction, Expr, ScalarFunction};
-use crate::types::{AbstractDataType, DataType};
+use crate::expr::{create_physical_expr, Alias, AttributeReference, CreateScalarFunction, Expr, GetStructField, Literal, ScalarFunction};
+use crate::types::{AbstractDataType, DataType, Field, Fields};
 use crate::physical_expr::{self as phy, PhysicalExpr};
 
 #[derive(Debug, Clone)]
@@ -45,4 +46,910 @@ impl ScalarFunction for Concat {
         let args = children.into_iter().map(|child| create_physical_expr(child)).collect::<Result<Vec<_>>>()?;
         Ok(Box::new(phy::Concat::new(args)))
     }
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone)]
+pub struct MapKeys {
+    pub child: Box<Expr>,
+    pub data_type: DataType,
+}
+
+impl MapKeys {
+    pub fn new(child: Box<Expr>) -> MapKeys {
+        let data_type = match child.data_type() {
+            DataType::Map(kt, _) => DataType::Array(kt.clone()),
+            _ => DataType::Null,
+        };
+        MapKeys { child, data_type }
+    }
+}
+
+impl CreateScalarFunction for MapKeys {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 {
+            return Err(format!("requires 1 argument, found:{}", args.len()));
+        }
+        Ok(Box::new(MapKeys::new(Box::new(args.into_iter().next().unwrap()))))
+    }
+}
+
+impl ScalarFunction for MapKeys {
+    fn name(&self) -> &str {
+        "map_keys"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child]
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        check_map_type(self.child.data_type())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::MapKeys::new(create_physical_expr(&self.child)?, self.data_type.clone())))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MapValues {
+    pub child: Box<Expr>,
+    pub data_type: DataType,
+}
+
+impl MapValues {
+    pub fn new(child: Box<Expr>) -> MapValues {
+        let data_type = match child.data_type() {
+            DataType::Map(_, vt) => DataType::Array(vt.clone()),
+            _ => DataType::Null,
+        };
+        MapValues { child, data_type }
+    }
+}
+
+impl CreateScalarFunction for MapValues {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 {
+            return Err(format!("requires 1 argument, found:{}", args.len()));
+        }
+        Ok(Box::new(MapValues::new(Box::new(args.into_iter().next().unwrap()))))
+    }
+}
+
+impl ScalarFunction for MapValues {
+    fn name(&self) -> &str {
+        "map_values"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child]
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        check_map_type(self.child.data_type())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::MapValues::new(create_physical_expr(&self.child)?, self.data_type.clone())))
+    }
+}
+
+fn check_map_type(data_type: &DataType) -> Result<()> {
+    if matches!(data_type, DataType::Map(_, _)) {
+        Ok(())
+    } else {
+        Err(format!("argument requires map type, not {}", data_type))
+    }
+}
+
+/// map_from_arrays(keys, values), 重复的key保留最后一个value
+#[derive(Debug, Clone)]
+pub struct MapFromArrays {
+    pub keys: Box<Expr>,
+    pub values: Box<Expr>,
+    pub data_type: DataType,
+}
+
+impl MapFromArrays {
+    pub fn new(keys: Box<Expr>, values: Box<Expr>) -> MapFromArrays {
+        let data_type = match (keys.data_type(), values.data_type()) {
+            (DataType::Array(kt), DataType::Array(vt)) => DataType::Map(kt.clone(), vt.clone()),
+            _ => DataType::Null,
+        };
+        MapFromArrays { keys, values, data_type }
+    }
+}
+
+impl CreateScalarFunction for MapFromArrays {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let keys = iter.next().unwrap();
+        let values = iter.next().unwrap();
+        Ok(Box::new(MapFromArrays::new(Box::new(keys), Box::new(values))))
+    }
+}
+
+impl ScalarFunction for MapFromArrays {
+    fn name(&self) -> &str {
+        "map_from_arrays"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.keys, &self.values]
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        match (self.keys.data_type(), self.values.data_type()) {
+            (DataType::Array(_), DataType::Array(_)) => Ok(()),
+            (kt, vt) => Err(format!("arguments requires array type, not {}, {}", kt, vt)),
+        }
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::MapFromArrays::new(create_physical_expr(&self.keys)?, create_physical_expr(&self.values)?, self.data_type.clone())))
+    }
+}
+
+/// str_to_map(text[, pair_delim[, key_value_delim]]), 默认分隔符为','和':'
+#[derive(Debug, Clone)]
+pub struct StringToMap {
+    pub text: Box<Expr>,
+    pub pair_delim: Box<Expr>,
+    pub key_value_delim: Box<Expr>,
+}
+
+impl StringToMap {
+    pub fn new(text: Box<Expr>, pair_delim: Box<Expr>, key_value_delim: Box<Expr>) -> StringToMap {
+        StringToMap { text, pair_delim, key_value_delim }
+    }
+}
+
+impl CreateScalarFunction for StringToMap {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.is_empty() || args.len() > 3 {
+            return Err(format!("requires 1 to 3 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let text = iter.next().unwrap();
+        let pair_delim = iter.next().unwrap_or_else(|| Expr::string_lit(","));
+        let key_value_delim = iter.next().unwrap_or_else(|| Expr::string_lit(":"));
+        Ok(Box::new(StringToMap::new(Box::new(text), Box::new(pair_delim), Box::new(key_value_delim))))
+    }
+}
+
+impl ScalarFunction for StringToMap {
+    fn name(&self) -> &str {
+        "str_to_map"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::string_map_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.text, &self.pair_delim, &self.key_value_delim]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::string_type(), AbstractDataType::string_type(), AbstractDataType::string_type()])
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let Self{text, pair_delim, key_value_delim} = self;
+        Ok(Box::new(phy::StringToMap::new(create_physical_expr(text)?, create_physical_expr(pair_delim)?, create_physical_expr(key_value_delim)?)))
+    }
+}
+
+fn array_element_type(data_type: &DataType) -> &DataType {
+    match data_type {
+        DataType::Array(element_type) => element_type.as_ref(),
+        _ => DataType::null_type(),
+    }
+}
+
+/// array(expr, ...), 参数由FunctionArgumentConversion转换成相同类型
+#[derive(Debug, Clone)]
+pub struct CreateArray {
+    pub children: Vec<Expr>,
+    pub data_type: DataType,
+}
+
+impl CreateArray {
+    pub fn new(children: Vec<Expr>) -> CreateArray {
+        let element_type = children.iter().map(|child| child.data_type()).find(|tp| *tp != DataType::null_type()).unwrap_or(DataType::null_type());
+        let data_type = DataType::Array(Box::new(element_type.clone()));
+        CreateArray { children, data_type }
+    }
+}
+
+impl CreateScalarFunction for CreateArray {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        Ok(Box::new(CreateArray::new(args)))
+    }
+}
+
+impl ScalarFunction for CreateArray {
+    fn name(&self) -> &str {
+        "array"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        self.children.iter().collect()
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Type(array_element_type(&self.data_type).clone()); self.children.len()])
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let children = self.children.iter().map(|child| create_physical_expr(child)).collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(phy::CreateArray::new(children, self.data_type.clone())))
+    }
+}
+
+/// named_struct(name1, value1, ...), name必须是字符串常量
+#[derive(Debug, Clone)]
+pub struct CreateNamedStruct {
+    pub children: Vec<Expr>,
+    pub data_type: DataType,
+}
+
+impl CreateNamedStruct {
+    pub fn new(children: Vec<Expr>) -> CreateNamedStruct {
+        let fields = children.chunks(2).map(|pair| match (&pair[0], pair.get(1)) {
+            (Expr::Literal(Literal{value, data_type: DataType::String}), Some(v)) if !value.is_null() =>
+                Field::new(value.get_string(), v.data_type().clone()),
+            (_, v) => Field::new("", v.map(|v| v.data_type().clone()).unwrap_or(DataType::Null)),
+        }).collect();
+        CreateNamedStruct { children, data_type: DataType::Struct(Fields(fields)) }
+    }
+}
+
+impl CreateScalarFunction for CreateNamedStruct {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(format!("requires an even number of arguments, found:{}", args.len()));
+        }
+        Ok(Box::new(CreateNamedStruct::new(args)))
+    }
+}
+
+impl ScalarFunction for CreateNamedStruct {
+    fn name(&self) -> &str {
+        "named_struct"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        self.children.iter().collect()
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(self.children.iter().enumerate().map(|(i, _)| if i % 2 == 0 { AbstractDataType::string_type() } else { AbstractDataType::Any }).collect())
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        for name in self.children.iter().step_by(2) {
+            if !matches!(name, Expr::Literal(Literal{value, data_type: DataType::String}) if !value.is_null()) {
+                return Err(format!("named_struct field name requires non-null string literal, not {}", name.sql()));
+            }
+        }
+        Ok(())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let values = self.children.iter().skip(1).step_by(2).map(|child| create_physical_expr(child)).collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(phy::CreateNamedStruct::new(values, self.data_type.clone())))
+    }
+}
+
+/// struct(expr, ...), 转换成named_struct, 列和结构体字段使用原来的名称, 其它为col1, col2...
+pub struct CreateStruct;
+
+impl CreateStruct {
+    pub fn create_function_expr(args: Vec<Expr>) -> Result<Expr> {
+        if args.is_empty() {
+            return Err("requires at least 1 argument".to_string());
+        }
+        let mut children = Vec::with_capacity(args.len() * 2);
+        for (i, arg) in args.into_iter().enumerate() {
+            let name = match &arg {
+                Expr::AttributeReference(AttributeReference{name, ..}) | Expr::Alias(Alias{name, ..}) => name.clone(),
+                Expr::ScalarFunction(f) if f.as_any().downcast_ref::<GetStructField>().is_some() =>
+                    f.as_any().downcast_ref::<GetStructField>().unwrap().field_name().to_string(),
+                _ => format!("col{}", i + 1),
+            };
+            children.push(Expr::string_lit(name));
+            children.push(arg);
+        }
+        CreateNamedStruct::create_function_expr(children)
+    }
+}
+
+/// size(array/map), null返回-1
+#[derive(Debug, Clone)]
+pub struct Size {
+    pub child: Box<Expr>,
+}
+
+impl CreateScalarFunction for Size {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 {
+            return Err(format!("requires 1 argument, found:{}", args.len()));
+        }
+        Ok(Box::new(Size { child: Box::new(args.into_iter().next().unwrap()) }))
+    }
+}
+
+impl ScalarFunction for Size {
+    fn name(&self) -> &str {
+        "size"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::int_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Collection(vec![AbstractDataType::Array, AbstractDataType::Map])])
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::Size::new(create_physical_expr(&self.child)?)))
+    }
+}
+
+/// 第二个参数转换成数组元素的类型
+fn array_and_element_types(array: &Expr) -> Option<Vec<AbstractDataType>> {
+    match array.data_type() {
+        DataType::Array(element_type) => Some(vec![AbstractDataType::Array, AbstractDataType::Type(element_type.as_ref().clone())]),
+        _ => Some(vec![AbstractDataType::Array, AbstractDataType::Any]),
+    }
+}
+
+/// array_contains(array, value), 没有找到且数组中有null时返回null
+#[derive(Debug, Clone)]
+pub struct ArrayContains {
+    pub array: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+impl CreateScalarFunction for ArrayContains {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        Ok(Box::new(ArrayContains { array: Box::new(iter.next().unwrap()), value: Box::new(iter.next().unwrap()) }))
+    }
+}
+
+impl ScalarFunction for ArrayContains {
+    fn name(&self) -> &str {
+        "array_contains"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::boolean_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.array, &self.value]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        array_and_element_types(&self.array)
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayContains::new(create_physical_expr(&self.array)?, create_physical_expr(&self.value)?)))
+    }
+}
+
+/// array_position(array, value), 返回从1开始的位置, 没有找到返回0
+#[derive(Debug, Clone)]
+pub struct ArrayPosition {
+    pub array: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+impl CreateScalarFunction for ArrayPosition {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        Ok(Box::new(ArrayPosition { array: Box::new(iter.next().unwrap()), value: Box::new(iter.next().unwrap()) }))
+    }
+}
+
+impl ScalarFunction for ArrayPosition {
+    fn name(&self) -> &str {
+        "array_position"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::long_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.array, &self.value]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        array_and_element_types(&self.array)
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayPosition::new(create_physical_expr(&self.array)?, create_physical_expr(&self.value)?)))
+    }
+}
+
+/// array_distinct(array), 保留第一次出现的元素
+#[derive(Debug, Clone)]
+pub struct ArrayDistinct {
+    pub child: Box<Expr>,
+}
+
+impl CreateScalarFunction for ArrayDistinct {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 {
+            return Err(format!("requires 1 argument, found:{}", args.len()));
+        }
+        Ok(Box::new(ArrayDistinct { child: Box::new(args.into_iter().next().unwrap()) }))
+    }
+}
+
+impl ScalarFunction for ArrayDistinct {
+    fn name(&self) -> &str {
+        "array_distinct"
+    }
+
+    fn data_type(&self) -> &DataType {
+        self.child.data_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Array])
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayDistinct::new(create_physical_expr(&self.child)?)))
+    }
+}
+
+/// array_union/array_intersect/array_except, 两个数组的类型必须相同, 结果去重
+macro_rules! array_set_operation {
+    ($ty:ident, $name:literal, $op:expr) => {
+        #[derive(Debug, Clone)]
+        pub struct $ty {
+            pub left: Box<Expr>,
+            pub right: Box<Expr>,
+        }
+
+        impl CreateScalarFunction for $ty {
+            fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+                if args.len() != 2 {
+                    return Err(format!("requires 2 argument, found:{}", args.len()));
+                }
+                let mut iter = args.into_iter();
+                Ok(Box::new($ty { left: Box::new(iter.next().unwrap()), right: Box::new(iter.next().unwrap()) }))
+            }
+        }
+
+        impl ScalarFunction for $ty {
+            fn name(&self) -> &str {
+                $name
+            }
+
+            fn data_type(&self) -> &DataType {
+                self.left.data_type()
+            }
+
+            fn args(&self) -> Vec<&Expr> {
+                vec![&self.left, &self.right]
+            }
+
+            fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+                match self.left.data_type() {
+                    tp @ DataType::Array(_) => Some(vec![AbstractDataType::Array, AbstractDataType::Type(tp.clone())]),
+                    _ => Some(vec![AbstractDataType::Array, AbstractDataType::Array]),
+                }
+            }
+
+            fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+                Ok(Box::new(phy::ArraySetOperation::new(create_physical_expr(&self.left)?, create_physical_expr(&self.right)?, $op)))
+            }
+        }
+    };
+}
+
+array_set_operation!(ArrayUnion, "array_union", phy::SetOperation::Union);
+array_set_operation!(ArrayIntersect, "array_intersect", phy::SetOperation::Intersect);
+array_set_operation!(ArrayExcept, "array_except", phy::SetOperation::Except);
+
+/// array_join(array, delimiter[, null_replacement]), 没有null_replacement时跳过null元素
+#[derive(Debug, Clone)]
+pub struct ArrayJoin {
+    pub array: Box<Expr>,
+    pub delimiter: Box<Expr>,
+    pub null_replacement: Option<Box<Expr>>,
+}
+
+impl CreateScalarFunction for ArrayJoin {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 && args.len() != 3 {
+            return Err(format!("requires 2 or 3 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let array = Box::new(iter.next().unwrap());
+        let delimiter = Box::new(iter.next().unwrap());
+        Ok(Box::new(ArrayJoin { array, delimiter, null_replacement: iter.next().map(Box::new) }))
+    }
+}
+
+impl ScalarFunction for ArrayJoin {
+    fn name(&self) -> &str {
+        "array_join"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::string_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        let mut args = vec![self.array.as_ref(), self.delimiter.as_ref()];
+        if let Some(null_replacement) = &self.null_replacement {
+            args.push(null_replacement);
+        }
+        args
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        let mut types = vec![AbstractDataType::string_array_type(), AbstractDataType::string_type()];
+        if self.null_replacement.is_some() {
+            types.push(AbstractDataType::string_type());
+        }
+        Some(types)
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let null_replacement = self.null_replacement.as_ref().map(|e| create_physical_expr(e)).transpose()?;
+        Ok(Box::new(phy::ArrayJoin::new(create_physical_expr(&self.array)?, create_physical_expr(&self.delimiter)?, null_replacement)))
+    }
+}
+
+/// slice(array, start, length), start从1开始, 负数从末尾开始
+#[derive(Debug, Clone)]
+pub struct Slice {
+    pub array: Box<Expr>,
+    pub start: Box<Expr>,
+    pub length: Box<Expr>,
+}
+
+impl CreateScalarFunction for Slice {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 3 {
+            return Err(format!("requires 3 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let array = Box::new(iter.next().unwrap());
+        let start = Box::new(iter.next().unwrap());
+        let length = Box::new(iter.next().unwrap());
+        Ok(Box::new(Slice { array, start, length }))
+    }
+}
+
+impl ScalarFunction for Slice {
+    fn name(&self) -> &str {
+        "slice"
+    }
+
+    fn data_type(&self) -> &DataType {
+        self.array.data_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.array, &self.start, &self.length]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Array, AbstractDataType::int_type(), AbstractDataType::int_type()])
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::Slice::new(create_physical_expr(&self.array)?, create_physical_expr(&self.start)?, create_physical_expr(&self.length)?)))
+    }
+}
+
+/// sort_array(array[, ascending]), 升序时null在最前, 降序时null在最后
+#[derive(Debug, Clone)]
+pub struct SortArray {
+    pub array: Box<Expr>,
+    pub ascending: Box<Expr>,
+}
+
+impl CreateScalarFunction for SortArray {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 && args.len() != 2 {
+            return Err(format!("requires 1 or 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let array = Box::new(iter.next().unwrap());
+        let ascending = Box::new(iter.next().unwrap_or_else(|| Expr::boolean_lit(true)));
+        Ok(Box::new(SortArray { array, ascending }))
+    }
+}
+
+impl ScalarFunction for SortArray {
+    fn name(&self) -> &str {
+        "sort_array"
+    }
+
+    fn data_type(&self) -> &DataType {
+        self.array.data_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.array, &self.ascending]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Array, AbstractDataType::boolean_type()])
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        match (self.array.data_type(), self.ascending.as_ref()) {
+            (DataType::Array(tp), _) if !tp.is_orderable() && tp.as_ref() != DataType::boolean_type() =>
+                Err(format!("sort_array does not support sorting array of type {}", tp)),
+            (DataType::Array(_), Expr::Literal(Literal{value, data_type: DataType::Boolean})) if !value.is_null() => Ok(()),
+            (DataType::Array(_), ascending) => Err(format!("sort order requires boolean literal, not {}", ascending.sql())),
+            (tp, _) => Err(format!("argument requires array type, not {}", tp)),
+        }
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::SortArray::new(create_physical_expr(&self.array)?, self.ascending.clone().literal_value().get_boolean())))
+    }
+}
+
+/// flatten(array<array<T>>), 有null子数组时返回null
+#[derive(Debug, Clone)]
+pub struct Flatten {
+    pub child: Box<Expr>,
+}
+
+impl CreateScalarFunction for Flatten {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 {
+            return Err(format!("requires 1 argument, found:{}", args.len()));
+        }
+        Ok(Box::new(Flatten { child: Box::new(args.into_iter().next().unwrap()) }))
+    }
+}
+
+impl ScalarFunction for Flatten {
+    fn name(&self) -> &str {
+        "flatten"
+    }
+
+    fn data_type(&self) -> &DataType {
+        array_element_type(self.child.data_type())
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Array])
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        match self.child.data_type() {
+            DataType::Array(tp) if matches!(tp.as_ref(), DataType::Array(_)) => Ok(()),
+            tp => Err(format!("flatten requires array of array type, not {}", tp)),
+        }
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::Flatten::new(create_physical_expr(&self.child)?)))
+    }
+}
+
+/// arrays_zip(array, ...), 返回结构体数组, 长度为最长的数组, 字段名为列名或参数下标
+/// 字段名作为字符串常量参数放在数组参数之后, 重新绑定参数时不会丢失
+#[derive(Debug, Clone)]
+pub struct ArraysZip {
+    pub children: Vec<Expr>,
+    pub names: Vec<Expr>,
+    pub data_type: DataType,
+}
+
+impl CreateScalarFunction for ArraysZip {
+    fn from_args(mut args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(format!("requires arrays and field names, found:{}", args.len()));
+        }
+        let names = args.split_off(args.len() / 2);
+        let fields = args.iter().zip(names.iter()).map(|(child, name)| {
+            let name = match name {
+                Expr::Literal(Literal{value, data_type: DataType::String}) if !value.is_null() => value.get_string(),
+                _ => "",
+            };
+            Field::new(name, array_element_type(child.data_type()).clone())
+        }).collect();
+        let data_type = DataType::Array(Box::new(DataType::Struct(Fields(fields))));
+        Ok(Box::new(ArraysZip { children: args, names, data_type }))
+    }
+
+    fn create_function_expr(args: Vec<Expr>) -> Result<Expr> {
+        if args.is_empty() {
+            return Err("requires at least 1 argument".to_string());
+        }
+        let names: Vec<Expr> = args.iter().enumerate().map(|(i, arg)| match arg {
+            Expr::AttributeReference(AttributeReference{name, ..}) | Expr::Alias(Alias{name, ..}) => Expr::string_lit(name.clone()),
+            _ => Expr::string_lit(i.to_string()),
+        }).collect();
+        Ok(Expr::ScalarFunction(Self::from_args(args.into_iter().chain(names).collect())?))
+    }
+}
+
+impl ScalarFunction for ArraysZip {
+    fn name(&self) -> &str {
+        "arrays_zip"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        self.children.iter().chain(self.names.iter()).collect()
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        let mut types = vec![AbstractDataType::Array; self.children.len()];
+        types.extend(vec![AbstractDataType::string_type(); self.names.len()]);
+        Some(types)
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let children = self.children.iter().map(|child| create_physical_expr(child)).collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(phy::ArraysZip::new(children, self.data_type.clone())))
+    }
+
+    fn sql(&self) -> String {
+        format!("{}({})", self.name(), self.children.iter().map(|child| child.sql()).join(", "))
+    }
+}
+
+/// sequence(start, stop[, step]), 包含stop, 默认step为1或-1, 参数有bigint时结果为array<bigint>
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub start: Box<Expr>,
+    pub stop: Box<Expr>,
+    pub step: Option<Box<Expr>>,
+    pub data_type: DataType,
+}
+
+impl CreateScalarFunction for Sequence {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 && args.len() != 3 {
+            return Err(format!("requires 2 or 3 argument, found:{}", args.len()));
+        }
+        let element_type = if args.iter().any(|arg| arg.data_type() == DataType::long_type()) { DataType::Long } else { DataType::Int };
+        let mut iter = args.into_iter();
+        let start = Box::new(iter.next().unwrap());
+        let stop = Box::new(iter.next().unwrap());
+        Ok(Box::new(Sequence { start, stop, step: iter.next().map(Box::new), data_type: DataType::Array(Box::new(element_type)) }))
+    }
+}
+
+impl ScalarFunction for Sequence {
+    fn name(&self) -> &str {
+        "sequence"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        let mut args = vec![self.start.as_ref(), self.stop.as_ref()];
+        if let Some(step) = &self.step {
+            args.push(step);
+        }
+        args
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        Some(vec![AbstractDataType::Type(array_element_type(&self.data_type).clone()); self.args().len()])
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let step = self.step.as_ref().map(|e| create_physical_expr(e)).transpose()?;
+        Ok(Box::new(phy::Sequence::new(create_physical_expr(&self.start)?, create_physical_expr(&self.stop)?, step, self.data_type.clone())))
+    }
+}
+
+/// element_at(array, index)下标从1开始, 负数从末尾开始, 越界返回null; element_at(map, key)
+#[derive(Debug, Clone)]
+pub struct ElementAt {
+    pub left: Box<Expr>,
+    pub right: Box<Expr>,
+}
+
+impl CreateScalarFunction for ElementAt {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        Ok(Box::new(ElementAt { left: Box::new(iter.next().unwrap()), right: Box::new(iter.next().unwrap()) }))
+    }
+}
+
+impl ScalarFunction for ElementAt {
+    fn name(&self) -> &str {
+        "element_at"
+    }
+
+    fn data_type(&self) -> &DataType {
+        match self.left.data_type() {
+            DataType::Array(tp) => tp.as_ref(),
+            DataType::Map(_, vt) => vt.as_ref(),
+            _ => DataType::null_type(),
+        }
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.left, &self.right]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        match self.left.data_type() {
+            DataType::Array(_) => Some(vec![AbstractDataType::Array, AbstractDataType::int_type()]),
+            DataType::Map(kt, _) => Some(vec![AbstractDataType::Map, AbstractDataType::Type(kt.as_ref().clone())]),
+            _ => Some(vec![AbstractDataType::Collection(vec![AbstractDataType::Array, AbstractDataType::Map]), AbstractDataType::Any]),
+        }
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let left = create_physical_expr(&self.left)?;
+        let right = create_physical_expr(&self.right)?;
+        match self.left.data_type() {
+            DataType::Map(_, _) => Ok(Box::new(phy::GetMapValue::new(left, right, self.data_type().clone()))),
+            _ => Ok(Box::new(phy::ElementAt::new(left, right, self.data_type().clone()))),
+        }
+    }
+}
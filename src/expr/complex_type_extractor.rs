@@ -1,7 +1,7 @@
 use crate::Result;
 use crate::expr::{CreateScalarFunction, Expr, ScalarFunction, create_physical_expr, Literal};
 use crate::physical_expr::{self as phy, PhysicalExpr};
-use crate::types::DataType;
+use crate::types::{AbstractDataType, DataType};
 
 #[derive(Debug, Clone)]
 pub struct GetStructField {
@@ -135,3 +135,67 @@ impl ScalarFunction for GetArrayItem {
         )))
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct GetMapValue {
+    pub child: Box<Expr>,
+    pub key: Box<Expr>,
+}
+
+impl GetMapValue {
+    pub fn new(child: Box<Expr>, key: Box<Expr>) -> GetMapValue {
+        GetMapValue { child, key }
+    }
+}
+
+impl CreateScalarFunction for GetMapValue {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let child = iter.next().unwrap();
+        let key = iter.next().unwrap();
+        Ok(Box::new(GetMapValue::new(Box::new(child), Box::new(key))))
+    }
+}
+
+impl ScalarFunction for GetMapValue {
+    fn name(&self) -> &str {
+        "get_map_value"
+    }
+
+    fn data_type(&self) -> &DataType {
+        match self.child.data_type() {
+            DataType::Map(_, vt) => vt.as_ref(),
+            _ => DataType::null_type()
+        }
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.child, &self.key]
+    }
+
+    fn expects_input_types(&self) -> Option<Vec<AbstractDataType>> {
+        match self.child.data_type() {
+            DataType::Map(kt, _) => Some(vec![AbstractDataType::Any, AbstractDataType::Type(kt.as_ref().clone())]),
+            _ => None,
+        }
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        match self.child.data_type() {
+            DataType::Map(kt, _) if kt.as_ref() == self.key.data_type() => Ok(()),
+            DataType::Map(kt, _) => Err(format!("key requires {} type, not {}", kt, self.key.data_type())),
+            tp => Err(format!("first arg requires map type, not {}", tp)),
+        }
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::GetMapValue::new(
+            create_physical_expr(&self.child)?,
+            create_physical_expr(&self.key)?,
+            self.data_type().clone(),
+        )))
+    }
+}
@@ -5,6 +5,7 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use itertools::Itertools;
 use crate::{Operator, Result};
+use crate::analysis::decimal_arithmetic_result_type;
 use crate::data::Value;
 use crate::expr::{binary_expr, Coalesce, Generator, Greatest, Least};
 use crate::expr::aggregate::{DeclarativeAggFunction, TypedAggFunction};
@@ -33,6 +34,9 @@ pub enum Expr {
     Like(Like),
     RLike(Like),
     In(In),
+    UnresolvedNamedLambdaVariable(Vec<String>),
+    NamedLambdaVariable(NamedLambdaVariable),
+    LambdaFunction(LambdaFunction),
     ScalarFunction(Box<dyn ScalarFunction>),
     DeclarativeAggFunction(Box<dyn DeclarativeAggFunction>),
     TypedAggFunction(Box<dyn TypedAggFunction>),
@@ -47,6 +51,9 @@ impl Expr {
             // We should never fold named expressions in order to not remove the alias.
             Expr::AttributeReference(_) | Expr::Alias(_)  => false,
             Expr::Literal(_)  => true,
+            Expr::UnresolvedNamedLambdaVariable(_) | Expr::NamedLambdaVariable(_) => false,
+            // lambda由高阶函数绑定参数, 即使函数体是常量也不能折叠为字面量
+            Expr::LambdaFunction(_) => false,
             Expr::ScalarFunction(f) => f.foldable(),
             Expr::DeclarativeAggFunction(_) => false,
             _ => self.children().iter().all(|c| c.foldable()),
@@ -57,7 +64,7 @@ impl Expr {
     pub fn data_type(&self) -> &DataType {
         match self {
             Expr::UnresolvedAttribute(_) | Expr::UnresolvedStar(_)  | Expr::UnresolvedAlias(_) | Expr::UnresolvedExtractValue(_)
-            | Expr::UnresolvedFunction(_) | Expr::UnresolvedGenerator(_)  =>
+            | Expr::UnresolvedFunction(_) | Expr::UnresolvedGenerator(_) | Expr::UnresolvedNamedLambdaVariable(_) =>
                 panic!("UnresolvedExpr:{:?}", self),
             Expr::NoOp => DataType::null_type(),
             Expr::BoundReference(b) => &b.data_type,
@@ -66,9 +73,12 @@ impl Expr {
             Expr::Literal(l) => &l.data_type,
             Expr::Cast(c) => &c.data_type,
             Expr::Not(_) | Expr::IsNull(_) | Expr::IsNotNull(_) => DataType::boolean_type(),
-            Expr::BinaryOperator(BinaryOperator{left, op, right:_ }) =>  match op {
+            Expr::BinaryOperator(BinaryOperator{left, op, right }) =>  match op {
                 Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo =>
-                    left.data_type(),
+                    match (left.data_type(), right.data_type()) {
+                        (DataType::Decimal(p1, s1), DataType::Decimal(p2, s2)) => decimal_arithmetic_result_type(*op, *p1, *s1, *p2, *s2),
+                        _ => left.data_type(),
+                    },
                 Operator::BitAnd | Operator::BitOr | Operator::BitXor =>
                     left.data_type(),
                 Operator::BitShiftLeft | Operator::BitShiftRight | Operator::BitShiftRightUnsigned =>
@@ -81,6 +91,8 @@ impl Expr {
             Expr::Like(_) => DataType::boolean_type(),
             Expr::RLike(_) => DataType::boolean_type(),
             Expr::In(_) => DataType::boolean_type(),
+            Expr::NamedLambdaVariable(v) => &v.data_type,
+            Expr::LambdaFunction(f) => f.function.data_type(),
             Expr::ScalarFunction(f) => f.data_type(),
             Expr::DeclarativeAggFunction(f) => f.data_type(),
             Expr::TypedAggFunction(f) => f.data_type(),
@@ -91,7 +103,7 @@ impl Expr {
     pub fn resolved(&self) -> bool {
         match self {
             Expr::UnresolvedAttribute(_) | Expr::UnresolvedStar(_)  | Expr::UnresolvedAlias(_) | Expr::UnresolvedExtractValue(_)
-            | Expr::UnresolvedFunction(_) | Expr::UnresolvedGenerator(_)  =>
+            | Expr::UnresolvedFunction(_) | Expr::UnresolvedGenerator(_) | Expr::UnresolvedNamedLambdaVariable(_) =>
                 false,
             _ => self.children_resolved() && self.check_input_data_types().is_ok()
         }
@@ -109,6 +121,9 @@ impl Expr {
              | Expr::UnresolvedExtractValue(_)
              | Expr::UnresolvedFunction(_)
              | Expr::UnresolvedGenerator(_)
+             | Expr::UnresolvedNamedLambdaVariable(_)
+             | Expr::NamedLambdaVariable(_)
+             | Expr::LambdaFunction(_)
              | Expr::NoOp
              | Expr::BoundReference(_)
              | Expr::AttributeReference(_)
@@ -137,14 +152,20 @@ impl Expr {
                         return Err(format!("shift Operator requires (int/long, int) type , but get {:?}", self));
                     }
                 } else if left.data_type() != right.data_type() {
-                    return Err(format!("differing types in {:?}", self));
+                    // decimal算术运算两边的精度可以不同
+                    let decimal_arithmetic = matches!(op, Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo)
+                        && matches!((left.data_type(), right.data_type()), (DataType::Decimal(_, _), DataType::Decimal(_, _)));
+                    if !decimal_arithmetic {
+                        return Err(format!("differing types in {:?}", self));
+                    }
                 }
                 match op {
                     Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo => {
                         if !left.data_type().is_numeric_type() {
                             Err(format!("{:?} requires numeric type, not {}", self, left.data_type()))
-                        } else if *op == Operator::Divide && left.data_type() != DataType::long_type() && left.data_type() != DataType::double_type() {
-                            Err(format!("{:?} requires long/double type, not {}", self, left.data_type()))
+                        } else if *op == Operator::Divide && left.data_type() != DataType::long_type() && left.data_type() != DataType::double_type()
+                            && !matches!(left.data_type(), DataType::Decimal(_, _)) {
+                            Err(format!("{:?} requires long/double/decimal type, not {}", self, left.data_type()))
                         } else {
                             Ok(())
                         }
@@ -209,10 +230,14 @@ impl Expr {
             | Expr::UnresolvedStar(_)
             | Expr::BoundReference(_)
             | Expr::AttributeReference(_)
+            | Expr::UnresolvedNamedLambdaVariable(_)
+            | Expr::NamedLambdaVariable(_)
             | Expr::NoOp
             | Expr::Literal(_) => Vec::new(),
             Expr::UnresolvedExtractValue(UnresolvedExtractValue{child, extraction}) =>
                 vec![child, extraction],
+            Expr::LambdaFunction(LambdaFunction{function, arguments}) =>
+                vec![function.as_ref()].into_iter().chain(arguments.iter()).collect(),
             Expr::Alias(Alias{ child, ..})
             | Expr::UnresolvedAlias(child)
             | Expr::Cast(Cast{ child, ..})
@@ -230,7 +255,7 @@ impl Expr {
             Expr::DeclarativeAggFunction(f) => f.args(),
             Expr::TypedAggFunction(f) => f.args(),
             Expr::Generator(g) => g.args(),
-            Expr::UnresolvedFunction(UnresolvedFunction{name: _, arguments}) =>
+            Expr::UnresolvedFunction(UnresolvedFunction{arguments, ..}) =>
                 arguments.iter().map(|a| a).collect(),
             Expr::UnresolvedGenerator(UnresolvedGenerator{arguments, ..}) =>
                 arguments.iter().map(|a| a).collect(),
@@ -269,8 +294,9 @@ impl Expr {
                 (v, DataType::Date | DataType::Timestamp)  => format!("'{}'", v.to_sql_string(data_type)),
                 (v, _)  => v.to_string(),
             },
-            Expr::UnresolvedFunction(UnresolvedFunction{name, arguments}) => {
-                format!("{}({})", name, arguments.into_iter().map(|arg| arg.sql()).join(", "))
+            Expr::UnresolvedFunction(UnresolvedFunction{name, arguments, is_distinct}) => {
+                let distinct = if *is_distinct { "DISTINCT " } else { "" };
+                format!("{}({}{})", name, distinct, arguments.into_iter().map(|arg| arg.sql()).join(", "))
             },
             Expr::UnresolvedGenerator(UnresolvedGenerator{name, arguments}) => {
                 format!("{}({})", name, arguments.into_iter().map(|arg| arg.sql()).join(", "))
@@ -284,6 +310,13 @@ impl Expr {
             Expr::In(In{value, list}) => {
                 format!("{} in ({})", value.sql(), list.into_iter().map(|e| e.sql()).join(", "))
             },
+            Expr::UnresolvedNamedLambdaVariable(name_parts) => name_parts.iter().join("."),
+            Expr::NamedLambdaVariable(NamedLambdaVariable{name, ..}) => name.clone(),
+            Expr::LambdaFunction(LambdaFunction{function, arguments}) => if arguments.len() == 1 {
+                format!("{} -> {}", arguments[0].sql(), function.sql())
+            } else {
+                format!("({}) -> {}", arguments.iter().map(|arg| arg.sql()).join(", "), function.sql())
+            },
             Expr::ScalarFunction(f) => f.sql(),
             Expr::DeclarativeAggFunction(f) => f.sql(),
             Expr::TypedAggFunction(f) => f.sql(),
@@ -566,6 +599,85 @@ impl AttributeReference {
     }
 }
 
+#[derive(Clone, PartialEq, Eq, PartialOrd, Hash, Debug)]
+pub struct NamedLambdaVariable {
+    pub name: String,
+    pub data_type: DataType,
+    pub expr_id: u32,
+}
+
+impl NamedLambdaVariable {
+    pub fn new(name: impl Into<String>, data_type: DataType) -> Self {
+        NamedLambdaVariable{name: name.into(), data_type, expr_id: ExprIdGenerator::get_next_expr_id()}
+    }
+}
+
+/// lambda函数, 参数为UnresolvedNamedLambdaVariable时未绑定, 由高阶函数按输入类型绑定
+#[derive(Clone, PartialEq, Eq, PartialOrd, Hash, Debug)]
+pub struct LambdaFunction {
+    pub function: Box<Expr>,
+    pub arguments: Vec<Expr>,
+}
+
+impl LambdaFunction {
+    pub fn new(function: Expr, arguments: Vec<Expr>) -> Self {
+        Self { function: Box::new(function), arguments }
+    }
+
+    pub fn bound(&self) -> bool {
+        self.arguments.iter().all(|arg| matches!(arg, Expr::NamedLambdaVariable(_)))
+    }
+
+    fn argument_name(arg: &Expr) -> &str {
+        match arg {
+            Expr::UnresolvedNamedLambdaVariable(name_parts) => &name_parts[0],
+            Expr::NamedLambdaVariable(v) => &v.name,
+            _ => "",
+        }
+    }
+
+    /// 按参数类型绑定lambda变量, 已绑定时只更新变量的类型
+    pub fn bind(self, arg_types: Vec<DataType>) -> Result<LambdaFunction> {
+        if self.arguments.len() != arg_types.len() {
+            return Err(format!("lambda function requires {} arguments, found:{}", arg_types.len(), self.arguments.len()));
+        }
+        let variables: Vec<_> = self.arguments.into_iter().zip(arg_types).map(|(arg, data_type)| match arg {
+            Expr::NamedLambdaVariable(v) => NamedLambdaVariable{data_type, ..v},
+            arg => NamedLambdaVariable::new(Self::argument_name(&arg), data_type),
+        }).collect();
+        let function = Self::bind_variables(*self.function, &variables)?;
+        Ok(LambdaFunction::new(function, variables.into_iter().map(Expr::NamedLambdaVariable).collect()))
+    }
+
+    fn bind_variables(function: Expr, variables: &[NamedLambdaVariable]) -> Result<Expr> {
+        function.transform_down(|expr| match expr {
+            Expr::UnresolvedNamedLambdaVariable(name_parts) => match variables.iter().find(|v| v.name.eq_ignore_ascii_case(&name_parts[0])) {
+                Some(v) => {
+                    let mut e = Expr::NamedLambdaVariable(v.clone());
+                    for nested_field in &name_parts[1..] {
+                        e = Expr::UnresolvedExtractValue(UnresolvedExtractValue::new(Box::new(e), Box::new(Expr::string_lit(nested_field))));
+                    }
+                    Ok(Transformed::yes(e))
+                },
+                None => Ok(Transformed::no(Expr::UnresolvedNamedLambdaVariable(name_parts))),
+            },
+            Expr::NamedLambdaVariable(v) => match variables.iter().find(|x| x.expr_id == v.expr_id) {
+                Some(x) if x.data_type != v.data_type => Ok(Transformed::yes(Expr::NamedLambdaVariable(x.clone()))),
+                _ => Ok(Transformed::no(Expr::NamedLambdaVariable(v))),
+            },
+            // 内层lambda的同名参数覆盖外层变量
+            Expr::LambdaFunction(LambdaFunction{function, arguments}) if arguments.iter().any(|arg| {
+                variables.iter().any(|v| v.name.eq_ignore_ascii_case(Self::argument_name(arg)))
+            }) => {
+                let visible: Vec<_> = variables.iter().filter(|v| !arguments.iter().any(|arg| v.name.eq_ignore_ascii_case(Self::argument_name(arg)))).cloned().collect();
+                let function = Self::bind_variables(*function, &visible)?;
+                Ok(Transformed::new(Expr::LambdaFunction(LambdaFunction::new(function, arguments)), true, TreeNodeRecursion::Jump))
+            },
+            e => Ok(Transformed::no(e)),
+        }).map(|t| t.data)
+    }
+}
+
 struct ExprIdGenerator {
     counter: std::sync::atomic::AtomicU32,
 }
@@ -595,6 +707,8 @@ impl Literal {
 pub struct UnresolvedFunction {
     pub name: String,
     pub arguments: Vec<Expr>,
+    /// 聚合函数参数去重, 如count(distinct user_id)
+    pub is_distinct: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Hash)]
@@ -826,12 +940,24 @@ pub fn create_physical_expr(
             let list = list.into_iter().map(|child| create_physical_expr(child)).collect::<Result<Vec<_>>>()?;
             Ok(Box::new(phy::In::new(value, list)))
         },
+        Expr::NamedLambdaVariable(NamedLambdaVariable{expr_id, ..}) =>
+            Ok(Box::new(phy::NamedLambdaVariable::bind(*expr_id)?)),
+        Expr::LambdaFunction(f) =>
+            Ok(Box::new(create_lambda_function(f)?)),
         Expr::ScalarFunction(func) => func.create_physical_expr(),
         _ => Err(format!("Not implemented:{:?}", e)),
     }
 
 }
 
+pub fn create_lambda_function(f: &LambdaFunction) -> Result<phy::LambdaFunction> {
+    let variables = f.arguments.iter().map(|arg| match arg {
+        Expr::NamedLambdaVariable(v) => Ok((v.expr_id, v.data_type.clone())),
+        _ => Err(format!("unbound lambda function argument:{:?}", arg)),
+    }).collect::<Result<Vec<_>>>()?;
+    phy::LambdaFunction::new(variables, || create_physical_expr(&f.function))
+}
+
 #[macro_export]
 macro_rules! match_downcast {
     ($func:expr, $($type:ident { $($field:ident),* } => $block:block),* $(,)? _ => $else_block:block) => {{
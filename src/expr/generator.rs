@@ -140,15 +140,11 @@ pub struct Explode {
 
 impl Explode {
     pub fn new(child: Box<Expr>) -> Self {
-        let tp = if child.resolved() {
-            match child.data_type() {
-                DataType::Array(t) => t.as_ref().clone(),
-                _ => DataType::Null
-            }
-        } else {
-            DataType::Null
+        let fields = match child.resolved().then(|| child.data_type()) {
+            Some(DataType::Array(t)) => vec![Field::new("item", t.as_ref().clone())],
+            Some(DataType::Map(kt, vt)) => vec![Field::new("key", kt.as_ref().clone()), Field::new("value", vt.as_ref().clone())],
+            _ => vec![Field::new("item", DataType::Null)],
         };
-        let fields = vec![Field::new("item", tp.clone())];
         let element_schema = Schema::new(fields);
         let data_type = DataType::Array(Box::new(element_schema.to_struct_type()));
         Self { child, element_schema, data_type }
@@ -183,9 +179,20 @@ impl Generator for Explode {
         vec![&self.child]
     }
 
+    fn check_input_data_types(&self) -> Result<()> {
+        match self.child.data_type() {
+            DataType::Array(_) | DataType::Map(_, _) => Ok(()),
+            tp => Err(format!("explode argument 1 requires array or map type, but get {}", tp)),
+        }
+    }
+
     fn physical_generator(&self) -> Result<Box<dyn PhysicalGenerator>> {
         let child = create_physical_expr(self.child.as_ref())?;
-        Ok(Box::new(phy::Explode::new(child)))
+        if let DataType::Map(_, _) = self.child.data_type() {
+            Ok(Box::new(phy::ExplodeMap::new(child)))
+        } else {
+            Ok(Box::new(phy::Explode::new(child)))
+        }
     }
 }
 
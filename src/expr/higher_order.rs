@@ -0,0 +1,317 @@
+use crate::Result;
+use crate::expr::{create_lambda_function, create_physical_expr, CreateScalarFunction, Expr, ScalarFunction};
+use crate::types::DataType;
+use crate::physical_expr::{self as phy, PhysicalExpr};
+
+/// 按lambda参数个数选择参数类型并绑定, 已绑定时更新变量类型
+fn bind_lambda_function(function: Expr, arg_types: Vec<Vec<DataType>>) -> Result<Expr> {
+    match function {
+        Expr::LambdaFunction(f) => {
+            let num = f.arguments.len();
+            match arg_types.into_iter().find(|types| types.len() == num) {
+                Some(types) => Ok(Expr::LambdaFunction(f.bind(types)?)),
+                None => Err(format!("lambda function with {} arguments is not supported", num)),
+            }
+        },
+        e => Err(format!("requires a lambda function argument, not {}", e.sql())),
+    }
+}
+
+fn array_element_type(argument: &Expr) -> Result<DataType> {
+    match argument.data_type() {
+        DataType::Array(element_type) => Ok(element_type.as_ref().clone()),
+        data_type => Err(format!("argument requires array type, not {}", data_type)),
+    }
+}
+
+fn check_lambda_return_type(name: &str, function: &Expr, data_type: &DataType) -> Result<()> {
+    if !function.resolved() || function.data_type() == data_type {
+        Ok(())
+    } else {
+        Err(format!("{} lambda function requires {} type, not {}", name, data_type, function.data_type()))
+    }
+}
+
+fn lambda_function(function: &Expr) -> Result<phy::LambdaFunction> {
+    match function {
+        Expr::LambdaFunction(f) => create_lambda_function(f),
+        e => Err(format!("not a lambda function:{:?}", e)),
+    }
+}
+
+/// transform(array, x -> expr) 或 transform(array, (x, i) -> expr)
+#[derive(Debug, Clone)]
+pub struct ArrayTransform {
+    pub argument: Box<Expr>,
+    pub function: Box<Expr>,
+    pub data_type: DataType,
+}
+
+impl CreateScalarFunction for ArrayTransform {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let argument = iter.next().unwrap();
+        let element_type = array_element_type(&argument)?;
+        let function = bind_lambda_function(iter.next().unwrap(), vec![vec![element_type.clone()], vec![element_type, DataType::Int]])?;
+        let data_type = if function.resolved() { DataType::Array(Box::new(function.data_type().clone())) } else { DataType::Null };
+        Ok(Box::new(ArrayTransform { argument: Box::new(argument), function: Box::new(function), data_type }))
+    }
+}
+
+impl ScalarFunction for ArrayTransform {
+    fn name(&self) -> &str {
+        "transform"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.argument, &self.function]
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayTransform::new(create_physical_expr(&self.argument)?, lambda_function(&self.function)?, self.data_type.clone())))
+    }
+}
+
+/// filter(array, x -> boolean) 或 filter(array, (x, i) -> boolean)
+#[derive(Debug, Clone)]
+pub struct ArrayFilter {
+    pub argument: Box<Expr>,
+    pub function: Box<Expr>,
+}
+
+impl CreateScalarFunction for ArrayFilter {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 2 {
+            return Err(format!("requires 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let argument = iter.next().unwrap();
+        let element_type = array_element_type(&argument)?;
+        let function = bind_lambda_function(iter.next().unwrap(), vec![vec![element_type.clone()], vec![element_type, DataType::Int]])?;
+        Ok(Box::new(ArrayFilter { argument: Box::new(argument), function: Box::new(function) }))
+    }
+}
+
+impl ScalarFunction for ArrayFilter {
+    fn name(&self) -> &str {
+        "filter"
+    }
+
+    fn data_type(&self) -> &DataType {
+        self.argument.data_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.argument, &self.function]
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        check_lambda_return_type(self.name(), &self.function, DataType::boolean_type())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayFilter::new(create_physical_expr(&self.argument)?, lambda_function(&self.function)?)))
+    }
+}
+
+/// exists(array, x -> boolean)
+#[derive(Debug, Clone)]
+pub struct ArrayExists {
+    pub argument: Box<Expr>,
+    pub function: Box<Expr>,
+}
+
+/// forall(array, x -> boolean)
+#[derive(Debug, Clone)]
+pub struct ArrayForAll {
+    pub argument: Box<Expr>,
+    pub function: Box<Expr>,
+}
+
+fn predicate_args(args: Vec<Expr>) -> Result<(Box<Expr>, Box<Expr>)> {
+    if args.len() != 2 {
+        return Err(format!("requires 2 argument, found:{}", args.len()));
+    }
+    let mut iter = args.into_iter();
+    let argument = iter.next().unwrap();
+    let element_type = array_element_type(&argument)?;
+    let function = bind_lambda_function(iter.next().unwrap(), vec![vec![element_type]])?;
+    Ok((Box::new(argument), Box::new(function)))
+}
+
+impl CreateScalarFunction for ArrayExists {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        let (argument, function) = predicate_args(args)?;
+        Ok(Box::new(ArrayExists { argument, function }))
+    }
+}
+
+impl ScalarFunction for ArrayExists {
+    fn name(&self) -> &str {
+        "exists"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::boolean_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.argument, &self.function]
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        check_lambda_return_type(self.name(), &self.function, DataType::boolean_type())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayExists::new(create_physical_expr(&self.argument)?, lambda_function(&self.function)?, false)))
+    }
+}
+
+impl CreateScalarFunction for ArrayForAll {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        let (argument, function) = predicate_args(args)?;
+        Ok(Box::new(ArrayForAll { argument, function }))
+    }
+}
+
+impl ScalarFunction for ArrayForAll {
+    fn name(&self) -> &str {
+        "forall"
+    }
+
+    fn data_type(&self) -> &DataType {
+        DataType::boolean_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        vec![&self.argument, &self.function]
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        check_lambda_return_type(self.name(), &self.function, DataType::boolean_type())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        Ok(Box::new(phy::ArrayExists::new(create_physical_expr(&self.argument)?, lambda_function(&self.function)?, true)))
+    }
+}
+
+/// aggregate(array, zero, (acc, x) -> merge[, acc -> finish]), merge的返回类型必须和zero相同
+#[derive(Debug, Clone)]
+pub struct ArrayAggregate {
+    pub argument: Box<Expr>,
+    pub zero: Box<Expr>,
+    pub merge: Box<Expr>,
+    pub finish: Option<Box<Expr>>,
+    pub data_type: DataType,
+}
+
+impl CreateScalarFunction for ArrayAggregate {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 3 && args.len() != 4 {
+            return Err(format!("requires 3 or 4 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let argument = iter.next().unwrap();
+        let element_type = array_element_type(&argument)?;
+        let zero = iter.next().unwrap();
+        let acc_type = zero.data_type().clone();
+        let merge = bind_lambda_function(iter.next().unwrap(), vec![vec![acc_type.clone(), element_type]])?;
+        let finish = iter.next().map(|finish| bind_lambda_function(finish, vec![vec![acc_type.clone()]]).map(Box::new)).transpose()?;
+        let data_type = match &finish {
+            Some(finish) if finish.resolved() => finish.data_type().clone(),
+            Some(_) => DataType::Null,
+            None => acc_type,
+        };
+        Ok(Box::new(ArrayAggregate { argument: Box::new(argument), zero: Box::new(zero), merge: Box::new(merge), finish, data_type }))
+    }
+}
+
+impl ScalarFunction for ArrayAggregate {
+    fn name(&self) -> &str {
+        "aggregate"
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        let mut args = vec![self.argument.as_ref(), self.zero.as_ref(), self.merge.as_ref()];
+        if let Some(finish) = &self.finish {
+            args.push(finish);
+        }
+        args
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        check_lambda_return_type(self.name(), &self.merge, self.zero.data_type())
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let finish = self.finish.as_ref().map(|finish| lambda_function(finish)).transpose()?;
+        Ok(Box::new(phy::ArrayAggregate::new(create_physical_expr(&self.argument)?, create_physical_expr(&self.zero)?,
+            lambda_function(&self.merge)?, finish, self.data_type.clone())))
+    }
+}
+
+/// array_sort(array[, (left, right) -> int]), 没有比较函数时升序排序, null排在最后
+#[derive(Debug, Clone)]
+pub struct ArraySort {
+    pub argument: Box<Expr>,
+    pub comparator: Option<Box<Expr>>,
+}
+
+impl CreateScalarFunction for ArraySort {
+    fn from_args(args: Vec<Expr>) -> Result<Box<dyn ScalarFunction>> {
+        if args.len() != 1 && args.len() != 2 {
+            return Err(format!("requires 1 or 2 argument, found:{}", args.len()));
+        }
+        let mut iter = args.into_iter();
+        let argument = iter.next().unwrap();
+        let element_type = array_element_type(&argument)?;
+        let comparator = iter.next().map(|f| bind_lambda_function(f, vec![vec![element_type.clone(), element_type.clone()]]).map(Box::new)).transpose()?;
+        Ok(Box::new(ArraySort { argument: Box::new(argument), comparator }))
+    }
+}
+
+impl ScalarFunction for ArraySort {
+    fn name(&self) -> &str {
+        "array_sort"
+    }
+
+    fn data_type(&self) -> &DataType {
+        self.argument.data_type()
+    }
+
+    fn args(&self) -> Vec<&Expr> {
+        let mut args = vec![self.argument.as_ref()];
+        if let Some(comparator) = &self.comparator {
+            args.push(comparator);
+        }
+        args
+    }
+
+    fn check_input_data_types(&self) -> Result<()> {
+        match &self.comparator {
+            Some(comparator) => check_lambda_return_type(self.name(), comparator, DataType::int_type()),
+            None => match array_element_type(&self.argument)? {
+                element_type if element_type.is_orderable() || element_type == DataType::Boolean => Ok(()),
+                element_type => Err(format!("array_sort does not support sorting array of type {}", element_type)),
+            },
+        }
+    }
+
+    fn create_physical_expr(&self) -> Result<Box<dyn PhysicalExpr>> {
+        let comparator = self.comparator.as_ref().map(|comparator| lambda_function(comparator)).transpose()?;
+        Ok(Box::new(phy::ArraySort::new(create_physical_expr(&self.argument)?, comparator)))
+    }
+}
@@ -16,6 +16,7 @@ mod misc;
 pub mod aggregate;
 mod generator;
 mod predicate;
+mod higher_order;
 
 pub use expr::*;
 pub use expr_fn::*;
@@ -33,4 +34,5 @@ pub use json::*;
 pub use misc::*;
 pub use predicate::*;
 pub use generator::*;
+pub use higher_order::*;
 
@@ -24,6 +24,8 @@ impl TreeNode for Expr {
             | Expr::UnresolvedStar(_)
             | Expr::BoundReference(_)
             | Expr::AttributeReference(_)
+            | Expr::UnresolvedNamedLambdaVariable(_)
+            | Expr::NamedLambdaVariable(_)
             | Expr::NoOp
             | Expr::Literal(_) => Transformed::no(self),
             Expr::UnresolvedAlias(child) => f(*child)?.update_data(|child| {
@@ -70,9 +72,14 @@ impl TreeNode for Expr {
                         Expr::In(In::new(new_value, new_list))
                     })
             },
-            Expr::UnresolvedFunction(UnresolvedFunction { name, arguments }) => {
+            Expr::LambdaFunction(LambdaFunction { function, arguments }) => (function, arguments)
+                .map_elements(f)?
+                .update_data(|(new_function, new_arguments)| {
+                    Expr::LambdaFunction(LambdaFunction { function: new_function, arguments: new_arguments })
+                }),
+            Expr::UnresolvedFunction(UnresolvedFunction { name, arguments, is_distinct }) => {
                 arguments.map_elements(f)?.update_data(|arguments| {
-                    Expr::UnresolvedFunction(UnresolvedFunction{name, arguments})
+                    Expr::UnresolvedFunction(UnresolvedFunction{name, arguments, is_distinct})
                 })
             },
             Expr::UnresolvedGenerator(UnresolvedGenerator{name, arguments}) => {
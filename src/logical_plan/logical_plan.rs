@@ -43,8 +43,8 @@ impl LogicalPlan {
             LogicalPlan::Project(Project{project_list, ..}) => project_list.iter().collect(),
             LogicalPlan::Filter(Filter{condition, ..}) => vec![condition],
             LogicalPlan::Expression(Expression{expr, ..}) => vec![expr],
-            LogicalPlan::Aggregate(Aggregate{grouping_exprs, aggregate_exprs, ..}) => {
-                grouping_exprs.iter().chain(aggregate_exprs.iter()).collect()
+            LogicalPlan::Aggregate(Aggregate{grouping_exprs, aggregate_exprs, having, ..}) => {
+                grouping_exprs.iter().chain(aggregate_exprs.iter()).chain(having.iter()).collect()
             },
             LogicalPlan::Generate(g) => {
                 let mut exprs = Vec::new();
@@ -111,6 +111,44 @@ impl LogicalPlan {
     pub fn child_attributes(&self) -> Vec<AttributeReference> {
         self.children().into_iter().flat_map(|p| p.output().into_iter()).collect()
     }
+
+    fn node_string(&self) -> String {
+        let sqls = |exprs: &[Expr]| exprs.iter().map(|e| e.sql()).collect::<Vec<_>>().join(", ");
+        match self {
+            LogicalPlan::UnresolvedRelation(name) => format!("UnresolvedRelation {}", name),
+            LogicalPlan::OneRowRelation => "OneRowRelation".to_string(),
+            LogicalPlan::RelationPlaceholder(RelationPlaceholder{name, output}) => {
+                format!("RelationPlaceholder {} [{}]", name, output.iter().map(|a| format!("{}:{}", a.name, a.data_type)).collect::<Vec<_>>().join(", "))
+            },
+            LogicalPlan::Project(Project{project_list, ..}) => format!("Project [{}]", sqls(project_list)),
+            LogicalPlan::Filter(Filter{condition, ..}) => format!("Filter {}", condition.sql()),
+            LogicalPlan::SubqueryAlias(SubqueryAlias{identifier, ..}) => format!("SubqueryAlias {}", identifier),
+            LogicalPlan::Expression(Expression{expr, ..}) => format!("Expression {}", expr.sql()),
+            LogicalPlan::Aggregate(Aggregate{grouping_exprs, aggregate_exprs, having, ..}) => match having {
+                Some(having) => format!("Aggregate [{}], [{}], having {}", sqls(grouping_exprs), sqls(aggregate_exprs), having.sql()),
+                None => format!("Aggregate [{}], [{}]", sqls(grouping_exprs), sqls(aggregate_exprs)),
+            },
+            LogicalPlan::Generate(g) => format!("Generate {}, outer: {}, [{}]", g.generator.sql(), g.outer, sqls(&g.generator_output)),
+        }
+    }
+
+    /// 树形格式输出执行计划
+    pub fn tree_string(&self) -> String {
+        let mut lines = Vec::new();
+        self.generate_tree_string(0, &mut lines);
+        lines.join("\n")
+    }
+
+    fn generate_tree_string(&self, depth: usize, lines: &mut Vec<String>) {
+        if depth == 0 {
+            lines.push(self.node_string());
+        } else {
+            lines.push(format!("{}+- {}", "   ".repeat(depth - 1), self.node_string()));
+        }
+        for child in self.children() {
+            child.generate_tree_string(depth + 1, lines);
+        }
+    }
 }
 
 impl<'a> TreeNodeContainer<'a, Self> for LogicalPlan {
@@ -274,25 +312,27 @@ impl Generate {
 pub struct Aggregate {
     pub grouping_exprs: Vec<Expr>,
     pub aggregate_exprs: Vec<Expr>,
+    /// having过滤条件, 可引用聚合函数和select中的别名
+    pub having: Option<Expr>,
     pub child: Arc<LogicalPlan>,
 }
 
 impl Aggregate {
-    pub fn new(grouping_exprs: Vec<Expr>, aggregate_exprs: Vec<Expr>, child: Arc<LogicalPlan>) -> Self {
+    pub fn new(grouping_exprs: Vec<Expr>, aggregate_exprs: Vec<Expr>, having: Option<Expr>, child: Arc<LogicalPlan>) -> Self {
         for expr in &aggregate_exprs {
             match expr {
                 Expr::Alias(_) | Expr::UnresolvedAlias(_) | Expr::AttributeReference(_) | Expr::UnresolvedAttribute(_) => (),
                 e => panic!("{}", format!("{:?} is not allowed in aggregate exprs", e)),
             }
         }
-        Self { grouping_exprs, aggregate_exprs, child }
+        Self { grouping_exprs, aggregate_exprs, having, child }
     }
 
-    // groupingExpressions, aggregateExpressions, resultExpressions, child
-    pub fn extract_exprs(&self) -> (Vec<Expr>, Vec<Expr>, Vec<Expr>, Arc<LogicalPlan>) {
+    // groupingExpressions, aggregateExpressions, resultExpressions, havingExpression, child
+    pub fn extract_exprs(&self) -> (Vec<Expr>, Vec<Expr>, Vec<Expr>, Option<Expr>, Arc<LogicalPlan>) {
         let mut equivalent_exprs = HashMap::new();
         let mut agg_exprs = Vec::with_capacity(self.aggregate_exprs.len());
-        for expr in &self.aggregate_exprs {
+        for expr in self.aggregate_exprs.iter().chain(self.having.iter()) {
             expr.apply(|e| {
                 match e {
                     Expr::DeclarativeAggFunction(f) => {
@@ -312,6 +352,8 @@ impl Aggregate {
                             equivalent_exprs.insert(e.clone(), f.result_attribute());
                             agg_exprs.push(e.clone());
                         }
+                        // distinct聚合函数的参数是内部聚合函数, 不单独计算
+                        return Ok(TreeNodeRecursion::Jump);
                     },
                     _ => ()
                 }
@@ -331,9 +373,7 @@ impl Aggregate {
             }
         }
         let group_expr_map = named_group_exprs.clone().into_iter().collect::<HashMap<_, _>>();
-        let mut rewritten_result_exprs = Vec::with_capacity(self.aggregate_exprs.len());
-        for expr in self.aggregate_exprs.clone() {
-            let ep = expr.transform_down(|e| match e {
+        let rewrite = |expr: Expr| expr.transform_down(|e| match e {
                 e @ Expr::DeclarativeAggFunction(_) | e @ Expr::TypedAggFunction(_) => {
                     let attr = Expr::AttributeReference(equivalent_exprs.get(&e).unwrap().clone());
                     Ok(Transformed::yes(attr))
@@ -350,13 +390,14 @@ impl Aggregate {
                     Ok(Transformed::no(e))
                 },
             }).unwrap().data;
-            rewritten_result_exprs.push(ep);
-        }
+        let rewritten_result_exprs = self.aggregate_exprs.clone().into_iter().map(rewrite).collect::<Vec<_>>();
+        let rewritten_having = self.having.clone().map(rewrite);
 
         (
             named_group_exprs.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
             agg_exprs,
             rewritten_result_exprs,
+            rewritten_having,
             self.child.clone(),
         )
     }
@@ -381,10 +422,11 @@ mod tests {
         let optimized_plan = sql_utils::sql_plan(sql, &schema).unwrap();
         println!("plan:{:#?}", optimized_plan);
         if let LogicalPlan::Aggregate(agg) = optimized_plan {
-            let (group_exprs, agg_exprs, result_exprs, child) = agg.extract_exprs();
+            let (group_exprs, agg_exprs, result_exprs, having, child) = agg.extract_exprs();
             println!("group_exprs:{:#?}", group_exprs);
             println!("agg_exprs:{:#?}", agg_exprs);
             println!("result_exprs:{:#?}", result_exprs);
+            println!("having:{:#?}", having);
             println!("child:{:#?}", child);
         }
     }
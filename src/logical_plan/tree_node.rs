@@ -45,10 +45,10 @@ impl TreeNode for LogicalPlan {
                 .update_data(|child| {
                     LogicalPlan::Expression(Expression {expr, child, })
                 }),
-            LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, child}) =>
+            LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, having, child}) =>
                 child.map_elements(f)?
                 .update_data(|child| {
-                    LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, child})
+                    LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, having, child})
                 }),
             LogicalPlan::Generate(Generate{generator, unrequired_child_index, outer, qualifier, generator_output, child}) =>
                 child.map_elements(f)?
@@ -91,10 +91,10 @@ impl LogicalPlan {
                     .update_data(|expr|
                     LogicalPlan::Expression(Expression {expr, child, }))
                 ),
-            LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, child}) =>
-                Ok((grouping_exprs, aggregate_exprs).map_elements(f)?
-                    .update_data(|(grouping_exprs, aggregate_exprs)|
-                    LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, child})
+            LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, having, child}) =>
+                Ok((grouping_exprs, aggregate_exprs, having).map_elements(f)?
+                    .update_data(|(grouping_exprs, aggregate_exprs, having)|
+                    LogicalPlan::Aggregate(Aggregate {grouping_exprs, aggregate_exprs, having, child})
                     )
                 ),
             LogicalPlan::Generate(Generate{generator, unrequired_child_index, outer, qualifier, generator_output, child}) =>
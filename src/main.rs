@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::io;
 use std::process::exit;
 use clap::{Parser, Subcommand};
 use log::{error, info};
 use flexi_logger::with_thread;
+use retl::config::ConfigKeys;
 use retl::execution::application;
 
 #[derive(Parser)]
@@ -18,6 +20,52 @@ pub struct Cli {
 enum Commands {
     Run {
         config_file: String,
+        /// Override config values, e.g. --set env.application.parallelism=2
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        sets: Vec<String>,
+    },
+    /// Parse config and build all nodes without opening connections
+    Validate {
+        config_file: String,
+        /// Override config values, e.g. --set env.application.parallelism=2
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        sets: Vec<String>,
+    },
+    /// Print node graph, schemas and sql plans
+    Explain {
+        config_file: String,
+        /// Override config values, e.g. --set env.application.parallelism=2
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        sets: Vec<String>,
+    },
+    /// Run test cases against the pipeline with in-memory sinks and a mock clock
+    Test {
+        config_file: String,
+        /// Cases file, each case feeds rows to sources and compares sink outputs
+        #[arg(long = "cases")]
+        cases_file: String,
+        /// Override config values, e.g. --set env.application.parallelism=2
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        sets: Vec<String>,
+    },
+    /// Encrypt a config value to enc@(kid:...) with a random iv, key from RETL_CONFIG_KEY or RETL_CONFIG_KEY_FILE
+    Encrypt {
+        /// Plaintext, read from stdin if not set
+        value: Option<String>,
+        /// Key id in the key file
+        #[arg(long = "kid", default_value = "default")]
+        kid: String,
+        /// Key file, overrides RETL_CONFIG_KEY_FILE
+        #[arg(long = "key-file")]
+        key_file: Option<String>,
+    },
+    /// Decrypt an enc@(...) config value, legacy values without kid use the old built-in key when RETL_ALLOW_LEGACY_CONFIG_KEY=true
+    Decrypt {
+        /// Ciphertext, read from stdin if not set
+        value: Option<String>,
+        /// Key file, overrides RETL_CONFIG_KEY_FILE
+        #[arg(long = "key-file")]
+        key_file: Option<String>,
     },
     Sql {
         #[arg(short = 'e')]
@@ -96,6 +144,22 @@ impl KafkaCommonArgs {
     }
 }
 
+fn run_crypt_command(value: Option<String>, key_file: Option<String>, f: impl FnOnce(&ConfigKeys, &str) -> Result<String, String>) -> Result<String, String> {
+    let keys = match key_file {
+        Some(path) => ConfigKeys::from_file(&path)?,
+        None => ConfigKeys::from_env()?,
+    };
+    let value = match value {
+        Some(value) => value,
+        None => {
+            let mut value = String::new();
+            io::stdin().read_line(&mut value).map_err(|e| e.to_string())?;
+            value.trim_end_matches(['\r', '\n']).to_string()
+        },
+    };
+    f(&keys, &value)
+}
+
 fn run_sql_command(sql: Option<String>, filename: Option<String>) {
     #[cfg(feature = "batch")]
     {
@@ -154,13 +218,59 @@ fn main() {
         .start()
         .unwrap();
     match cli.command {
-        Commands::Run { config_file } => {
-            if let Err(e) = application::run_application(&config_file) {
+        Commands::Run { config_file, sets } => {
+            if let Err(e) = application::run_application(&config_file, &sets) {
                 error!("execution error {}", e);
             } else {
                 info!("execution success");
             }
         },
+        Commands::Validate { config_file, sets } => {
+            match application::validate_application(&config_file, &sets) {
+                Ok(_) => println!("config {} is valid", config_file),
+                Err(e) => {
+                    error!("validate error: {}", e);
+                    exit(1);
+                },
+            }
+        },
+        Commands::Explain { config_file, sets } => {
+            match application::explain_application(&config_file, &sets) {
+                Ok(explain) => println!("{}", explain),
+                Err(e) => {
+                    error!("explain error: {}", e);
+                    exit(1);
+                },
+            }
+        },
+        Commands::Test { config_file, cases_file, sets } => {
+            match application::test_application(&config_file, &cases_file, &sets) {
+                Ok(true) => (),
+                Ok(false) => exit(1),
+                Err(e) => {
+                    error!("test error: {}", e);
+                    exit(1);
+                },
+            }
+        },
+        Commands::Encrypt { value, kid, key_file } => {
+            match run_crypt_command(value, key_file, |keys, value| keys.encrypt(value, &kid)) {
+                Ok(encrypted) => println!("{}", encrypted),
+                Err(e) => {
+                    error!("encrypt error: {}", e);
+                    exit(1);
+                },
+            }
+        },
+        Commands::Decrypt { value, key_file } => {
+            match run_crypt_command(value, key_file, |keys, value| keys.decrypt(value)) {
+                Ok(decrypted) => println!("{}", decrypted),
+                Err(e) => {
+                    error!("decrypt error: {}", e);
+                    exit(1);
+                },
+            }
+        },
         Commands::Sql { sql, filename } => {
             run_sql_command(sql, filename);
         },
@@ -7,10 +7,11 @@ use pest::{
 };
 use pest_derive::Parser;
 use serde_json::Value as JValue;
-use crate::{Operator, Result};
+use crate::{decimal_utils, Operator, Result};
 use crate::data::Value;
-use crate::expr::{BinaryOperator, CaseWhen, Cast, Expr, In, Like, Literal, UnaryMinus, BitwiseNot,UnresolvedExtractValue, UnresolvedFunction, UnresolvedGenerator};
+use crate::expr::{Alias, BinaryOperator, CaseWhen, Cast, Expr, In, LambdaFunction, Like, Literal, UnaryMinus, BitwiseNot,UnresolvedExtractValue, UnresolvedFunction, UnresolvedGenerator};
 use crate::logical_plan::{Aggregate, Filter, Generate, LogicalPlan, Project, SubqueryAlias};
+use crate::tree_node::{Transformed, TreeNode};
 use crate::types::*;
 
 #[derive(Parser)]
@@ -68,11 +69,13 @@ pub fn parse_ast(mut pair: Pair<Rule>) -> Result<Ast> {
             Rule::singleQuery | Rule::singleExpression | Rule::singleDataType =>
                 pair = pair.into_inner().next().unwrap(),
             Rule::singleTableSchema => return parse_single_table_schema(pair),
+            Rule::query => return parse_query_ast(pair),
             Rule::queryPrimary => return parse_query_primary_ast(pair),
             Rule::tableNameRelation => return parse_table_name_relation_ast(pair),
             Rule::subqueryAliasRelation => return parse_subquery_alias_relation_ast(pair),
             Rule::namedExpressionSeq => return parse_named_expression_seq(pair).map(|x| Ast::Projects(x)),
             Rule::functionCall => return parse_function_call(pair).map(|x| Ast::Expression(x)),
+            Rule::lambda => return parse_lambda(pair).map(|x| Ast::Expression(x)),
             Rule::constant => return parse_constant(pair).map(|x| Ast::Expression(x)),
             Rule::star => return parse_star(pair).map(|x| Ast::Expression(x)),
             Rule::columnReference => return parse_column_reference(pair).map(|x| Ast::Expression(x)),
@@ -89,6 +92,7 @@ pub fn parse_ast(mut pair: Pair<Rule>) -> Result<Ast> {
             Rule::unaryExpression => return parse_unary_expression_ast(pair),
             Rule::primaryExpression => return parse_primary_expression_ast(pair),
             Rule::arrayDataType => return parse_array_data_type(pair).map(|x| Ast::DataType(x)),
+            Rule::mapDataType => return parse_map_data_type(pair).map(|x| Ast::DataType(x)),
             Rule::structDataType => return parse_struct_data_type(pair).map(|x| Ast::DataType(x)),
             Rule::primitiveDataType => return parse_primitive_data_type(pair).map(|x| Ast::DataType(x)),
             _ => {
@@ -125,6 +129,51 @@ fn parse_identifier(mut pair: Pair<Rule>) -> Result<&str> {
     }
 }
 
+/// with子句中的查询按顺序替换后面查询中同名的表, 每个查询只能引用前面定义的查询
+fn parse_query_ast(pair: Pair<Rule>) -> Result<Ast> {
+    let mut ctes: Vec<(String, LogicalPlan)> = Vec::new();
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::ctes => {
+                for named_query in pair.into_inner() {
+                    let mut pairs = named_query.into_inner();
+                    let name = parse_identifier(pairs.next().unwrap())?.to_string();
+                    if ctes.iter().any(|(n, _)| n.eq_ignore_ascii_case(&name)) {
+                        return Err(format!("CTE definition can't have duplicate names: {}", name));
+                    }
+                    let plan = match parse_query_ast(pairs.next().unwrap())? {
+                        Ast::Plan(plan) => substitute_ctes(plan, &ctes)?,
+                        ast => return Err(format!("Expected a plan but found {:?}", ast)),
+                    };
+                    ctes.push((name, plan));
+                }
+            },
+            Rule::queryPrimary => {
+                return match parse_query_primary_ast(pair)? {
+                    Ast::Plan(plan) => Ok(Ast::Plan(substitute_ctes(plan, &ctes)?)),
+                    ast => Err(format!("Expected a plan but found {:?}", ast)),
+                };
+            },
+            _ => {}
+        }
+    }
+    Err("query without select".to_string())
+}
+
+/// 把引用cte的UnresolvedRelation替换为SubqueryAlias(cte名称, cte查询), 其余的表由ResolveRelations解析
+fn substitute_ctes(plan: LogicalPlan, ctes: &[(String, LogicalPlan)]) -> Result<LogicalPlan> {
+    if ctes.is_empty() {
+        return Ok(plan);
+    }
+    plan.transform_up(|plan| match &plan {
+        LogicalPlan::UnresolvedRelation(name) => match ctes.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            Some((n, cte)) => Ok(Transformed::yes(LogicalPlan::SubqueryAlias(SubqueryAlias::new(n.clone(), Arc::new(cte.clone()))))),
+            None => Ok(Transformed::no(plan)),
+        },
+        _ => Ok(Transformed::no(plan)),
+    }).map(|t| t.data)
+}
+
 fn parse_query_primary_ast(pair: Pair<Rule>) -> Result<Ast> {
     let query = pair;
     let mut project_list: Vec<_> = Vec::new();
@@ -132,9 +181,17 @@ fn parse_query_primary_ast(pair: Pair<Rule>) -> Result<Ast> {
     let mut filter: Option<Expr> = None;
     let mut lateral_view: Option<Generate> = None;
     let mut group_exprs: Option<Vec<Expr>> = None;
+    let mut having: Option<Expr> = None;
+    let mut distinct = false;
     for pair in query.into_inner() {
         match pair.as_rule() {
             Rule::selectClause => {
+                let mut pairs = pair.into_inner();
+                let mut pair = pairs.next().unwrap();
+                if pair.as_rule() == Rule::DISTINCT {
+                    distinct = true;
+                    pair = pairs.next().unwrap();
+                }
                 let ast = parse_ast(pair)?;
                 if let Ast::Projects(projects) = ast {
                     project_list = projects;
@@ -173,6 +230,9 @@ fn parse_query_primary_ast(pair: Pair<Rule>) -> Result<Ast> {
             Rule::aggregationClause => {
                 group_exprs = Some(pair.into_inner().map(parse_expression).try_collect()?);
             },
+            Rule::havingClause => {
+                having = Some(parse_expression(pair)?);
+            },
             _ => {}
         }
     }
@@ -184,8 +244,27 @@ fn parse_query_primary_ast(pair: Pair<Rule>) -> Result<Ast> {
     if let Some(filter) = filter {
         child = Arc::new(LogicalPlan::Filter(Filter::new(filter, child)));
     }
+    if distinct {
+        if group_exprs.is_some() {
+            return Err("SELECT DISTINCT with GROUP BY is not supported".to_string());
+        }
+        // select distinct按所有输出列分组
+        let mut exprs = Vec::with_capacity(project_list.len());
+        for expr in &project_list {
+            match expr {
+                Expr::Alias(Alias{child, ..}) => exprs.push(child.as_ref().clone()),
+                Expr::UnresolvedAlias(child) if matches!(child.as_ref(), Expr::UnresolvedStar(_)) => return Err("SELECT DISTINCT * is not supported".to_string()),
+                Expr::UnresolvedAlias(child) => exprs.push(child.as_ref().clone()),
+                e => exprs.push(e.clone()),
+            }
+        }
+        group_exprs = Some(exprs);
+    }
     if let Some(group_exprs) = group_exprs {
-        Ok(Ast::Plan(LogicalPlan::Aggregate(Aggregate::new(group_exprs, project_list, child))))
+        Ok(Ast::Plan(LogicalPlan::Aggregate(Aggregate::new(group_exprs, project_list, having, child))))
+    } else if having.is_some() {
+        // 没有group by的having为全局聚合
+        Ok(Ast::Plan(LogicalPlan::Aggregate(Aggregate::new(vec![], project_list, having, child))))
     } else {
         Ok(Ast::Plan(LogicalPlan::Project(Project::new(project_list, child))))
     }
@@ -204,7 +283,7 @@ fn parse_table_name_relation_ast(pair: Pair<Rule>) -> Result<Ast> {
 
 fn parse_subquery_alias_relation_ast(pair: Pair<Rule>) -> Result<Ast> {
     let mut pairs = pair.into_inner();
-    let ast = parse_query_primary_ast(pairs.next().unwrap())?;
+    let ast = parse_query_ast(pairs.next().unwrap())?;
     if let Ast::Plan(plan) = ast {
         let pair_option = pairs.next();
         if let Some(pair) = pair_option {
@@ -321,6 +400,13 @@ fn parse_array_data_type(pair: Pair<Rule>) -> Result<DataType> {
     }
 }
 
+fn parse_map_data_type(pair: Pair<Rule>) -> Result<DataType> {
+    let mut pairs = pair.into_inner();
+    let key_type = parse_datatype(pairs.next().unwrap())?;
+    let value_type = parse_datatype(pairs.next().unwrap())?;
+    Ok(DataType::Map(Box::new(key_type), Box::new(value_type)))
+}
+
 fn parse_struct_data_type(pair: Pair<Rule>) -> Result<DataType> {
     let mut fields = Vec::new();
     for complex in pair.into_inner() {
@@ -337,9 +423,24 @@ fn parse_struct_data_type(pair: Pair<Rule>) -> Result<DataType> {
 }
 
 fn parse_primitive_data_type(pair: Pair<Rule>) -> Result<DataType> {
-    let tp = parse_identifier(pair)?.to_string();
+    let mut pairs = pair.into_inner();
+    let tp = parse_identifier(pairs.next().unwrap())?.to_string();
+    let params: Vec<u8> = pairs.map(|p| p.as_str().parse::<u8>().map_err(|_| format!("invalid data type parameter: {}", p.as_str()))).try_collect()?;
     let tp_lower = tp.to_lowercase();
     match tp_lower.as_str() {
+        "decimal" | "dec" | "numeric" => {
+            let (precision, scale) = match params.as_slice() {
+                [] => (decimal_utils::DEFAULT_PRECISION, decimal_utils::DEFAULT_SCALE),
+                [precision] => (*precision, 0),
+                [precision, scale] => (*precision, *scale),
+                _ => unreachable!(),
+            };
+            if precision == 0 || precision > decimal_utils::MAX_PRECISION || scale > precision {
+                return Err(format!("invalid decimal type: {}({},{}), requires 0 < precision <= {} and scale <= precision", tp, precision, scale, decimal_utils::MAX_PRECISION));
+            }
+            Ok(DataType::Decimal(precision, scale))
+        },
+        _ if !params.is_empty() => Err(format!("data type {} does not support parameters", tp)),
         "boolean" => Ok(DataType::Boolean),
         "int" | "integer" => Ok(DataType::Int),
         "bigint" | "long" => Ok(DataType::Long),
@@ -403,15 +504,35 @@ fn parse_function_call(pair: Pair<Rule>) -> Result<Expr> {
     let mut pairs = pair.into_inner();
     let name = parse_identifier(pairs.next().unwrap())?.to_string();
     let args_pair = pairs.next().unwrap();
-    let mut arguments:Vec<_> = args_pair.into_inner().map(parse_expression).try_collect()?;
+    let is_distinct = args_pair.as_rule() == Rule::distinctFunctionArgs;
+    let mut arguments:Vec<_> = args_pair.into_inner().filter(|pair| pair.as_rule() != Rule::DISTINCT).map(parse_expression).try_collect()?;
     // Transform count(*) into count(1).
     if arguments.len() == 1 && name.to_lowercase() == "count" {
         arguments = match & arguments[0]{
-            Expr::UnresolvedStar(target) if target.is_empty() => vec![Expr::int_lit(1)],
+            Expr::UnresolvedStar(target) if target.is_empty() && !is_distinct => vec![Expr::int_lit(1)],
             _ => arguments,
         };
     }
-    Ok(Expr::UnresolvedFunction(UnresolvedFunction{name, arguments}))
+    Ok(Expr::UnresolvedFunction(UnresolvedFunction{name, arguments, is_distinct}))
+}
+
+/// 函数体中引用参数的列替换为lambda变量, 内层lambda先解析, 同名参数内层优先
+fn parse_lambda(pair: Pair<Rule>) -> Result<Expr> {
+    let mut pairs: Vec<_> = pair.into_inner().collect();
+    let function = parse_expression(pairs.pop().unwrap())?;
+    let names = pairs.into_iter().map(|pair| parse_identifier(pair).map(|name| name.to_string())).collect::<Result<Vec<_>>>()?;
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            return Err(format!("lambda function arguments should not have names that are semantically the same: {}", names.iter().join(", ")));
+        }
+    }
+    let function = function.transform_up(|expr| match expr {
+        Expr::UnresolvedAttribute(name_parts) if names.iter().any(|name| name.eq_ignore_ascii_case(&name_parts[0])) =>
+            Ok(Transformed::yes(Expr::UnresolvedNamedLambdaVariable(name_parts))),
+        e => Ok(Transformed::no(e)),
+    })?.data;
+    let arguments = names.into_iter().map(|name| Expr::UnresolvedNamedLambdaVariable(vec![name])).collect();
+    Ok(Expr::LambdaFunction(LambdaFunction::new(function, arguments)))
 }
 
 fn parse_cast(pair: Pair<Rule>) -> Result<Expr> {
@@ -744,4 +865,62 @@ mod tests {
         println!("{:#?}", result);
         Ok(())
     }
+
+    #[test]
+    fn test_with() {
+        let schema = parse_schema("id bigint, name string, tags array<string>").unwrap();
+        let sql = r"
+        with a as (
+            select id, name, tag from tbl lateral view explode(tags) t as tag where id > 1
+        ), b as (
+            with c as (select id, tag, length(tag) len from a)
+            select id, tag, len from c where len > 2
+        )
+        select b.id, tag, len from b
+        ";
+        let plan = crate::sql_utils::analyzed_sql_plan(sql, &schema).unwrap();
+        let output: Vec<_> = plan.output().into_iter().map(|a| format!("{}:{}", a.name, a.data_type)).collect();
+        assert_eq!(output, vec!["id:long", "tag:string", "len:int"]);
+
+        // with中的tbl引用输入表, 外层的tbl引用cte
+        let plan = crate::sql_utils::analyzed_sql_plan("with tbl as (select id + 1 id2 from tbl) select id2 from tbl", &schema).unwrap();
+        assert_eq!(plan.output()[0].name, "id2");
+        assert!(crate::sql_utils::analyzed_sql_plan("with a as (select id from b), b as (select id from tbl) select id from a", &schema).is_err());
+        assert!(parse_query("with a as (select 1 x), A as (select 2 x) select x from a").is_err());
+    }
+
+    #[test]
+    fn test_having_distinct() {
+        let schema = parse_schema("id bigint, name string, cnt int").unwrap();
+        let agg = |sql: &str| match crate::sql_utils::sql_plan(sql, &schema).unwrap() {
+            LogicalPlan::Aggregate(agg) => agg,
+            plan => panic!("not aggregate plan: {:?}", plan),
+        };
+
+        // having可以引用select中的别名和select中没有的聚合函数
+        let plan = agg("select name, count(1) having_cnt from tbl group by name having having_cnt > 1 and sum(cnt) > 10");
+        let (_, agg_exprs, _, having, _) = plan.extract_exprs();
+        assert_eq!(agg_exprs.len(), 2);
+        assert!(having.unwrap().resolved());
+        // 没有group by时为全局聚合
+        let plan = agg("select count(1) c from tbl having c > 1");
+        assert!(plan.grouping_exprs.is_empty() && plan.having.is_some());
+
+        let plan = agg("select distinct name, id + 1 id2 from tbl");
+        assert_eq!(plan.grouping_exprs.len(), 2);
+        let output: Vec<_> = LogicalPlan::Aggregate(plan).output().into_iter().map(|a| format!("{}:{}", a.name, a.data_type)).collect();
+        assert_eq!(output, vec!["name:string", "id2:long"]);
+
+        let plan = agg("select name, count(distinct id) c1, count(id) c2, sum(DISTINCT cnt) c3 from tbl group by name");
+        let (_, agg_exprs, _, _, _) = plan.extract_exprs();
+        let sqls: Vec<_> = agg_exprs.iter().map(|e| e.sql()).collect();
+        assert_eq!(sqls, vec!["count(DISTINCT `id`)", "count(`id`)", "sum(DISTINCT `cnt`)"]);
+
+        assert!(crate::sql_utils::sql_plan("select distinct name, count(1) c from tbl group by name", &schema).is_err());
+        assert!(crate::sql_utils::sql_plan("select distinct * from tbl", &schema).is_err());
+        assert!(crate::sql_utils::sql_plan("select name, count(1) c from tbl group by name having name", &schema).is_err());
+        assert!(crate::sql_utils::sql_plan("select name, count(1) c from tbl group by name having id > 1", &schema).is_err());
+        assert!(crate::sql_utils::sql_plan("select substr(distinct name, 1, 2) from tbl", &schema).is_err());
+        assert!(crate::sql_utils::sql_plan("select count(distinct count(id)) from tbl", &schema).is_err());
+    }
 }
\ No newline at end of file
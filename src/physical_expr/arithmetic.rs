@@ -31,6 +31,7 @@ impl PhysicalExpr for UnaryMinus {
             Value::Long(v) => Value::Long(-v),
             Value::Float(v) => Value::Float(-v),
             Value::Double(v) => Value::Double(-v),
+            Value::Decimal(v, scale) => Value::Decimal(-v, scale),
             _ => Value::Null
         }
     }
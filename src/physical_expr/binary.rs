@@ -2,7 +2,8 @@ use std::any::Any;
 use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
 use crate::data::{Row, Value};
-use crate::Operator;
+use crate::{decimal_utils, Operator};
+use crate::analysis::decimal_arithmetic_result_type;
 use crate::physical_expr::{BinaryExpr, PhysicalExpr};
 use crate::types::DataType;
 
@@ -21,12 +22,19 @@ pub struct BinaryArithmetic {
 
 impl BinaryArithmetic {
     pub fn new(left: Box<dyn PhysicalExpr>, op: Operator, right: Box<dyn PhysicalExpr>) -> Self {
-        let data_type = left.data_type();
+        let data_type = arithmetic_result_type(op, left.data_type(), right.data_type());
         let f = get_binary_arithmetic_func(op, data_type);
         Self {left, op, right, f}
     }
 }
 
+fn arithmetic_result_type(op: Operator, left_type: DataType, right_type: DataType) -> DataType {
+    match (left_type, right_type) {
+        (DataType::Decimal(p1, s1), DataType::Decimal(p2, s2)) => decimal_arithmetic_result_type(op, p1, s1, p2, s2).clone(),
+        (left_type, _) => left_type,
+    }
+}
+
 impl Debug for BinaryArithmetic {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BinaryArithmetic")
@@ -58,7 +66,7 @@ impl PhysicalExpr for BinaryArithmetic {
     }
 
     fn data_type(&self) -> DataType {
-        self.left.data_type()
+        arithmetic_result_type(self.op, self.left.data_type(), self.right.data_type())
     }
 
     fn eval(&self, input: &dyn Row) -> Value {
@@ -67,6 +75,9 @@ impl PhysicalExpr for BinaryArithmetic {
 }
 
 fn get_binary_arithmetic_func(op: Operator, data_type: DataType) -> Box<BinaryFunc> {
+    if let DataType::Decimal(precision, scale) = data_type {
+        return get_decimal_arithmetic_func(op, precision, scale);
+    }
     match op {
         Operator::Plus => match data_type {
             DataType::Int => Box::new(binary_int_add),
@@ -120,6 +131,27 @@ fn get_binary_arithmetic_func(op: Operator, data_type: DataType) -> Box<BinaryFu
     }
 }
 
+type DecimalFunc = fn(i128, u8, i128, u8, u8, u8) -> Option<i128>;
+
+/// 计算结果超出decimal(precision, scale)时返回null
+fn get_decimal_arithmetic_func(op: Operator, precision: u8, scale: u8) -> Box<BinaryFunc> {
+    let f: DecimalFunc = match op {
+        Operator::Plus => decimal_utils::add,
+        Operator::Minus => decimal_utils::subtract,
+        Operator::Multiply => decimal_utils::multiply,
+        Operator::Divide => decimal_utils::divide,
+        Operator::Modulo => decimal_utils::remainder,
+        _ => panic!("{:?} not support data type {:?}", op, DataType::Decimal(precision, scale)),
+    };
+    Box::new(move |left, right| match (left, right) {
+        (Value::Decimal(v1, s1), Value::Decimal(v2, s2)) => match f(v1, s1, v2, s2, precision, scale) {
+            Some(v) => Value::Decimal(v, scale),
+            None => Value::Null,
+        },
+        _ => Value::Null,
+    })
+}
+
 fn int_bitwise_and(left: Value, right: Value) -> Value {
     match (left, right) {
         (Value::Int(x), Value::Int(y)) => Value::Int(x & y),
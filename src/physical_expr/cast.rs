@@ -6,6 +6,7 @@ use std::sync::Arc;
 use chrono::{NaiveDateTime};
 use crate::data::{Row, Value};
 use crate::datetime_utils::{format_datetime_fafault, from_timestamp_micros_utc};
+use crate::decimal_utils;
 use crate::physical_expr::{PhysicalExpr};
 use crate::types::DataType;
 
@@ -238,6 +239,53 @@ fn string_to_timestamp(v: Value) -> Value {
     }
 }
 
+fn decimal_to_int(v: Value) -> Value {
+    let (v, scale) = v.get_decimal();
+    match decimal_utils::to_i64(v, scale).and_then(|v| i32::try_from(v).ok()) {
+        Some(v) => Value::Int(v),
+        None => Value::Null,
+    }
+}
+
+fn decimal_to_long(v: Value) -> Value {
+    let (v, scale) = v.get_decimal();
+    match decimal_utils::to_i64(v, scale) {
+        Some(v) => Value::Long(v),
+        None => Value::Null,
+    }
+}
+
+fn decimal_to_float(v: Value) -> Value {
+    let (v, scale) = v.get_decimal();
+    Value::Float(decimal_utils::to_f64(v, scale) as f32)
+}
+
+fn decimal_to_double(v: Value) -> Value {
+    let (v, scale) = v.get_decimal();
+    Value::Double(decimal_utils::to_f64(v, scale))
+}
+
+/// 超出精度时返回null
+fn get_cast_to_decimal_func(from: DataType, precision: u8, scale: u8) -> Box<CastFunc> {
+    let to_decimal = move |v: Option<i128>| match v {
+        Some(v) => Value::Decimal(v, scale),
+        None => Value::Null,
+    };
+    match from {
+        DataType::Int => Box::new(move |v| to_decimal(decimal_utils::from_i64(v.get_int() as i64, precision, scale))),
+        DataType::Long => Box::new(move |v| to_decimal(decimal_utils::from_i64(v.get_long(), precision, scale))),
+        DataType::Float => Box::new(move |v| to_decimal(decimal_utils::from_f64(v.get_float() as f64, precision, scale))),
+        DataType::Double => Box::new(move |v| to_decimal(decimal_utils::from_f64(v.get_double(), precision, scale))),
+        DataType::Boolean => Box::new(move |v| to_decimal(decimal_utils::from_i64(v.get_boolean() as i64, precision, scale))),
+        DataType::String => Box::new(move |v| to_decimal(decimal_utils::parse(v.get_string(), precision, scale))),
+        DataType::Decimal(_, _) => Box::new(move |v| {
+            let (v, from_scale) = v.get_decimal();
+            to_decimal(decimal_utils::change_precision(v, from_scale, precision, scale))
+        }),
+        _ =>  panic!("Cannot cast {from} to {}.", DataType::Decimal(precision, scale))
+    }
+}
+
 pub fn get_cast_func(from: DataType, to: DataType) -> Box<CastFunc> {
     match to {
         dt if dt == from => Box::new(identity),
@@ -253,6 +301,7 @@ pub fn get_cast_func(from: DataType, to: DataType) -> Box<CastFunc> {
             _ =>  Box::new(value_to_string),
         },
         DataType::Int => match from {
+            DataType::Decimal(_, _) => Box::new(decimal_to_int),
             DataType::Long => Box::new(long_to_int),
             DataType::Float => Box::new(float_to_int),
             DataType::Double => Box::new(double_to_int),
@@ -261,6 +310,7 @@ pub fn get_cast_func(from: DataType, to: DataType) -> Box<CastFunc> {
             _ =>  panic!("Cannot cast {from} to {to}.")
         },
         DataType::Long => match from {
+            DataType::Decimal(_, _) => Box::new(decimal_to_long),
             DataType::Int => Box::new(int_to_long),
             DataType::Float => Box::new(float_to_long),
             DataType::Double => Box::new(double_to_long),
@@ -270,6 +320,7 @@ pub fn get_cast_func(from: DataType, to: DataType) -> Box<CastFunc> {
             _ =>  panic!("Cannot cast {from} to {to}.")
         },
         DataType::Float => match from {
+            DataType::Decimal(_, _) => Box::new(decimal_to_float),
             DataType::Int => Box::new(int_to_float),
             DataType::Long => Box::new(long_to_float),
             DataType::Double => Box::new(double_to_float),
@@ -278,6 +329,7 @@ pub fn get_cast_func(from: DataType, to: DataType) -> Box<CastFunc> {
             _ =>  panic!("Cannot cast {from} to {to}.")
         },
         DataType::Double => match from {
+            DataType::Decimal(_, _) => Box::new(decimal_to_double),
             DataType::Int => Box::new(int_to_double),
             DataType::Long => Box::new(long_to_double),
             DataType::Float => Box::new(float_to_double),
@@ -297,6 +349,7 @@ pub fn get_cast_func(from: DataType, to: DataType) -> Box<CastFunc> {
             DataType::String => Box::new(string_to_binary),
             _ =>  panic!("Cannot cast {from} to {to}.")
         },
+        DataType::Decimal(precision, scale) => get_cast_to_decimal_func(from, precision, scale),
         _ =>  panic!("Cannot cast {from} to {to}.")
     }
 }
@@ -309,6 +362,7 @@ pub fn can_cast(from: &DataType, to: &DataType) -> bool {
         (DataType::String, DataType::Binary) => true,
         (DataType::String | DataType::Boolean, to_type) if to_type.is_numeric_type() => true,
         (from_type, to_type) if from_type.is_numeric_type() && to_type.is_numeric_type() => true,
+        (DataType::Decimal(_, _), DataType::Timestamp) => false,
         (from_type, DataType::Timestamp) if from_type.is_numeric_type() || matches!(from_type, DataType::String) => true,
         (DataType::Timestamp, DataType::Long) => true,
         (_, _) => false
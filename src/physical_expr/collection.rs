@@ -1,7 +1,10 @@
 use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::hash::Hash;
-use crate::data::{Row, Value};
-use crate::physical_expr::PhysicalExpr;
+use std::sync::Arc;
+use crate::data::{GenericRow, Row, Value};
+use crate::physical_expr::{merge_sort_by, BinaryExpr, PhysicalExpr, TernaryExpr, UnaryExpr};
 use crate::types::DataType;
 
 #[derive(Debug)]
@@ -38,4 +41,893 @@ impl PhysicalExpr for Concat {
             .collect::<Vec<_>>().concat();
         Value::string(string)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+pub struct MapKeys {
+    child: Box<dyn PhysicalExpr>,
+    data_type: DataType,
+}
+
+impl MapKeys {
+    pub fn new(child: Box<dyn PhysicalExpr>, data_type: DataType) -> MapKeys {
+        MapKeys { child, data_type }
+    }
+}
+
+impl UnaryExpr for MapKeys {
+    fn child(&self) -> &dyn PhysicalExpr {
+        self.child.as_ref()
+    }
+
+    fn null_safe_eval(&self, value: Value) -> Value {
+        Value::Array(Arc::new(value.get_map().iter().map(|(k, _)| k.clone()).collect()))
+    }
+}
+
+impl PhysicalExpr for MapKeys {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        UnaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct MapValues {
+    child: Box<dyn PhysicalExpr>,
+    data_type: DataType,
+}
+
+impl MapValues {
+    pub fn new(child: Box<dyn PhysicalExpr>, data_type: DataType) -> MapValues {
+        MapValues { child, data_type }
+    }
+}
+
+impl UnaryExpr for MapValues {
+    fn child(&self) -> &dyn PhysicalExpr {
+        self.child.as_ref()
+    }
+
+    fn null_safe_eval(&self, value: Value) -> Value {
+        Value::Array(Arc::new(value.get_map().iter().map(|(_, v)| v.clone()).collect()))
+    }
+}
+
+impl PhysicalExpr for MapValues {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        UnaryExpr::eval(self, input)
+    }
+}
+
+/// 按顺序插入entry, 重复的key覆盖之前的value
+fn put_map_entry(entries: &mut Vec<(Value, Value)>, key: Value, value: Value) {
+    match entries.iter_mut().find(|(k, _)| *k == key) {
+        Some(entry) => entry.1 = value,
+        None => entries.push((key, value)),
+    }
+}
+
+#[derive(Debug)]
+pub struct MapFromArrays {
+    keys: Box<dyn PhysicalExpr>,
+    values: Box<dyn PhysicalExpr>,
+    data_type: DataType,
+}
+
+impl MapFromArrays {
+    pub fn new(keys: Box<dyn PhysicalExpr>, values: Box<dyn PhysicalExpr>, data_type: DataType) -> MapFromArrays {
+        MapFromArrays { keys, values, data_type }
+    }
+}
+
+impl BinaryExpr for MapFromArrays {
+    fn left(&self) -> &dyn PhysicalExpr {
+        self.keys.as_ref()
+    }
+
+    fn right(&self) -> &dyn PhysicalExpr {
+        self.values.as_ref()
+    }
+
+    /// 数组长度不同或者key为null时返回null
+    fn null_safe_eval(&self, keys: Value, values: Value) -> Value {
+        let (keys, values) = (keys.get_array(), values.get_array());
+        if keys.len() != values.len() || keys.iter().any(|k| k.is_null()) {
+            return Value::Null;
+        }
+        let mut entries = Vec::with_capacity(keys.len());
+        for (k, v) in keys.iter().zip(values.iter()) {
+            put_map_entry(&mut entries, k.clone(), v.clone());
+        }
+        Value::Map(Arc::new(entries))
+    }
+}
+
+impl PhysicalExpr for MapFromArrays {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        BinaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct StringToMap {
+    text: Box<dyn PhysicalExpr>,
+    pair_delim: Box<dyn PhysicalExpr>,
+    key_value_delim: Box<dyn PhysicalExpr>,
+}
+
+impl StringToMap {
+    pub fn new(text: Box<dyn PhysicalExpr>, pair_delim: Box<dyn PhysicalExpr>, key_value_delim: Box<dyn PhysicalExpr>) -> StringToMap {
+        StringToMap { text, pair_delim, key_value_delim }
+    }
+}
+
+impl TernaryExpr for StringToMap {
+    fn child1(&self) -> &dyn PhysicalExpr {
+        self.text.as_ref()
+    }
+
+    fn child2(&self) -> &dyn PhysicalExpr {
+        self.pair_delim.as_ref()
+    }
+
+    fn child3(&self) -> &dyn PhysicalExpr {
+        self.key_value_delim.as_ref()
+    }
+
+    /// 没有key_value_delim的pair, value为null
+    fn null_safe_eval(&self, text: Value, pair_delim: Value, key_value_delim: Value) -> Value {
+        let (text, pair_delim, key_value_delim) = (text.get_string(), pair_delim.get_string(), key_value_delim.get_string());
+        let mut entries = Vec::new();
+        for pair in text.split(pair_delim) {
+            let (k, v) = match pair.split_once(key_value_delim) {
+                Some((k, v)) => (k, Value::string(v)),
+                None => (pair, Value::Null),
+            };
+            put_map_entry(&mut entries, Value::string(k), v);
+        }
+        Value::Map(Arc::new(entries))
+    }
+}
+
+impl PhysicalExpr for StringToMap {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        DataType::string_map_type().clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        TernaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateArray {
+    children: Vec<Box<dyn PhysicalExpr>>,
+    data_type: DataType,
+}
+
+impl CreateArray {
+    pub fn new(children: Vec<Box<dyn PhysicalExpr>>, data_type: DataType) -> CreateArray {
+        CreateArray { children, data_type }
+    }
+}
+
+impl PhysicalExpr for CreateArray {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        Value::Array(Arc::new(self.children.iter().map(|child| child.eval(input)).collect()))
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateNamedStruct {
+    values: Vec<Box<dyn PhysicalExpr>>,
+    data_type: DataType,
+}
+
+impl CreateNamedStruct {
+    pub fn new(values: Vec<Box<dyn PhysicalExpr>>, data_type: DataType) -> CreateNamedStruct {
+        CreateNamedStruct { values, data_type }
+    }
+}
+
+impl PhysicalExpr for CreateNamedStruct {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        Value::Struct(Arc::new(GenericRow::new(self.values.iter().map(|value| value.eval(input)).collect())))
+    }
+}
+
+#[derive(Debug)]
+pub struct Size {
+    child: Box<dyn PhysicalExpr>,
+}
+
+impl Size {
+    pub fn new(child: Box<dyn PhysicalExpr>) -> Size {
+        Size { child }
+    }
+}
+
+impl PhysicalExpr for Size {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        DataType::Int
+    }
+
+    /// null返回-1
+    fn eval(&self, input: &dyn Row) -> Value {
+        match self.child.eval(input) {
+            Value::Array(array) => Value::Int(array.len() as i32),
+            Value::Map(map) => Value::Int(map.len() as i32),
+            _ => Value::Int(-1),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayContains {
+    array: Box<dyn PhysicalExpr>,
+    value: Box<dyn PhysicalExpr>,
+}
+
+impl ArrayContains {
+    pub fn new(array: Box<dyn PhysicalExpr>, value: Box<dyn PhysicalExpr>) -> ArrayContains {
+        ArrayContains { array, value }
+    }
+}
+
+impl BinaryExpr for ArrayContains {
+    fn left(&self) -> &dyn PhysicalExpr {
+        self.array.as_ref()
+    }
+
+    fn right(&self) -> &dyn PhysicalExpr {
+        self.value.as_ref()
+    }
+
+    /// 没有找到且数组中有null时返回null
+    fn null_safe_eval(&self, array: Value, value: Value) -> Value {
+        let array = array.get_array();
+        if array.contains(&value) {
+            Value::Boolean(true)
+        } else if array.iter().any(|v| v.is_null()) {
+            Value::Null
+        } else {
+            Value::Boolean(false)
+        }
+    }
+}
+
+impl PhysicalExpr for ArrayContains {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        DataType::Boolean
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        BinaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayPosition {
+    array: Box<dyn PhysicalExpr>,
+    value: Box<dyn PhysicalExpr>,
+}
+
+impl ArrayPosition {
+    pub fn new(array: Box<dyn PhysicalExpr>, value: Box<dyn PhysicalExpr>) -> ArrayPosition {
+        ArrayPosition { array, value }
+    }
+}
+
+impl BinaryExpr for ArrayPosition {
+    fn left(&self) -> &dyn PhysicalExpr {
+        self.array.as_ref()
+    }
+
+    fn right(&self) -> &dyn PhysicalExpr {
+        self.value.as_ref()
+    }
+
+    fn null_safe_eval(&self, array: Value, value: Value) -> Value {
+        let position = array.get_array().iter().position(|v| *v == value).map(|i| i + 1).unwrap_or(0);
+        Value::Long(position as i64)
+    }
+}
+
+impl PhysicalExpr for ArrayPosition {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        DataType::Long
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        BinaryExpr::eval(self, input)
+    }
+}
+
+/// 按顺序去重, 保留第一次出现的元素
+fn distinct_values<'a>(values: impl Iterator<Item = &'a Value>) -> Vec<Value> {
+    let mut seen = HashSet::new();
+    values.filter(|v| seen.insert(*v)).cloned().collect()
+}
+
+#[derive(Debug)]
+pub struct ArrayDistinct {
+    child: Box<dyn PhysicalExpr>,
+}
+
+impl ArrayDistinct {
+    pub fn new(child: Box<dyn PhysicalExpr>) -> ArrayDistinct {
+        ArrayDistinct { child }
+    }
+}
+
+impl UnaryExpr for ArrayDistinct {
+    fn child(&self) -> &dyn PhysicalExpr {
+        self.child.as_ref()
+    }
+
+    fn null_safe_eval(&self, value: Value) -> Value {
+        Value::Array(Arc::new(distinct_values(value.get_array().iter())))
+    }
+}
+
+impl PhysicalExpr for ArrayDistinct {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.child.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        UnaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SetOperation {
+    Union,
+    Intersect,
+    Except,
+}
+
+#[derive(Debug)]
+pub struct ArraySetOperation {
+    left: Box<dyn PhysicalExpr>,
+    right: Box<dyn PhysicalExpr>,
+    op: SetOperation,
+}
+
+impl ArraySetOperation {
+    pub fn new(left: Box<dyn PhysicalExpr>, right: Box<dyn PhysicalExpr>, op: SetOperation) -> ArraySetOperation {
+        ArraySetOperation { left, right, op }
+    }
+}
+
+impl BinaryExpr for ArraySetOperation {
+    fn left(&self) -> &dyn PhysicalExpr {
+        self.left.as_ref()
+    }
+
+    fn right(&self) -> &dyn PhysicalExpr {
+        self.right.as_ref()
+    }
+
+    /// 结果去重, 按元素在left中出现的顺序, union时再接上right中的元素
+    fn null_safe_eval(&self, left: Value, right: Value) -> Value {
+        let (left, right) = (left.get_array(), right.get_array());
+        let values = match self.op {
+            SetOperation::Union => distinct_values(left.iter().chain(right.iter())),
+            SetOperation::Intersect => {
+                let right: HashSet<&Value> = right.iter().collect();
+                distinct_values(left.iter().filter(|v| right.contains(v)))
+            },
+            SetOperation::Except => {
+                let right: HashSet<&Value> = right.iter().collect();
+                distinct_values(left.iter().filter(|v| !right.contains(v)))
+            },
+        };
+        Value::Array(Arc::new(values))
+    }
+}
+
+impl PhysicalExpr for ArraySetOperation {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.left.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        BinaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayJoin {
+    array: Box<dyn PhysicalExpr>,
+    delimiter: Box<dyn PhysicalExpr>,
+    null_replacement: Option<Box<dyn PhysicalExpr>>,
+}
+
+impl ArrayJoin {
+    pub fn new(array: Box<dyn PhysicalExpr>, delimiter: Box<dyn PhysicalExpr>, null_replacement: Option<Box<dyn PhysicalExpr>>) -> ArrayJoin {
+        ArrayJoin { array, delimiter, null_replacement }
+    }
+}
+
+impl PhysicalExpr for ArrayJoin {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        DataType::String
+    }
+
+    /// 没有null_replacement时跳过null元素
+    fn eval(&self, input: &dyn Row) -> Value {
+        let array = self.array.eval(input);
+        if array.is_null() {
+            return Value::Null;
+        }
+        let delimiter = self.delimiter.eval(input);
+        if delimiter.is_null() {
+            return Value::Null;
+        }
+        let null_replacement = match &self.null_replacement {
+            Some(null_replacement) => match null_replacement.eval(input) {
+                Value::Null => return Value::Null,
+                v => Some(v),
+            },
+            None => None,
+        };
+        let array = array.get_array();
+        let strings: Vec<&str> = array.iter().filter_map(|v| match v {
+            Value::Null => null_replacement.as_ref().map(|r| r.get_string()),
+            v => Some(v.get_string()),
+        }).collect();
+        Value::string(strings.join(delimiter.get_string()))
+    }
+}
+
+#[derive(Debug)]
+pub struct Slice {
+    array: Box<dyn PhysicalExpr>,
+    start: Box<dyn PhysicalExpr>,
+    length: Box<dyn PhysicalExpr>,
+}
+
+impl Slice {
+    pub fn new(array: Box<dyn PhysicalExpr>, start: Box<dyn PhysicalExpr>, length: Box<dyn PhysicalExpr>) -> Slice {
+        Slice { array, start, length }
+    }
+}
+
+impl TernaryExpr for Slice {
+    fn child1(&self) -> &dyn PhysicalExpr {
+        self.array.as_ref()
+    }
+
+    fn child2(&self) -> &dyn PhysicalExpr {
+        self.start.as_ref()
+    }
+
+    fn child3(&self) -> &dyn PhysicalExpr {
+        self.length.as_ref()
+    }
+
+    /// start为0或者length小于0时返回null
+    fn null_safe_eval(&self, array: Value, start: Value, length: Value) -> Value {
+        let (array, start, length) = (array.get_array(), start.get_int() as i64, length.get_int());
+        if start == 0 || length < 0 {
+            return Value::Null;
+        }
+        let start = if start > 0 { start - 1 } else { array.len() as i64 + start };
+        if start < 0 || start >= array.len() as i64 {
+            return Value::Array(Arc::new(Vec::new()));
+        }
+        Value::Array(Arc::new(array.iter().skip(start as usize).take(length as usize).cloned().collect()))
+    }
+}
+
+impl PhysicalExpr for Slice {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.array.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        TernaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct SortArray {
+    child: Box<dyn PhysicalExpr>,
+    ascending: bool,
+}
+
+impl SortArray {
+    pub fn new(child: Box<dyn PhysicalExpr>, ascending: bool) -> SortArray {
+        SortArray { child, ascending }
+    }
+}
+
+impl UnaryExpr for SortArray {
+    fn child(&self) -> &dyn PhysicalExpr {
+        self.child.as_ref()
+    }
+
+    /// null当作最小值
+    fn null_safe_eval(&self, value: Value) -> Value {
+        let sorted = merge_sort_by(value.get_array().as_ref(), &mut |a, b| {
+            let ordering = match (a.is_null(), b.is_null()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            };
+            if self.ascending { ordering } else { ordering.reverse() }
+        });
+        Value::Array(Arc::new(sorted))
+    }
+}
+
+impl PhysicalExpr for SortArray {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.child.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        UnaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct Flatten {
+    child: Box<dyn PhysicalExpr>,
+}
+
+impl Flatten {
+    pub fn new(child: Box<dyn PhysicalExpr>) -> Flatten {
+        Flatten { child }
+    }
+}
+
+impl UnaryExpr for Flatten {
+    fn child(&self) -> &dyn PhysicalExpr {
+        self.child.as_ref()
+    }
+
+    /// 有null子数组时返回null
+    fn null_safe_eval(&self, value: Value) -> Value {
+        let mut values = Vec::new();
+        for array in value.get_array().iter() {
+            if array.is_null() {
+                return Value::Null;
+            }
+            values.extend(array.get_array().iter().cloned());
+        }
+        Value::Array(Arc::new(values))
+    }
+}
+
+impl PhysicalExpr for Flatten {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        match self.child.data_type() {
+            DataType::Array(element_type) => *element_type,
+            data_type => data_type,
+        }
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        UnaryExpr::eval(self, input)
+    }
+}
+
+#[derive(Debug)]
+pub struct ArraysZip {
+    children: Vec<Box<dyn PhysicalExpr>>,
+    data_type: DataType,
+}
+
+impl ArraysZip {
+    pub fn new(children: Vec<Box<dyn PhysicalExpr>>, data_type: DataType) -> ArraysZip {
+        ArraysZip { children, data_type }
+    }
+}
+
+impl PhysicalExpr for ArraysZip {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    /// 长度为最长的数组, 短的数组补null, 有null数组时返回null
+    fn eval(&self, input: &dyn Row) -> Value {
+        let mut arrays = Vec::with_capacity(self.children.len());
+        for child in self.children.iter() {
+            match child.eval(input) {
+                Value::Null => return Value::Null,
+                v => arrays.push(v.get_array()),
+            }
+        }
+        let len = arrays.iter().map(|array| array.len()).max().unwrap_or(0);
+        let values = (0..len).map(|i| {
+            let fields = arrays.iter().map(|array| array.get(i).cloned().unwrap_or(Value::Null)).collect();
+            Value::Struct(Arc::new(GenericRow::new(fields)))
+        }).collect();
+        Value::Array(Arc::new(values))
+    }
+}
+
+/// sequence生成的最大元素个数
+const MAX_SEQUENCE_LENGTH: i64 = 1_000_000;
+
+#[derive(Debug)]
+pub struct Sequence {
+    start: Box<dyn PhysicalExpr>,
+    stop: Box<dyn PhysicalExpr>,
+    step: Option<Box<dyn PhysicalExpr>>,
+    data_type: DataType,
+}
+
+impl Sequence {
+    pub fn new(start: Box<dyn PhysicalExpr>, stop: Box<dyn PhysicalExpr>, step: Option<Box<dyn PhysicalExpr>>, data_type: DataType) -> Sequence {
+        Sequence { start, stop, step, data_type }
+    }
+
+    fn get_long(value: &Value) -> i64 {
+        match value {
+            Value::Int(v) => *v as i64,
+            v => v.get_long(),
+        }
+    }
+}
+
+impl PhysicalExpr for Sequence {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    /// step为0, 方向和start到stop相反或者元素个数超过上限时返回null
+    fn eval(&self, input: &dyn Row) -> Value {
+        let start = self.start.eval(input);
+        let stop = self.stop.eval(input);
+        if start.is_null() || stop.is_null() {
+            return Value::Null;
+        }
+        let (start, stop) = (Self::get_long(&start), Self::get_long(&stop));
+        let step = match &self.step {
+            Some(step) => match step.eval(input) {
+                Value::Null => return Value::Null,
+                v => Self::get_long(&v),
+            },
+            None => if start <= stop { 1 } else { -1 },
+        };
+        // 用i128计算, 避免极值边界溢出
+        let (start, stop, step) = (start as i128, stop as i128, step as i128);
+        if step == 0 && start != stop || (stop - start).signum() * step.signum() < 0 {
+            return Value::Null;
+        }
+        let len = if start == stop { 1 } else { (stop - start) / step + 1 };
+        if len > MAX_SEQUENCE_LENGTH as i128 {
+            return Value::Null;
+        }
+        let is_int = matches!(self.data_type, DataType::Array(ref tp) if tp.as_ref() == DataType::int_type());
+        let values = (0..len).map(|i| {
+            let v = (start + i * step) as i64;
+            if is_int { Value::Int(v as i32) } else { Value::Long(v) }
+        }).collect();
+        Value::Array(Arc::new(values))
+    }
+}
+
+#[derive(Debug)]
+pub struct ElementAt {
+    array: Box<dyn PhysicalExpr>,
+    index: Box<dyn PhysicalExpr>,
+    data_type: DataType,
+}
+
+impl ElementAt {
+    pub fn new(array: Box<dyn PhysicalExpr>, index: Box<dyn PhysicalExpr>, data_type: DataType) -> ElementAt {
+        ElementAt { array, index, data_type }
+    }
+}
+
+impl BinaryExpr for ElementAt {
+    fn left(&self) -> &dyn PhysicalExpr {
+        self.array.as_ref()
+    }
+
+    fn right(&self) -> &dyn PhysicalExpr {
+        self.index.as_ref()
+    }
+
+    /// 下标从1开始, 负数从末尾开始, 为0或越界时返回null
+    fn null_safe_eval(&self, array: Value, index: Value) -> Value {
+        let (array, index) = (array.get_array(), index.get_int() as i64);
+        let i = if index > 0 { index - 1 } else { array.len() as i64 + index };
+        if index == 0 || i < 0 || i >= array.len() as i64 {
+            return Value::Null;
+        }
+        array[i as usize].clone()
+    }
+}
+
+impl PhysicalExpr for ElementAt {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        BinaryExpr::eval(self, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::BoundReference;
+    use crate::parser::parse_schema;
+    use crate::physical_expr::create_physical_expr;
+    use crate::sql_utils;
+    use super::*;
+
+    fn eval(sql: &str, row: &GenericRow) -> (String, String) {
+        eval_with_schema("m map<string, bigint>, s string", sql, row)
+    }
+
+    fn eval_with_schema(schema: &str, sql: &str, row: &GenericRow) -> (String, String) {
+        let schema = parse_schema(schema).unwrap();
+        let expression = sql_utils::parse_expr(sql, &schema).unwrap();
+        let expr = BoundReference::bind_reference(expression.expr, expression.child.output()).unwrap();
+        let expr = create_physical_expr(&expr).unwrap();
+        (expr.data_type().to_string(), expr.eval(row).to_string())
+    }
+
+    #[test]
+    fn test_map_functions() {
+        let map = Value::Map(Arc::new(vec![(Value::string("a"), Value::long(1)), (Value::string("b"), Value::Null)]));
+        let row = GenericRow::new(vec![map, Value::string("x:1,y,x:3")]);
+        assert_eq!(eval("m['a']", &row), ("long".to_string(), "1".to_string()));
+        assert_eq!(eval("m['c']", &row).1, "null");
+        assert_eq!(eval("map_keys(m)", &row), ("array<string>".to_string(), "['a', 'b']".to_string()));
+        assert_eq!(eval("map_values(m)", &row).1, "[1, null]");
+        assert_eq!(eval("str_to_map(s)", &row), ("map<string,string>".to_string(), "{'x': '3', 'y': null}".to_string()));
+        assert_eq!(eval("str_to_map(s, ',', 'x')['']", &row).1, "':3'");
+        assert_eq!(eval("map_from_arrays(split(s, ','), map_keys(m))", &row).1, "null");
+        assert_eq!(eval("map_from_arrays(map_keys(m), map_values(m))['b']", &row).1, "null");
+        assert_eq!(eval("map_from_arrays(split('1,2', ','), split('a,b', ','))['2']", &row).1, "'b'");
+    }
+
+    #[test]
+    fn test_array_functions() {
+        let eval = |sql: &str, row: &GenericRow| eval_with_schema("a array<bigint>, b array<bigint>, s array<string>, n int", sql, row);
+        let longs = |values: Vec<Option<i64>>| Value::Array(Arc::new(values.into_iter().map(|v| v.map(Value::long).unwrap_or(Value::Null)).collect()));
+        let row = GenericRow::new(vec![
+            longs(vec![Some(3), Some(1), None, Some(3), Some(2)]),
+            longs(vec![Some(2), Some(4)]),
+            Value::Array(Arc::new(vec![Value::string("x"), Value::Null, Value::string("y")])),
+            Value::Null,
+        ]);
+        assert_eq!(eval("array(1, n, 2.5)", &row), ("array<double>".to_string(), "[1, null, 2.5]".to_string()));
+        assert_eq!(eval("array()", &row).0, "array<null>");
+        assert_eq!(eval("named_struct('x', 1, 'y', s[0])", &row), ("struct<x: int,y: string>".to_string(), "[1, 'x']".to_string()));
+        assert_eq!(eval("struct(n, a[0] + 1)", &row).0, "struct<n: int,col2: long>");
+        assert_eq!(eval("size(a)", &row), ("int".to_string(), "5".to_string()));
+        assert_eq!(eval("cardinality(cast(null as array<int>))", &row).1, "-1");
+        assert_eq!(eval("array_contains(a, 2)", &row).1, "true");
+        assert_eq!(eval("array_contains(a, 5)", &row).1, "null");
+        assert_eq!(eval("array_contains(b, 5)", &row).1, "false");
+        assert_eq!(eval("array_position(a, 3)", &row), ("long".to_string(), "1".to_string()));
+        assert_eq!(eval("array_position(a, 5)", &row).1, "0");
+        assert_eq!(eval("array_distinct(a)", &row).1, "[3, 1, null, 2]");
+        assert_eq!(eval("array_union(a, b)", &row).1, "[3, 1, null, 2, 4]");
+        assert_eq!(eval("array_intersect(a, b)", &row).1, "[2]");
+        assert_eq!(eval("array_except(a, b)", &row).1, "[3, 1, null]");
+        assert_eq!(eval("array_join(s, ',')", &row), ("string".to_string(), "'x,y'".to_string()));
+        assert_eq!(eval("array_join(s, ',', '-')", &row).1, "'x,-,y'");
+        assert_eq!(eval("slice(a, 2, 2)", &row).1, "[1, null]");
+        assert_eq!(eval("slice(a, -2, 5)", &row).1, "[3, 2]");
+        assert_eq!(eval("slice(a, 0, 1)", &row).1, "null");
+        assert_eq!(eval("sort_array(a)", &row).1, "[null, 1, 2, 3, 3]");
+        assert_eq!(eval("sort_array(a, false)", &row).1, "[3, 3, 2, 1, null]");
+        assert_eq!(eval("flatten(array(b, b))", &row), ("array<long>".to_string(), "[2, 4, 2, 4]".to_string()));
+        assert_eq!(eval("flatten(array(b, null))", &row).1, "null");
+        assert_eq!(eval("arrays_zip(b, s)", &row), ("array<struct<b: long,s: string>>".to_string(),
+            "[[2, 'x'], [4, null], [null, 'y']]".to_string()));
+        assert_eq!(eval("sequence(1, 5, 2)", &row), ("array<int>".to_string(), "[1, 3, 5]".to_string()));
+        assert_eq!(eval("sequence(3, cast(1 as bigint))", &row), ("array<long>".to_string(), "[3, 2, 1]".to_string()));
+        assert_eq!(eval("sequence(1, 5, -1)", &row).1, "null");
+        assert_eq!(eval("sequence(-9223372036854775807 - 1, 9223372036854775807)", &row).1, "null");
+        assert_eq!(eval("sequence(-9223372036854775807 - 1, 9223372036854775807, 9223372036854775807)", &row).1,
+            "[-9223372036854775808, -1, 9223372036854775806]");
+        assert_eq!(eval("element_at(a, 1)", &row), ("long".to_string(), "3".to_string()));
+        assert_eq!(eval("element_at(a, -1)", &row).1, "2");
+        assert_eq!(eval("element_at(a, 6)", &row).1, "null");
+        assert_eq!(eval("element_at(map_from_arrays(s, b), 'x')", &row).1, "null");
+        assert_eq!(eval("element_at(str_to_map('k:v'), 'k')", &row).1, "'v'");
+    }
+}
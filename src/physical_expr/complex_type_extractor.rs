@@ -80,3 +80,44 @@ impl PhysicalExpr for GetArrayItem {
         BinaryExpr::eval(self, input)
     }
 }
+
+#[derive(Debug)]
+pub struct GetMapValue {
+    child: Box<dyn PhysicalExpr>,
+    key: Box<dyn PhysicalExpr>,
+    value_type: DataType,
+}
+
+impl GetMapValue {
+    pub fn new(child: Box<dyn PhysicalExpr>, key: Box<dyn PhysicalExpr>, value_type: DataType) -> GetMapValue {
+        GetMapValue { child, key, value_type }
+    }
+}
+
+impl BinaryExpr for GetMapValue {
+    fn left(&self) -> &dyn PhysicalExpr {
+        self.child.as_ref()
+    }
+
+    fn right(&self) -> &dyn PhysicalExpr {
+        self.key.as_ref()
+    }
+
+    fn null_safe_eval(&self, child: Value, key: Value) -> Value {
+        child.map_get(&key).cloned().unwrap_or(Value::Null)
+    }
+}
+
+impl PhysicalExpr for GetMapValue {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.value_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        BinaryExpr::eval(self, input)
+    }
+}
@@ -44,6 +44,43 @@ impl PhysicalGenerator for Explode {
     }
 }
 
+/// map的每个entry输出一行(key, value)
+#[derive(Debug)]
+pub struct ExplodeMap {
+    pub child: Box<dyn PhysicalExpr>,
+    pub rows: Vec<GenericRow>,
+}
+
+impl ExplodeMap {
+    pub fn new(child: Box<dyn PhysicalExpr>) -> Self {
+        let rows = Vec::new();
+        ExplodeMap { child, rows}
+    }
+}
+
+impl PhysicalGenerator for ExplodeMap {
+    fn generate(&mut self, input: &dyn Row) -> &[GenericRow]{
+        let value = self.child.eval(input);
+        if value.is_null() {
+            return &self.rows[..0];
+        }
+        let map = value.get_map();
+        if self.rows.len() > 100 && map.len() <= 100 {
+            self.rows.truncate(100);
+        }
+        for _ in self.rows.len()..map.len() {
+            self.rows.push(GenericRow::new_with_size(2));
+        }
+
+        for (i, (k, v)) in map.iter().enumerate() {
+            self.rows[i].update(0, k.clone());
+            self.rows[i].update(1, v.clone());
+        }
+
+        &self.rows[ ..map.len()]
+    }
+}
+
 #[derive(Debug)]
 pub struct PathFileUnroll {
     pub path: Box<dyn PhysicalExpr>,
@@ -114,3 +151,33 @@ impl PhysicalGenerator for PathFileUnroll {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::physical_expr::BoundReference;
+    use crate::types::DataType;
+    use super::*;
+
+    #[test]
+    fn test_explode_map() {
+        let data_type = DataType::Map(Box::new(DataType::String), Box::new(DataType::Long));
+        let mut generator = ExplodeMap::new(Box::new(BoundReference::new(0, data_type)));
+        let row = GenericRow::new(vec![Value::Map(Arc::new(vec![
+            (Value::string("a"), Value::long(1)),
+            (Value::string("b"), Value::Null),
+        ]))]);
+        let rows = generator.generate(&row);
+        assert_eq!(rows, &[
+            GenericRow::new(vec![Value::string("a"), Value::long(1)]),
+            GenericRow::new(vec![Value::string("b"), Value::Null]),
+        ]);
+
+        // 复用的行不能残留上一次的数据
+        let row = GenericRow::new(vec![Value::Map(Arc::new(vec![(Value::string("c"), Value::long(3))]))]);
+        assert_eq!(generator.generate(&row), &[GenericRow::new(vec![Value::string("c"), Value::long(3)])]);
+
+        assert!(generator.generate(&GenericRow::new(vec![Value::Null])).is_empty());
+        assert!(generator.generate(&GenericRow::new(vec![Value::Map(Arc::new(vec![]))])).is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::Result;
+use crate::data::{Row, Value};
+use crate::physical_expr::PhysicalExpr;
+use crate::types::DataType;
+
+thread_local! {
+    /// 创建lambda函数体时可见的lambda变量: expr_id -> 变量值
+    static LAMBDA_VARIABLES: RefCell<HashMap<u32, (Rc<RefCell<Value>>, DataType)>> = RefCell::new(HashMap::new());
+}
+
+/// lambda变量, 值由所属的LambdaFunction在调用函数体前设置
+#[derive(Debug)]
+pub struct NamedLambdaVariable {
+    value: Rc<RefCell<Value>>,
+    data_type: DataType,
+}
+
+impl NamedLambdaVariable {
+    /// 只能在LambdaFunction::new创建函数体时调用
+    pub fn bind(expr_id: u32) -> Result<Self> {
+        LAMBDA_VARIABLES.with_borrow(|variables| match variables.get(&expr_id) {
+            Some((value, data_type)) => Ok(Self { value: value.clone(), data_type: data_type.clone() }),
+            None => Err(format!("lambda variable {} is not bound", expr_id)),
+        })
+    }
+}
+
+impl PhysicalExpr for NamedLambdaVariable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, _input: &dyn Row) -> Value {
+        self.value.borrow().clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct LambdaFunction {
+    function: Box<dyn PhysicalExpr>,
+    variables: Vec<Rc<RefCell<Value>>>,
+}
+
+impl LambdaFunction {
+    pub fn new(variables: Vec<(u32, DataType)>, create_function: impl FnOnce() -> Result<Box<dyn PhysicalExpr>>) -> Result<Self> {
+        let values: Vec<_> = variables.iter().map(|_| Rc::new(RefCell::new(Value::Null))).collect();
+        LAMBDA_VARIABLES.with_borrow_mut(|map| {
+            for ((expr_id, data_type), value) in variables.iter().zip(values.iter()) {
+                map.insert(*expr_id, (value.clone(), data_type.clone()));
+            }
+        });
+        let function = create_function();
+        LAMBDA_VARIABLES.with_borrow_mut(|map| {
+            for (expr_id, _) in variables.iter() {
+                map.remove(expr_id);
+            }
+        });
+        Ok(Self { function: function?, variables: values })
+    }
+
+    pub fn num_arguments(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn invoke(&self, input: &dyn Row, args: Vec<Value>) -> Value {
+        for (variable, arg) in self.variables.iter().zip(args) {
+            *variable.borrow_mut() = arg;
+        }
+        self.function.eval(input)
+    }
+}
+
+impl PhysicalExpr for LambdaFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.function.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        self.function.eval(input)
+    }
+}
+
+/// lambda参数为(元素)或(元素, 下标)
+fn element_args(function: &LambdaFunction, element: &Value, i: usize) -> Vec<Value> {
+    if function.num_arguments() == 1 {
+        vec![element.clone()]
+    } else {
+        vec![element.clone(), Value::Int(i as i32)]
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayTransform {
+    argument: Box<dyn PhysicalExpr>,
+    function: LambdaFunction,
+    data_type: DataType,
+}
+
+impl ArrayTransform {
+    pub fn new(argument: Box<dyn PhysicalExpr>, function: LambdaFunction, data_type: DataType) -> Self {
+        Self { argument, function, data_type }
+    }
+}
+
+impl PhysicalExpr for ArrayTransform {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        let value = self.argument.eval(input);
+        if value.is_null() {
+            return Value::Null;
+        }
+        let array = value.get_array();
+        let result = array.iter().enumerate().map(|(i, element)| self.function.invoke(input, element_args(&self.function, element, i))).collect();
+        Value::Array(Arc::new(result))
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayFilter {
+    argument: Box<dyn PhysicalExpr>,
+    function: LambdaFunction,
+}
+
+impl ArrayFilter {
+    pub fn new(argument: Box<dyn PhysicalExpr>, function: LambdaFunction) -> Self {
+        Self { argument, function }
+    }
+}
+
+impl PhysicalExpr for ArrayFilter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.argument.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        let value = self.argument.eval(input);
+        if value.is_null() {
+            return Value::Null;
+        }
+        let array = value.get_array();
+        let result = array.iter().enumerate().filter(|(i, element)| {
+            let keep = self.function.invoke(input, element_args(&self.function, element, *i));
+            !keep.is_null() && keep.get_boolean()
+        }).map(|(_, element)| element.clone()).collect();
+        Value::Array(Arc::new(result))
+    }
+}
+
+/// exists: 有true返回true, 否则有null返回null; forall: 有false返回false, 否则有null返回null
+#[derive(Debug)]
+pub struct ArrayExists {
+    argument: Box<dyn PhysicalExpr>,
+    function: LambdaFunction,
+    for_all: bool,
+}
+
+impl ArrayExists {
+    pub fn new(argument: Box<dyn PhysicalExpr>, function: LambdaFunction, for_all: bool) -> Self {
+        Self { argument, function, for_all }
+    }
+}
+
+impl PhysicalExpr for ArrayExists {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        DataType::Boolean
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        let value = self.argument.eval(input);
+        if value.is_null() {
+            return Value::Null;
+        }
+        let mut found_null = false;
+        for element in value.get_array().iter() {
+            let ret = self.function.invoke(input, vec![element.clone()]);
+            if ret.is_null() {
+                found_null = true;
+            } else if ret.get_boolean() != self.for_all {
+                return Value::Boolean(!self.for_all);
+            }
+        }
+        if found_null {
+            Value::Null
+        } else {
+            Value::Boolean(self.for_all)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayAggregate {
+    argument: Box<dyn PhysicalExpr>,
+    zero: Box<dyn PhysicalExpr>,
+    merge: LambdaFunction,
+    finish: Option<LambdaFunction>,
+    data_type: DataType,
+}
+
+impl ArrayAggregate {
+    pub fn new(argument: Box<dyn PhysicalExpr>, zero: Box<dyn PhysicalExpr>, merge: LambdaFunction, finish: Option<LambdaFunction>, data_type: DataType) -> Self {
+        Self { argument, zero, merge, finish, data_type }
+    }
+}
+
+impl PhysicalExpr for ArrayAggregate {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        let value = self.argument.eval(input);
+        if value.is_null() {
+            return Value::Null;
+        }
+        let mut acc = self.zero.eval(input);
+        for element in value.get_array().iter() {
+            acc = self.merge.invoke(input, vec![acc, element.clone()]);
+        }
+        match &self.finish {
+            Some(finish) => finish.invoke(input, vec![acc]),
+            None => acc,
+        }
+    }
+}
+
+/// 没有比较函数时升序排序, null排在最后; 比较函数返回负数/0/正数, 返回null时视为相等
+#[derive(Debug)]
+pub struct ArraySort {
+    argument: Box<dyn PhysicalExpr>,
+    comparator: Option<LambdaFunction>,
+}
+
+impl ArraySort {
+    pub fn new(argument: Box<dyn PhysicalExpr>, comparator: Option<LambdaFunction>) -> Self {
+        Self { argument, comparator }
+    }
+}
+
+impl PhysicalExpr for ArraySort {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.argument.data_type()
+    }
+
+    fn eval(&self, input: &dyn Row) -> Value {
+        let value = self.argument.eval(input);
+        if value.is_null() {
+            return Value::Null;
+        }
+        let array = value.get_array();
+        let sorted = match &self.comparator {
+            Some(comparator) => merge_sort_by(array.as_ref(), &mut |a, b| {
+                let ret = comparator.invoke(input, vec![a.clone(), b.clone()]);
+                if ret.is_null() { Ordering::Equal } else { ret.get_int().cmp(&0) }
+            }),
+            None => merge_sort_by(array.as_ref(), &mut |a, b| match (a.is_null(), b.is_null()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                _ => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            }),
+        };
+        Value::Array(Arc::new(sorted))
+    }
+}
+
+/// 稳定的归并排序, 比较函数不满足全序时不会像slice::sort_by一样panic
+pub(crate) fn merge_sort_by(values: &[Value], compare: &mut impl FnMut(&Value, &Value) -> Ordering) -> Vec<Value> {
+    if values.len() <= 1 {
+        return values.to_vec();
+    }
+    let (left, right) = values.split_at(values.len() / 2);
+    let (left, right) = (merge_sort_by(left, compare), merge_sort_by(right, compare));
+    let mut result = Vec::with_capacity(values.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if compare(b, a) == Ordering::Less {
+            result.push(right.next().unwrap());
+        } else {
+            result.push(left.next().unwrap());
+        }
+    }
+    result.extend(left);
+    result.extend(right);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::GenericRow;
+    use crate::parser::parse_schema;
+    use crate::physical_expr::create_physical_expr;
+    use crate::sql_utils;
+    use super::*;
+
+    fn schema() -> crate::types::Schema {
+        parse_schema("a array<bigint>, s array<struct<name:string, v:int>>, n bigint").unwrap()
+    }
+
+    fn eval(sql: &str, row: &GenericRow) -> (String, String) {
+        let expression = sql_utils::parse_expr(sql, &schema()).unwrap();
+        let expr = crate::expr::BoundReference::bind_reference(expression.expr, expression.child.output()).unwrap();
+        let expr = create_physical_expr(&expr).unwrap();
+        (expr.data_type().to_string(), expr.eval(row).to_string())
+    }
+
+    #[test]
+    fn test_higher_order_functions() {
+        let a = Value::Array(Arc::new(vec![Value::long(2), Value::long(1), Value::Null]));
+        let s = Value::Array(Arc::new(vec![
+            Value::Struct(Arc::new(GenericRow::new(vec![Value::string("a"), Value::int(1)]))),
+            Value::Struct(Arc::new(GenericRow::new(vec![Value::string("b"), Value::int(2)]))),
+        ]));
+        let row = GenericRow::new(vec![a, s, Value::long(10)]);
+        assert_eq!(eval("transform(a, x -> x + 1)", &row), ("array<long>".to_string(), "[3, 2, null]".to_string()));
+        assert_eq!(eval("transform(a, (x, i) -> x * i)", &row).1, "[0, 1, null]");
+        assert_eq!(eval("transform(a, x -> x + n)", &row).1, "[12, 11, null]");
+        assert_eq!(eval("transform(s, x -> x.name)", &row), ("array<string>".to_string(), "['a', 'b']".to_string()));
+        assert_eq!(eval("transform(a, x -> filter(a, y -> y < x))", &row).1, "[[1], [], []]");
+        assert_eq!(eval("transform(a, x -> transform(s, x -> x.v))", &row).1, "[[1, 2], [1, 2], [1, 2]]");
+        assert_eq!(eval("filter(a, x -> x > 1)", &row), ("array<long>".to_string(), "[2]".to_string()));
+        assert_eq!(eval("filter(a, (x, i) -> i > 0)", &row).1, "[1, null]");
+        assert_eq!(eval("exists(a, x -> x > 1)", &row), ("boolean".to_string(), "true".to_string()));
+        assert_eq!(eval("exists(a, x -> x > 5)", &row).1, "null");
+        assert_eq!(eval("forall(a, x -> x > 1)", &row).1, "false");
+        assert_eq!(eval("forall(a, x -> x > 0)", &row).1, "null");
+        assert_eq!(eval("aggregate(a, 0L, (acc, x) -> acc + coalesce(x, 0L))", &row), ("long".to_string(), "3".to_string()));
+        assert_eq!(eval("aggregate(s, '', (acc, x) -> concat(acc, x.name), acc -> upper(acc))", &row), ("string".to_string(), "'AB'".to_string()));
+        assert_eq!(eval("array_sort(a)", &row).1, "[1, 2, null]");
+        assert_eq!(eval("array_sort(a, (l, r) -> if(l < r, 1, if(l > r, -1, 0)))", &row).1, "[2, 1, null]");
+        assert_eq!(eval("transform(array_sort(s, (l, r) -> r.v - l.v), x -> x.name)", &row).1, "['b', 'a']");
+        assert!(sql_utils::parse_expr("array_sort(s)", &schema()).is_err());
+        // 函数体是常量的lambda
+        assert_eq!(eval("transform(a, x -> 1)", &row), ("array<int>".to_string(), "[1, 1, 1]".to_string()));
+        assert_eq!(eval("filter(a, x -> true)", &row).1, "[2, 1, null]");
+        assert_eq!(eval("array_sort(a, (l, r) -> 0)", &row).1, "[2, 1, null]");
+
+        let schema = schema();
+        assert!(sql_utils::parse_expr("upper(x -> x)", &schema).is_err());
+        assert!(sql_utils::parse_expr("transform(a, x -> x.foo)", &schema).is_err());
+        assert!(sql_utils::parse_expr("transform(a, (x, i, j) -> x)", &schema).is_err());
+        assert!(sql_utils::parse_expr("filter(a, x -> x + 1)", &schema).is_err());
+        assert!(sql_utils::parse_expr("aggregate(a, 0, (acc, x) -> acc + x)", &schema).is_err());
+        assert!(sql_utils::parse_expr("transform(a, (x, x) -> x)", &schema).is_err());
+    }
+}
@@ -18,6 +18,7 @@ mod arithmetic;
 mod projection;
 mod generator;
 mod misc;
+mod higher_order;
 
 pub use crate::physical_expr::physical_expr::*;
 pub use crate::physical_expr::attribute::*;
@@ -39,3 +40,4 @@ pub use crate::physical_expr::arithmetic::*;
 pub use crate::physical_expr::projection::*;
 pub use crate::physical_expr::generator::*;
 pub use crate::physical_expr::misc::*;
+pub use crate::physical_expr::higher_order::*;
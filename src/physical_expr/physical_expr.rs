@@ -143,4 +143,33 @@ mod tests {
         let rst = expr.eval(&row);
         println!("{:?},{:?}", row, rst);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decimal() {
+        let schema = crate::parser::parse_schema("a decimal(10,2), b decimal(5,3), i int, s string").unwrap();
+        let row = GenericRow::new(vec![Value::Decimal(12345, 2), Value::Decimal(-1500, 3), Value::Int(3), Value::string("0.125")]);
+        let eval = |sql: &str| {
+            let expression = crate::sql_utils::parse_expr(sql, &schema).unwrap();
+            let expr = crate::expr::BoundReference::bind_reference(expression.expr, expression.child.output()).unwrap();
+            let expr = create_physical_expr(&expr).unwrap();
+            (expr.data_type().to_string(), expr.eval(&row).to_string())
+        };
+        assert_eq!(eval("a + b"), ("decimal(12,3)".to_string(), "121.950".to_string()));
+        assert_eq!(eval("a - b"), ("decimal(12,3)".to_string(), "124.950".to_string()));
+        assert_eq!(eval("a * b"), ("decimal(16,5)".to_string(), "-185.17500".to_string()));
+        assert_eq!(eval("a / b"), ("decimal(19,8)".to_string(), "-82.30000000".to_string()));
+        assert_eq!(eval("a + i"), ("decimal(13,2)".to_string(), "126.45".to_string()));
+        assert_eq!(eval("a / 0"), ("decimal(21,13)".to_string(), "null".to_string()));
+        assert_eq!(eval("-a").1, "-123.45");
+        assert_eq!(eval("a * 1.0").0, "double");
+        assert_eq!(eval("cast(s as decimal(5,2))").1, "0.13");
+        assert_eq!(eval("cast(s as decimal(2,2))").1, "0.13");
+        assert_eq!(eval("cast('123.456' as decimal(4,2))").1, "null");
+        assert_eq!(eval("cast(0.1 + 0.2 as decimal(10,2))").1, "0.30");
+        assert_eq!(eval("cast(a as double)").1, "123.45");
+        assert_eq!(eval("cast(b as int)").1, "-1");
+        assert_eq!(eval("cast(a as string)").1, "'123.45'");
+        assert_eq!(eval("a > b").1, "true");
+        assert_eq!(eval("cast(1.5 as decimal(2,1)) = cast(1.50 as decimal(3,2))").1, "true");
+    }
+}
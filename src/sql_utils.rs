@@ -8,16 +8,21 @@ use crate::optimizer::Optimizer;
 use crate::types::Schema;
 
 pub fn sql_plan(sql: &str, schema: &Schema) -> Result<LogicalPlan> {
-    let mut temp_views = HashMap::new();
-    temp_views.insert("tbl".to_string(), RelationPlaceholder::new("tbl".to_string(), schema.to_attributes()));
-    let plan = parser::parse_query(sql)?;
-    let plan = Analyzer::new(temp_views).analyze(plan)?;
+    let plan = analyzed_sql_plan(sql, schema)?;
     //println!("plan:\n{:?}", plan);
     let optimized_plan = Optimizer::new().optimize(plan)?;
     //println!("optimized_plan:\n{:?}", optimized_plan);
     Ok(optimized_plan)
 }
 
+/// 分析后未优化的执行计划
+pub fn analyzed_sql_plan(sql: &str, schema: &Schema) -> Result<LogicalPlan> {
+    let mut temp_views = HashMap::new();
+    temp_views.insert("tbl".to_string(), RelationPlaceholder::new("tbl".to_string(), schema.to_attributes()));
+    let plan = parser::parse_query(sql)?;
+    Analyzer::new(temp_views).analyze(plan)
+}
+
 pub fn parse_filter(condition: &str, schema: &Schema) -> Result<Filter> {
     let expr = parser::parse_expr(condition)?;
     let plan = LogicalPlan::Filter(Filter::new(expr, Arc::new(LogicalPlan::RelationPlaceholder(RelationPlaceholder::new("tbl".to_string(), schema.to_attributes())))));
@@ -3,18 +3,28 @@ use crate::{sql_utils, Result};
 use crate::config::{TaskContext, TransformConfig, TransformProvider};
 use crate::data::{Row};
 use crate::expr::{AttributeReference, Expr};
+use crate::expr::aggregate::DistinctAggFunction;
 use crate::logical_plan::LogicalPlan;
 use crate::transform::{Transform, OutOperator, ProcessOperator, get_process_operator_chain};
-use crate::transform::aggregate::TaskAggregateTransform;
-use crate::types::Schema;
+use crate::transform::aggregate::{TaskAggregateTransform, TopNConfig, WindowAggregateTransform, WindowConfig};
+use crate::types::{DataType, Field, Schema};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskAggregateTransformConfig {
     sql: String,
+    /// 缓存的分组数达到max_rows时输出, distinct聚合函数去重集合中的元素也计入.
+    /// 溢出时输出的是部分聚合的结果, 不应用having条件(没有top_n时), 下游需要再次聚合; distinct聚合的部分结果不能合并
     #[serde(default = "default_max_rows")]
     max_rows: usize,
     #[serde(default = "default_interval_ms")]
     interval_ms: u64,
+    /// 每次interval输出时每个分区只输出top n个分组, max_rows溢出时只保留当前top n分组的buffer继续聚合, 不能和flush_on_checkpoint一起使用
+    #[serde(default)]
+    top_n: Option<TopNConfig>,
+    /// 为true时每次checkpoint barrier都输出所有分组, 保证提交offset前数据已经输出, 但一个interval内的分组会被拆分为多次部分聚合的结果.
+    /// 默认false, barrier时只保存快照(需要配置state)
+    #[serde(default)]
+    flush_on_checkpoint: bool,
 }
 
 fn default_max_rows() -> usize {
@@ -31,7 +41,17 @@ impl TransformConfig for TaskAggregateTransformConfig {
         let plan = sql_utils::sql_plan(&self.sql, &schema)?;
         if let LogicalPlan::Aggregate(agg) = &plan {
             let schema = Schema::from_attributes(plan.output());
-            let (group_exprs, agg_exprs, result_exprs, child) = agg.extract_exprs();
+            if let Some(top_n) = &self.top_n {
+                if self.flush_on_checkpoint {
+                    return Err("top_n can not be used with flush_on_checkpoint".to_string());
+                }
+                top_n.build(&schema)?;
+            }
+            let (group_exprs, agg_exprs, result_exprs, having, child) = agg.extract_exprs();
+            // having和distinct不能用于部分聚合的结果
+            if self.flush_on_checkpoint && (having.is_some() || agg_exprs.iter().any(|e| matches!(e, Expr::TypedAggFunction(f) if f.as_any().is::<DistinctAggFunction>()))) {
+                return Err("having and distinct aggregate can not be used with flush_on_checkpoint".to_string());
+            }
             let child = child.as_ref().clone();
             let input_attrs = child.output();
             Ok(Box::new(TaskAggregateTransformProvider {
@@ -41,13 +61,20 @@ impl TransformConfig for TaskAggregateTransformConfig {
                 group_exprs,
                 agg_exprs,
                 result_exprs,
+                having,
                 max_rows: self.max_rows,
                 interval_ms: self.interval_ms,
+                top_n: self.top_n.clone(),
+                flush_on_checkpoint: self.flush_on_checkpoint,
             }))
         } else {
             Err(format!("plan is not aggregate plan:{:?}", plan))
         }
     }
+
+    fn sql(&self) -> Option<&str> {
+        Some(&self.sql)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -58,11 +85,18 @@ pub struct TaskAggregateTransformProvider {
     group_exprs: Vec<Expr>,
     agg_exprs: Vec<Expr>,
     result_exprs: Vec<Expr>,
+    having: Option<Expr>,
     max_rows: usize,
     interval_ms: u64,
+    top_n: Option<TopNConfig>,
+    flush_on_checkpoint: bool,
 }
 
 impl TransformProvider for TaskAggregateTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
         let (no_pre, pre_process) = if let LogicalPlan::RelationPlaceholder(_) = &self.child {
             (true, Box::new(OutOperator) as Box<dyn ProcessOperator>)
@@ -74,8 +108,99 @@ impl TransformProvider for TaskAggregateTransformProvider {
         let group_exprs = self.group_exprs.clone();
         let agg_exprs = self.agg_exprs.clone();
         let result_exprs = self.result_exprs.clone();
-        let transform= TaskAggregateTransform::new(task_context, self.schema.clone(), no_pre, pre_process, agg_exprs, group_exprs, result_exprs, input_attrs, self.max_rows, self.interval_ms)?;
+        let having = self.having.clone();
+        let top_n = self.top_n.as_ref().map(|top_n| top_n.build(&self.schema)).transpose()?;
+        let transform= TaskAggregateTransform::new(task_context, self.schema.clone(), no_pre, pre_process, agg_exprs, group_exprs, result_exprs, having, input_attrs, self.max_rows, self.interval_ms, top_n, self.flush_on_checkpoint)?;
         Ok(Box::new(transform))
     }
 }
 
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowAggregateTransformConfig {
+    sql: String,
+    time_column: String,
+    window: WindowConfig,
+    #[serde(default)]
+    max_out_of_orderness_ms: u64,
+    #[serde(default)]
+    late_output: Option<String>,
+}
+
+#[typetag::serde(name = "window_aggregate")]
+impl TransformConfig for WindowAggregateTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        self.window.validate()?;
+        let time_index = schema.field_index(&self.time_column).ok_or_else(|| format!("time_column {} not found in {}", self.time_column, schema))?;
+        let time_type = schema.fields[time_index].data_type.clone();
+        if !matches!(time_type, DataType::Timestamp | DataType::Long) {
+            return Err(format!("time_column {} requires timestamp or bigint, but get {}", self.time_column, time_type));
+        }
+        let plan = sql_utils::sql_plan(&self.sql, &schema)?;
+        if let LogicalPlan::Aggregate(agg) = &plan {
+            let mut fields = vec![Field::new("window_start", DataType::Timestamp), Field::new("window_end", DataType::Timestamp)];
+            fields.extend(Schema::from_attributes(plan.output()).fields);
+            let (group_exprs, agg_exprs, result_exprs, having, child) = agg.extract_exprs();
+            let child = child.as_ref().clone();
+            let input_attrs = child.output();
+            Ok(Box::new(WindowAggregateTransformProvider {
+                schema: Schema::new(fields),
+                input_attrs,
+                child,
+                group_exprs,
+                agg_exprs,
+                result_exprs,
+                having,
+                time_index,
+                time_type,
+                window: self.window,
+                max_out_of_orderness_ms: self.max_out_of_orderness_ms,
+                late_output: self.late_output.clone(),
+            }))
+        } else {
+            Err(format!("plan is not aggregate plan:{:?}", plan))
+        }
+    }
+
+    fn side_outputs(&self) -> Vec<String> {
+        self.late_output.iter().cloned().collect()
+    }
+
+    fn sql(&self) -> Option<&str> {
+        Some(&self.sql)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowAggregateTransformProvider {
+    schema: Schema,
+    input_attrs: Vec<AttributeReference>,
+    child: LogicalPlan,
+    group_exprs: Vec<Expr>,
+    agg_exprs: Vec<Expr>,
+    result_exprs: Vec<Expr>,
+    having: Option<Expr>,
+    time_index: usize,
+    time_type: DataType,
+    window: WindowConfig,
+    max_out_of_orderness_ms: u64,
+    late_output: Option<String>,
+}
+
+impl TransformProvider for WindowAggregateTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        let (no_pre, pre_process) = if let LogicalPlan::RelationPlaceholder(_) = &self.child {
+            (true, Box::new(OutOperator) as Box<dyn ProcessOperator>)
+        } else {
+            let process_operator = get_process_operator_chain(self.child.clone())?;
+            (false, process_operator)
+        };
+        let transform = WindowAggregateTransform::new(task_context, self.schema.clone(), no_pre, pre_process, self.agg_exprs.clone(), self.group_exprs.clone(),
+            self.result_exprs.clone(), self.having.clone(), self.input_attrs.clone(), self.time_index, self.time_type.clone(), self.window, self.max_out_of_orderness_ms, self.late_output.clone())?;
+        Ok(Box::new(transform))
+    }
+}
@@ -1,5 +1,9 @@
 mod config;
 mod transform;
+mod window;
+mod top_n;
 
 pub use config::*;
-pub use transform::*;
\ No newline at end of file
+pub use transform::*;
+pub use window::*;
+pub use top_n::*;
\ No newline at end of file
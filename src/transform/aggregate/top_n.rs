@@ -0,0 +1,182 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::BuildHasherDefault;
+use ahash::AHasher;
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::data::{GenericRow, Row, Value};
+use crate::types::Schema;
+
+/// 每次输出时每个分区只保留排序最前的n个分组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopNConfig {
+    /// 分区列, 为空时全部分组取top n
+    #[serde(default)]
+    pub partition_by: Vec<String>,
+    /// 排序列, 格式: "列名 [asc|desc]", 默认asc, null排在最后
+    pub order_by: Vec<String>,
+    pub n: usize,
+}
+
+impl TopNConfig {
+    /// 列名为聚合结果schema中的列
+    pub fn build(&self, schema: &Schema) -> Result<TopN> {
+        if self.n == 0 || self.order_by.is_empty() {
+            return Err(format!("top_n requires n > 0 and non-empty order_by: {:?}", self));
+        }
+        let field_index = |name: &str| schema.field_index(name).ok_or_else(|| format!("top_n column {} not found in {}", name, schema));
+        let partition_indices = self.partition_by.iter().map(|name| field_index(name)).collect::<Result<Vec<_>>>()?;
+        let mut order_by = Vec::with_capacity(self.order_by.len());
+        for item in self.order_by.iter() {
+            let parts: Vec<&str> = item.split_whitespace().collect();
+            let descending = match parts.as_slice() {
+                [_] => false,
+                [_, direction] if direction.eq_ignore_ascii_case("asc") => false,
+                [_, direction] if direction.eq_ignore_ascii_case("desc") => true,
+                _ => return Err(format!("invalid top_n order_by: {}", item)),
+            };
+            order_by.push((field_index(parts[0])?, descending));
+        }
+        Ok(TopN { partition_indices, order_by, n: self.n, heaps: HashMap::default() })
+    }
+}
+
+/// 排序在前的值更小, null最大
+#[derive(Debug)]
+enum SortValue {
+    Asc(Value),
+    Desc(Value),
+}
+
+impl SortValue {
+    fn cmp_value(a: &Value, b: &Value) -> Ordering {
+        match (a.is_null(), b.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            _ => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortValue::Desc(a), SortValue::Desc(b)) if !a.is_null() && !b.is_null() => Self::cmp_value(b, a),
+            (SortValue::Asc(a) | SortValue::Desc(a), SortValue::Asc(b) | SortValue::Desc(b)) => Self::cmp_value(a, b),
+        }
+    }
+}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortValue {}
+
+struct RankedRow {
+    sort_key: Vec<SortValue>,
+    key: GenericRow,
+    row: GenericRow,
+}
+
+impl Ord for RankedRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key.cmp(&other.sort_key)
+    }
+}
+
+impl PartialOrd for RankedRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RankedRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key == other.sort_key
+    }
+}
+
+impl Eq for RankedRow {}
+
+/// 每个分区使用大小为n的大顶堆, 堆顶为当前第n名, 不需要对所有分组排序
+pub struct TopN {
+    partition_indices: Vec<usize>,
+    order_by: Vec<(usize, bool)>,
+    n: usize,
+    heaps: HashMap<GenericRow, BinaryHeap<RankedRow>, BuildHasherDefault<AHasher>>,
+}
+
+impl TopN {
+    fn sort_key(&self, row: &dyn Row) -> Vec<SortValue> {
+        self.order_by.iter().map(|(i, descending)| {
+            let value = row.get(*i).clone();
+            if *descending { SortValue::Desc(value) } else { SortValue::Asc(value) }
+        }).collect()
+    }
+
+    /// key为分组key, row为分组的结果, 每个分组只能加入一次
+    pub fn add(&mut self, key: &GenericRow, row: &dyn Row) {
+        let sort_key = self.sort_key(row);
+        let partition = GenericRow::new(self.partition_indices.iter().map(|i| row.get(*i).clone()).collect());
+        let heap = self.heaps.entry(partition).or_default();
+        if heap.len() < self.n {
+            heap.push(RankedRow { sort_key, key: key.clone(), row: row.to_generic_row() });
+        } else if heap.peek().is_some_and(|top| sort_key < top.sort_key) {
+            heap.pop();
+            heap.push(RankedRow { sort_key, key: key.clone(), row: row.to_generic_row() });
+        }
+    }
+
+    /// 返回所有分区的top n, 分区内按排序顺序输出
+    pub fn drain(&mut self) -> impl Iterator<Item = GenericRow> + '_ {
+        self.heaps.drain().flat_map(|(_, heap)| heap.into_sorted_vec().into_iter().map(|ranked| ranked.row))
+    }
+
+    /// 返回所有分区top n的分组key, 不排序
+    pub fn drain_keys(&mut self) -> impl Iterator<Item = GenericRow> + '_ {
+        self.heaps.drain().flat_map(|(_, heap)| heap.into_iter().map(|ranked| ranked.key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_schema;
+    use super::*;
+
+    #[test]
+    fn test_top_n() {
+        let schema = parse_schema("cate string, domain string, bytes bigint").unwrap();
+        let config = TopNConfig { partition_by: vec!["cate".to_string()], order_by: vec!["bytes desc".to_string(), "domain".to_string()], n: 2 };
+        let mut top_n = config.build(&schema).unwrap();
+        let rows = [("a", "x", Some(10)), ("a", "y", Some(30)), ("a", "z", None), ("a", "w", Some(30)), ("a", "v", Some(20)), ("b", "x", Some(1))];
+        for (cate, domain, bytes) in rows {
+            let bytes = bytes.map(Value::long).unwrap_or(Value::Null);
+            let key = GenericRow::new(vec![Value::string(cate), Value::string(domain)]);
+            top_n.add(&key, &GenericRow::new(vec![Value::string(cate), Value::string(domain), bytes]));
+        }
+        let mut result: Vec<String> = top_n.drain().map(|row| format!("{}:{}", row.get_string(0), row.get_string(1))).collect();
+        result.sort_by_key(|s| s.starts_with('b'));
+        assert_eq!(result, vec!["a:w", "a:y", "b:x"]);
+        assert!(top_n.heaps.is_empty());
+
+        top_n.add(&GenericRow::new(vec![Value::int(1)]), &GenericRow::new(vec![Value::string("a"), Value::string("x"), Value::long(1)]));
+        top_n.add(&GenericRow::new(vec![Value::int(2)]), &GenericRow::new(vec![Value::string("a"), Value::string("y"), Value::long(2)]));
+        top_n.add(&GenericRow::new(vec![Value::int(3)]), &GenericRow::new(vec![Value::string("a"), Value::string("z"), Value::long(3)]));
+        let mut keys: Vec<i32> = top_n.drain_keys().map(|key| key.get_int(0)).collect();
+        keys.sort();
+        assert_eq!(keys, vec![2, 3]);
+
+        let config = TopNConfig { partition_by: vec![], order_by: vec!["bytes down".to_string()], n: 2 };
+        assert!(config.build(&schema).is_err());
+    }
+}
@@ -1,18 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::BuildHasherDefault;
 use std::mem;
 use std::sync::Arc;
 use ahash::{AHasher};
+use log::{info, warn};
 use crate::config::TaskContext;
 use crate::Result;
-use crate::data::{GenericRow, JoinedRow, Object, Row};
-use crate::datetime_utils::current_timestamp_millis;
-use crate::execution::{Collector, TimeService};
+use crate::data::{GenericRow, JoinedRow, Object, Row, Value};
+use crate::datetime_utils::processing_time_millis;
+use crate::execution::{Collector, StateReader, StateStore, StateWriter, TimeService};
 use crate::expr::{AttributeReference, BoundReference, Expr};
 use crate::expr::aggregate::PhysicalTypedAggFunction;
 use crate::physical_expr::{create_physical_expr, MutableProjection, PhysicalExpr, Projection};
 use crate::transform::{Transform, ProcessOperator, OutOperator};
+use crate::transform::aggregate::TopN;
 use crate::types::Schema;
 
 struct PreProcessCollector<'a> {
@@ -46,48 +48,80 @@ pub struct TaskAggregateTransform {
     rst_func: RowResultFunction,
     key_selector: RowKeySelector,
     buffers: HashMap<GenericRow, GenericRow,BuildHasherDefault<AHasher>>,
+    /// distinct聚合函数去重集合中的元素数, 和分组数一起计入max_rows
+    distinct_rows: usize,
     max_rows: usize,
     interval_ms: u64,
     trigger_time_ms: u64,
+    top_n: Option<TopN>,
+    state_store: Option<StateStore>,
+    snapshot_time_ms: u64,
+    flush_on_checkpoint: bool,
+    /// 收到过checkpoint barrier, 之后只在barrier时保存快照, 和source提交的offset对应
+    checkpointed: bool,
 }
 
 impl TaskAggregateTransform {
-    pub fn new(task_context: TaskContext, schema: Schema, no_pre: bool, pre_process: Box<dyn ProcessOperator>, agg_exprs: Vec<Expr>, group_exprs: Vec<Expr>,  result_exprs: Vec<Expr>,
-               input_attrs: Vec<AttributeReference>, max_rows: usize, interval_ms: u64) -> Result<Self> {
-        let mut agg_attrs = Vec::with_capacity(agg_exprs.len());
-        let mut final_agg_attrs = Vec::with_capacity(agg_exprs.len());
-        for expr in &agg_exprs {
-            match expr {
-                Expr::DeclarativeAggFunction(f) => {
-                    for attr in f.agg_buffer_attributes() {
-                        agg_attrs.push(attr);
-                    }
-                    final_agg_attrs.push(f.result_attribute());
-                },
-                Expr::TypedAggFunction(f) => {
-                    for attr in f.agg_buffer_attributes() {
-                        agg_attrs.push(attr);
-                    }
-                    final_agg_attrs.push(f.result_attribute());
-                },
-                _ => return Err(format!("not support agg expr:{:?}", expr))
-            }
-        }
-        let mut group_attrs = Vec::with_capacity(group_exprs.len());
-        for expr in &group_exprs {
-            group_attrs.push(expr.to_attribute()?);
-        }
-
-        let agg_func = RowAggregateFunction::new(agg_exprs, agg_attrs, input_attrs.clone())?;
-        let exprs: Result<Vec<Box<dyn PhysicalExpr>>, String> = BoundReference::bind_references(group_exprs, input_attrs)?.iter().map(|expr| create_physical_expr(expr)).collect();
-        let key_selector = RowKeySelector::new(exprs?);
-        let rst_func = RowResultFunction::new(result_exprs, group_attrs.into_iter().chain(final_agg_attrs.into_iter()).collect())?;
+    pub fn new(task_context: TaskContext, schema: Schema, no_pre: bool, pre_process: Box<dyn ProcessOperator>, agg_exprs: Vec<Expr>, group_exprs: Vec<Expr>,  result_exprs: Vec<Expr>, having: Option<Expr>,
+               input_attrs: Vec<AttributeReference>, max_rows: usize, interval_ms: u64, top_n: Option<TopN>, flush_on_checkpoint: bool) -> Result<Self> {
+        let fingerprint = state_fingerprint(&group_exprs, &agg_exprs);
+        let (agg_func, key_selector, rst_func) = create_row_functions(agg_exprs, group_exprs, result_exprs, having, input_attrs)?;
 
         let trigger_time_ms = 0;
-        Ok(Self { task_context, schema, no_pre, pre_process, agg_func, rst_func, key_selector, buffers: HashMap::default(), max_rows, interval_ms,trigger_time_ms })
+        let state_store = StateStore::new(&task_context, "task_aggregate", fingerprint);
+        Ok(Self { task_context, schema, no_pre, pre_process, agg_func, rst_func, key_selector, buffers: HashMap::default(), distinct_rows: 0, max_rows, interval_ms,trigger_time_ms, top_n,
+            state_store, snapshot_time_ms: 0, flush_on_checkpoint, checkpointed: false })
     }
 }
 
+/// 状态中key和聚合buffer的类型, 如: key(int),buffer(sum:bigint;count:bigint)
+pub(super) fn state_fingerprint(group_exprs: &[Expr], agg_exprs: &[Expr]) -> String {
+    let keys: Vec<String> = group_exprs.iter().map(|expr| expr.data_type().to_string()).collect();
+    let buffers: Vec<String> = agg_exprs.iter().map(|expr| {
+        let (name, attrs) = match expr {
+            Expr::DeclarativeAggFunction(f) => (f.name(), f.agg_buffer_attributes()),
+            Expr::TypedAggFunction(f) => (f.name(), f.agg_buffer_attributes()),
+            _ => ("", Vec::new()),
+        };
+        let types: Vec<String> = attrs.iter().map(|attr| attr.data_type.to_string()).collect();
+        format!("{}:{}", name, types.join(","))
+    }).collect();
+    format!("key({}),buffer({})", keys.join(","), buffers.join(";"))
+}
+
+pub(super) fn create_row_functions(agg_exprs: Vec<Expr>, group_exprs: Vec<Expr>,  result_exprs: Vec<Expr>, having: Option<Expr>, input_attrs: Vec<AttributeReference>)
+    -> Result<(RowAggregateFunction, RowKeySelector, RowResultFunction)> {
+    let mut agg_attrs = Vec::with_capacity(agg_exprs.len());
+    let mut final_agg_attrs = Vec::with_capacity(agg_exprs.len());
+    for expr in &agg_exprs {
+        match expr {
+            Expr::DeclarativeAggFunction(f) => {
+                for attr in f.agg_buffer_attributes() {
+                    agg_attrs.push(attr);
+                }
+                final_agg_attrs.push(f.result_attribute());
+            },
+            Expr::TypedAggFunction(f) => {
+                for attr in f.agg_buffer_attributes() {
+                    agg_attrs.push(attr);
+                }
+                final_agg_attrs.push(f.result_attribute());
+            },
+            _ => return Err(format!("not support agg expr:{:?}", expr))
+        }
+    }
+    let mut group_attrs = Vec::with_capacity(group_exprs.len());
+    for expr in &group_exprs {
+        group_attrs.push(expr.to_attribute()?);
+    }
+
+    let agg_func = RowAggregateFunction::new(agg_exprs, agg_attrs, input_attrs.clone())?;
+    let exprs: Result<Vec<Box<dyn PhysicalExpr>>, String> = BoundReference::bind_references(group_exprs, input_attrs)?.iter().map(|expr| create_physical_expr(expr)).collect();
+    let key_selector = RowKeySelector::new(exprs?);
+    let rst_func = RowResultFunction::new(result_exprs, having, group_attrs.into_iter().chain(final_agg_attrs.into_iter()).collect())?;
+    Ok((agg_func, key_selector, rst_func))
+}
+
 impl Debug for TaskAggregateTransform {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QueryTransform")
@@ -104,7 +138,7 @@ impl TaskAggregateTransform {
         let key = self.key_selector.get_key(row);
         // 也可以这样实现
         let buffer = self.buffers.entry(key).or_insert_with(|| self.agg_func.create_aggregation());
-        self.agg_func.update(buffer, row);
+        self.distinct_rows += self.agg_func.update(buffer, row);
         /* if let Some(buffer) = self.buffers.get_mut(&key) {
             self.agg_func.update(buffer, row);
         } else {
@@ -112,25 +146,85 @@ impl TaskAggregateTransform {
             self.agg_func.update(&mut buffer, row);
             self.buffers.insert(key, buffer);
         }*/
-        if self.buffers.len() >= self.max_rows {
-            self.flush(out)
+        if self.trigger_time_ms == 0 {
+            self.trigger_time_ms = processing_time_millis() / self.interval_ms * self.interval_ms + self.interval_ms;
+            time_service.register_timer(self.trigger_time_ms);
+        }
+        if self.buffers.len() + self.distinct_rows >= self.max_rows {
+            self.flush(out, false)
         } else {
-            if self.trigger_time_ms == 0 {
-                self.trigger_time_ms = current_timestamp_millis() / self.interval_ms * self.interval_ms + self.interval_ms;
-                time_service.register_timer(self.trigger_time_ms);
+            if let Some(state_store) = &self.state_store && self.snapshot_time_ms == 0 {
+                self.snapshot_time_ms = processing_time_millis() + state_store.snapshot_interval_ms;
+                time_service.register_timer(self.snapshot_time_ms);
             }
             Ok(())
         }
     }
+
+    /// 快照内容: trigger_time_ms + key数 + (key, buffer)列表, 没有缓存数据时删除快照
+    fn snapshot(&mut self) -> Result<()> {
+        let Some(state_store) = &self.state_store else {
+            return Ok(());
+        };
+        if self.buffers.is_empty() {
+            return state_store.clear();
+        }
+        let mut writer = StateWriter::new();
+        writer.write_u64(self.trigger_time_ms);
+        writer.write_u32(self.buffers.len() as u32);
+        for (key, buffer) in &self.buffers {
+            writer.write_row(key)?;
+            writer.write_row(buffer)?;
+        }
+        state_store.save(&writer.into_bytes())
+    }
     
-    fn flush(&mut self, out: &mut dyn Collector) -> Result<()> {
+    /// rank为false时(max_rows溢出)没有top n时输出部分聚合的结果, 不应用having条件;
+    /// 有top n时只保留当前每个分区排序在前的n个分组的buffer继续聚合, interval触发时才排序输出, 其它分组的部分结果丢弃
+    fn flush(&mut self, out: &mut dyn Collector, rank: bool) -> Result<()> {
+        if !rank && self.top_n.is_some() {
+            return self.retain_top_n();
+        }
         for (key, buffer) in &mut self.buffers {
             let value = self.agg_func.eval(buffer);
             let joiner = JoinedRow::new(key, value) ;
-            let row= self.rst_func.result_projection.apply(&joiner);
-            out.collect(row)?;
+            let Some(row) = self.rst_func.apply(&joiner, rank) else {
+                continue;
+            };
+            match &mut self.top_n {
+                Some(top_n) => top_n.add(key, row),
+                None => out.collect(row)?,
+            }
         }
         self.buffers.clear();
+        self.distinct_rows = 0;
+        if rank && let Some(top_n) = &mut self.top_n {
+            for row in top_n.drain() {
+                out.collect(&row)?;
+            }
+        }
+        // 输出后删除快照, 避免重启后重复输出
+        self.snapshot()
+    }
+}
+
+impl TaskAggregateTransform {
+    /// 同一个分组只保留一个buffer, 不会作为多个候选重复排序. eval会消耗buffer, 所以对副本计算结果
+    fn retain_top_n(&mut self) -> Result<()> {
+        let Some(top_n) = &mut self.top_n else {
+            return Ok(());
+        };
+        for (key, buffer) in &self.buffers {
+            let mut buffer = buffer.clone();
+            let value = self.agg_func.eval(&mut buffer);
+            let joiner = JoinedRow::new(key, value);
+            if let Some(row) = self.rst_func.apply(&joiner, true) {
+                top_n.add(key, row);
+            }
+        }
+        let keys: HashSet<GenericRow> = top_n.drain_keys().collect();
+        self.buffers.retain(|key, _| keys.contains(key));
+        self.distinct_rows = self.buffers.values().map(|buffer| self.agg_func.buffer_rows(buffer)).sum();
         Ok(())
     }
 }
@@ -153,25 +247,87 @@ impl Transform for TaskAggregateTransform {
         }
     }
 
+    fn restore_state(&mut self, time_service: &mut TimeService) -> Result<()> {
+        let Some(data) = self.state_store.as_ref().map(|state_store| state_store.load()).transpose()?.flatten() else {
+            return Ok(());
+        };
+        let mut reader = StateReader::new(&data);
+        let trigger_time_ms = reader.read_u64()?;
+        let len = reader.read_u32()? as usize;
+        for _ in 0..len {
+            let key = reader.read_row()?;
+            let buffer = reader.read_row()?;
+            self.distinct_rows += self.agg_func.buffer_rows(&buffer);
+            self.buffers.insert(key, buffer);
+        }
+        if !self.buffers.is_empty() {
+            // 触发时间已经过去时在下一次检查timer时输出
+            self.trigger_time_ms = trigger_time_ms;
+            time_service.register_timer(self.trigger_time_ms);
+        }
+        info!("transform{}_{} restored {} keys from state", self.task_context.operator_config.id, self.task_context.task_config.subtask_index, len);
+        Ok(())
+    }
+
     fn on_time(&mut self, time: u64, out: &mut dyn Collector) -> Result<()> {
-        self.trigger_time_ms = 0;
-        self.flush(out)
+        if self.trigger_time_ms != 0 && time >= self.trigger_time_ms {
+            self.trigger_time_ms = 0;
+            self.flush(out, true)?;
+        }
+        if self.snapshot_time_ms != 0 && time >= self.snapshot_time_ms {
+            self.snapshot_time_ms = 0;
+            if !self.checkpointed {
+                self.snapshot()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 默认在barrier时保存快照, 缓存的分组包含已提交offset的数据, 没有配置state时出错重启会丢失这些数据.
+    /// flush_on_checkpoint为true时在barrier时输出所有分组, 一个interval会输出多次部分聚合的结果
+    fn on_barrier(&mut self, checkpoint_id: u64, out: &mut dyn Collector) -> Result<()> {
+        if self.flush_on_checkpoint {
+            return self.flush(out, true);
+        }
+        if !self.checkpointed && self.state_store.is_none() {
+            warn!("transform{} receive checkpoint barrier without state config, buffered groups will be lost on failure", self.task_context.operator_config.id);
+        }
+        self.checkpointed = true;
+        self.snapshot().map_err(|e| format!("checkpoint {} snapshot error: {}", checkpoint_id, e))
+    }
+
+    /// 出错重启和停止时保存还没有输出的数据, 使用checkpoint时保留最后一次barrier的快照
+    fn close(&mut self) -> Result<()> {
+        if self.checkpointed {
+            return Ok(());
+        }
+        self.snapshot()
     }
 }
 
-struct RowResultFunction {
+pub(super) struct RowResultFunction {
     result_projection: MutableProjection,
+    having: Option<Box<dyn PhysicalExpr>>,
 }
 
 impl RowResultFunction {
-    fn new(result_exprs: Vec<Expr>, input: Vec<AttributeReference>) -> Result<Self> {
+    fn new(result_exprs: Vec<Expr>, having: Option<Expr>, input: Vec<AttributeReference>) -> Result<Self> {
+        let having = having.map(|having| BoundReference::bind_reference(having, input.clone()).and_then(|having| create_physical_expr(&having))).transpose()?;
         let expressions = BoundReference::bind_references(result_exprs, input)?;
         let result_projection = MutableProjection::new(expressions)?;
-        Ok(Self { result_projection })
+        Ok(Self { result_projection, having })
+    }
+
+    /// having为true且不满足having条件时返回None
+    pub(super) fn apply(&mut self, input: &dyn Row, having: bool) -> Option<&GenericRow> {
+        if having && let Some(having) = &self.having && !matches!(having.eval(input), Value::Boolean(true)) {
+            return None;
+        }
+        Some(self.result_projection.apply(input))
     }
 }
 
-struct RowAggregateFunction {
+pub(super) struct RowAggregateFunction {
     agg_exprs: Vec<Expr>,
     agg_attributes: Vec<AttributeReference>,
     input_attrs: Vec<AttributeReference>,
@@ -179,6 +335,7 @@ struct RowAggregateFunction {
     expr_agg_init: Projection,
     typed_functions: Vec<(usize, Box<dyn PhysicalTypedAggFunction>)>,
     process_row: ProcessRow,
+    merge_exprs: Vec<(usize, Box<dyn PhysicalExpr>)>,
     eavl_projection: Projection,
     agg_rst: GenericRow,
     empty_row: GenericRow,
@@ -211,10 +368,34 @@ impl RowAggregateFunction {
         }
         let expr_agg_init = Projection::new(init_exprs)?;
         let process_row = ProcessRow::new(&agg_exprs, agg_attributes.clone(), input_attrs.clone())?;
+        let merge_exprs = Self::merge_exprs(&agg_exprs, agg_attributes.clone())?;
         let agg_rst = GenericRow::new_with_size(eval_exprs.len());
         let eavl_projection = Projection::new_with_input_attrs(eval_exprs, agg_attributes.clone())?;
         let empty_row = GenericRow::new(Vec::new());
-        Ok(Self { agg_exprs, agg_attributes, input_attrs, agg_buffer_len, expr_agg_init, typed_functions, process_row, eavl_projection, agg_rst, empty_row })
+        Ok(Self { agg_exprs, agg_attributes, input_attrs, agg_buffer_len, expr_agg_init, typed_functions, process_row, merge_exprs, eavl_projection, agg_rst, empty_row })
+    }
+
+    /// 合并两个buffer的表达式, 输入为buffer + 另一个buffer, typed函数使用merge_value合并
+    fn merge_exprs(agg_exprs: &[Expr], agg_attributes: Vec<AttributeReference>) -> Result<Vec<(usize, Box<dyn PhysicalExpr>)>> {
+        let mut merge_exprs = Vec::new();
+        let mut input_agg_attrs = Vec::new();
+        for expr in agg_exprs {
+            match expr {
+                Expr::DeclarativeAggFunction(f) => {
+                    merge_exprs.extend(f.merge_expressions());
+                    input_agg_attrs.extend(f.input_agg_buffer_attributes());
+                },
+                Expr::TypedAggFunction(f) => {
+                    merge_exprs.push(Expr::NoOp);
+                    input_agg_attrs.extend(f.agg_buffer_attributes().iter().map(|attr| attr.new_instance()));
+                },
+                _ => return Err(format!("not support agg expr:{:?}", expr))
+            }
+        }
+        let input = agg_attributes.into_iter().chain(input_agg_attrs.into_iter()).collect();
+        BoundReference::bind_references(merge_exprs, input)?.iter().enumerate()
+            .filter(|(_, expr)| !matches!(expr, Expr::NoOp))
+            .map(|(i, expr)| create_physical_expr(expr).map(|expr| (i, expr))).collect()
     }
 
     fn initialize_agg_functions(agg_exprs: Vec<Expr>, input_attrs: Vec<AttributeReference>) -> Result<Vec<Expr>> {
@@ -245,7 +426,7 @@ impl RowAggregateFunction {
 
 
 impl RowAggregateFunction {
-    fn create_aggregation(&self) -> GenericRow {
+    pub(super) fn create_aggregation(&self) -> GenericRow {
         let mut buffer = GenericRow::new_with_size(self.agg_buffer_len);
         self.expr_agg_init.apply_targert(&mut buffer, &self.empty_row);
         for (_, func) in self.typed_functions.iter() {
@@ -254,11 +435,28 @@ impl RowAggregateFunction {
         buffer
     }
 
-    fn update(&self, buffer: &mut GenericRow, input: &dyn Row) {
-        self.process_row.process(buffer, input);
+    /// 返回buffer中新增的缓存行数
+    pub(super) fn update(&self, buffer: &mut GenericRow, input: &dyn Row) -> usize {
+        self.process_row.process(buffer, input)
+    }
+
+    /// 把other合并到buffer, 如合并session窗口
+    pub(super) fn merge(&self, buffer: &mut GenericRow, mut other: GenericRow) {
+        for (i, expr) in self.merge_exprs.iter() {
+            let joiner = JoinedRow::new(buffer, &other);
+            buffer.update(*i, expr.eval(&joiner));
+        }
+        for (_, func) in self.typed_functions.iter() {
+            let offset = func.mutable_agg_buffer_offset();
+            func.merge_value(buffer.get_mut(offset), mem::replace(other.get_mut(offset), Value::Null));
+        }
+    }
+
+    pub(super) fn buffer_rows(&self, buffer: &GenericRow) -> usize {
+        self.typed_functions.iter().map(|(_, func)| func.buffer_rows(buffer)).sum()
     }
 
-    fn eval(&mut self, buffer: &mut GenericRow) -> &GenericRow {
+    pub(super) fn eval(&mut self, buffer: &mut GenericRow) -> &GenericRow {
         self.eavl_projection.apply_targert(&mut self.agg_rst, buffer);
         for (i, func) in self.typed_functions.iter() {
             self.agg_rst.update(*i, func.eval(buffer));
@@ -299,18 +497,22 @@ impl ProcessRow {
         Ok(Self { exprs, functions})
     }
 
-    fn process(&self, row: &mut GenericRow, input: &dyn Row)  {
+    fn process(&self, row: &mut GenericRow, input: &dyn Row) -> usize {
         for (i, expr) in self.exprs.iter() {
             let joiner = JoinedRow::new(row, input) ;
             row.update(*i, expr.eval(&joiner));
         }
+        let mut rows = 0;
         for func in self.functions.iter() {
+            let before = func.buffer_rows(row);
             func.update(row, input);
+            rows += func.buffer_rows(row) - before;
         }
+        rows
     }
 }
 
-struct RowKeySelector {
+pub(super) struct RowKeySelector {
     group_exprs: Vec<(usize, Box<dyn PhysicalExpr>)>,
 }
 
@@ -320,11 +522,157 @@ impl RowKeySelector {
         Self {group_exprs}
     }
 
-    fn get_key(&self, row: &dyn Row) -> GenericRow {
+    pub(super) fn get_key(&self, row: &dyn Row) -> GenericRow {
         let mut key = GenericRow::new_with_size(self.group_exprs.len());
         for (index, expr) in &self.group_exprs {
             key.update(*index, expr.eval(row));
         }
         key
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use serde_json::json;
+    use crate::config::{StateConfig, TransformConfig};
+    use crate::data::Value;
+    use crate::execution::VecCollector;
+    use crate::parser::parse_schema;
+    use super::*;
+
+    fn new_transform(config: serde_json::Value, state_dir: Option<&str>) -> Box<dyn Transform> {
+        let config: Box<dyn TransformConfig> = serde_json::from_value(config).unwrap();
+        let provider = config.build(parse_schema("cate_id int, bytes bigint").unwrap()).unwrap();
+        let mut task_context = TaskContext::default();
+        task_context.task_config = task_context.task_config.with_state_config(state_dir.map(|dir| StateConfig { dir: dir.to_string(), snapshot_interval_ms: 60000 }));
+        provider.create_transform(task_context).unwrap()
+    }
+
+    fn process(transform: &mut Box<dyn Transform>, out: &mut VecCollector, rows: &[(i32, i64)]) {
+        for (cate_id, bytes) in rows {
+            let row = GenericRow::new(vec![Value::int(*cate_id), Value::long(*bytes)]);
+            transform.process(&row, out, &mut TimeService::new()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("retl_task_agg_checkpoint_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let sql = "select cate_id, sum(bytes) bytes from tbl group by cate_id";
+        // 默认barrier时只保存快照, 不输出部分聚合的结果
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql}), dir.to_str());
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 10), (2, 20), (1, 30)]);
+        transform.on_barrier(1, &mut out).unwrap();
+        assert!(out.rows.is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let mut rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        rows.sort();
+        assert_eq!(rows, vec![(1, 40), (2, 20)]);
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "flush_on_checkpoint": true}), None);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 10), (2, 20)]);
+        transform.on_barrier(1, &mut out).unwrap();
+        assert_eq!(out.rows.len(), 2);
+    }
+
+    #[test]
+    fn test_top_n_max_rows() {
+        let sql = "select cate_id, sum(bytes) bytes from tbl group by cate_id";
+        let top_n = json!({"order_by": ["bytes desc"], "n": 1});
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "max_rows": 2, "top_n": top_n}), None);
+        let mut out = VecCollector::default();
+        // 溢出时只保留top n分组的buffer, interval触发时只输出一次top n
+        process(&mut transform, &mut out, &[(1, 10), (2, 30), (3, 20), (1, 5)]);
+        assert!(out.rows.is_empty());
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        assert_eq!(rows, vec![(2, 30)]);
+
+        // 分组2的数据跨过两次溢出, 合并后的40才是top 1, 不能被部分结果35的分组3挤掉
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "max_rows": 2, "top_n": top_n}), None);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(2, 30), (1, 10), (2, 10), (3, 35)]);
+        assert!(out.rows.is_empty());
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        assert_eq!(rows, vec![(2, 40)]);
+
+        let config: Box<dyn TransformConfig> = serde_json::from_value(json!({"type": "task_aggregate", "sql": sql, "top_n": top_n, "flush_on_checkpoint": true})).unwrap();
+        assert!(config.build(parse_schema("cate_id int, bytes bigint").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_having_distinct() {
+        let sql = "select cate_id, count(distinct bytes) cnt, sum(distinct bytes) bytes, count(bytes) total from tbl group by cate_id having cnt > 1";
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql}), None);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 10), (1, 20), (1, 10), (2, 30), (2, 30), (3, 5)]);
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1), row.get_long(2), row.get_long(3))).collect();
+        assert_eq!(rows, vec![(1, 2, 30, 3)]);
+
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": "select distinct cate_id from tbl"}), None);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 10), (2, 20), (1, 30)]);
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let mut rows: Vec<_> = out.rows.iter().map(|row| row.get_int(0)).collect();
+        rows.sort();
+        assert_eq!(rows, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_having_max_rows() {
+        // 溢出时输出部分聚合的结果, 不过滤; interval触发时应用having
+        let sql = "select cate_id, sum(bytes) total from tbl group by cate_id having total > 25";
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "max_rows": 3}), None);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 20), (2, 10), (3, 5), (1, 30), (2, 5)]);
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let mut rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        rows.sort();
+        assert_eq!(rows, vec![(1, 20), (1, 30), (2, 10), (3, 5)]);
+
+        let schema = parse_schema("cate_id int, bytes bigint").unwrap();
+        let config: Box<dyn TransformConfig> = serde_json::from_value(json!({"type": "task_aggregate", "sql": sql, "flush_on_checkpoint": true})).unwrap();
+        assert!(config.build(schema.clone()).is_err());
+        let sql = "select cate_id, count(distinct bytes) cnt from tbl group by cate_id";
+        let config: Box<dyn TransformConfig> = serde_json::from_value(json!({"type": "task_aggregate", "sql": sql, "flush_on_checkpoint": true})).unwrap();
+        assert!(config.build(schema).is_err());
+    }
+
+    #[test]
+    fn test_distinct_max_rows() {
+        // 去重集合中的元素计入max_rows: 1个分组 + 2个元素时输出
+        let sql = "select cate_id, count(distinct bytes) cnt from tbl group by cate_id";
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "max_rows": 3}), None);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 10), (1, 10)]);
+        assert!(out.rows.is_empty());
+        process(&mut transform, &mut out, &[(1, 20)]);
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        assert_eq!(rows, vec![(1, 2)]);
+        process(&mut transform, &mut out, &[(1, 30)]);
+        transform.on_time(u64::MAX, &mut out).unwrap();
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        assert_eq!(rows, vec![(1, 2), (1, 1)]);
+
+        // 从快照恢复去重集合后重新计算缓存行数
+        let dir = std::env::temp_dir().join(format!("retl_task_agg_distinct_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "max_rows": 3}), dir.to_str());
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, &[(1, 10)]);
+        transform.close().unwrap();
+        let mut transform = new_transform(json!({"type": "task_aggregate", "sql": sql, "max_rows": 3}), dir.to_str());
+        transform.restore_state(&mut TimeService::new()).unwrap();
+        process(&mut transform, &mut out, &[(1, 10), (1, 20)]);
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_int(0), row.get_long(1))).collect();
+        assert_eq!(rows, vec![(1, 2)]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
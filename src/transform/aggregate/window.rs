@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::BuildHasherDefault;
+use std::mem;
+use ahash::AHasher;
+use prometheus::IntCounter;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::config::TaskContext;
+use crate::Result;
+use crate::data::{GenericRow, JoinedRow, Row, Value};
+use crate::datetime_utils::processing_time_millis;
+use crate::execution::{Collector, StateReader, StateStore, StateWriter, TimeService};
+use crate::expr::{AttributeReference, Expr};
+use crate::transform::{Transform, ProcessOperator, OutOperator};
+use crate::transform::aggregate::{create_row_functions, state_fingerprint, RowAggregateFunction, RowKeySelector, RowResultFunction};
+use crate::types::{DataType, Schema};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WindowConfig {
+    Tumbling { size_ms: u64 },
+    Hopping { size_ms: u64, slide_ms: u64 },
+    /// 同一个key相邻数据间隔不超过gap_ms属于同一个session, 乱序数据连接已存在的两个session时合并为一个session
+    Session { gap_ms: u64 },
+}
+
+impl WindowConfig {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            WindowConfig::Tumbling { size_ms } if size_ms > 0 => Ok(()),
+            WindowConfig::Hopping { size_ms, slide_ms } if slide_ms > 0 && slide_ms <= size_ms => Ok(()),
+            WindowConfig::Session { gap_ms } if gap_ms > 0 => Ok(()),
+            _ => Err(format!("invalid window config:{:?}", self)),
+        }
+    }
+
+    /// 返回ts所属的且还没有触发的窗口
+    fn assign_windows(&self, ts: i64, watermark: i64, windows: &mut Vec<(i64, i64)>) {
+        windows.clear();
+        match *self {
+            WindowConfig::Tumbling { size_ms } => {
+                let size = size_ms as i64;
+                let start = ts - ts.rem_euclid(size);
+                if start + size > watermark {
+                    windows.push((start, start + size));
+                }
+            },
+            WindowConfig::Hopping { size_ms, slide_ms } => {
+                let (size, slide) = (size_ms as i64, slide_ms as i64);
+                let mut start = ts - ts.rem_euclid(slide);
+                while start > ts - size {
+                    if start + size > watermark {
+                        windows.push((start, start + size));
+                    }
+                    start -= slide;
+                }
+            },
+            WindowConfig::Session { gap_ms } => {
+                let gap = gap_ms as i64;
+                if ts + gap > watermark {
+                    windows.push((ts, ts + gap));
+                }
+            },
+        }
+    }
+}
+
+struct WindowBuffer {
+    start: i64,
+    end: i64,
+    buffer: GenericRow,
+}
+
+struct PreProcessCollector<'a> {
+    transform: &'a mut WindowAggregateTransform,
+}
+
+impl<'a> Collector for PreProcessCollector<'a> {
+    fn collect(&mut self, row: &dyn Row) -> Result<()> {
+        self.transform.post_process(row);
+        Ok(())
+    }
+
+    fn check_timer(&mut self, _time: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 基于事件时间的窗口聚合, watermark = 最大事件时间 - max_out_of_orderness_ms, watermark超过窗口结束时间时输出窗口
+pub struct WindowAggregateTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    no_pre: bool,
+    pre_process: Box<dyn ProcessOperator>,
+    agg_func: RowAggregateFunction,
+    rst_func: RowResultFunction,
+    key_selector: RowKeySelector,
+    time_index: usize,
+    time_type: DataType,
+    window: WindowConfig,
+    max_out_of_orderness_ms: i64,
+    late_output: Option<String>,
+    buffers: HashMap<GenericRow, Vec<WindowBuffer>, BuildHasherDefault<AHasher>>,
+    current_windows: Vec<(i64, i64)>,
+    max_timestamp: i64,
+    watermark: i64,
+    next_fire_ms: i64,
+    window_row: GenericRow,
+    state_store: Option<StateStore>,
+    snapshot_time_ms: u64,
+    /// 收到过checkpoint barrier, 之后只在barrier时保存快照, 和source提交的offset对应
+    checkpointed: bool,
+    num_records_invalid_time: IntCounter,
+}
+
+impl WindowAggregateTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, no_pre: bool, pre_process: Box<dyn ProcessOperator>, agg_exprs: Vec<Expr>, group_exprs: Vec<Expr>,  result_exprs: Vec<Expr>, having: Option<Expr>,
+               input_attrs: Vec<AttributeReference>, time_index: usize, time_type: DataType, window: WindowConfig, max_out_of_orderness_ms: u64, late_output: Option<String>) -> Result<Self> {
+        let fingerprint = state_fingerprint(&group_exprs, &agg_exprs);
+        let (agg_func, key_selector, rst_func) = create_row_functions(agg_exprs, group_exprs, result_exprs, having, input_attrs)?;
+        let state_store = StateStore::new(&task_context, "window_aggregate", fingerprint);
+        let registry = &task_context.task_config.metrics_registry;
+        let num_records_invalid_time = task_context.base_iometrics.new_counter(registry, "num_records_invalid_time", "number of records dropped for null or invalid event time")?;
+        Ok(Self {
+            task_context,
+            schema,
+            no_pre,
+            pre_process,
+            agg_func,
+            rst_func,
+            key_selector,
+            time_index,
+            time_type,
+            window,
+            max_out_of_orderness_ms: max_out_of_orderness_ms as i64,
+            late_output,
+            buffers: HashMap::default(),
+            current_windows: Vec::new(),
+            max_timestamp: i64::MIN,
+            watermark: i64::MIN,
+            next_fire_ms: i64::MAX,
+            window_row: GenericRow::new_with_size(2),
+            state_store,
+            snapshot_time_ms: 0,
+            checkpointed: false,
+            num_records_invalid_time,
+        })
+    }
+
+    fn event_time_millis(&self, row: &dyn Row) -> Option<i64> {
+        match (row.get(self.time_index), &self.time_type) {
+            (Value::Long(ts), DataType::Timestamp) => Some(ts.div_euclid(1000)),
+            (Value::Long(ts), _) => Some(*ts),
+            _ => None,
+        }
+    }
+
+    fn post_process(&mut self, row: &dyn Row) {
+        let key = self.key_selector.get_key(row);
+        let windows = self.buffers.entry(key).or_default();
+        for (start, end) in self.current_windows.iter().copied() {
+            if let WindowConfig::Session { .. } = self.window {
+                // 数据可能连接多个已有的session, 和数据重叠的session都合并到一个窗口
+                let mut session = WindowBuffer { start, end, buffer: self.agg_func.create_aggregation() };
+                let mut i = 0;
+                while i < windows.len() {
+                    if start < windows[i].end && end > windows[i].start {
+                        let window = windows.swap_remove(i);
+                        session.start = session.start.min(window.start);
+                        session.end = session.end.max(window.end);
+                        self.agg_func.merge(&mut session.buffer, window.buffer);
+                    } else {
+                        i += 1;
+                    }
+                }
+                self.agg_func.update(&mut session.buffer, row);
+                self.next_fire_ms = self.next_fire_ms.min(session.end);
+                windows.push(session);
+                continue;
+            }
+            let window = match windows.iter_mut().find(|w| w.start == start) {
+                Some(window) => {
+                    window.start = window.start.min(start);
+                    window.end = window.end.max(end);
+                    window
+                },
+                None => {
+                    windows.push(WindowBuffer { start, end, buffer: self.agg_func.create_aggregation() });
+                    windows.last_mut().unwrap()
+                },
+            };
+            self.agg_func.update(&mut window.buffer, row);
+            self.next_fire_ms = self.next_fire_ms.min(window.end);
+        }
+    }
+
+    fn fire(&mut self, out: &mut dyn Collector) -> Result<()> {
+        let watermark = self.watermark;
+        let mut fired = Vec::new();
+        let mut next_fire_ms = i64::MAX;
+        self.buffers.retain(|key, windows| {
+            let mut i = 0;
+            while i < windows.len() {
+                if windows[i].end <= watermark {
+                    fired.push((key.clone(), windows.swap_remove(i)));
+                } else {
+                    next_fire_ms = next_fire_ms.min(windows[i].end);
+                    i += 1;
+                }
+            }
+            !windows.is_empty()
+        });
+        self.next_fire_ms = next_fire_ms;
+        fired.sort_by_key(|(_, w)| (w.end, w.start));
+        let mut rows = 0;
+        for (key, mut window) in fired {
+            self.window_row.update(0, Value::Long(window.start * 1000));
+            self.window_row.update(1, Value::Long(window.end * 1000));
+            let value = self.agg_func.eval(&mut window.buffer);
+            let joiner = JoinedRow::new(&key, value);
+            let Some(row) = self.rst_func.apply(&joiner, true) else {
+                continue;
+            };
+            out.collect(&JoinedRow::new(&self.window_row, row))?;
+            rows += 1;
+        }
+        self.task_context.base_iometrics.num_records_out_inc_by(rows);
+        Ok(())
+    }
+
+    /// 快照内容: max_timestamp + watermark + key数 + (key, 窗口数, (start, end, buffer)列表)列表, 快照之后输出的窗口重启后会再次输出
+    fn snapshot(&mut self) -> Result<()> {
+        let Some(state_store) = &self.state_store else {
+            return Ok(());
+        };
+        if self.buffers.is_empty() {
+            return state_store.clear();
+        }
+        let mut writer = StateWriter::new();
+        writer.write_i64(self.max_timestamp);
+        writer.write_i64(self.watermark);
+        writer.write_u32(self.buffers.len() as u32);
+        for (key, windows) in &self.buffers {
+            writer.write_row(key)?;
+            writer.write_u32(windows.len() as u32);
+            for window in windows {
+                writer.write_i64(window.start);
+                writer.write_i64(window.end);
+                writer.write_row(&window.buffer)?;
+            }
+        }
+        state_store.save(&writer.into_bytes())
+    }
+}
+
+impl Debug for WindowAggregateTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowAggregateTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("window", &self.window)
+            .field("max_out_of_orderness_ms", &self.max_out_of_orderness_ms)
+            .finish()
+    }
+}
+
+impl Transform for WindowAggregateTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        let ts = match self.event_time_millis(row) {
+            Some(ts) => ts,
+            None => {
+                // 事件时间为null或者类型不对, 丢弃并计数
+                debug!("transform{} drop record with invalid event time: {:?}", self.task_context.operator_config.id, row.get(self.time_index));
+                self.num_records_invalid_time.inc();
+                return Ok(());
+            },
+        };
+        let mut windows = mem::take(&mut self.current_windows);
+        self.window.assign_windows(ts, self.watermark, &mut windows);
+        self.current_windows = windows;
+        if self.current_windows.is_empty() {
+            // 迟到数据
+            return match &self.late_output {
+                Some(late_output) => out.collect_side(late_output, row),
+                None => Ok(()),
+            };
+        }
+
+        if self.no_pre {
+            self.post_process(row);
+        } else {
+            let mut pre_process = mem::replace(&mut self.pre_process, Box::new(OutOperator));
+            let mut pre_out = PreProcessCollector{transform: self};
+            let rst = pre_process.process(row, &mut pre_out);
+            self.pre_process = pre_process;
+            rst?;
+        }
+
+        if ts > self.max_timestamp {
+            self.max_timestamp = ts;
+            self.watermark = ts.saturating_sub(self.max_out_of_orderness_ms);
+            if self.watermark >= self.next_fire_ms {
+                time_service.register_timer(processing_time_millis());
+            }
+        }
+        if let Some(state_store) = &self.state_store && self.snapshot_time_ms == 0 {
+            self.snapshot_time_ms = processing_time_millis() + state_store.snapshot_interval_ms;
+            time_service.register_timer(self.snapshot_time_ms);
+        }
+        Ok(())
+    }
+
+    fn restore_state(&mut self, time_service: &mut TimeService) -> Result<()> {
+        let Some(data) = self.state_store.as_ref().map(|state_store| state_store.load()).transpose()?.flatten() else {
+            return Ok(());
+        };
+        let mut reader = StateReader::new(&data);
+        self.max_timestamp = reader.read_i64()?;
+        self.watermark = reader.read_i64()?;
+        let len = reader.read_u32()? as usize;
+        for _ in 0..len {
+            let key = reader.read_row()?;
+            let num_windows = reader.read_u32()? as usize;
+            let mut windows = Vec::with_capacity(num_windows);
+            for _ in 0..num_windows {
+                let (start, end) = (reader.read_i64()?, reader.read_i64()?);
+                windows.push(WindowBuffer { start, end, buffer: reader.read_row()? });
+                self.next_fire_ms = self.next_fire_ms.min(end);
+            }
+            self.buffers.insert(key, windows);
+        }
+        if self.watermark >= self.next_fire_ms {
+            time_service.register_timer(processing_time_millis());
+        }
+        info!("transform{}_{} restored {} keys from state", self.task_context.operator_config.id, self.task_context.task_config.subtask_index, len);
+        Ok(())
+    }
+
+    fn on_time(&mut self, time: u64, out: &mut dyn Collector) -> Result<()> {
+        self.fire(out)?;
+        if self.snapshot_time_ms != 0 && time >= self.snapshot_time_ms {
+            self.snapshot_time_ms = 0;
+            if !self.checkpointed {
+                self.snapshot()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// source在barrier返回后提交offset, 未触发的窗口包含已提交offset的数据, 需要在barrier时保存快照, 没有配置state时出错重启会丢失这些窗口
+    fn on_barrier(&mut self, checkpoint_id: u64, _out: &mut dyn Collector) -> Result<()> {
+        if !self.checkpointed && self.state_store.is_none() {
+            warn!("transform{} receive checkpoint barrier without state config, open windows will be lost on failure", self.task_context.operator_config.id);
+        }
+        self.checkpointed = true;
+        self.snapshot().map_err(|e| format!("checkpoint {} snapshot error: {}", checkpoint_id, e))
+    }
+
+    /// 输入结束时watermark推进到最大值, 输出所有还没有触发的窗口
+    fn finish(&mut self, out: &mut dyn Collector) -> Result<()> {
+        self.watermark = i64::MAX;
+        self.fire(out)
+    }
+
+    /// 出错重启时保存还没有触发的窗口, 使用checkpoint时保留最后一次barrier的快照
+    fn close(&mut self) -> Result<()> {
+        if self.checkpointed {
+            return Ok(());
+        }
+        self.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{StateConfig, TransformConfig};
+    use crate::execution::VecCollector;
+    use crate::execution::test_runner::{run_test_case, TestCase};
+    use crate::parser::parse_schema;
+    use super::*;
+
+    #[test]
+    fn test_assign_windows() {
+        let mut windows = Vec::new();
+        WindowConfig::Tumbling { size_ms: 1000 }.assign_windows(2500, i64::MIN, &mut windows);
+        assert_eq!(windows, vec![(2000, 3000)]);
+        WindowConfig::Tumbling { size_ms: 1000 }.assign_windows(2500, 3000, &mut windows);
+        assert!(windows.is_empty());
+        WindowConfig::Hopping { size_ms: 3000, slide_ms: 1000 }.assign_windows(2500, i64::MIN, &mut windows);
+        assert_eq!(windows, vec![(2000, 5000), (1000, 4000), (0, 3000)]);
+        WindowConfig::Hopping { size_ms: 3000, slide_ms: 1000 }.assign_windows(2500, 4000, &mut windows);
+        assert_eq!(windows, vec![(2000, 5000)]);
+        WindowConfig::Tumbling { size_ms: 1000 }.assign_windows(-500, i64::MIN, &mut windows);
+        assert_eq!(windows, vec![(-1000, 0)]);
+        WindowConfig::Session { gap_ms: 1000 }.assign_windows(2500, i64::MIN, &mut windows);
+        assert_eq!(windows, vec![(2500, 3500)]);
+        assert!(WindowConfig::Hopping { size_ms: 1000, slide_ms: 3000 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_finish_fire_all_windows() {
+        let case: TestCase = serde_yaml::from_str(r#"
+name: finish
+steps:
+  - source: inline_source
+    rows: [{"ts": 1000, "cate_id": 1, "bytes": 10}, {"ts": 3500, "cate_id": 1, "bytes": 20}, {"ts": 3800, "cate_id": 2, "bytes": 30}]
+expected:
+  print_sink:
+    - {"window_start": "1970-01-01 00:00:00", "window_end": "1970-01-01 00:00:02", "cate_id": 1, "bytes": 10, "count": 1}
+    - {"window_start": "1970-01-01 00:00:02", "window_end": "1970-01-01 00:00:04", "cate_id": 1, "bytes": 20, "count": 1}
+    - {"window_start": "1970-01-01 00:00:02", "window_end": "1970-01-01 00:00:04", "cate_id": 2, "bytes": 30, "count": 1}
+ordered: false
+"#).unwrap();
+        let result = run_test_case("config/application_window_agg.yaml", &[], &case).unwrap();
+        assert!(result.passed(), "{:?}", result.failures);
+    }
+
+    #[test]
+    fn test_session_bridge() {
+        let config: Box<dyn TransformConfig> = serde_json::from_value(serde_json::json!({"type": "window_aggregate", "time_column": "ts", "max_out_of_orderness_ms": 10000,
+            "window": {"type": "session", "gap_ms": 2000}, "sql": "select cate_id, sum(bytes) bytes, count(distinct bytes) cnt from tbl group by cate_id"})).unwrap();
+        let provider = config.build(parse_schema("ts bigint, cate_id int, bytes bigint").unwrap()).unwrap();
+        let mut transform = provider.create_transform(TaskContext::default()).unwrap();
+        let mut out = VecCollector::default();
+        // [1000, 3000)和[4000, 6000)两个session, 乱序的2500连接两个session
+        for (ts, bytes) in [(1000, 10), (4000, 20), (2500, 10), (9000, 5)] {
+            transform.process(&GenericRow::new(vec![Value::long(ts), Value::int(1), Value::long(bytes)]), &mut out, &mut TimeService::new()).unwrap();
+        }
+        transform.finish(&mut out).unwrap();
+        let rows: Vec<_> = out.rows.iter().map(|row| (row.get_long(0) / 1000, row.get_long(1) / 1000, row.get_long(3), row.get_long(4))).collect();
+        assert_eq!(rows, vec![(1000, 6000, 40, 2), (9000, 11000, 5, 1)]);
+    }
+
+    #[test]
+    fn test_checkpoint_snapshot() {
+        let dir = std::env::temp_dir().join(format!("retl_window_checkpoint_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config: Box<dyn TransformConfig> = serde_json::from_value(serde_json::json!({"type": "window_aggregate", "time_column": "ts",
+            "window": {"type": "tumbling", "size_ms": 2000}, "sql": "select cate_id, sum(bytes) bytes from tbl group by cate_id"})).unwrap();
+        let provider = config.build(parse_schema("ts bigint, cate_id int, bytes bigint").unwrap()).unwrap();
+        let new_transform = || {
+            let mut task_context = TaskContext::default();
+            task_context.task_config = task_context.task_config.with_state_config(Some(StateConfig { dir: dir.to_string_lossy().into_owned(), snapshot_interval_ms: 60000 }));
+            provider.create_transform(task_context).unwrap()
+        };
+        let mut out = VecCollector::default();
+        let mut transform = new_transform();
+        transform.process(&GenericRow::new(vec![Value::long(1000), Value::int(1), Value::long(10)]), &mut out, &mut TimeService::new()).unwrap();
+        transform.on_barrier(1, &mut out).unwrap();
+        // barrier之后的数据重启后由source重新消费, 不能进入快照
+        transform.process(&GenericRow::new(vec![Value::long(1500), Value::int(1), Value::long(20)]), &mut out, &mut TimeService::new()).unwrap();
+        transform.close().unwrap();
+        assert!(out.rows.is_empty());
+
+        let mut transform = new_transform();
+        transform.restore_state(&mut TimeService::new()).unwrap();
+        transform.finish(&mut out).unwrap();
+        assert_eq!(out.rows.len(), 1);
+        assert_eq!(out.rows[0].get_long(3), 10);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::config::{TaskContext, TransformConfig, TransformProvider};
+use crate::expr::{BoundReference, Expr};
+use crate::sql_utils;
+use crate::transform::dedup::DedupTransform;
+use crate::transform::Transform;
+use crate::types::Schema;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupKeep {
+    /// 立即输出key第一次出现的数据, ttl内重复的数据丢弃
+    #[default]
+    First,
+    /// 缓存key最后一次出现的数据, ttl到期或者被淘汰时输出
+    Last,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupTransformConfig {
+    /// 去重key表达式
+    keys: Vec<String>,
+    /// key第一次出现后保留的时间(处理时间)
+    ttl_ms: u64,
+    #[serde(default)]
+    keep: DedupKeep,
+    /// 最多保留的key数, 超过时淘汰最近最少访问的key. 每个key只保存一份, 内存约为max_keys * (key + keep last时缓存的数据)
+    #[serde(default = "default_max_keys")]
+    max_keys: usize,
+}
+
+fn default_max_keys() -> usize {
+    1000000
+}
+
+#[typetag::serde(name = "dedup")]
+impl TransformConfig for DedupTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        if self.keys.is_empty() {
+            return Err("dedup keys is empty".to_string());
+        }
+        if self.ttl_ms == 0 || self.max_keys == 0 {
+            return Err(format!("dedup ttl_ms and max_keys must be greater than 0, ttl_ms:{}, max_keys:{}", self.ttl_ms, self.max_keys));
+        }
+        let mut key_exprs = Vec::with_capacity(self.keys.len());
+        for key in self.keys.iter() {
+            let expression = sql_utils::parse_expr(key, &schema)?;
+            key_exprs.push(BoundReference::bind_reference(expression.expr, expression.child.output())?);
+        }
+        Ok(Box::new(DedupTransformProvider { schema, config: self.clone(), key_exprs }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DedupTransformProvider {
+    schema: Schema,
+    config: DedupTransformConfig,
+    key_exprs: Vec<Expr>,
+}
+
+impl TransformProvider for DedupTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        Ok(Box::new(DedupTransform::new(task_context, self.schema.clone(), &self.key_exprs, self.config.ttl_ms, self.config.keep, self.config.max_keys)?))
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::BuildHasherDefault;
+use std::rc::Rc;
+use ahash::AHasher;
+use prometheus::IntCounter;
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::{GenericRow, Row};
+use crate::datetime_utils::processing_time_millis;
+use crate::execution::{Collector, TimeService};
+use crate::expr::Expr;
+use crate::physical_expr::{create_physical_expr, PhysicalExpr};
+use crate::transform::dedup::DedupKeep;
+use crate::transform::Transform;
+use crate::types::Schema;
+
+/// 过期检查的timer间隔, 过期时间向上取整到间隔, 每个间隔最多一个timer
+const TIMER_INTERVAL_MS: u64 = 1000;
+
+struct DedupEntry {
+    expire_at: u64,
+    /// 插入时的序号, expiry中的key为(expire_at, insert_seq)
+    insert_seq: u64,
+    /// lru中的访问序号
+    seq: u64,
+    /// keep last时缓存的数据
+    row: Option<GenericRow>,
+}
+
+/// 按key去重, key第一次出现后ttl_ms内的数据视为重复, key数超过max_keys时淘汰最近最少访问的key
+pub struct DedupTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    keys: Vec<Box<dyn PhysicalExpr>>,
+    ttl_ms: u64,
+    keep: DedupKeep,
+    max_keys: usize,
+    /// 每个key只保存一份, lru和expiry索引共享entries中的key
+    entries: HashMap<Rc<GenericRow>, DedupEntry, BuildHasherDefault<AHasher>>,
+    lru: BTreeMap<u64, Rc<GenericRow>>,
+    /// 按过期时间排序的索引, 过期检查只访问已过期的key
+    expiry: BTreeMap<(u64, u64), Rc<GenericRow>>,
+    next_seq: u64,
+    key: GenericRow,
+    num_dedup_hits: IntCounter,
+    num_dedup_misses: IntCounter,
+}
+
+impl DedupTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, key_exprs: &[Expr], ttl_ms: u64, keep: DedupKeep, max_keys: usize) -> Result<Self> {
+        let keys = key_exprs.iter().map(create_physical_expr).collect::<Result<Vec<_>>>()?;
+        let key = GenericRow::new_with_size(keys.len());
+        let registry = &task_context.task_config.metrics_registry;
+        let num_dedup_hits = task_context.base_iometrics.new_counter(registry, "num_dedup_hits", "number of duplicate records")?;
+        let num_dedup_misses = task_context.base_iometrics.new_counter(registry, "num_dedup_misses", "number of records with new key")?;
+        Ok(Self { task_context, schema, keys, ttl_ms, keep, max_keys, entries: HashMap::default(), lru: BTreeMap::new(),
+            expiry: BTreeMap::new(), next_seq: 0,
+            key, num_dedup_hits, num_dedup_misses })
+    }
+
+    fn emit(&self, row: Option<GenericRow>, out: &mut dyn Collector) -> Result<()> {
+        if let Some(row) = row {
+            self.task_context.base_iometrics.num_records_out_inc_by(1);
+            out.collect(&row)?;
+        }
+        Ok(())
+    }
+
+    fn process_at(&mut self, row: &dyn Row, out: &mut dyn Collector, time_service: &mut TimeService, now: u64) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        for (i, key) in self.keys.iter().enumerate() {
+            self.key.update(i, key.eval(row));
+        }
+        self.next_seq += 1;
+        let seq = self.next_seq;
+        if let Some(entry) = self.entries.get_mut(&self.key) && entry.expire_at > now {
+            self.num_dedup_hits.inc();
+            let key = self.lru.remove(&entry.seq).unwrap();
+            self.lru.insert(seq, key);
+            entry.seq = seq;
+            if self.keep == DedupKeep::Last {
+                entry.row = Some(row.to_generic_row());
+            }
+            return Ok(());
+        }
+
+        self.num_dedup_misses.inc();
+        // key已过期但timer还没有触发
+        if let Some(entry) = self.entries.remove(&self.key) {
+            self.lru.remove(&entry.seq);
+            self.expiry.remove(&(entry.expire_at, entry.insert_seq));
+            self.emit(entry.row, out)?;
+        }
+        let expire_at = now + self.ttl_ms;
+        let entry_row = match self.keep {
+            DedupKeep::First => {
+                self.emit(Some(row.to_generic_row()), out)?;
+                None
+            },
+            DedupKeep::Last => Some(row.to_generic_row()),
+        };
+        let key = Rc::new(self.key.clone());
+        self.lru.insert(seq, key.clone());
+        self.expiry.insert((expire_at, seq), key.clone());
+        self.entries.insert(key, DedupEntry { expire_at, insert_seq: seq, seq, row: entry_row });
+        time_service.register_timer(expire_at.div_ceil(TIMER_INTERVAL_MS) * TIMER_INTERVAL_MS);
+
+        while self.entries.len() > self.max_keys {
+            let (_, key) = self.lru.pop_first().unwrap();
+            let entry = self.entries.remove(&key).unwrap();
+            self.expiry.remove(&(entry.expire_at, entry.insert_seq));
+            self.emit(entry.row, out)?;
+        }
+        Ok(())
+    }
+
+    /// 删除过期的key, keep last时按过期时间顺序输出缓存的数据
+    fn expire(&mut self, time: u64, out: &mut dyn Collector) -> Result<()> {
+        while let Some(entry) = self.expiry.first_entry() && entry.key().0 <= time {
+            let key = entry.remove();
+            let entry = self.entries.remove(&key).unwrap();
+            self.lru.remove(&entry.seq);
+            self.emit(entry.row, out)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for DedupTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DedupTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("ttl_ms", &self.ttl_ms)
+            .field("keep", &self.keep)
+            .field("max_keys", &self.max_keys)
+            .finish()
+    }
+}
+
+impl Transform for DedupTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, time_service: &mut TimeService) -> Result<()> {
+        self.process_at(row, out, time_service, processing_time_millis())
+    }
+
+    fn on_time(&mut self, time: u64, out: &mut dyn Collector) -> Result<()> {
+        self.expire(time, out)
+    }
+
+    /// keep last时输出缓存的数据, 保留key的过期时间, checkpoint后重复的数据最多再输出一次
+    fn on_barrier(&mut self, _checkpoint_id: u64, out: &mut dyn Collector) -> Result<()> {
+        let mut rows: Vec<_> = self.entries.values_mut().filter_map(|entry| entry.row.take().map(|row| (entry.seq, row))).collect();
+        rows.sort_by_key(|(seq, _)| *seq);
+        for (_, row) in rows {
+            self.emit(Some(row), out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Value;
+    use crate::expr::BoundReference;
+    use crate::execution::VecCollector;
+    use crate::types::{DataType, Field};
+    use super::*;
+
+    fn new_transform(keep: DedupKeep, max_keys: usize) -> DedupTransform {
+        let schema = Schema::new(vec![Field::new("id", DataType::Long), Field::new("seq", DataType::Int)]);
+        let key = Expr::BoundReference(BoundReference::new(0, DataType::Long));
+        DedupTransform::new(TaskContext::default(), schema, &[key], 1000, keep, max_keys).unwrap()
+    }
+
+    fn process(transform: &mut DedupTransform, out: &mut VecCollector, id: i64, seq: i32, now: u64) {
+        let row = GenericRow::new(vec![Value::long(id), Value::int(seq)]);
+        transform.process_at(&row, out, &mut TimeService::new(), now).unwrap();
+    }
+
+    fn seqs(out: &VecCollector) -> Vec<i32> {
+        out.rows.iter().map(|row| row.get_int(1)).collect()
+    }
+
+    #[test]
+    fn test_dedup_first() {
+        let mut transform = new_transform(DedupKeep::First, 2);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, 1, 1, 0);
+        process(&mut transform, &mut out, 1, 2, 500);
+        process(&mut transform, &mut out, 2, 3, 500);
+        process(&mut transform, &mut out, 1, 4, 1000);
+        assert_eq!(seqs(&out), vec![1, 3, 4]);
+        // 超过max_keys淘汰最近最少访问的key 2
+        process(&mut transform, &mut out, 3, 5, 1100);
+        process(&mut transform, &mut out, 2, 6, 1200);
+        assert_eq!(seqs(&out), vec![1, 3, 4, 5, 6]);
+        assert_eq!(transform.num_dedup_hits.get(), 1);
+        assert_eq!(transform.num_dedup_misses.get(), 5);
+        assert_eq!(transform.expiry.len(), transform.entries.len());
+        transform.on_time(2100, &mut out).unwrap();
+        assert_eq!(transform.entries.len(), 1);
+        assert_eq!(transform.expiry.keys().next(), Some(&(2200, 6)));
+    }
+
+    #[test]
+    fn test_dedup_last() {
+        let mut transform = new_transform(DedupKeep::Last, 10);
+        let mut out = VecCollector::default();
+        process(&mut transform, &mut out, 1, 1, 0);
+        process(&mut transform, &mut out, 2, 2, 100);
+        process(&mut transform, &mut out, 1, 3, 200);
+        process(&mut transform, &mut out, 2, 4, 300);
+        assert!(out.rows.is_empty());
+        transform.on_time(1000, &mut out).unwrap();
+        assert_eq!(seqs(&out), vec![3]);
+        process(&mut transform, &mut out, 2, 5, 1050);
+        transform.on_barrier(1, &mut out).unwrap();
+        assert_eq!(seqs(&out), vec![3, 5]);
+        transform.on_time(2000, &mut out).unwrap();
+        assert_eq!(seqs(&out), vec![3, 5]);
+        assert!(transform.entries.is_empty() && transform.lru.is_empty() && transform.expiry.is_empty());
+        // key只保存一份
+        process(&mut transform, &mut out, 3, 6, 3000);
+        let key = transform.entries.keys().next().unwrap();
+        assert_eq!(Rc::strong_count(key), 3);
+        assert!(Rc::ptr_eq(key, transform.lru.values().next().unwrap()));
+    }
+}
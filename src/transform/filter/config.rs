@@ -28,6 +28,10 @@ pub struct FilterTransformProvider {
 }
 
 impl TransformProvider for FilterTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn create_transform(&self, task_context: TaskContext) -> crate::Result<Box<dyn Transform>> {
         let filter = self.filter.clone();
         let condition = filter.condition;
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::config::{TaskContext, TransformConfig, TransformProvider};
+use crate::expr::{BoundReference, Cast, Expr};
+use crate::parser::parse_schema;
+use crate::physical_expr::can_cast;
+use crate::sql_utils;
+use crate::transform::lookup::{LookupJoinTransform, LookupTableConfig, SharedLookupTable};
+use crate::transform::Transform;
+use crate::types::{Field, Schema};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupJoinType {
+    Inner,
+    #[default]
+    Left,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupJoinTransformConfig {
+    /// 输入数据的关联key表达式, 和table_keys一一对应
+    keys: Vec<String>,
+    table: LookupTableConfig,
+    table_schema: String,
+    table_keys: Vec<String>,
+    /// 输出的维表列, 默认为除table_keys外的所有列
+    #[serde(default)]
+    columns: Vec<String>,
+    #[serde(default)]
+    join_type: LookupJoinType,
+    /// 维表刷新间隔, 0不刷新
+    #[serde(default)]
+    refresh_interval_ms: u64,
+    /// 同一个节点所有subtask共享的维表
+    #[serde(skip)]
+    shared_table: SharedLookupTable,
+}
+
+#[typetag::serde(name = "lookup_join")]
+impl TransformConfig for LookupJoinTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        let table_schema = parse_schema(&self.table_schema)?;
+        if self.keys.is_empty() || self.keys.len() != self.table_keys.len() {
+            return Err(format!("lookup_join keys {:?} not match table_keys {:?}", self.keys, self.table_keys));
+        }
+        let field_index = |name: &String| table_schema.field_index(name).ok_or_else(|| format!("lookup table column {} not found in {}", name, table_schema));
+        let key_indices = self.table_keys.iter().map(field_index).collect::<Result<Vec<_>>>()?;
+        let value_indices = if self.columns.is_empty() {
+            (0..table_schema.fields.len()).filter(|i| !key_indices.contains(i)).collect()
+        } else {
+            self.columns.iter().map(field_index).collect::<Result<Vec<_>>>()?
+        };
+
+        let mut key_exprs = Vec::with_capacity(self.keys.len());
+        for (key, i) in self.keys.iter().zip(key_indices.iter()) {
+            let expression = sql_utils::parse_expr(key, &schema)?;
+            let expr = BoundReference::bind_reference(expression.expr, expression.child.output())?;
+            let key_type = &table_schema.fields[*i].data_type;
+            if expr.data_type() == key_type {
+                key_exprs.push(expr);
+            } else if can_cast(expr.data_type(), key_type) {
+                key_exprs.push(Expr::Cast(Cast::new(expr, key_type.clone())));
+            } else {
+                return Err(format!("lookup_join key {} can not cast to {}", key, key_type));
+            }
+        }
+
+        let mut fields = schema.fields.clone();
+        for i in value_indices.iter() {
+            let field = &table_schema.fields[*i];
+            if schema.field_index(&field.name).is_some() {
+                return Err(format!("lookup table column {} already exists in {}", field.name, schema));
+            }
+            fields.push(Field::new(field.name.clone(), field.data_type.clone()));
+        }
+
+        Ok(Box::new(LookupJoinTransformProvider {
+            schema: Schema::new(fields),
+            config: self.clone(),
+            table_schema,
+            key_exprs,
+            key_indices,
+            value_indices,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LookupJoinTransformProvider {
+    schema: Schema,
+    config: LookupJoinTransformConfig,
+    table_schema: Schema,
+    key_exprs: Vec<Expr>,
+    key_indices: Vec<usize>,
+    value_indices: Vec<usize>,
+}
+
+impl TransformProvider for LookupJoinTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        Ok(Box::new(LookupJoinTransform::new(task_context, self.schema.clone(), self.config.table.clone(), self.table_schema.clone(), self.config.shared_table.clone(),
+            &self.key_exprs, self.key_indices.clone(), self.value_indices.clone(), self.config.join_type, self.config.refresh_interval_ms)?))
+    }
+}
@@ -0,0 +1,7 @@
+mod config;
+mod table;
+mod transform;
+
+pub use config::*;
+pub use table::*;
+pub use transform::*;
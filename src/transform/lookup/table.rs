@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::BuildHasherDefault;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use ahash::AHasher;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::codecs::DeserializerConfig;
+use crate::data::{GenericRow, Row};
+use crate::types::Schema;
+
+/// 维表数据源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LookupTableConfig {
+    /// 本地文件, 按decoding切分记录, 默认每行一条数据, csv支持引号中的换行
+    File { path: String, decoding: Box<dyn DeserializerConfig> },
+    #[cfg(feature = "mysql")]
+    Mysql { url: String, sql: String },
+    #[cfg(feature = "postgres")]
+    Postgres { connect_params: String, sql: String },
+}
+
+pub type LookupRows = HashMap<GenericRow, GenericRow, BuildHasherDefault<AHasher>>;
+
+impl LookupTableConfig {
+    /// 加载维表数据, 返回key列 -> 输出列
+    pub fn load(&self, schema: &Schema, key_indices: &[usize], value_indices: &[usize]) -> Result<LookupRows> {
+        let mut rows = LookupRows::default();
+        let mut put = |row: &dyn Row| {
+            if key_indices.iter().any(|i| row.is_null(*i)) {
+                return;
+            }
+            let key = GenericRow::new(key_indices.iter().map(|i| row.get(*i).clone()).collect());
+            let value = GenericRow::new(value_indices.iter().map(|i| row.get(*i).clone()).collect());
+            rows.insert(key, value);
+        };
+        match self {
+            LookupTableConfig::File { path, decoding } => {
+                let content = fs::read(path).map_err(|e| format!("read lookup table {} error: {}", path, e))?;
+                let mut deserializer = decoding.build(schema.clone())?;
+                for (i, record) in decoding.split_records(&content)?.into_iter().enumerate() {
+                    let row = deserializer.deserialize(record).map_err(|e| format!("parse lookup table {} record {} error: {}", path, i + 1, e))?;
+                    put(row);
+                }
+            },
+            #[cfg(feature = "mysql")]
+            LookupTableConfig::Mysql { url, sql } => {
+                use mysql::prelude::Queryable;
+                let mut cast = StringRowCast::new(schema)?;
+                let pool = mysql::Pool::new(url.as_str()).map_err(|e| e.to_string())?;
+                let mut conn = pool.get_conn().map_err(|e| e.to_string())?;
+                let results: Vec<mysql::Row> = conn.query(sql).map_err(|e| e.to_string())?;
+                for result in results {
+                    let values = (0..schema.fields.len()).map(|i| match result.as_ref(i) {
+                        None | Some(mysql::Value::NULL) => None,
+                        Some(mysql::Value::Bytes(bytes)) => Some(String::from_utf8_lossy(bytes).to_string()),
+                        Some(v) => Some(v.as_sql(true)),
+                    }).collect();
+                    put(cast.apply(values));
+                }
+            },
+            #[cfg(feature = "postgres")]
+            LookupTableConfig::Postgres { connect_params, sql } => {
+                let mut cast = StringRowCast::new(schema)?;
+                let mut client = postgres::Client::connect(connect_params, postgres::NoTls).map_err(|e| e.to_string())?;
+                for message in client.simple_query(sql).map_err(|e| e.to_string())? {
+                    if let postgres::SimpleQueryMessage::Row(result) = message {
+                        let values = (0..schema.fields.len()).map(|i| result.get(i).map(|s| s.to_string())).collect();
+                        put(cast.apply(values));
+                    }
+                }
+            },
+        }
+        Ok(rows)
+    }
+}
+
+/// 同一个lookup_join节点所有subtask共享的维表, 由后台线程刷新, 数据线程发现版本变化时替换为新的数据
+#[derive(Debug)]
+pub struct LookupTable {
+    rows: Mutex<Arc<LookupRows>>,
+    version: AtomicU64,
+}
+
+/// 保存在节点配置中, 所有subtask关闭后维表和刷新线程释放, 重启后重新加载
+pub type SharedLookupTable = Arc<Mutex<Weak<LookupTable>>>;
+
+impl LookupTable {
+    /// 第一个open的subtask加载维表并启动刷新线程, 其它subtask直接使用
+    pub fn open(shared: &SharedLookupTable, config: &LookupTableConfig, schema: &Schema, key_indices: &[usize], value_indices: &[usize],
+                refresh_interval_ms: u64) -> Result<Arc<LookupTable>> {
+        let mut weak = shared.lock().unwrap();
+        if let Some(table) = weak.upgrade() {
+            return Ok(table);
+        }
+        let rows = config.load(schema, key_indices, value_indices)?;
+        info!("load lookup table {} rows", rows.len());
+        let table = Arc::new(LookupTable { rows: Mutex::new(Arc::new(rows)), version: AtomicU64::new(0) });
+        *weak = Arc::downgrade(&table);
+        if refresh_interval_ms > 0 {
+            let (table, config, schema, key_indices, value_indices) = (Arc::downgrade(&table), config.clone(), schema.clone(), key_indices.to_vec(), value_indices.to_vec());
+            thread::Builder::new().name("lookup-refresh".to_string()).spawn(move || {
+                Self::refresh(table, config, schema, key_indices, value_indices, refresh_interval_ms)
+            }).map_err(|e| e.to_string())?;
+        }
+        Ok(table)
+    }
+
+    /// 刷新失败时继续使用旧数据, 维表释放后退出
+    fn refresh(table: Weak<LookupTable>, config: LookupTableConfig, schema: Schema, key_indices: Vec<usize>, value_indices: Vec<usize>, refresh_interval_ms: u64) {
+        loop {
+            let mut waited = 0;
+            while waited < refresh_interval_ms {
+                if table.strong_count() == 0 {
+                    return;
+                }
+                let ms = (refresh_interval_ms - waited).min(1000);
+                thread::sleep(Duration::from_millis(ms));
+                waited += ms;
+            }
+            let rows = config.load(&schema, &key_indices, &value_indices);
+            let Some(table) = table.upgrade() else {
+                return;
+            };
+            match rows {
+                Ok(rows) => {
+                    info!("reload lookup table {} rows", rows.len());
+                    *table.rows.lock().unwrap() = Arc::new(rows);
+                    table.version.fetch_add(1, Ordering::Release);
+                },
+                Err(e) => warn!("reload lookup table error: {}", e),
+            }
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    pub fn rows(&self) -> Arc<LookupRows> {
+        self.rows.lock().unwrap().clone()
+    }
+}
+
+/// 数据库查询结果按字符串读取, 按位置转换为维表schema的类型
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+struct StringRowCast {
+    projection: crate::physical_expr::MutableProjection,
+    row: GenericRow,
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+impl StringRowCast {
+    fn new(schema: &Schema) -> Result<Self> {
+        use crate::expr::{BoundReference, Cast, Expr};
+        use crate::types::DataType;
+        let exprs = schema.fields.iter().enumerate().map(|(i, field)| {
+            let expr = Expr::BoundReference(BoundReference::new(i, DataType::String));
+            if field.data_type == DataType::String { expr } else { Expr::Cast(Cast::new(expr, field.data_type.clone())) }
+        }).collect();
+        let projection = crate::physical_expr::MutableProjection::new(exprs)?;
+        Ok(Self { projection, row: GenericRow::new_with_size(schema.fields.len()) })
+    }
+
+    fn apply(&mut self, values: Vec<Option<String>>) -> &GenericRow {
+        for (i, value) in values.into_iter().enumerate() {
+            self.row.update(i, value.map(crate::data::Value::string).unwrap_or(crate::data::Value::Null));
+        }
+        self.projection.apply(&self.row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use crate::data::Value;
+    use crate::parser::parse_schema;
+    use super::*;
+
+    #[test]
+    fn test_load_file() {
+        let path = std::env::temp_dir().join("retl_lookup_table_test.json");
+        let mut file = fs::File::create(&path).unwrap();
+        writeln!(file, r#"{{"app_id": 1, "app_name": "a"}}"#).unwrap();
+        writeln!(file, "").unwrap();
+        writeln!(file, r#"{{"app_id": 2, "app_name": "b"}}"#).unwrap();
+        writeln!(file, r#"{{"app_id": null, "app_name": "c"}}"#).unwrap();
+        let schema = parse_schema("app_id int, app_name string").unwrap();
+        let config: LookupTableConfig = serde_json::from_value(serde_json::json!({
+            "type": "file", "path": path.to_string_lossy(), "decoding": {"codec": "json"}
+        })).unwrap();
+        let rows = config.load(&schema, &[0], &[1]).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows.get(&GenericRow::new(vec![Value::int(2)])).unwrap().get(0), &Value::string("b"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_csv_file() {
+        let path = std::env::temp_dir().join("retl_lookup_table_test.csv");
+        fs::write(&path, "1,\"a\nb\"\n\n2,\"c,d\"\n").unwrap();
+        let schema = parse_schema("app_id int, app_name string").unwrap();
+        let config: LookupTableConfig = serde_json::from_value(serde_json::json!({
+            "type": "file", "path": path.to_string_lossy(), "decoding": {"codec": "csv"}
+        })).unwrap();
+        let rows = config.load(&schema, &[0], &[1]).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows.get(&GenericRow::new(vec![Value::int(1)])).unwrap().get(0), &Value::string("a\nb"));
+        assert_eq!(rows.get(&GenericRow::new(vec![Value::int(2)])).unwrap().get(0), &Value::string("c,d"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_shared_table() {
+        let path = std::env::temp_dir().join("retl_lookup_table_shared_test.json");
+        fs::write(&path, r#"{"app_id": 1, "app_name": "a"}"#).unwrap();
+        let schema = parse_schema("app_id int, app_name string").unwrap();
+        let config: LookupTableConfig = serde_json::from_value(serde_json::json!({
+            "type": "file", "path": path.to_string_lossy(), "decoding": {"codec": "json"}
+        })).unwrap();
+        let shared = SharedLookupTable::default();
+        let table = LookupTable::open(&shared, &config, &schema, &[0], &[1], 100).unwrap();
+        let other = LookupTable::open(&shared, &config, &schema, &[0], &[1], 100).unwrap();
+        assert!(Arc::ptr_eq(&table, &other));
+        fs::write(&path, "{\"app_id\": 1, \"app_name\": \"a\"}\n{\"app_id\": 2, \"app_name\": \"b\"}").unwrap();
+        for _ in 0..50 {
+            if table.version() > 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(table.version() > 0);
+        assert_eq!(table.rows().len(), 2);
+        drop((table, other));
+        assert!(shared.lock().unwrap().upgrade().is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
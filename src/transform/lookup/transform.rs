@@ -0,0 +1,98 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::{GenericRow, JoinedRow, Row};
+use crate::execution::{Collector, TimeService};
+use crate::expr::Expr;
+use crate::physical_expr::{create_physical_expr, PhysicalExpr};
+use crate::transform::lookup::{LookupJoinType, LookupRows, LookupTable, LookupTableConfig, SharedLookupTable};
+use crate::transform::Transform;
+use crate::types::Schema;
+
+/// 按key关联维表, 所有subtask共享一份维表, 第一个subtask open时加载, 后台线程按refresh_interval_ms定时刷新, 刷新失败时继续使用旧数据
+pub struct LookupJoinTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    table_config: LookupTableConfig,
+    table_schema: Schema,
+    shared_table: SharedLookupTable,
+    table: Option<Arc<LookupTable>>,
+    version: u64,
+    keys: Vec<Box<dyn PhysicalExpr>>,
+    key_indices: Vec<usize>,
+    value_indices: Vec<usize>,
+    join_type: LookupJoinType,
+    refresh_interval_ms: u64,
+    rows: Arc<LookupRows>,
+    key: GenericRow,
+    null_row: GenericRow,
+}
+
+impl LookupJoinTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, table_config: LookupTableConfig, table_schema: Schema, shared_table: SharedLookupTable, key_exprs: &[Expr],
+               key_indices: Vec<usize>, value_indices: Vec<usize>, join_type: LookupJoinType, refresh_interval_ms: u64) -> Result<Self> {
+        let keys = key_exprs.iter().map(create_physical_expr).collect::<Result<Vec<_>>>()?;
+        let key = GenericRow::new_with_size(keys.len());
+        let null_row = GenericRow::new_with_size(value_indices.len());
+        Ok(Self { task_context, schema, table_config, table_schema, shared_table, table: None, version: 0, keys, key_indices, value_indices, join_type,
+            refresh_interval_ms, rows: Arc::new(LookupRows::default()), key, null_row })
+    }
+}
+
+impl Debug for LookupJoinTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LookupJoinTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("table_config", &self.table_config)
+            .field("join_type", &self.join_type)
+            .field("refresh_interval_ms", &self.refresh_interval_ms)
+            .finish()
+    }
+}
+
+impl Transform for LookupJoinTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn open(&mut self) -> Result<()> {
+        let table = LookupTable::open(&self.shared_table, &self.table_config, &self.table_schema, &self.key_indices, &self.value_indices, self.refresh_interval_ms)?;
+        self.version = table.version();
+        self.rows = table.rows();
+        self.table = Some(table);
+        Ok(())
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, _time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        // 刷新线程替换了维表数据
+        if let Some(table) = &self.table && table.version() != self.version {
+            self.version = table.version();
+            self.rows = table.rows();
+        }
+
+        let mut has_null = false;
+        for (i, key) in self.keys.iter().enumerate() {
+            let value = key.eval(row);
+            has_null |= value.is_null();
+            self.key.update(i, value);
+        }
+        let value = if has_null { None } else { self.rows.get(&self.key) };
+        let value = match (value, self.join_type) {
+            (Some(value), _) => value,
+            (None, LookupJoinType::Left) => &self.null_row,
+            (None, LookupJoinType::Inner) => return Ok(()),
+        };
+        self.task_context.base_iometrics.num_records_out_inc_by(1);
+        out.collect(&JoinedRow::new(row, value))
+    }
+
+    /// 所有subtask关闭后释放维表, 刷新线程退出
+    fn close(&mut self) -> Result<()> {
+        self.table = None;
+        self.rows = Arc::new(LookupRows::default());
+        Ok(())
+    }
+}
@@ -3,6 +3,12 @@ mod operator;
 mod query;
 mod filter;
 mod aggregate;
+mod union;
+mod lookup;
+mod dedup;
+mod route;
+mod throttle;
+mod sample;
 #[cfg(feature = "vrl")]
 mod vrl;
 
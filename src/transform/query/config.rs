@@ -20,6 +20,9 @@ impl TransformConfig for QueryTransformConfig {
         Ok(Box::new(QueryTransformProvider::new(optimized_plan)))
     }
 
+    fn sql(&self) -> Option<&str> {
+        Some(&self.sql)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +39,10 @@ impl QueryTransformProvider {
 }
 
 impl TransformProvider for QueryTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
         let process_operator = get_process_operator_chain(self.plan.clone())?;
         Ok(Box::new(QueryTransform::new(task_context, self.schema.clone(), process_operator)))
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::config::{TaskContext, TransformConfig, TransformProvider};
+use crate::expr::{BoundReference, Expr};
+use crate::sql_utils;
+use crate::transform::route::RouteTransform;
+use crate::transform::Transform;
+use crate::types::Schema;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteTransformConfig {
+    /// 输出名称 -> 条件, 数据输出到所有条件为true的输出, 下游通过inputs引用输出名称.
+    /// 不匹配任何条件的数据输出到transform的outputs, outputs就是默认输出, 不需要默认输出时outputs不被下游引用即可
+    routes: BTreeMap<String, String>,
+}
+
+#[typetag::serde(name = "route")]
+impl TransformConfig for RouteTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        if self.routes.is_empty() {
+            return Err("route routes is empty".to_string());
+        }
+        let mut predicates = Vec::with_capacity(self.routes.len());
+        for (name, condition) in self.routes.iter() {
+            let filter = sql_utils::parse_filter(condition, &schema).map_err(|e| format!("route {} condition error: {}", name, e))?;
+            let predicate = BoundReference::bind_reference(filter.condition, filter.child.output())?;
+            predicates.push((name.clone(), predicate));
+        }
+        Ok(Box::new(RouteTransformProvider { schema, predicates }))
+    }
+
+    /// route名称作为side output, 不能和outputs重名
+    fn side_outputs(&self) -> Vec<String> {
+        self.routes.keys().cloned().collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteTransformProvider {
+    schema: Schema,
+    predicates: Vec<(String, Expr)>,
+}
+
+impl TransformProvider for RouteTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        Ok(Box::new(RouteTransform::new(task_context, self.schema.clone(), &self.predicates)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{GenericRow, Row, Value};
+    use crate::execution::{TimeService, VecCollector};
+    use crate::parser::parse_schema;
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let schema = parse_schema("id bigint, status int, latency int").unwrap();
+        let routes = BTreeMap::from([("errors".to_string(), "status >= 500".to_string()), ("slow".to_string(), "latency > 1000".to_string())]);
+        let config: Box<dyn TransformConfig> = Box::new(RouteTransformConfig { routes });
+        assert_eq!(config.side_outputs(), vec!["errors", "slow"]);
+        let mut transform = config.build(schema).unwrap().create_transform(TaskContext::default()).unwrap();
+        let mut out = VecCollector::default();
+        for (id, status, latency) in [(1, 200, 10), (2, 500, 10), (3, 200, 2000), (4, 503, 3000)] {
+            let row = GenericRow::new(vec![Value::long(id), Value::int(status), Value::int(latency)]);
+            transform.process(&row, &mut out, &mut TimeService::new()).unwrap();
+        }
+        assert_eq!(out.rows.iter().map(|row| row.get_long(0)).collect::<Vec<_>>(), vec![1]);
+        let expected: Vec<_> = [("errors", 2), ("slow", 3), ("errors", 4), ("slow", 4)].iter().map(|(n, id)| (n.to_string(), *id)).collect();
+        assert_eq!(out.side_rows.iter().map(|(n, row)| (n.clone(), row.get_long(0))).collect::<Vec<_>>(), expected);
+    }
+}
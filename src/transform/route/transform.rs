@@ -0,0 +1,57 @@
+use std::fmt::Debug;
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::Row;
+use crate::execution::{Collector, TimeService};
+use crate::expr::Expr;
+use crate::physical_expr::{create_physical_expr, PhysicalExpr};
+use crate::transform::Transform;
+use crate::types::Schema;
+
+/// 每个route的条件只计算一次, 数据输出到所有匹配的route(side output), 都不匹配时输出到主输出(outputs)作为默认输出
+pub struct RouteTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    predicates: Vec<(String, Box<dyn PhysicalExpr>)>,
+}
+
+impl RouteTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, predicates: &[(String, Expr)]) -> Result<Self> {
+        let predicates = predicates.iter().map(|(name, expr)| Ok((name.clone(), create_physical_expr(expr)?))).collect::<Result<Vec<_>>>()?;
+        Ok(Self { task_context, schema, predicates })
+    }
+}
+
+impl Debug for RouteTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("routes", &self.predicates.iter().map(|(name, _)| name).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Transform for RouteTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, _time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        let mut matched = 0;
+        for (name, predicate) in self.predicates.iter() {
+            let value = predicate.eval(row);
+            if !value.is_null() && value.get_boolean() {
+                matched += 1;
+                out.collect_side(name, row)?;
+            }
+        }
+        if matched == 0 {
+            out.collect(row)?;
+        }
+        self.task_context.base_iometrics.num_records_out_inc_by(matched.max(1));
+        Ok(())
+    }
+}
+
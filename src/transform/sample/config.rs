@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::config::{TaskContext, TransformConfig, TransformProvider};
+use crate::expr::{BoundReference, Expr};
+use crate::sql_utils;
+use crate::transform::sample::{HashSampleTransform, ReservoirSampleTransform};
+use crate::transform::Transform;
+use crate::types::Schema;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SamplerConfig {
+    /// 按keys的hash值采样ratio比例的数据, 相同key的数据在所有subtask和每次运行中采样结果相同
+    Hash { keys: Vec<String>, ratio: f64 },
+    /// 每个interval_ms(处理时间)内等概率保留size行, 间隔结束时输出
+    Reservoir { size: usize, interval_ms: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleTransformConfig {
+    sampler: SamplerConfig,
+}
+
+#[typetag::serde(name = "sample")]
+impl TransformConfig for SampleTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        let mut key_exprs = Vec::new();
+        match &self.sampler {
+            SamplerConfig::Hash { keys, ratio } => {
+                if keys.is_empty() || !(0.0..=1.0).contains(ratio) {
+                    return Err(format!("sample hash requires non-empty keys and ratio in [0, 1]: {:?}", self.sampler));
+                }
+                for key in keys.iter() {
+                    let expression = sql_utils::parse_expr(key, &schema)?;
+                    key_exprs.push(BoundReference::bind_reference(expression.expr, expression.child.output())?);
+                }
+            },
+            SamplerConfig::Reservoir { size, interval_ms } => {
+                if *size == 0 || *interval_ms == 0 {
+                    return Err(format!("sample reservoir size and interval_ms must be greater than 0: {:?}", self.sampler));
+                }
+            },
+        }
+        Ok(Box::new(SampleTransformProvider { schema, config: self.clone(), key_exprs }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SampleTransformProvider {
+    schema: Schema,
+    config: SampleTransformConfig,
+    key_exprs: Vec<Expr>,
+}
+
+impl TransformProvider for SampleTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        match self.config.sampler {
+            SamplerConfig::Hash { ratio, .. } => Ok(Box::new(HashSampleTransform::new(task_context, self.schema.clone(), &self.key_exprs, ratio)?)),
+            SamplerConfig::Reservoir { size, interval_ms } => Ok(Box::new(ReservoirSampleTransform::new(task_context, self.schema.clone(), size, interval_ms))),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::Debug;
+use std::io::Cursor;
+use murmur3::murmur3_x64_128;
+use rand::Rng;
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::{GenericRow, Row, Value};
+use crate::datetime_utils::processing_time_millis;
+use crate::execution::{Collector, TimeService};
+use crate::expr::Expr;
+use crate::physical_expr::{create_physical_expr, PhysicalExpr};
+use crate::transform::Transform;
+use crate::types::Schema;
+
+/// hash值映射到[0, 1)小于ratio时保留, hash不依赖进程和平台
+pub struct HashSampleTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    keys: Vec<Box<dyn PhysicalExpr>>,
+    ratio: f64,
+    buf: Vec<u8>,
+}
+
+impl HashSampleTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, key_exprs: &[Expr], ratio: f64) -> Result<Self> {
+        let keys = key_exprs.iter().map(create_physical_expr).collect::<Result<Vec<_>>>()?;
+        Ok(Self { task_context, schema, keys, ratio, buf: Vec::new() })
+    }
+
+    fn sampled(&mut self, row: &dyn Row) -> bool {
+        self.buf.clear();
+        for key in self.keys.iter() {
+            match key.eval(row) {
+                Value::Null => (),
+                Value::String(v) => self.buf.extend_from_slice(v.as_bytes()),
+                Value::Binary(v) => self.buf.extend_from_slice(&v),
+                Value::Int(v) => self.buf.extend_from_slice(&v.to_le_bytes()),
+                Value::Long(v) => self.buf.extend_from_slice(&v.to_le_bytes()),
+                v => self.buf.extend_from_slice(v.to_string().as_bytes()),
+            }
+            self.buf.push(0);
+        }
+        let hash = murmur3_x64_128(&mut Cursor::new(&self.buf), 0).unwrap() as u64;
+        ((hash >> 11) as f64 / (1u64 << 53) as f64) < self.ratio
+    }
+}
+
+impl Debug for HashSampleTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashSampleTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("ratio", &self.ratio)
+            .finish()
+    }
+}
+
+impl Transform for HashSampleTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, _time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        if self.sampled(row) {
+            self.task_context.base_iometrics.num_records_out_inc_by(1);
+            out.collect(row)?;
+        }
+        Ok(())
+    }
+}
+
+/// 蓄水池采样, 第n行以size/n的概率替换已保留的一行
+pub struct ReservoirSampleTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    size: usize,
+    interval_ms: u64,
+    rows: Vec<GenericRow>,
+    seen: u64,
+    trigger_time_ms: u64,
+}
+
+impl ReservoirSampleTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, size: usize, interval_ms: u64) -> Self {
+        Self { task_context, schema, size, interval_ms, rows: Vec::with_capacity(size), seen: 0, trigger_time_ms: 0 }
+    }
+
+    fn add(&mut self, row: &dyn Row) {
+        self.seen += 1;
+        if self.rows.len() < self.size {
+            self.rows.push(row.to_generic_row());
+        } else {
+            let i = rand::rng().random_range(0..self.seen);
+            if i < self.size as u64 {
+                self.rows[i as usize] = row.to_generic_row();
+            }
+        }
+    }
+
+    fn flush(&mut self, out: &mut dyn Collector) -> Result<()> {
+        self.seen = 0;
+        self.task_context.base_iometrics.num_records_out_inc_by(self.rows.len() as u64);
+        for row in self.rows.drain(..) {
+            out.collect(&row)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for ReservoirSampleTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReservoirSampleTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("size", &self.size)
+            .field("interval_ms", &self.interval_ms)
+            .finish()
+    }
+}
+
+impl Transform for ReservoirSampleTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, _out: &mut dyn Collector, time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        self.add(row);
+        if self.trigger_time_ms == 0 {
+            self.trigger_time_ms = processing_time_millis() / self.interval_ms * self.interval_ms + self.interval_ms;
+            time_service.register_timer(self.trigger_time_ms);
+        }
+        Ok(())
+    }
+
+    fn on_time(&mut self, _time: u64, out: &mut dyn Collector) -> Result<()> {
+        self.trigger_time_ms = 0;
+        self.flush(out)
+    }
+
+    fn on_barrier(&mut self, _checkpoint_id: u64, out: &mut dyn Collector) -> Result<()> {
+        self.flush(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::BoundReference;
+    use crate::types::{DataType, Field};
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let schema = Schema::new(vec![Field::new("id", DataType::Long)]);
+        let key = Expr::BoundReference(BoundReference::new(0, DataType::Long));
+        let mut transform = HashSampleTransform::new(TaskContext::default(), schema.clone(), &[key], 0.2).unwrap();
+        let rows: Vec<GenericRow> = (0..10000).map(|id| GenericRow::new(vec![Value::long(id)])).collect();
+        let sampled: Vec<bool> = rows.iter().map(|row| transform.sampled(row)).collect();
+        let count = sampled.iter().filter(|s| **s).count();
+        assert!(count > 1800 && count < 2200, "{}", count);
+        assert_eq!(sampled, rows.iter().map(|row| transform.sampled(row)).collect::<Vec<_>>());
+
+        let mut transform = ReservoirSampleTransform::new(TaskContext::default(), schema, 3, 1000);
+        for row in rows.iter() {
+            transform.add(row);
+        }
+        assert_eq!(transform.rows.len(), 3);
+        assert_eq!(transform.seen, 10000);
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::config::{TaskContext, TransformConfig, TransformProvider};
+use crate::expr::{BoundReference, Expr};
+use crate::sql_utils;
+use crate::transform::throttle::ThrottleTransform;
+use crate::transform::Transform;
+use crate::types::Schema;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleMode {
+    /// 超过速率的数据丢弃
+    #[default]
+    Drop,
+    /// 超过速率时阻塞到下一秒, 反压上游, 配置keys时一个key超过速率会阻塞整个subtask.
+    /// 阻塞时分段等待并检查停止信号, barrier和数据在同一线程中传递, 最多延迟到阻塞的数据输出之后
+    Block,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrottleTransformConfig {
+    /// 每个subtask每秒最多输出的行数, 配置keys时为每个key每秒最多输出的行数
+    rows_per_second: u64,
+    /// 限流key表达式, 为空时按subtask限流
+    #[serde(default)]
+    keys: Vec<String>,
+    #[serde(default)]
+    mode: ThrottleMode,
+}
+
+#[typetag::serde(name = "throttle")]
+impl TransformConfig for ThrottleTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        if self.rows_per_second == 0 {
+            return Err("throttle rows_per_second must be greater than 0".to_string());
+        }
+        let mut key_exprs = Vec::with_capacity(self.keys.len());
+        for key in self.keys.iter() {
+            let expression = sql_utils::parse_expr(key, &schema)?;
+            key_exprs.push(BoundReference::bind_reference(expression.expr, expression.child.output())?);
+        }
+        Ok(Box::new(ThrottleTransformProvider { schema, config: self.clone(), key_exprs }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ThrottleTransformProvider {
+    schema: Schema,
+    config: ThrottleTransformConfig,
+    key_exprs: Vec<Expr>,
+}
+
+impl TransformProvider for ThrottleTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        Ok(Box::new(ThrottleTransform::new(task_context, self.schema.clone(), &self.key_exprs, self.config.rows_per_second, self.config.mode)?))
+    }
+}
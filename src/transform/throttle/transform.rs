@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::BuildHasherDefault;
+use std::thread::sleep;
+use std::time::Duration;
+use ahash::AHasher;
+use prometheus::IntCounter;
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::{GenericRow, Row};
+use crate::datetime_utils::processing_time_millis;
+use crate::execution::{Collector, TimeService};
+use crate::expr::Expr;
+use crate::physical_expr::{create_physical_expr, PhysicalExpr};
+use crate::rate_limiter::{RateLimiter, RATE_LIMIT_MAX_WAIT_MS};
+use crate::transform::throttle::ThrottleMode;
+use crate::transform::Transform;
+use crate::types::Schema;
+
+/// 限制每秒输出的行数, 按key限流时只保留当前秒出现的key
+pub struct ThrottleTransform {
+    task_context: TaskContext,
+    schema: Schema,
+    keys: Vec<Box<dyn PhysicalExpr>>,
+    rows_per_second: u64,
+    mode: ThrottleMode,
+    limiter: RateLimiter,
+    key_limiters: HashMap<GenericRow, RateLimiter, BuildHasherDefault<AHasher>>,
+    key_second_ms: u64,
+    key: GenericRow,
+    num_records_throttled: IntCounter,
+}
+
+impl ThrottleTransform {
+    pub fn new(task_context: TaskContext, schema: Schema, key_exprs: &[Expr], rows_per_second: u64, mode: ThrottleMode) -> Result<Self> {
+        let keys = key_exprs.iter().map(create_physical_expr).collect::<Result<Vec<_>>>()?;
+        let key = GenericRow::new_with_size(keys.len());
+        let registry = &task_context.task_config.metrics_registry;
+        let num_records_throttled = task_context.base_iometrics.new_counter(registry, "num_records_throttled", "number of records dropped or delayed by throttle")?;
+        Ok(Self { task_context, schema, keys, rows_per_second, mode, limiter: RateLimiter::new(rows_per_second), key_limiters: HashMap::default(),
+            key_second_ms: 0, key, num_records_throttled })
+    }
+
+    fn try_acquire(&mut self, row: &dyn Row, now: u64) -> bool {
+        if self.keys.is_empty() {
+            return self.limiter.try_acquire_at(now);
+        }
+        let second_ms = now / 1000 * 1000;
+        if second_ms != self.key_second_ms {
+            self.key_limiters.clear();
+            self.key_second_ms = second_ms;
+        }
+        for (i, key) in self.keys.iter().enumerate() {
+            self.key.update(i, key.eval(row));
+        }
+        match self.key_limiters.get_mut(&self.key) {
+            Some(limiter) => limiter.try_acquire_at(now),
+            None => {
+                let mut limiter = RateLimiter::new(self.rows_per_second);
+                let acquired = limiter.try_acquire_at(now);
+                self.key_limiters.insert(self.key.clone(), limiter);
+                acquired
+            },
+        }
+    }
+}
+
+impl Debug for ThrottleTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThrottleTransform")
+            .field("task_context", &self.task_context)
+            .field("schema", &self.schema)
+            .field("rows_per_second", &self.rows_per_second)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl Transform for ThrottleTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, _time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        let mut now = processing_time_millis();
+        if !self.try_acquire(row, now) {
+            self.num_records_throttled.inc();
+            if self.mode == ThrottleMode::Drop {
+                return Ok(());
+            }
+            // 每次最多等待RATE_LIMIT_MAX_WAIT_MS, 收到停止信号时不再限流直接输出
+            while !self.task_context.task_config.is_terminated() {
+                let wait_ms = (1000 - now % 1000).min(RATE_LIMIT_MAX_WAIT_MS);
+                sleep(Duration::from_millis(wait_ms));
+                // mock时间不会随sleep前进
+                now = processing_time_millis().max(now + wait_ms);
+                if self.try_acquire(row, now) {
+                    break;
+                }
+            }
+        }
+        self.task_context.base_iometrics.num_records_out_inc_by(1);
+        out.collect(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Instant;
+    use crate::data::Value;
+    use crate::datetime_utils::set_mock_processing_time_millis;
+    use crate::execution::VecCollector;
+    use crate::expr::BoundReference;
+    use crate::types::{DataType, Field};
+    use super::*;
+
+    #[test]
+    fn test_throttle_keys() {
+        let schema = Schema::new(vec![Field::new("id", DataType::Long)]);
+        let key = Expr::BoundReference(BoundReference::new(0, DataType::Long));
+        let mut transform = ThrottleTransform::new(TaskContext::default(), schema, &[key], 2, ThrottleMode::Drop).unwrap();
+        let rows: Vec<GenericRow> = (0..2).map(|id| GenericRow::new(vec![Value::long(id)])).collect();
+        let acquired: Vec<bool> = [(0, 1000), (0, 1100), (1, 1200), (0, 1300), (0, 2000)].iter()
+            .map(|(i, now)| transform.try_acquire(&rows[*i], *now)).collect();
+        assert_eq!(acquired, vec![true, true, true, false, true]);
+        assert_eq!(transform.key_limiters.len(), 1);
+    }
+
+    #[test]
+    fn test_throttle_block_terminated() {
+        let schema = Schema::new(vec![Field::new("id", DataType::Long)]);
+        let task_context = TaskContext::default();
+        let terminated = task_context.task_config.terminated.clone();
+        let mut transform = ThrottleTransform::new(task_context, schema, &[], 1, ThrottleMode::Block).unwrap();
+        let mut out = VecCollector::default();
+        let row = GenericRow::new(vec![Value::long(1)]);
+        set_mock_processing_time_millis(Some(1000));
+        transform.process(&row, &mut out, &mut TimeService::new()).unwrap();
+        // 停止后阻塞的数据直接输出
+        terminated.store(true, Ordering::Release);
+        let start = Instant::now();
+        transform.process(&row, &mut out, &mut TimeService::new()).unwrap();
+        set_mock_processing_time_millis(None);
+        assert!(start.elapsed() < Duration::from_millis(RATE_LIMIT_MAX_WAIT_MS));
+        assert_eq!(out.rows.len(), 2);
+        assert_eq!(transform.num_records_throttled.get(), 1);
+    }
+}
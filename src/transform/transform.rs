@@ -11,12 +11,27 @@ pub trait Transform: Debug {
         Ok(())
     }
 
+    /// open之后调用, 从状态快照恢复数据并重新注册timer
+    fn restore_state(&mut self, _time_service: &mut TimeService) -> Result<()> {
+        Ok(())
+    }
+
     fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, time_service: &mut TimeService) -> Result<()> ;
 
     fn on_time(&mut self, time: u64, out: &mut dyn Collector) -> Result<()> {
         Ok(())
     }
 
+    /// barrier向下游传递前调用, 缓存数据的transform需要在这里输出缓存的数据
+    fn on_barrier(&mut self, _checkpoint_id: u64, _out: &mut dyn Collector) -> Result<()> {
+        Ok(())
+    }
+
+    /// 输入结束(source结束或者停止任务)时调用, 所有timer触发之后调用, 输出还没有输出的缓存数据
+    fn finish(&mut self, _out: &mut dyn Collector) -> Result<()> {
+        Ok(())
+    }
+
     fn close(&mut self) -> Result<()> {
         Ok(())
     }
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use crate::Result;
+use crate::config::{TaskContext, TransformConfig, TransformProvider};
+use crate::parser::parse_schema;
+use crate::physical_expr::can_cast;
+use crate::transform::Transform;
+use crate::transform::union::UnionTransform;
+use crate::types::Schema;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnionTransformConfig {
+    /// 合并后的schema, 默认使用第一个input的schema
+    #[serde(default)]
+    schema: Option<String>,
+    /// inputs的列按位置对应, 类型不一致时是否转换类型
+    #[serde(default)]
+    cast: bool,
+}
+
+#[typetag::serde(name = "union")]
+impl TransformConfig for UnionTransformConfig {
+    fn build(&self, schema: Schema) -> Result<Box<dyn TransformProvider>> {
+        Ok(Box::new(UnionTransformProvider{schema}))
+    }
+
+    fn input_schema(&self, input_schemas: Vec<Schema>) -> Result<Schema> {
+        let schema = match &self.schema {
+            Some(schema) => parse_schema(schema)?,
+            None => input_schemas.first().cloned().ok_or_else(|| "union transform has no inputs".to_string())?,
+        };
+        for input_schema in input_schemas.iter() {
+            let compatible = input_schema.fields.len() == schema.fields.len() && input_schema.fields.iter().zip(schema.fields.iter()).all(|(from, to)| {
+                from.data_type == to.data_type || self.cast && can_cast(&from.data_type, &to.data_type)
+            });
+            if !compatible {
+                return Err(format!("union input {} not compatible with {}", input_schema, schema));
+            }
+        }
+        Ok(schema)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnionTransformProvider {
+    schema: Schema,
+}
+
+impl TransformProvider for UnionTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
+        Ok(Box::new(UnionTransform::new(task_context, self.schema.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union_input_schema() {
+        let a = parse_schema("id bigint, name string").unwrap();
+        let b = parse_schema("id2 int, name2 string").unwrap();
+        let config = UnionTransformConfig { schema: None, cast: false };
+        assert_eq!(config.input_schema(vec![a.clone(), a.clone()]).unwrap(), a);
+        assert!(config.input_schema(vec![a.clone(), b.clone()]).is_err());
+        let config = UnionTransformConfig { schema: None, cast: true };
+        assert_eq!(config.input_schema(vec![a.clone(), b.clone()]).unwrap(), a);
+        let config = UnionTransformConfig { schema: Some("id bigint".to_string()), cast: true };
+        assert!(config.input_schema(vec![a, b]).is_err());
+    }
+}
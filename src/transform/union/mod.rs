@@ -0,0 +1,5 @@
+mod config;
+mod transform;
+
+pub use config::*;
+pub use transform::*;
@@ -0,0 +1,31 @@
+use crate::Result;
+use crate::config::TaskContext;
+use crate::data::Row;
+use crate::execution::{Collector, TimeService};
+use crate::transform::Transform;
+use crate::types::Schema;
+
+/// 合并多个inputs的数据, 各个input的数据在上游已经转换为相同的schema
+#[derive(Debug)]
+pub struct UnionTransform {
+    task_context: TaskContext,
+    schema: Schema,
+}
+
+impl UnionTransform {
+    pub fn new(task_context: TaskContext, schema: Schema) -> Self {
+        Self {task_context, schema}
+    }
+}
+
+impl Transform for UnionTransform {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn process(&mut self, row: &dyn Row, out: &mut dyn Collector, _time_service: &mut TimeService) -> Result<()> {
+        self.task_context.base_iometrics.num_records_in_inc_by(1);
+        self.task_context.base_iometrics.num_records_out_inc_by(1);
+        out.collect(row)
+    }
+}
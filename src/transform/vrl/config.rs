@@ -59,6 +59,10 @@ pub struct VrlTransformProvider {
 }
 
 impl TransformProvider for VrlTransformProvider {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn create_transform(&self, task_context: TaskContext) -> Result<Box<dyn Transform>> {
         let mut converts = Vec::with_capacity(self.input_schema.fields.len());
         for (i, field) in self.input_schema.fields.iter().enumerate() {
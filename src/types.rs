@@ -4,6 +4,7 @@ use std::hash::Hash;
 use std::sync::LazyLock;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize, Serializer};
+use crate::decimal_utils::MAX_PRECISION;
 use crate::expr::AttributeReference;
 
 static NULL_TYPE: DataType = DataType::Null;
@@ -17,6 +18,12 @@ static DATE_TYPE: DataType = DataType::Date;
 static TIMESTAMP_TYPE: DataType = DataType::Timestamp;
 static BINARY_TYPE: DataType = DataType::Binary;
 static STRING_ARRAY_TYPE: LazyLock<DataType> = LazyLock::new(|| DataType::Array(Box::new(DataType::String)));
+static STRING_MAP_TYPE: LazyLock<DataType> = LazyLock::new(|| DataType::Map(Box::new(DataType::String), Box::new(DataType::String)));
+/// 所有decimal(p,s)类型, 下标为p * (MAX_PRECISION + 1) + s
+static DECIMAL_TYPES: LazyLock<Vec<DataType>> = LazyLock::new(|| {
+    let n = MAX_PRECISION + 1;
+    (0..n).flat_map(|p| (0..n).map(move |s| DataType::Decimal(p, s))).collect()
+});
 
 #[derive(Clone, Debug)]
 pub enum AbstractDataType {
@@ -24,6 +31,10 @@ pub enum AbstractDataType {
     Integral,
     Numeric,
     Type(DataType),
+    /// 任意元素类型的array
+    Array,
+    /// 任意key/value类型的map
+    Map,
     Collection(Vec<AbstractDataType>),
 }
 
@@ -34,6 +45,8 @@ impl AbstractDataType {
             AbstractDataType::Integral => other.is_integral_type(),
             AbstractDataType::Numeric => other.is_numeric_type(),
             AbstractDataType::Type(data_type) => data_type == other,
+            AbstractDataType::Array => matches!(other, DataType::Array(_)),
+            AbstractDataType::Map => matches!(other, DataType::Map(_, _)),
             AbstractDataType::Collection(data_types) => data_types.iter().any(|data_type| data_type.accepts_type(other)),
         }
     }
@@ -44,6 +57,8 @@ impl AbstractDataType {
             AbstractDataType::Integral => DataType::Int,
             AbstractDataType::Numeric => DataType::Double,
             AbstractDataType::Type(dt) => dt.clone(),
+            AbstractDataType::Array => DataType::Array(Box::new(DataType::Null)),
+            AbstractDataType::Map => DataType::Map(Box::new(DataType::Null), Box::new(DataType::Null)),
             AbstractDataType::Collection(dts) => dts[0].default_concrete_type(),
         }
     }
@@ -124,12 +139,16 @@ pub enum DataType {
     Binary,
     Struct(Fields),
     Array(Box<DataType>),
+    /// key类型, value类型
+    Map(Box<DataType>, Box<DataType>),
+    /// 精度, 标度. 值为i128非标度值
+    Decimal(u8, u8),
 }
 
 impl DataType {
     pub fn is_numeric_type(&self) -> bool {
         match self {
-            DataType::Int | DataType::Long | DataType::Float | DataType::Double => true,
+            DataType::Int | DataType::Long | DataType::Float | DataType::Double | DataType::Decimal(_, _) => true,
             _ => false
         }
     }
@@ -144,7 +163,7 @@ impl DataType {
     pub fn is_atomic_type(&self) -> bool {
         match self {
             DataType::Int | DataType::Long | DataType::Float | DataType::Double | DataType::Boolean |
-            DataType::String | DataType::Date | DataType::Timestamp | DataType::Binary => true,
+            DataType::String | DataType::Date | DataType::Timestamp | DataType::Binary | DataType::Decimal(_, _) => true,
             _ => false
         }
     }
@@ -158,7 +177,7 @@ impl DataType {
 
     pub fn is_orderable(&self) -> bool {
         match self {
-            DataType::Int | DataType::Long | DataType::Float | DataType::Double => true,
+            DataType::Int | DataType::Long | DataType::Float | DataType::Double | DataType::Decimal(_, _) => true,
             DataType::String | DataType::Date | DataType::Timestamp => true,
             _ => false
         }
@@ -207,6 +226,15 @@ impl DataType {
     pub fn string_array_type() -> &'static DataType {
         &STRING_ARRAY_TYPE
     }
+
+    pub fn string_map_type() -> &'static DataType {
+        &STRING_MAP_TYPE
+    }
+
+    /// precision和scale需要不超过38且scale <= precision
+    pub fn decimal_type(precision: u8, scale: u8) -> &'static DataType {
+        &DECIMAL_TYPES[precision as usize * (MAX_PRECISION as usize + 1) + scale as usize]
+    }
 }
 
 impl Display for DataType {
@@ -224,6 +252,8 @@ impl Display for DataType {
             DataType::Binary => write!(f, "binary"),
             DataType::Struct(fields) => write!(f, "struct<{}>", fields.0.iter().map(|field| format!("{}: {}", field.name, field.data_type)).join(",")),
             DataType::Array(element_type) => write!(f, "array<{}>", element_type),
+            DataType::Map(key_type, value_type) => write!(f, "map<{},{}>", key_type, value_type),
+            DataType::Decimal(precision, scale) => write!(f, "decimal({},{})", precision, scale),
         }
     }
 }